@@ -0,0 +1,272 @@
+//! Alternate (private) registries
+//!
+//! By default dependents are discovered on crates.io and the offered version
+//! is patched over crates.io. With `--registry <name>` (index URL from
+//! cargo's configuration) or `--index-url <sparse+https://...>`, both happen
+//! against another registry instead, such as Kellnr, Artifactory or
+//! Shipyard: versions come from its sparse index, .crate files from its `dl`
+//! endpoint, dependents from its web API, and the `[patch]` targets it. A
+//! token in `CARGO_REGISTRIES_<NAME>_TOKEN` is sent with every request.
+//! Only sparse indexes are supported.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::Deserialize;
+
+use crate::api::ReverseDependency;
+
+/// Registry name used when only `--index-url` is given
+const DEFAULT_NAME: &str = "copter-registry";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AltRegistry {
+    /// Name in cargo's `registries` table, and the `[patch]` key
+    pub name: String,
+    /// `sparse+https://...`
+    pub index_url: String,
+    /// Download URL template from config.json
+    dl: String,
+    /// Web API root from config.json, if the registry has one
+    api: Option<String>,
+    pub token: Option<String>,
+}
+
+/// The index's config.json
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexConfig {
+    pub dl: String,
+    #[serde(default)]
+    pub api: Option<String>,
+}
+
+impl AltRegistry {
+    /// Registry for `--registry`/`--index-url`; `config` is the content of the
+    /// index's config.json, fetched from `config_url()`
+    pub fn new(name: Option<&str>, index_url: &str, config: &str) -> Result<Self, String> {
+        let config: IndexConfig = serde_json::from_str(config)
+            .map_err(|e| format!("Invalid config.json for {}: {}", index_url, e))?;
+        let name = name.unwrap_or(DEFAULT_NAME).to_string();
+        Ok(AltRegistry {
+            token: env_token(&name),
+            name,
+            index_url: index_url.to_string(),
+            dl: config.dl,
+            api: config.api.map(|api| api.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// Index root without the `sparse+` prefix
+    fn index_base(&self) -> &str {
+        self.index_url.trim_start_matches("sparse+").trim_end_matches('/')
+    }
+
+    pub fn config_url(index_url: &str) -> String {
+        format!("{}/config.json", index_url.trim_start_matches("sparse+").trim_end_matches('/'))
+    }
+
+    /// URL of a crate's index file
+    pub fn index_file_url(&self, crate_name: &str) -> String {
+        format!("{}/{}", self.index_base(), crate::registry::index_path(crate_name))
+    }
+
+    /// URL of `{name}-{version}.crate`, from config.json's `dl` template
+    pub fn download_url(&self, crate_name: &str, version: &str) -> String {
+        const MARKERS: [&str; 5] = ["{crate}", "{version}", "{prefix}", "{lowerprefix}", "{sha256-checksum}"];
+        if !MARKERS.iter().any(|m| self.dl.contains(m)) {
+            return format!("{}/{}/{}/download", self.dl.trim_end_matches('/'), crate_name, version);
+        }
+        let prefix = crate::registry::index_path(crate_name);
+        let prefix = prefix.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        self.dl
+            .replace("{crate}", crate_name)
+            .replace("{version}", version)
+            .replace("{lowerprefix}", &prefix.to_lowercase())
+            .replace("{prefix}", prefix)
+    }
+
+    /// crates.io-style reverse dependency listing, if the registry has a web API
+    pub fn reverse_dependencies_url(&self, crate_name: &str, page: usize, per_page: usize) -> Option<String> {
+        let api = self.api.as_ref()?;
+        Some(format!("{}/api/v1/crates/{}/reverse_dependencies?page={}&per_page={}", api, crate_name, page, per_page))
+    }
+
+    /// Search listing every crate, for registries without the reverse dependency endpoint
+    pub fn all_crates_url(&self, page: usize, per_page: usize) -> Option<String> {
+        let api = self.api.as_ref()?;
+        Some(format!("{}/api/v1/crates?q=&page={}&per_page={}", api, page, per_page))
+    }
+
+    /// `--config` arguments that make the registry known to cargo under `name`
+    pub fn cargo_config_args(&self) -> Vec<String> {
+        vec!["--config".to_string(), format!("registries.{}.index=\"{}\"", self.name, self.index_url)]
+    }
+}
+
+/// `CARGO_REGISTRIES_<NAME>_<KEY>`, as cargo spells it
+fn env_key(name: &str, key: &str) -> String {
+    format!("CARGO_REGISTRIES_{}_{}", name.to_uppercase().replace('-', "_"), key)
+}
+
+/// Token for the registry from `CARGO_REGISTRIES_<NAME>_TOKEN`
+pub fn env_token(name: &str) -> Option<String> {
+    env::var(env_key(name, "TOKEN")).ok().filter(|t| !t.is_empty())
+}
+
+/// Index URL for `--registry`/`--index-url`
+///
+/// `--index-url` wins; otherwise the registry's index comes from
+/// `CARGO_REGISTRIES_<NAME>_INDEX` or the `registries` table of the cargo
+/// config files cargo would read from `cwd`.
+pub fn resolve_index_url(name: Option<&str>, index_url: Option<&str>, cwd: &Path) -> Result<String, String> {
+    let url = match (index_url, name) {
+        (Some(url), _) => url.to_string(),
+        (None, Some(name)) => match env::var(env_key(name, "INDEX")) {
+            Ok(url) => url,
+            Err(_) => cargo_config_files(cwd).iter()
+                .find_map(|path| configured_index(path, name))
+                .ok_or_else(|| format!("Registry '{}' is not configured: add registries.{}.index to .cargo/config.toml or use --index-url", name, name))?,
+        },
+        (None, None) => return Err("--registry or --index-url is required".to_string()),
+    };
+    if !url.starts_with("sparse+") {
+        return Err(format!("Only sparse registry indexes (sparse+https://...) are supported, got '{}'", url));
+    }
+    Ok(url)
+}
+
+/// Config files cargo reads, most specific first
+fn cargo_config_files(cwd: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = cwd.ancestors()
+        .flat_map(|dir| [dir.join(".cargo").join("config.toml"), dir.join(".cargo").join("config")])
+        .collect();
+    if let Some(home) = crate::paths::cargo_home() {
+        files.push(home.join("config.toml"));
+        files.push(home.join("config"));
+    }
+    files
+}
+
+fn configured_index(path: &Path, name: &str) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let value: toml::Value = toml::from_str(&content)
+        .map_err(|e| debug!("skipping unparsable cargo config {:?}: {}", path, e))
+        .ok()?;
+    let index = value.get("registries")?.get(name)?.get("index")?.as_str()?;
+    debug!("registry {} index {} from {:?}", name, index, path);
+    Some(index.to_string())
+}
+
+/// One page of a crates.io-style reverse dependency listing
+#[derive(Debug, Deserialize)]
+struct ReverseDependencyPage {
+    dependencies: Vec<ReverseDependencyLink>,
+    versions: Vec<DependentVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReverseDependencyLink {
+    version_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DependentVersion {
+    id: u64,
+    #[serde(rename = "crate")]
+    crate_name: String,
+    #[serde(default)]
+    downloads: u64,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+}
+
+/// Dependents on a reverse dependency page, and how many links the page held
+pub fn parse_reverse_dependencies(body: &str) -> Result<(Vec<ReverseDependency>, usize), String> {
+    let page: ReverseDependencyPage = serde_json::from_str(body)
+        .map_err(|e| format!("Unexpected reverse dependency response: {}", e))?;
+    let dependents = page.dependencies.iter()
+        .filter_map(|link| page.versions.iter().find(|v| v.id == link.version_id))
+        .map(|v| ReverseDependency { name: v.crate_name.clone(), downloads: v.downloads, published_at: v.created_at })
+        .collect();
+    Ok((dependents, page.dependencies.len()))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchPage {
+    crates: Vec<SearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    name: String,
+}
+
+/// Crate names on a search page
+pub fn parse_search(body: &str) -> Result<Vec<String>, String> {
+    let page: SearchPage = serde_json::from_str(body)
+        .map_err(|e| format!("Unexpected search response: {}", e))?;
+    Ok(page.crates.into_iter().map(|hit| hit.name).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(dl: &str) -> AltRegistry {
+        let config = format!(r#"{{"dl": "{}", "api": "https://kellnr.example.com/"}}"#, dl);
+        AltRegistry::new(Some("kellnr"), "sparse+https://kellnr.example.com/api/v1/cratesio/", &config).unwrap()
+    }
+
+    #[test]
+    fn test_urls() {
+        let kellnr = registry("https://kellnr.example.com/api/v1/crates/dl");
+        assert_eq!(kellnr.index_file_url("rgb"), "https://kellnr.example.com/api/v1/cratesio/3/r/rgb");
+        assert_eq!(kellnr.download_url("rgb", "0.8.50"), "https://kellnr.example.com/api/v1/crates/dl/rgb/0.8.50/download");
+        assert_eq!(kellnr.reverse_dependencies_url("rgb", 2, 100).unwrap(),
+            "https://kellnr.example.com/api/v1/crates/rgb/reverse_dependencies?page=2&per_page=100");
+        assert_eq!(kellnr.cargo_config_args()[1], "registries.kellnr.index=\"sparse+https://kellnr.example.com/api/v1/cratesio/\"");
+
+        let templated = registry("https://dl.example.com/{prefix}/{crate}/{crate}-{version}.crate");
+        assert_eq!(templated.download_url("Image", "0.25.8"), "https://dl.example.com/im/ag/Image/Image-0.25.8.crate");
+        assert_eq!(AltRegistry::config_url("sparse+https://example.com/index/"), "https://example.com/index/config.json");
+    }
+
+    #[test]
+    fn test_resolve_index_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path().join("crates").join("rgb");
+        fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(dir.path().join(".cargo").join("config.toml"),
+            "[registries.copter-test-private]\nindex = \"sparse+https://private.example.com/index/\"\n").unwrap();
+
+        assert_eq!(resolve_index_url(Some("copter-test-private"), None, &crate_dir).unwrap(), "sparse+https://private.example.com/index/");
+        assert_eq!(resolve_index_url(Some("copter-test-private"), Some("sparse+https://other.example.com/"), &crate_dir).unwrap(),
+            "sparse+https://other.example.com/");
+        assert!(resolve_index_url(Some("copter-test-missing"), None, &crate_dir).unwrap_err().contains("not configured"));
+        assert!(resolve_index_url(None, Some("https://github.com/example/index"), &crate_dir).unwrap_err().contains("sparse"));
+    }
+
+    #[test]
+    fn test_parse_reverse_dependencies() {
+        let body = r#"{
+            "dependencies": [{"id": 1, "version_id": 20, "crate_id": "rgb"}, {"id": 2, "version_id": 21, "crate_id": "rgb"}],
+            "versions": [
+                {"id": 21, "crate": "ravif", "num": "0.11.0", "downloads": 5},
+                {"id": 20, "crate": "image", "num": "0.25.8", "downloads": 900, "created_at": "2025-01-02T03:04:05Z"}
+            ],
+            "meta": {"total": 2}
+        }"#;
+        let (dependents, links) = parse_reverse_dependencies(body).unwrap();
+        assert_eq!(links, 2);
+        assert_eq!(dependents.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["image", "ravif"]);
+        assert_eq!(dependents[0].downloads, 900);
+        assert!(dependents[0].published_at.is_some());
+
+        assert_eq!(parse_search(r#"{"crates": [{"name": "image"}, {"name": "ravif"}], "meta": {"total": 2}}"#).unwrap(),
+            vec!["image", "ravif"]);
+    }
+}
@@ -1,15 +1,33 @@
-/// API module for interacting with crates.io
-///
-/// This module provides functions for fetching reverse dependencies,
-/// resolving versions, and downloading crate files.
-
+//! API module for interacting with crates.io
+//!
+//! This module provides functions for fetching reverse dependencies,
+//! resolving versions, and downloading crate files. Unpublished dependents
+//! can also be discovered through GitHub code search.
+//!
+//! Version data comes from the sparse index (index.crates.io), which isn't
+//! subject to the web API's 1 request/second limit; the web API is only
+//! needed for reverse-dependency listings, and as a fallback when the index
+//! can't be reached. With an alternate registry (`--registry`/`--index-url`)
+//! all of this goes to that registry instead. With `--offline` nothing is
+//! requested at all: lookups come from the response cache or fail.
+
+use chrono::{DateTime, Utc};
 use crates_io_api::SyncClient;
-use semver::Version;
+use semver::{Version, VersionReq};
 use std::io::Read;
+use std::sync::RwLock;
 use std::time::Duration;
 use log::debug;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+
+use crate::alt_registry::AltRegistry;
+use crate::api_cache::ApiCache;
+use crate::registry::{self, IndexEntry, RegistrySnapshot};
+
+pub const USER_AGENT: &str = "cargo-copter/0.1.1 (https://github.com/imazen/cargo-copter)";
 
-const USER_AGENT: &str = "cargo-copter/0.1.1 (https://github.com/imazen/cargo-copter)";
+const SPARSE_INDEX_URL: &str = "https://index.crates.io";
 
 lazy_static::lazy_static! {
     static ref CRATES_IO_CLIENT: SyncClient = {
@@ -18,54 +36,344 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Where lookups, downloads and cargo get crates from; set up before the run starts
+#[derive(Default)]
+struct Sources {
+    snapshot: Option<RegistrySnapshot>,
+    alt_registry: Option<AltRegistry>,
+    cache: Option<ApiCache>,
+    offline: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref SOURCES: RwLock<Sources> = RwLock::new(Sources::default());
+}
+
+/// Serve all lookups and downloads from a local registry snapshot instead of crates.io
+pub fn init_registry_snapshot(snapshot: RegistrySnapshot) {
+    SOURCES.write().unwrap().snapshot = Some(snapshot);
+}
+
+/// Look up dependents and versions in an alternate registry instead of crates.io,
+/// and patch the offered version over it
+pub fn init_alt_registry(registry: AltRegistry) {
+    SOURCES.write().unwrap().alt_registry = Some(registry);
+}
+
+/// Open the registry given by `--registry`/`--index-url`, reading its config.json
+pub fn open_alt_registry(name: Option<&str>, index_url: Option<&str>, cwd: &std::path::Path) -> Result<AltRegistry, String> {
+    let index_url = crate::alt_registry::resolve_index_url(name, index_url, cwd)?;
+    let config_url = AltRegistry::config_url(&index_url);
+    require_online(&config_url)?;
+    let token = name.and_then(crate::alt_registry::env_token);
+    let mut request = ureq::get(&config_url).set("User-Agent", USER_AGENT);
+    if let Some(ref token) = token {
+        request = request.set("Authorization", token);
+    }
+    let config = call(request)
+        .map_err(|e| request_error(&format!("Failed to fetch {}", config_url), &e))?
+        .into_string()
+        .map_err(|e| format!("Failed to read {}: {}", config_url, e))?;
+    AltRegistry::new(name, &index_url, &config)
+}
+
+/// Forbid network access (`--offline`): lookups are served from the response
+/// cache and cargo runs with `--offline`
+pub fn init_offline() {
+    SOURCES.write().unwrap().offline = true;
+}
+
+pub fn offline() -> bool {
+    SOURCES.read().unwrap().offline
+}
+
+/// Error for a lookup that would need the network in offline mode
+fn require_online(what: &str) -> Result<(), String> {
+    if offline() {
+        return Err(format!("{} is not cached (--offline)", what));
+    }
+    Ok(())
+}
+
+/// Name of the registry the offered version is patched over (`crates-io` by default)
+pub fn patch_registry() -> String {
+    SOURCES.read().unwrap().alt_registry
+        .as_ref()
+        .map(|r| r.name.clone())
+        .unwrap_or_else(|| "crates-io".to_string())
+}
+
+/// GET request to the alternate registry, with its token if there is one
+fn registry_get(registry: &AltRegistry, url: &str) -> ureq::Request {
+    let request = ureq::get(url).set("User-Agent", USER_AGENT);
+    match registry.token {
+        Some(ref token) => request.set("Authorization", token),
+        None => request,
+    }
+}
+
+/// Attempts per request before a rate limit or server error is given up on
+const MAX_ATTEMPTS: u32 = 5;
+/// Delay before the first retry, doubled for each one after it
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Marks errors that persisted through every retry; reported as NETWORK, not BROKEN
+pub const NETWORK_MARKER: &str = "NETWORK:";
+
+/// Whether a failed request is worth sending again
+enum Retry {
+    No,
+    /// Yes, after the server's `Retry-After` if it sent one, else after the backoff
+    After(Option<Duration>),
+}
+
+/// Run `send` until it succeeds, fails for good, or runs out of attempts,
+/// sleeping with exponential backoff and jitter between attempts
+fn with_retries<T, E: std::fmt::Display>(mut send: impl FnMut() -> Result<T, E>, retry: impl Fn(&E) -> Retry) -> Result<T, E> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let err = match send() {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        let delay = match retry(&err) {
+            Retry::After(_) if attempt >= MAX_ATTEMPTS => return Err(err),
+            Retry::No => return Err(err),
+            Retry::After(Some(retry_after)) => retry_after.min(MAX_BACKOFF),
+            Retry::After(None) => jitter(backoff),
+        };
+        debug!("request failed ({}), retrying in {:?} (attempt {}/{})", err, delay, attempt + 1, MAX_ATTEMPTS);
+        std::thread::sleep(delay);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        attempt += 1;
+    }
+}
+
+/// Somewhere between half of `backoff` and all of it, so parallel workers
+/// that were throttled together don't retry together
+fn jitter(backoff: Duration) -> Duration {
+    use std::hash::{BuildHasher, Hasher};
+    let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    backoff / 2 + backoff.mul_f64((random % 1000) as f64 / 2000.0)
+}
+
+/// Seconds form of a `Retry-After` header; HTTP dates fall back to the backoff
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Rate limits, server errors and transport failures (DNS, connection, timeouts) are retried
+fn retry_ureq(e: &ureq::Error) -> Retry {
+    match e {
+        ureq::Error::Status(429 | 500 | 502 | 503 | 504, resp) => {
+            Retry::After(resp.header("Retry-After").and_then(parse_retry_after))
+        }
+        ureq::Error::Status(..) => Retry::No,
+        ureq::Error::Transport(_) => Retry::After(None),
+    }
+}
+
+fn retry_crates_io(e: &crates_io_api::Error) -> Retry {
+    match e {
+        crates_io_api::Error::Http(e) if e.status().is_none_or(|s| s.as_u16() == 429 || s.is_server_error()) => Retry::After(None),
+        _ => Retry::No,
+    }
+}
+
+/// Send a request, retrying the failures `retry_ureq` allows
+fn call(request: ureq::Request) -> Result<ureq::Response, Box<ureq::Error>> {
+    with_retries(|| request.clone().call().map_err(Box::new), |e| retry_ureq(e))
+}
+
+/// Error message for a failed request, with NETWORK_MARKER in front when
+/// it failed for reasons retrying couldn't fix
+fn request_error(context: &str, e: &ureq::Error) -> String {
+    match retry_ureq(e) {
+        Retry::After(_) => format!("{} {}: {}", NETWORK_MARKER, context, e),
+        Retry::No => format!("{}: {}", context, e),
+    }
+}
+
+/// `request_error` for crates.io web API calls
+fn crates_io_error(context: &str, e: &crates_io_api::Error) -> String {
+    match retry_crates_io(e) {
+        Retry::After(_) => format!("{} {}: {}", NETWORK_MARKER, context, e),
+        Retry::No => format!("{}: {}", context, e),
+    }
+}
+
+/// Reuse crates.io responses from an on-disk cache (not used with a registry snapshot)
+pub fn init_api_cache(cache: ApiCache) {
+    SOURCES.write().unwrap().cache = Some(cache);
+}
+
+impl Sources {
+    /// Cache entries of an alternate registry are kept apart from crates.io's
+    fn cache_kind(&self, kind: &str) -> String {
+        match self.alt_registry {
+            Some(ref registry) => format!("{}-{}", kind, registry.name),
+            None => kind.to_string(),
+        }
+    }
+}
+
+fn cache_get<T: DeserializeOwned>(kind: &str, key: &str) -> Option<T> {
+    let sources = SOURCES.read().unwrap();
+    sources.cache.as_ref()?.get(&sources.cache_kind(kind), key)
+}
+
+fn cache_put<T: Serialize>(kind: &str, key: &str, value: &T) {
+    let sources = SOURCES.read().unwrap();
+    if let Some(ref cache) = sources.cache {
+        cache.put(&sources.cache_kind(kind), key, value);
+    }
+}
+
+/// Arguments every cargo invocation needs (source replacement when running
+/// from a registry snapshot, the alternate registry's index, `--offline`)
+pub fn cargo_source_args() -> Vec<String> {
+    let sources = SOURCES.read().unwrap();
+    let mut args: Vec<String> = sources.snapshot
+        .as_ref()
+        .map(|s| s.cargo_config_args())
+        .unwrap_or_default();
+    if let Some(ref registry) = sources.alt_registry {
+        args.extend(registry.cargo_config_args());
+    }
+    if sources.offline {
+        args.push("--offline".to_string());
+    }
+    args
+}
+
 /// A reverse dependency (crate that depends on our crate)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReverseDependency {
     pub name: String,
     pub downloads: u64,
+    /// When the dependent's version was published (unknown in registry snapshots)
+    #[serde(default)]
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+/// Criteria a discovered dependent must meet to be tested
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependentFilter {
+    /// Minimum downloads of the dependent's version
+    pub min_downloads: u64,
+    /// Only dependents with a release in the last N days
+    pub updated_within_days: Option<u64>,
+}
+
+impl DependentFilter {
+    fn accepts(&self, dep: &ReverseDependency, now: DateTime<Utc>) -> bool {
+        if dep.downloads < self.min_downloads {
+            return false;
+        }
+        match (self.updated_within_days, dep.published_at) {
+            (Some(days), Some(published)) => now.signed_duration_since(published).num_days() < days as i64,
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Distinguishes cached listings taken with different filters
+    fn cache_suffix(&self) -> String {
+        let mut suffix = String::new();
+        if self.min_downloads > 0 {
+            suffix.push_str(&format!("-dl{}", self.min_downloads));
+        }
+        if let Some(days) = self.updated_within_days {
+            suffix.push_str(&format!("-within{}d", days));
+        }
+        suffix
+    }
 }
 
 /// Get reverse dependencies with pagination and optional limiting
 ///
 /// This uses the paginated API to avoid downloading all reverse deps at once.
-/// Results are sorted by download count descending and limited to the requested amount.
+/// Dependents rejected by `filter` are skipped without counting toward the
+/// limit. Results are sorted by download count descending and limited to the
+/// requested amount.
 ///
 /// # Arguments
 /// * `crate_name` - The crate to find reverse dependencies for
 /// * `limit` - Maximum number of dependents to return (default: all)
+/// * `filter` - Download and recency criteria
 pub fn get_reverse_dependencies(
     crate_name: &str,
     limit: Option<usize>,
+    filter: &DependentFilter,
 ) -> Result<Vec<ReverseDependency>, String> {
     debug!("fetching reverse dependencies for {}", crate_name);
 
+    if let Some(snapshot) = SOURCES.read().unwrap().snapshot.as_ref() {
+        // The snapshot has no download counts; take dependents by name
+        let mut names = snapshot.reverse_dependencies(crate_name)?;
+        if let Some(lim) = limit {
+            names.truncate(lim);
+        }
+        return Ok(names.into_iter().map(|name| ReverseDependency { name, downloads: 0, published_at: None }).collect());
+    }
+
+    let cache_key = match limit {
+        Some(lim) => format!("{}-{}{}", crate_name, lim, filter.cache_suffix()),
+        None => format!("{}-all{}", crate_name, filter.cache_suffix()),
+    };
+    if let Some(cached) = cache_get("reverse-deps", &cache_key) {
+        return Ok(cached);
+    }
+    require_online(&format!("The list of dependents of {} (for this --top-dependents and filter)", crate_name))?;
+
+    let now = Utc::now();
     let mut all_deps = Vec::new();
 
     // The API returns 100 items per page by default
     let per_page = 100;
 
-    // Determine how many pages we need
+    // Determine how many pages we need (unknown when dependents are filtered out)
     let max_pages = match limit {
-        Some(lim) => ((lim + per_page - 1) / per_page), // Round up
-        None => 100, // Safety limit: don't fetch more than 10,000 deps
+        Some(lim) if *filter == DependentFilter::default() => lim.div_ceil(per_page), // Round up
+        _ => 100, // Safety limit: don't fetch more than 10,000 deps
     };
 
+    let alt_registry = SOURCES.read().unwrap().alt_registry.clone();
     for page in 1..=max_pages {
         debug!("fetching page {} of reverse dependencies", page);
 
-        let deps = CRATES_IO_CLIENT
-            .crate_reverse_dependencies_page(crate_name, page as u64)
-            .map_err(|e| format!("Failed to fetch reverse dependencies: {}", e))?;
-
-        let page_size = deps.dependencies.len();
+        let (deps, page_size) = match alt_registry {
+            Some(ref registry) => match alt_reverse_dependencies_page(registry, crate_name, page, per_page)? {
+                Some(page) => page,
+                None => {
+                    // No reverse dependency endpoint: look through every crate instead
+                    all_deps = scan_alt_registry(registry, crate_name)?;
+                    break;
+                }
+            },
+            None => {
+                let deps = with_retries(|| CRATES_IO_CLIENT.crate_reverse_dependencies_page(crate_name, page as u64), retry_crates_io)
+                    .map_err(|e| crates_io_error("Failed to fetch reverse dependencies", &e))?;
+                let page_size = deps.dependencies.len();
+                let deps = deps.dependencies.into_iter().map(|dep| ReverseDependency {
+                    name: dep.crate_version.crate_name.clone(),
+                    downloads: dep.crate_version.downloads,
+                    published_at: Some(dep.crate_version.created_at),
+                }).collect::<Vec<_>>();
+                (deps, page_size)
+            }
+        };
         debug!("got {} dependencies on page {}", page_size, page);
 
         // Extract dependency info
-        for dep in deps.dependencies {
-            all_deps.push(ReverseDependency {
-                name: dep.crate_version.crate_name.clone(),
-                downloads: dep.crate_version.downloads,
-            });
+        for dep in deps {
+            if filter.accepts(&dep, now) {
+                all_deps.push(dep);
+            } else {
+                debug!("skipping {} ({} downloads, published {:?})", dep.name, dep.downloads, dep.published_at);
+            }
         }
 
         // If we got less than expected, we've reached the end
@@ -95,52 +403,413 @@ pub fn get_reverse_dependencies(
         crate_name
     );
 
+    cache_put("reverse-deps", &cache_key, &all_deps);
     Ok(all_deps)
 }
 
+/// One page of the alternate registry's reverse dependencies; None when it
+/// has no such endpoint
+fn alt_reverse_dependencies_page(
+    registry: &AltRegistry,
+    crate_name: &str,
+    page: usize,
+    per_page: usize,
+) -> Result<Option<(Vec<ReverseDependency>, usize)>, String> {
+    let Some(url) = registry.reverse_dependencies_url(crate_name, page, per_page) else {
+        return Ok(None);
+    };
+    debug!("fetching {}", url);
+    let body = match call(registry_get(registry, &url)) {
+        Ok(resp) => resp.into_string().map_err(|e| format!("Failed to read {}: {}", url, e))?,
+        Err(e) => match *e {
+            ureq::Error::Status(code @ (404 | 405 | 501), _) => {
+                debug!("{} has no reverse dependency endpoint ({})", registry.name, code);
+                return Ok(None);
+            }
+            e => return Err(request_error("Failed to fetch reverse dependencies", &e)),
+        },
+    };
+    crate::budget::record_download(body.len() as u64);
+    crate::alt_registry::parse_reverse_dependencies(&body).map(Some)
+}
+
+/// Crates of the alternate registry whose newest version depends on
+/// `crate_name`, found by listing every crate and reading its index file;
+/// fine for the size of a private registry
+fn scan_alt_registry(registry: &AltRegistry, crate_name: &str) -> Result<Vec<ReverseDependency>, String> {
+    const PER_PAGE: usize = 100;
+    let mut names = Vec::new();
+    for page in 1.. {
+        let url = registry.all_crates_url(page, PER_PAGE)
+            .ok_or_else(|| format!("Registry {} has no web API to discover dependents with; use --dependents", registry.name))?;
+        let body = call(registry_get(registry, &url))
+            .map_err(|e| request_error(&format!("Failed to list crates of {}", registry.name), &e))?
+            .into_string()
+            .map_err(|e| format!("Failed to read {}: {}", url, e))?;
+        let crates = crate::alt_registry::parse_search(&body)?;
+        let done = crates.len() < PER_PAGE;
+        names.extend(crates);
+        if done {
+            break;
+        }
+    }
+
+    let mut dependents = Vec::new();
+    for name in names {
+        match sparse_index_versions(&name) {
+            Ok(entries) => {
+                if registry::latest_entry(entries).is_some_and(|latest| latest.depends_on(crate_name)) {
+                    dependents.push(ReverseDependency { name, downloads: 0, published_at: None });
+                }
+            }
+            Err(e) => debug!("skipping {}: {}", name, e),
+        }
+    }
+    Ok(dependents)
+}
+
 /// Get top N reverse dependencies sorted by download count
 ///
 /// # Arguments
 /// * `crate_name` - The crate to find reverse dependencies for
 /// * `limit` - Number of top dependents to return
+/// * `filter` - Download and recency criteria
 pub fn get_top_dependents(
     crate_name: &str,
     limit: usize,
+    filter: &DependentFilter,
 ) -> Result<Vec<ReverseDependency>, String> {
-    get_reverse_dependencies(crate_name, Some(limit))
+    get_reverse_dependencies(crate_name, Some(limit), filter)
+}
+
+/// An unpublished dependent found by GitHub code search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubDependent {
+    /// `package.name` of the manifest that depends on our crate
+    pub name: String,
+    /// Clone URL of the repository
+    pub repo_url: String,
+}
+
+/// One code search hit: a Cargo.toml mentioning the crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CodeSearchHit {
+    full_name: String,
+    html_url: String,
+    path: String,
+}
+
+/// Find repositories whose Cargo.toml depends on `crate_name` via GitHub code search
+///
+/// Code search needs a token and only matches text, so each hit's manifest is
+/// fetched to confirm it really depends on the crate (renames included) and
+/// to learn the package name. Forks and manifests without a `[package]` are
+/// skipped. Returns at most `limit` dependents, one per package name.
+pub fn search_github_dependents(
+    crate_name: &str,
+    limit: usize,
+    token: &str,
+) -> Result<Vec<GitHubDependent>, String> {
+    debug!("searching GitHub for dependents of {}", crate_name);
+    require_online("GitHub code search")?;
+
+    let query = format!("\"{}\" filename:Cargo.toml", crate_name);
+    let mut found: Vec<GitHubDependent> = Vec::new();
+
+    // Code search serves at most 1000 results, 100 per page
+    for page in 1..=10 {
+        let resp = call(ureq::get("https://api.github.com/search/code")
+            .set("User-Agent", USER_AGENT)
+            .set("Accept", "application/vnd.github+json")
+            .set("Authorization", &format!("Bearer {}", token))
+            .query("q", &query)
+            .query("per_page", "100")
+            .query("page", &page.to_string()))
+            .map_err(|e| request_error("GitHub code search failed", &e))?;
+        let body: serde_json::Value = serde_json::from_reader(resp.into_reader())
+            .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
+
+        let hits = parse_code_search(&body);
+        if hits.is_empty() {
+            break;
+        }
+
+        for hit in hits {
+            let url = format!("https://raw.githubusercontent.com/{}/HEAD/{}", hit.full_name, hit.path);
+            let manifest = match call(ureq::get(&url).set("User-Agent", USER_AGENT)) {
+                Ok(resp) => match resp.into_string() {
+                    Ok(m) => m,
+                    Err(e) => {
+                        debug!("Failed to read {}: {}", url, e);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    debug!("Failed to fetch {}: {}", url, e);
+                    continue;
+                }
+            };
+
+            if let Some(name) = manifest_dependent_name(&manifest, crate_name) {
+                if name != crate_name && !found.iter().any(|d| d.name == name) {
+                    debug!("found GitHub dependent {} in {}", name, hit.html_url);
+                    found.push(GitHubDependent { name, repo_url: hit.html_url });
+                    if found.len() >= limit {
+                        return Ok(found);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Non-fork hits of a code search response
+fn parse_code_search(body: &serde_json::Value) -> Vec<CodeSearchHit> {
+    let items = match body.get("items").and_then(|i| i.as_array()) {
+        Some(items) => items,
+        None => return Vec::new(),
+    };
+
+    items.iter().filter_map(|item| {
+        let repo = item.get("repository")?;
+        if repo.get("fork").and_then(|f| f.as_bool()).unwrap_or(false) {
+            return None;
+        }
+        Some(CodeSearchHit {
+            full_name: repo.get("full_name")?.as_str()?.to_string(),
+            html_url: repo.get("html_url")?.as_str()?.to_string(),
+            path: item.get("path")?.as_str()?.to_string(),
+        })
+    }).collect()
+}
+
+/// Package name of `manifest` if it depends on `crate_name` in any dependency table
+fn manifest_dependent_name(manifest: &str, crate_name: &str) -> Option<String> {
+    let value = crate::manifest::parse_manifest(manifest.as_bytes()).ok()?;
+    let name = value.get("package")?.get("name")?.as_str()?.to_string();
+
+    let depends = |table: &toml::Value| {
+        ["dependencies", "dev-dependencies", "build-dependencies"].iter().any(|kind| {
+            table.get(*kind).and_then(|d| d.as_table()).map(|deps| {
+                deps.iter().any(|(key, spec)| {
+                    let package = spec.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+                    package == crate_name
+                })
+            }).unwrap_or(false)
+        })
+    };
+
+    let target_depends = value.get("target")
+        .and_then(|t| t.as_table())
+        .map(|targets| targets.values().any(depends))
+        .unwrap_or(false);
+
+    if depends(&value) || target_depends {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Which versions count when picking a dependent's newest (`--dependent-prerelease`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PrereleasePolicy {
+    /// Releases only
+    Exclude,
+    /// Releases and pre-releases, whichever is newest
+    #[default]
+    Include,
+    /// Pre-releases only, to test release candidates
+    Only,
+}
+
+impl PrereleasePolicy {
+    pub fn accepts(&self, version: &Version) -> bool {
+        match self {
+            PrereleasePolicy::Exclude => version.pre.is_empty(),
+            PrereleasePolicy::Include => true,
+            PrereleasePolicy::Only => !version.pre.is_empty(),
+        }
+    }
 }
 
 /// Resolve the latest version of a crate from crates.io
 ///
 /// # Arguments
 /// * `crate_name` - The crate name to resolve
+/// * `prerelease` - Whether pre-releases are candidates
 ///
 /// # Returns
-/// The latest version of the crate
-pub fn resolve_latest_version(crate_name: &str) -> Result<Version, String> {
-    debug!("resolving latest version for {}", crate_name);
-
-    let krate = CRATES_IO_CLIENT
-        .get_crate(crate_name)
-        .map_err(|e| format!("Failed to get crate info: {}", e))?;
-
-    // Pull out the version numbers and sort them
-    let versions: Vec<Version> = krate
-        .versions
-        .iter()
-        .filter_map(|v| Version::parse(&v.num).ok())
-        .collect();
+/// The latest version of the crate, None if no version qualifies
+pub fn resolve_latest_version(crate_name: &str, prerelease: PrereleasePolicy) -> Result<Option<Version>, String> {
+    debug!("resolving latest version for {} (pre-releases: {:?})", crate_name, prerelease);
 
+    let versions = list_versions(crate_name)?;
     if versions.is_empty() {
         return Err(format!("No versions found for crate {}", crate_name));
     }
 
-    let mut sorted_versions = versions;
-    sorted_versions.sort();
+    Ok(versions
+        .into_iter()
+        .map(|(version, _)| version)
+        .filter(|version| prerelease.accepts(version))
+        .max())
+}
+
+/// Resolve the highest published, non-yanked version matching a requirement
+///
+/// This is what a fresh `cargo generate-lockfile` would pick for a lone
+/// dependency; used as the baseline when a dependent's own resolution can't
+/// be read.
+///
+/// # Arguments
+/// * `crate_name` - The crate name to resolve
+/// * `req` - Version requirement (e.g. "^0.8.52")
+pub fn resolve_max_matching_version(crate_name: &str, req: &VersionReq) -> Result<Version, String> {
+    debug!("resolving {} {} against crates.io", crate_name, req);
+
+    let versions = list_versions(crate_name)?;
+    max_matching_version(&versions, req)
+        .ok_or_else(|| format!("No published version of {} matches {}", crate_name, req))
+}
+
+/// All published versions of a crate, with their yanked flag
+///
+/// # Arguments
+/// * `crate_name` - The crate name to look up
+pub fn list_versions(crate_name: &str) -> Result<Vec<(Version, bool)>, String> {
+    let snapshot_versions = SOURCES.read().unwrap().snapshot
+        .as_ref()
+        .map(|snapshot| snapshot.versions(crate_name))
+        .transpose()?;
+
+    let versions: Vec<(String, bool)> = match snapshot_versions {
+        Some(entries) => entries.into_iter().map(|e| (e.vers, e.yanked)).collect(),
+        None => match cache_get("versions", crate_name) {
+            Some(cached) => cached,
+            None => {
+                let fetched = fetch_versions(crate_name)?;
+                cache_put("versions", crate_name, &fetched);
+                fetched
+            }
+        },
+    };
+
+    Ok(parse_versions(versions))
+}
+
+/// All published versions of a crate, bypassing the response cache
+///
+/// For noticing versions published since the cached list was fetched; the
+/// fresh list replaces the cached one.
+pub fn refresh_versions(crate_name: &str) -> Result<Vec<(Version, bool)>, String> {
+    if SOURCES.read().unwrap().snapshot.is_some() || offline() {
+        return list_versions(crate_name);
+    }
+    let fetched = fetch_versions(crate_name)?;
+    cache_put("versions", crate_name, &fetched);
+    Ok(parse_versions(fetched))
+}
+
+fn parse_versions(versions: Vec<(String, bool)>) -> Vec<(Version, bool)> {
+    versions
+        .into_iter()
+        .filter_map(|(num, yanked)| Version::parse(&num).ok().map(|parsed| (parsed, yanked)))
+        .collect()
+}
+
+/// Version numbers and yanked flags from the sparse index, or from the web
+/// API when the index can't be reached
+fn fetch_versions(crate_name: &str) -> Result<Vec<(String, bool)>, String> {
+    require_online(&format!("The version list of {}", crate_name))?;
+    if SOURCES.read().unwrap().alt_registry.is_some() {
+        return Ok(sparse_index_versions(crate_name)?.into_iter().map(|e| (e.vers, e.yanked)).collect());
+    }
+    match sparse_index_versions(crate_name) {
+        Ok(entries) => return Ok(entries.into_iter().map(|e| (e.vers, e.yanked)).collect()),
+        Err(e) => debug!("sparse index lookup failed, falling back to the web API: {}", e),
+    }
+
+    let krate = with_retries(|| CRATES_IO_CLIENT.get_crate(crate_name), retry_crates_io)
+        .map_err(|e| crates_io_error("Failed to get crate info", &e))?;
 
-    Ok(sorted_versions
-        .pop()
-        .expect("versions vec should not be empty"))
+    Ok(krate.versions.into_iter().map(|v| (v.num, v.yanked)).collect())
+}
+
+/// Newest release of a crate on crates.io, and whether its GitHub repository is archived
+///
+/// GitHub is asked with `token` if there is one; without, its rate limit is
+/// low, and a repository that can't be looked up counts as not archived.
+pub fn crate_activity(crate_name: &str, token: Option<&str>) -> Result<crate::maintenance::CrateActivity, String> {
+    let crates_io = {
+        let sources = SOURCES.read().unwrap();
+        sources.snapshot.is_none() && sources.alt_registry.is_none()
+    };
+    if !crates_io {
+        return Err("release dates are only known for crates.io".to_string());
+    }
+    if let Some(cached) = cache_get("activity", crate_name) {
+        return Ok(cached);
+    }
+    require_online(&format!("The release history of {}", crate_name))?;
+
+    let krate = with_retries(|| CRATES_IO_CLIENT.get_crate(crate_name), retry_crates_io)
+        .map_err(|e| format!("Failed to get crate info: {}", e))?;
+    let last_release = krate.versions.iter().filter(|v| !v.yanked).map(|v| v.created_at).max();
+
+    let repo = krate.crate_data.repository.as_deref().and_then(crate::maintenance::github_repo);
+    let archived = repo.is_some_and(|repo| {
+        let mut request = ureq::get(&format!("https://api.github.com/repos/{}", repo))
+            .set("User-Agent", USER_AGENT)
+            .set("Accept", "application/vnd.github+json");
+        if let Some(token) = token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        match call(request).map(|resp| serde_json::from_reader::<_, serde_json::Value>(resp.into_reader())) {
+            Ok(Ok(body)) => body["archived"].as_bool().unwrap_or(false),
+            Ok(Err(e)) => {
+                debug!("Failed to parse GitHub response for {}: {}", repo, e);
+                false
+            }
+            Err(e) => {
+                debug!("Failed to look up {} on GitHub: {}", repo, e);
+                false
+            }
+        }
+    });
+
+    let activity = crate::maintenance::CrateActivity { last_release, archived };
+    cache_put("activity", crate_name, &activity);
+    Ok(activity)
+}
+
+/// Every version of a crate from the sparse index
+fn sparse_index_versions(crate_name: &str) -> Result<Vec<IndexEntry>, String> {
+    let alt_registry = SOURCES.read().unwrap().alt_registry.clone();
+    let request = match alt_registry {
+        Some(ref registry) => registry_get(registry, &registry.index_file_url(crate_name)),
+        None => ureq::get(&format!("{}/{}", SPARSE_INDEX_URL, registry::index_path(crate_name))).set("User-Agent", USER_AGENT),
+    };
+    let url = request.url().to_string();
+    debug!("fetching {}", url);
+
+    let body = call(request)
+        .map_err(|e| request_error(&format!("Failed to fetch {}", url), &e))?
+        .into_string()
+        .map_err(|e| format!("Failed to read {}: {}", url, e))?;
+    crate::budget::record_download(body.len() as u64);
+    registry::parse_index(&body)
+}
+
+/// Highest non-yanked version satisfying `req` (pre-releases only if `req` names one)
+fn max_matching_version(versions: &[(Version, bool)], req: &VersionReq) -> Option<Version> {
+    versions
+        .iter()
+        .filter(|(version, yanked)| !yanked && req.matches(version))
+        .map(|(version, _)| version.clone())
+        .max()
 }
 
 /// Download a .crate file from crates.io
@@ -154,15 +823,25 @@ pub fn resolve_latest_version(crate_name: &str) -> Result<Version, String> {
 pub fn download_crate(crate_name: &str, version: &str) -> Result<Vec<u8>, String> {
     debug!("downloading {}-{}.crate", crate_name, version);
 
-    let url = format!(
-        "https://crates.io/api/v1/crates/{}/{}/download",
-        crate_name, version
-    );
+    if let Some(snapshot) = SOURCES.read().unwrap().snapshot.as_ref() {
+        return snapshot.read_crate(crate_name, version);
+    }
 
-    let resp = ureq::get(&url)
-        .set("User-Agent", USER_AGENT)
-        .call()
-        .map_err(|e| format!("HTTP request failed: {}", e))?;
+    require_online(&format!("{}-{}.crate", crate_name, version))?;
+    let alt_registry = SOURCES.read().unwrap().alt_registry.clone();
+    let request = match alt_registry {
+        Some(ref registry) => registry_get(registry, &registry.download_url(crate_name, version)),
+        None => {
+            let url = format!(
+                "https://crates.io/api/v1/crates/{}/{}/download",
+                crate_name, version
+            );
+            ureq::get(&url).set("User-Agent", USER_AGENT)
+        }
+    };
+
+    let resp = call(request)
+        .map_err(|e| request_error("HTTP request failed", &e))?;
 
     let len = resp
         .header("Content-Length")
@@ -180,6 +859,7 @@ pub fn download_crate(crate_name: &str, version: &str) -> Result<Vec<u8>, String
         crate_name,
         version
     );
+    crate::budget::record_download(data.len() as u64);
 
     Ok(data)
 }
@@ -194,14 +874,23 @@ mod tests {
     #[test]
     #[ignore] // Requires network access
     fn test_resolve_latest_version() {
-        let version = resolve_latest_version("serde").unwrap();
+        let version = resolve_latest_version("serde", PrereleasePolicy::Exclude).unwrap().unwrap();
         assert!(version >= Version::parse("1.0.0").unwrap());
     }
 
+    #[test]
+    fn test_prerelease_policy() {
+        let release = Version::parse("0.25.8").unwrap();
+        let candidate = Version::parse("0.26.0-rc.1").unwrap();
+        assert!(PrereleasePolicy::Exclude.accepts(&release) && !PrereleasePolicy::Exclude.accepts(&candidate));
+        assert!(PrereleasePolicy::Include.accepts(&release) && PrereleasePolicy::Include.accepts(&candidate));
+        assert!(!PrereleasePolicy::Only.accepts(&release) && PrereleasePolicy::Only.accepts(&candidate));
+    }
+
     #[test]
     #[ignore] // Requires network access
     fn test_get_top_dependents() {
-        let deps = get_top_dependents("serde", 5).unwrap();
+        let deps = get_top_dependents("serde", 5, &DependentFilter::default()).unwrap();
         assert_eq!(deps.len(), 5);
 
         // Should be sorted by downloads descending
@@ -213,17 +902,114 @@ mod tests {
     #[test]
     #[ignore] // Requires network access
     fn test_get_reverse_dependencies_with_limit() {
-        let deps = get_reverse_dependencies("log", Some(10)).unwrap();
+        let deps = get_reverse_dependencies("log", Some(10), &DependentFilter::default()).unwrap();
         assert_eq!(deps.len(), 10);
     }
 
+    #[test]
+    fn test_max_matching_version() {
+        let versions: Vec<(Version, bool)> = [
+            ("0.8.50", false), ("0.8.52", false), ("0.8.53", true),
+            ("0.9.0", false), ("0.8.60-rc.1", false),
+        ]
+        .iter()
+        .map(|(v, yanked)| (Version::parse(v).unwrap(), *yanked))
+        .collect();
+
+        let req = VersionReq::parse("^0.8.51").unwrap();
+        assert_eq!(max_matching_version(&versions, &req), Some(Version::parse("0.8.52").unwrap()));
+
+        let req = VersionReq::parse("^1").unwrap();
+        assert_eq!(max_matching_version(&versions, &req), None);
+    }
+
+    #[test]
+    fn test_parse_code_search() {
+        let body = serde_json::json!({
+            "total_count": 2,
+            "items": [
+                {"path": "app/Cargo.toml", "repository": {"full_name": "a/viewer", "html_url": "https://github.com/a/viewer", "fork": false}},
+                {"path": "Cargo.toml", "repository": {"full_name": "b/viewer", "html_url": "https://github.com/b/viewer", "fork": true}}
+            ]
+        });
+        assert_eq!(parse_code_search(&body), vec![CodeSearchHit {
+            full_name: "a/viewer".to_string(),
+            html_url: "https://github.com/a/viewer".to_string(),
+            path: "app/Cargo.toml".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_manifest_dependent_name() {
+        let direct = "[package]\nname = \"viewer\"\n[dependencies]\nrgb = \"0.8\"\n";
+        assert_eq!(manifest_dependent_name(direct, "rgb").as_deref(), Some("viewer"));
+
+        let renamed = "[package]\nname = \"viewer\"\n[target.'cfg(unix)'.dependencies]\ncolors = { version = \"0.8\", package = \"rgb\" }\n";
+        assert_eq!(manifest_dependent_name(renamed, "rgb").as_deref(), Some("viewer"));
+
+        // Mentions the name without depending on it
+        let mention = "[package]\nname = \"viewer\"\ndescription = \"not rgb\"\n[dependencies]\nrgb2 = \"1\"\n";
+        assert_eq!(manifest_dependent_name(mention, "rgb"), None);
+
+        let workspace = "[workspace]\nmembers = [\"app\"]\n[workspace.dependencies]\nrgb = \"0.8\"\n";
+        assert_eq!(manifest_dependent_name(workspace, "rgb"), None);
+    }
+
     #[test]
     fn test_reverse_dependency_structure() {
         let dep = ReverseDependency {
             name: "test-crate".to_string(),
             downloads: 1000,
+            published_at: None,
         };
         assert_eq!(dep.name, "test-crate");
         assert_eq!(dep.downloads, 1000);
     }
+
+    #[test]
+    fn test_dependent_filter() {
+        let now: DateTime<Utc> = "2025-06-01T00:00:00Z".parse().unwrap();
+        let dep = |downloads, published: Option<&str>| ReverseDependency {
+            name: "dep".to_string(),
+            downloads,
+            published_at: published.map(|p| p.parse().unwrap()),
+        };
+
+        assert!(DependentFilter::default().accepts(&dep(0, None), now));
+
+        let filter = DependentFilter { min_downloads: 1000, updated_within_days: Some(365) };
+        assert!(filter.accepts(&dep(5000, Some("2025-01-15T00:00:00Z")), now));
+        assert!(!filter.accepts(&dep(999, Some("2025-01-15T00:00:00Z")), now));  // Too few downloads
+        assert!(!filter.accepts(&dep(5000, Some("2021-03-01T00:00:00Z")), now));  // Abandoned
+        assert!(!filter.accepts(&dep(5000, None), now));  // Release date unknown
+        assert_eq!(filter.cache_suffix(), "-dl1000-within365d");
+    }
+
+    #[test]
+    fn test_with_retries() {
+        // A zero Retry-After keeps the test from sleeping
+        let mut attempts = 0;
+        let result: Result<(), String> = with_retries(|| { attempts += 1; Err("503".to_string()) }, |_| Retry::After(Some(Duration::ZERO)));
+        assert!(result.is_err());
+        assert_eq!(attempts, MAX_ATTEMPTS);
+
+        let mut attempts = 0;
+        let result = with_retries(|| { attempts += 1; if attempts < 3 { Err("429") } else { Ok(attempts) } }, |_| Retry::After(Some(Duration::ZERO)));
+        assert_eq!(result, Ok(3));
+
+        let mut attempts = 0;
+        let result: Result<(), &str> = with_retries(|| { attempts += 1; Err("404") }, |_| Retry::No);
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_retry_delays() {
+        assert_eq!(parse_retry_after(" 30 "), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        for _ in 0..20 {
+            let delay = jitter(Duration::from_secs(8));
+            assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(8), "{:?}", delay);
+        }
+    }
 }
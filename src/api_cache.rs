@@ -0,0 +1,95 @@
+//! On-disk cache of crates.io responses
+//!
+//! Reverse-dependency lists and version lists are stored as JSON under
+//! `{dir}/{kind}/{key}.json` and reused until they are older than the TTL,
+//! so iterating on the same crate doesn't re-query crates.io every run.
+//! Entries that can't be read or parsed are treated as misses.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ApiCache {
+    pub fn new(dir: &Path, ttl: Duration) -> Self {
+        ApiCache { dir: dir.to_path_buf(), ttl }
+    }
+
+    fn entry_path(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir.join(kind).join(format!("{}.json", key))
+    }
+
+    /// Cached value, if present and younger than the TTL
+    pub fn get<T: DeserializeOwned>(&self, kind: &str, key: &str) -> Option<T> {
+        let path = self.entry_path(kind, key);
+        let age = fs::metadata(&path).ok()?
+            .modified().ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+        if age >= self.ttl {
+            debug!("api cache entry {:?} is stale ({}s old)", path, age.as_secs());
+            return None;
+        }
+
+        let content = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(value) => {
+                debug!("api cache hit: {:?}", path);
+                Some(value)
+            }
+            Err(e) => {
+                debug!("ignoring unreadable api cache entry {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Store a value; failures only cost a refetch next time, so they are logged and ignored
+    pub fn put<T: Serialize>(&self, kind: &str, key: &str, value: &T) {
+        let path = self.entry_path(kind, key);
+        let result = fs::create_dir_all(path.parent().unwrap())
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_string(value).map_err(|e| e.to_string()))
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            debug!("failed to write api cache entry {:?}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ApiCache::new(dir.path(), Duration::from_secs(3600));
+
+        assert_eq!(cache.get::<Vec<String>>("versions", "rgb"), None);
+        cache.put("versions", "rgb", &vec!["0.8.50".to_string(), "0.8.52".to_string()]);
+        assert_eq!(cache.get::<Vec<String>>("versions", "rgb"), Some(vec!["0.8.50".to_string(), "0.8.52".to_string()]));
+        assert!(dir.path().join("versions").join("rgb.json").is_file());
+
+        // Same files, but nothing is fresh with a zero TTL
+        let expired = ApiCache::new(dir.path(), Duration::ZERO);
+        assert_eq!(expired.get::<Vec<String>>("versions", "rgb"), None);
+    }
+
+    #[test]
+    fn test_corrupt_entry_is_a_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ApiCache::new(dir.path(), Duration::from_secs(3600));
+        fs::create_dir_all(dir.path().join("versions")).unwrap();
+        fs::write(dir.path().join("versions").join("rgb.json"), "not json").unwrap();
+        assert_eq!(cache.get::<Vec<String>>("versions", "rgb"), None);
+    }
+}
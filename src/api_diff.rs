@@ -0,0 +1,287 @@
+//! Base-crate items behind regressions (`--api-diff`)
+//!
+//! Rustdoc JSON (nightly only) is built for the baseline and the offered
+//! version of the base crate. Public items missing from the offered version
+//! are removed, functions whose signature differs are changed, and items
+//! only in the offered version are added (for the version bump). Each
+//! regression's errors are then matched against those items by the names
+//! rustc puts in backticks ("no method named `as_slice` found for struct
+//! `Rgb`"), so a pile of errors reads as "removed `Rgb::as_slice`".
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use log::debug;
+use serde_json::Value;
+
+use crate::error_extract::ErrorPattern;
+
+/// Public items of a crate: path without the crate name -> signature
+/// fingerprint (empty for anything but functions and methods)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiItems(BTreeMap<String, String>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Removed,
+    Changed,
+    Added,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApiChange {
+    pub kind: ChangeKind,
+    pub path: String,
+}
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+            ChangeKind::Added => "added",
+        };
+        write!(f, "{} `{}`", kind, self.path)
+    }
+}
+
+/// Build rustdoc JSON for the crate in `crate_dir` and read its public items
+pub fn public_api(crate_dir: &Path, crate_name: &str, target_dir: &Path) -> Result<ApiItems, String> {
+    debug!("building rustdoc JSON for {:?}", crate_dir);
+    let output = Command::new("cargo")
+        .args(["+nightly", "rustdoc", "--lib", "--target-dir"])
+        .arg(target_dir)
+        .args(crate::api::cargo_source_args())
+        .args(["--", "-Z", "unstable-options", "--output-format", "json"])
+        .current_dir(crate_dir)
+        .output()
+        .map_err(|e| format!("Failed to run cargo +nightly rustdoc: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("cargo +nightly rustdoc failed in {}: {}",
+            crate_dir.display(), stderr.lines().last().unwrap_or("")));
+    }
+
+    let json_path = target_dir.join("doc").join(format!("{}.json", crate_name.replace('-', "_")));
+    let content = std::fs::read_to_string(&json_path)
+        .map_err(|e| format!("Failed to read {}: {}", json_path.display(), e))?;
+    let json: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", json_path.display(), e))?;
+    Ok(parse_rustdoc_json(&json))
+}
+
+/// Ids are strings in older format versions, numbers in newer ones
+fn id_key(id: &Value) -> Option<String> {
+    match id {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The item's kind and its contents, e.g. ("function", {...})
+fn inner(item: &Value) -> Option<(&str, &Value)> {
+    item["inner"].as_object()?.iter().next().map(|(kind, value)| (kind.as_str(), value))
+}
+
+/// A function's signature with ids (which differ between builds) stripped
+fn fingerprint(function: &Value) -> String {
+    fn strip_ids(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(map.iter()
+                .filter(|(key, _)| key.as_str() != "id")
+                .map(|(key, value)| (key.clone(), strip_ids(value)))
+                .collect()),
+            Value::Array(values) => Value::Array(values.iter().map(strip_ids).collect()),
+            other => other.clone(),
+        }
+    }
+    let signature = if function["sig"].is_null() { &function["decl"] } else { &function["sig"] };
+    serde_json::json!([strip_ids(signature), strip_ids(&function["generics"])]).to_string()
+}
+
+/// Public items of the local crate in rustdoc JSON: everything in `paths`,
+/// plus inherent methods (`Type::method`) and trait methods (`Trait::method`)
+pub fn parse_rustdoc_json(json: &Value) -> ApiItems {
+    let index = &json["index"];
+    let mut items = BTreeMap::new();
+
+    let add = |path: String, item: &Value, items: &mut BTreeMap<String, String>| {
+        let signature = match inner(item) {
+            Some(("function", function)) => fingerprint(function),
+            _ => String::new(),
+        };
+        items.insert(path, signature);
+    };
+
+    if let Some(paths) = json["paths"].as_object() {
+        for (id, summary) in paths {
+            if summary["crate_id"] != 0 {
+                continue;
+            }
+            let Some(path) = summary["path"].as_array() else { continue };
+            let path: Vec<&str> = path.iter().skip(1).filter_map(Value::as_str).collect();
+            if path.is_empty() {
+                continue;
+            }
+            add(path.join("::"), &index[id.as_str()], &mut items);
+        }
+    }
+
+    let Some(all) = index.as_object() else {
+        return ApiItems(items);
+    };
+    for item in all.values().filter(|item| item["crate_id"] == 0) {
+        let (owner, members, inherent) = match inner(item) {
+            Some(("impl", imp)) if imp["trait"].is_null() && imp["synthetic"] != true && imp["blanket_impl"].is_null() => {
+                let path = &imp["for"]["resolved_path"];
+                let name = path["path"].as_str().or(path["name"].as_str());
+                (name, &imp["items"], true)
+            }
+            Some(("trait", tr)) => (item["name"].as_str(), &tr["items"], false),
+            _ => continue,
+        };
+        let (Some(owner), Some(members)) = (owner, members.as_array()) else { continue };
+        let owner = owner.rsplit("::").next().unwrap_or(owner);
+        for member in members.iter().filter_map(id_key).filter_map(|id| all.get(&id)) {
+            if inherent && member["visibility"] != "public" {
+                continue;
+            }
+            if let Some(name) = member["name"].as_str() {
+                add(format!("{}::{}", owner, name), member, &mut items);
+            }
+        }
+    }
+    ApiItems(items)
+}
+
+/// Items of `old` missing from `new` or whose signature changed, then items only in `new`
+pub fn diff(old: &ApiItems, new: &ApiItems) -> Vec<ApiChange> {
+    let mut changes: Vec<ApiChange> = old.0.iter()
+        .filter_map(|(path, signature)| {
+            let kind = match new.0.get(path) {
+                None => ChangeKind::Removed,
+                Some(new_signature) if new_signature != signature => ChangeKind::Changed,
+                Some(_) => return None,
+            };
+            Some(ApiChange { kind, path: path.clone() })
+        })
+        .collect();
+    changes.extend(new.0.keys()
+        .filter(|path| !old.0.contains_key(*path))
+        .map(|path| ApiChange { kind: ChangeKind::Added, path: path.clone() }));
+    changes
+}
+
+/// Identifiers inside the backticked parts of an error message
+fn mentioned_names(message: &str) -> HashSet<&str> {
+    message.split('`')
+        .skip(1)
+        .step_by(2)
+        .flat_map(|code| code.split(|c: char| !(c.is_alphanumeric() || c == '_')))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The changes most likely behind these errors: those whose item name is
+/// mentioned, preferring ones whose type or module is mentioned too
+pub fn attribute<'a>(changes: &'a [ApiChange], errors: &[ErrorPattern]) -> Vec<&'a ApiChange> {
+    let mut causes = Vec::new();
+    for error in errors {
+        let names = mentioned_names(&error.message);
+        let scored: Vec<(usize, &ApiChange)> = changes.iter()
+            .filter(|change| change.kind != ChangeKind::Added)
+            .filter_map(|change| {
+                let mut segments = change.path.rsplit("::");
+                let name = segments.next()?;
+                if !names.contains(name) {
+                    return None;
+                }
+                let score = 1 + segments.filter(|s| names.contains(s)).count();
+                Some((score, change))
+            })
+            .collect();
+        let Some(best) = scored.iter().map(|(score, _)| *score).max() else { continue };
+        for (_, change) in scored.into_iter().filter(|(score, _)| *score == best) {
+            if !causes.contains(&change) {
+                causes.push(change);
+            }
+        }
+    }
+    causes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rustdoc(new_signature: bool, with_as_slice: bool) -> Value {
+        let arg = if new_signature { "u16" } else { "u8" };
+        let mut index = serde_json::json!({
+            "1": {"crate_id": 0, "name": "Rgb", "visibility": "public", "inner": {"struct": {}}},
+            "2": {"crate_id": 0, "name": null, "visibility": "default", "inner": {"impl": {
+                "trait": null, "blanket_impl": null, "synthetic": false,
+                "for": {"resolved_path": {"path": "Rgb", "id": 1, "args": null}},
+                "items": [3, 4, 5]}}},
+            "3": {"crate_id": 0, "name": "new", "visibility": "public", "inner": {"function": {
+                "sig": {"inputs": [["r", {"primitive": arg}]], "output": {"resolved_path": {"path": "Rgb", "id": 1}}},
+                "generics": {"params": []}}}},
+            "5": {"crate_id": 0, "name": "helper", "visibility": "crate", "inner": {"function": {"sig": {}, "generics": {}}}},
+            "6": {"crate_id": 0, "name": "ComponentMap", "visibility": "public", "inner": {"trait": {"items": [7]}}},
+            "7": {"crate_id": 0, "name": "map", "visibility": "default", "inner": {"function": {"sig": {}, "generics": {}}}}
+        });
+        if with_as_slice {
+            index["4"] = serde_json::json!({"crate_id": 0, "name": "as_slice", "visibility": "public",
+                "inner": {"function": {"sig": {"inputs": []}, "generics": {}}}});
+        }
+        serde_json::json!({
+            "index": index,
+            "paths": {
+                "1": {"crate_id": 0, "path": ["rgb", "Rgb"], "kind": "struct"},
+                "6": {"crate_id": 0, "path": ["rgb", "ComponentMap"], "kind": "trait"},
+                "9": {"crate_id": 1, "path": ["core", "fmt", "Debug"], "kind": "trait"}
+            }
+        })
+    }
+
+    #[test]
+    fn test_parse_and_diff() {
+        let old = parse_rustdoc_json(&rustdoc(false, true));
+        assert_eq!(old.0.keys().map(String::as_str).collect::<Vec<_>>(),
+                   vec!["ComponentMap", "ComponentMap::map", "Rgb", "Rgb::as_slice", "Rgb::new"]);
+
+        let new = parse_rustdoc_json(&rustdoc(true, false));
+        assert_eq!(diff(&old, &new), vec![
+            ApiChange { kind: ChangeKind::Removed, path: "Rgb::as_slice".to_string() },
+            ApiChange { kind: ChangeKind::Changed, path: "Rgb::new".to_string() },
+        ]);
+        assert_eq!(diff(&new, &old), vec![
+            ApiChange { kind: ChangeKind::Changed, path: "Rgb::new".to_string() },
+            ApiChange { kind: ChangeKind::Added, path: "Rgb::as_slice".to_string() },
+        ]);
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_attribute_prefers_mentioned_owner() {
+        let change = |kind, path: &str| ApiChange { kind, path: path.to_string() };
+        let changes = vec![
+            change(ChangeKind::Removed, "Rgb::as_slice"),
+            change(ChangeKind::Changed, "Rgb::new"),
+            change(ChangeKind::Changed, "Gray::new"),
+            change(ChangeKind::Removed, "alt::BGR"),
+            change(ChangeKind::Added, "Rgb::as_slice_mut"),
+        ];
+        let error = |code: &str, message: &str| ErrorPattern { code: Some(code.to_string()), message: message.to_string() };
+        let causes = attribute(&changes, &[
+            error("E0599", "no method named `as_slice` found for struct `Rgb` in the current scope"),
+            error("E0061", "this function takes N arguments but N arguments were supplied"),
+            error("E0599", "no function or associated item named `new` found for struct `Rgb`"),
+            error("E0432", "unresolved import `rgb::alt::BGR`"),
+            error("E0599", "no method named `as_slice_mut` found for struct `Rgb`"),
+        ]);
+        assert_eq!(causes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+                   vec!["removed `Rgb::as_slice`", "changed `Rgb::new`", "removed `alt::BGR`"]);
+    }
+}
@@ -0,0 +1,272 @@
+//! Per-dependent artifact writing
+//!
+//! Full cargo output for every dependent is compressed with zstd on a
+//! background thread as soon as the dependent's result is finalized, so
+//! large runs stay small enough to upload as a CI artifact. An index.json
+//! describing every written artifact is emitted when the run finishes.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use log::debug;
+use serde::Serialize;
+
+use crate::compile::{CompileResult, ThreeStepResult, VersionSource};
+use crate::format::{HumanDuration, HumanSize};
+use crate::lockfile;
+use crate::{TestResult, TestResultData};
+
+/// zstd compression level (3 is zstd's default: fast with a good ratio)
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Entry in the artifacts index.json
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactEntry {
+    pub dependent: String,
+    pub version: String,
+    /// Toolchain it was tested with (`--channels`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub status: String,
+    pub file: String,
+    pub uncompressed_bytes: usize,
+    pub compressed_bytes: usize,
+}
+
+/// The artifacts index.json document
+#[derive(Debug, Serialize)]
+pub struct ArtifactIndex {
+    pub crate_name: String,
+    pub crate_version: String,
+    pub generated_at: String,
+    pub artifacts: Vec<ArtifactEntry>,
+}
+
+/// Work item handed to the compression thread
+struct ArtifactJob {
+    dependent: String,
+    version: String,
+    channel: Option<String>,
+    status: String,
+    contents: String,
+}
+
+/// Compresses per-dependent logs on a background thread
+pub struct ArtifactWriter {
+    dir: PathBuf,
+    tx: Option<Sender<ArtifactJob>>,
+    handle: Option<JoinHandle<Vec<ArtifactEntry>>>,
+}
+
+impl ArtifactWriter {
+    /// Create the artifacts directory and start the compression thread
+    pub fn new(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let (tx, rx) = mpsc::channel::<ArtifactJob>();
+        let thread_dir = dir.to_path_buf();
+        let handle = thread::spawn(move || {
+            let mut entries = Vec::new();
+            for job in rx {
+                match write_compressed(&thread_dir, &job) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => eprintln!("Warning: Failed to write artifact for {}: {}", job.dependent, e),
+                }
+            }
+            entries
+        });
+
+        Ok(ArtifactWriter {
+            dir: dir.to_path_buf(),
+            tx: Some(tx),
+            handle: Some(handle),
+        })
+    }
+
+    /// Queue a finalized dependent result for compression
+    pub fn submit(&self, result: &TestResult, this_label: &str, crate_name: &str) {
+        let job = ArtifactJob {
+            dependent: result.rev_dep.name.clone(),
+            version: result.rev_dep.vers.to_string(),
+            channel: result.rev_dep.channel.clone(),
+            status: result.quick_str().to_string(),
+            contents: render_dependent_log(result, this_label, crate_name),
+        };
+        if let Some(ref tx) = self.tx {
+            let _ = tx.send(job);
+        }
+    }
+
+    /// Wait for pending compression to finish and write index.json
+    pub fn finish(mut self, crate_name: &str, crate_version: &str) -> io::Result<PathBuf> {
+        drop(self.tx.take());
+        let mut artifacts = match self.handle.take() {
+            Some(handle) => handle.join().unwrap_or_default(),
+            None => Vec::new(),
+        };
+        artifacts.sort_by(|a, b| a.file.cmp(&b.file));
+
+        let index = ArtifactIndex {
+            crate_name: crate_name.to_string(),
+            crate_version: crate_version.to_string(),
+            generated_at: chrono::Local::now().to_rfc3339(),
+            artifacts,
+        };
+
+        let index_path = self.dir.join("index.json");
+        let json = serde_json::to_string_pretty(&index).map_err(io::Error::other)?;
+        fs::write(&index_path, json)?;
+        debug!("Wrote artifact index to {:?}", index_path);
+        Ok(index_path)
+    }
+}
+
+/// Name of a dependent's compressed log in the artifacts directory
+pub fn log_file_name(dependent: &str, version: &str, channel: Option<&str>) -> String {
+    match channel {
+        Some(channel) => format!("{}-{}@{}.log.zst", dependent, version, channel),
+        None => format!("{}-{}.log.zst", dependent, version),
+    }
+}
+
+fn write_compressed(dir: &Path, job: &ArtifactJob) -> io::Result<ArtifactEntry> {
+    let file = log_file_name(&job.dependent, &job.version, job.channel.as_deref());
+    let compressed = zstd::encode_all(job.contents.as_bytes(), COMPRESSION_LEVEL)?;
+    fs::write(dir.join(&file), &compressed)?;
+    debug!("Compressed {} ({} -> {})", file, HumanSize(job.contents.len() as u64), HumanSize(compressed.len() as u64));
+
+    Ok(ArtifactEntry {
+        dependent: job.dependent.clone(),
+        version: job.version.clone(),
+        channel: job.channel.clone(),
+        status: job.status.clone(),
+        file,
+        uncompressed_bytes: job.contents.len(),
+        compressed_bytes: compressed.len(),
+    })
+}
+
+/// Render the complete cargo output for one dependent as plain text
+///
+/// `this_label` names the local work-in-progress version (see `Config::this_label`);
+/// offered versions list their Cargo.lock changes against the baseline, with
+/// the base crate `crate_name` first
+pub fn render_dependent_log(result: &TestResult, this_label: &str, crate_name: &str) -> String {
+    let mut out = format!("# {} {}\n", result.rev_dep.name, result.rev_dep.vers);
+
+    match &result.data {
+        TestResultData::Skipped(reason) => {
+            out.push_str(&format!("\nSKIPPED: {}\n", reason));
+        }
+        TestResultData::PlatformUnsupported(reason) => {
+            out.push_str(&format!("\nPLATFORM UNSUPPORTED: {}\n", reason));
+        }
+        TestResultData::NotRun(reason) => {
+            out.push_str(&format!("\nNOT RUN: {}\n", reason));
+        }
+        TestResultData::Error(e) => {
+            out.push_str(&format!("\nERROR: {}\n", e));
+        }
+        TestResultData::MultiVersion(outcomes) => {
+            for (idx, outcome) in outcomes.iter().enumerate() {
+                let version = match &outcome.version_source {
+                    VersionSource::Local(_) => this_label.to_string(),
+                    VersionSource::Published(v) => v.clone(),
+                };
+                let label = if idx == 0 {
+                    format!("baseline ({})", version)
+                } else {
+                    format!("offered ({})", version)
+                };
+                out.push_str(&format!("\n## {}\n", label));
+                if idx > 0 {
+                    let changes = lockfile::diff(&outcomes[0].result.lock_packages, &outcome.result.lock_packages, crate_name);
+                    if !changes.is_empty() {
+                        out.push_str("Cargo.lock changes vs baseline:\n");
+                        for change in changes {
+                            out.push_str(&format!("  {}\n", change));
+                        }
+                    }
+                }
+                if let Some(step) = outcome.result.timed_out_step() {
+                    out.push_str(&format!("TIMEOUT: cargo {} was killed\n", step.as_str()));
+                }
+                render_steps(&mut out, &outcome.result);
+            }
+        }
+    }
+
+    out
+}
+
+fn render_steps(out: &mut String, result: &ThreeStepResult) {
+    for step in result.steps() {
+        out.push('\n');
+        render_step(out, step);
+    }
+}
+
+/// Heading, exit and complete stdout and stderr of one step
+pub fn render_step(out: &mut String, step: &CompileResult) {
+    out.push_str(&format!(
+        "### cargo {} ({}, {})\n",
+        step.step.as_str(),
+        if step.success {
+            "passed"
+        } else if step.timed_out {
+            "timeout"
+        } else if step.limit_exceeded.is_some() {
+            "resource limit"
+        } else {
+            "failed"
+        },
+        HumanDuration(step.duration)
+    ));
+    if let Some(exit) = step.exit.filter(|e| !e.is_success()) {
+        out.push_str(&format!("Exit: {}\n", exit));
+    }
+    out.push_str("--- stdout ---\n");
+    out.push_str(&step.stdout);
+    out.push_str("\n--- stderr ---\n");
+    out.push_str(&step.stderr);
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_compressed_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let job = ArtifactJob {
+            dependent: "image".to_string(),
+            version: "0.25.8".to_string(),
+            channel: None,
+            status: "passed".to_string(),
+            contents: "cargo output\n".repeat(100),
+        };
+
+        let entry = write_compressed(dir.path(), &job).unwrap();
+        assert_eq!(entry.file, "image-0.25.8.log.zst");
+        assert!(entry.compressed_bytes < entry.uncompressed_bytes);
+
+        let data = fs::read(dir.path().join(&entry.file)).unwrap();
+        let decoded = zstd::decode_all(&data[..]).unwrap();
+        assert_eq!(String::from_utf8(decoded).unwrap(), job.contents);
+    }
+
+    #[test]
+    fn test_finish_writes_index() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let writer = ArtifactWriter::new(dir.path()).unwrap();
+        let index_path = writer.finish("rgb", "0.8.91").unwrap();
+
+        let index: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(index_path).unwrap()).unwrap();
+        assert_eq!(index["crate_name"], "rgb");
+        assert_eq!(index["artifacts"].as_array().unwrap().len(), 0);
+    }
+}
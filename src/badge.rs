@@ -0,0 +1,125 @@
+//! README status badge
+//!
+//! A shields.io-style flat SVG (`copter | 47/50 dependents passing`) written
+//! next to the other reports, so a library can show the downstream status of
+//! its last run. A dependent passes when none of its offered versions failed;
+//! the badge is red if any regressed, yellow if some were already broken.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use crate::report::row_status;
+use crate::OfferedRow;
+
+const LABEL: &str = "copter";
+
+/// Rough width in pixels of `text` in 11px Verdana, as shields.io renders it
+fn text_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c {
+            'i' | 'l' | 'j' | '.' | ',' | ':' | '/' | ' ' => 4,
+            'm' | 'w' | 'M' | 'W' => 10,
+            _ => 7,
+        })
+        .sum()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    pub passing: usize,
+    pub total: usize,
+    pub regressed: bool,
+}
+
+impl Badge {
+    /// Count dependents (not rows) whose offered versions all passed
+    pub fn from_rows(rows: &[OfferedRow]) -> Self {
+        let mut dependents: BTreeMap<(&str, &str), bool> = BTreeMap::new();
+        let mut regressed = false;
+        for row in rows {
+            let passing = dependents.entry((&row.primary.dependent_name, &row.primary.dependent_version)).or_insert(true);
+            if row.offered.is_some() {
+                let status = row_status(row);
+                *passing &= !status.is_failure();
+                regressed |= status == crate::json_report::RowStatus::Regressed;
+            }
+        }
+        Badge {
+            passing: dependents.values().filter(|&&passing| passing).count(),
+            total: dependents.len(),
+            regressed,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        format!("{}/{} dependents passing", self.passing, self.total)
+    }
+
+    /// shields.io color names: brightgreen, yellow, red
+    fn color(&self) -> &'static str {
+        if self.regressed {
+            "#e05d44"
+        } else if self.passing < self.total {
+            "#dfb317"
+        } else {
+            "#4c1"
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let message = self.message();
+        let label_width = text_width(LABEL) + 10;
+        let message_width = text_width(&message) + 10;
+        let width = label_width + message_width;
+        // Text is drawn at 10x scale, as shields.io does, for sub-pixel centering
+        let label_x = label_width * 5;
+        let message_x = (label_width * 2 + message_width) * 5;
+        format!(r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{LABEL}: {message}">
+<title>{LABEL}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="110">
+<text x="{label_x}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)">{LABEL}</text><text x="{label_x}" y="140" transform="scale(.1)">{LABEL}</text>
+<text x="{message_x}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)">{message}</text><text x="{message_x}" y="140" transform="scale(.1)">{message}</text>
+</g>
+</svg>
+"##, color = self.color())
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_svg())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge_svg() {
+        let badge = Badge { passing: 47, total: 50, regressed: true };
+        assert_eq!(badge.message(), "47/50 dependents passing");
+        let svg = badge.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("aria-label=\"copter: 47/50 dependents passing\""));
+        assert!(svg.contains("fill=\"#e05d44\""));
+
+        assert_eq!(Badge { passing: 50, total: 50, regressed: false }.color(), "#4c1");
+        assert_eq!(Badge { passing: 49, total: 50, regressed: false }.color(), "#dfb317");
+    }
+
+    #[test]
+    fn test_badge_counts_dependents() {
+        let rows = vec![
+            OfferedRow::for_test("image", None, None, true),
+            OfferedRow::for_test("image", Some("0.8.91"), Some(true), true),
+            OfferedRow::for_test("ravif", None, None, true),
+            OfferedRow::for_test("ravif", Some("0.8.91"), Some(true), false),
+            OfferedRow::for_test("lodepng", None, None, false),
+            OfferedRow::for_test("lodepng", Some("0.8.91"), Some(false), false),
+        ];
+        assert_eq!(Badge::from_rows(&rows), Badge { passing: 1, total: 3, regressed: true });
+    }
+}
@@ -0,0 +1,147 @@
+//! Baseline results reused across runs
+//!
+//! A dependent built against the published version it already resolves
+//! to gives the same outcome every time, as long as neither version nor
+//! the toolchain changes. Baseline `ThreeStepResult`s are stored as JSON
+//! under `{staging}/baseline-cache/{dependent}-{version}/` and reused
+//! until one of those does; `--no-cache` rebuilds them. Entries that
+//! can't be read or parsed are treated as misses.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use log::debug;
+
+use crate::compile::ThreeStepResult;
+
+/// What a cached baseline result depends on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaselineKey {
+    pub dependent: String,
+    pub dependent_version: String,
+    pub crate_name: String,
+    pub base_version: String,
+    /// `rustc --version` as seen from the dependent's directory
+    pub toolchain: String,
+}
+
+impl BaselineKey {
+    fn entry_path(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("{}-{}", self.dependent, self.dependent_version))
+            .join(format!("{}-{}-{}.json", self.crate_name, self.base_version, file_safe(&self.toolchain)))
+    }
+}
+
+/// `rustc 1.83.0 (90b35a623 2024-11-26)` -> `rustc_1.83.0_90b35a623_2024-11-26`
+fn file_safe(s: &str) -> String {
+    s.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Toolchain cargo will use in `crate_path` (rustup honors rust-toolchain files there)
+pub fn toolchain(crate_path: &Path) -> Option<String> {
+    let output = Command::new("rustc").arg("--version").envs(crate::compile::toolchain_env()).current_dir(crate_path).output().ok()?;
+    if !output.status.success() {
+        debug!("rustc --version failed in {:?}", crate_path);
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether a result says something about the dependent rather than this machine
+///
+/// Fetch failures (usually network), timeouts and resource limits could
+/// go the other way next time, so they aren't cached.
+fn is_cacheable(result: &ThreeStepResult) -> bool {
+    let steps = [Some(&result.fetch), result.check.as_ref(), result.build.as_ref(), result.install.as_ref(),
+        result.clippy.as_ref(), result.doc.as_ref(), result.test.as_ref(), result.doctest.as_ref(), result.miri.as_ref()];
+    result.fetch.success && steps.iter().flatten().all(|step| !step.timed_out && step.limit_exceeded.is_none())
+}
+
+/// Cached baseline result for `key`, if any
+pub fn load(dir: &Path, key: &BaselineKey) -> Option<ThreeStepResult> {
+    let path = key.entry_path(dir);
+    let content = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(result) => {
+            debug!("baseline cache hit: {:?}", path);
+            if let Some(entry) = path.parent() {
+                crate::cache::touch(entry);
+            }
+            Some(result)
+        }
+        Err(e) => {
+            debug!("ignoring unreadable baseline cache entry {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Store a baseline result; failures only cost a rebuild next time, so they are logged and ignored
+pub fn store(dir: &Path, key: &BaselineKey, result: &ThreeStepResult) {
+    if !is_cacheable(result) {
+        return;
+    }
+    let path = key.entry_path(dir);
+    let stored = fs::create_dir_all(path.parent().unwrap())
+        .map_err(|e| e.to_string())
+        .and_then(|_| serde_json::to_string(result).map_err(|e| e.to_string()))
+        .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+    if let Err(e) = stored {
+        debug!("failed to write baseline cache entry {:?}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::compile::{CompileResult, CompileStep};
+
+    fn key(toolchain: &str) -> BaselineKey {
+        BaselineKey {
+            dependent: "image".to_string(),
+            dependent_version: "0.25.8".to_string(),
+            crate_name: "rgb".to_string(),
+            base_version: "0.8.50".to_string(),
+            toolchain: toolchain.to_string(),
+        }
+    }
+
+    fn baseline(fetched: bool) -> ThreeStepResult {
+        let step = |step, success| CompileResult { duration: Duration::from_secs(3), ..CompileResult::for_test(step, success) };
+        ThreeStepResult {
+            actual_version: Some("0.8.50".to_string()),
+            expected_version: Some("0.8.50".to_string()),
+            original_requirement: Some("^0.8".to_string()),
+            base_features: vec!["default".to_string()],
+            ..ThreeStepResult::from_steps(step(CompileStep::Fetch, fetched), fetched.then(|| step(CompileStep::Check, false)))
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_per_toolchain() {
+        let dir = tempfile::tempdir().unwrap();
+        let stable = key("rustc 1.83.0 (90b35a623 2024-11-26)");
+        assert!(load(dir.path(), &stable).is_none());
+
+        store(dir.path(), &stable, &baseline(true));
+        let cached = load(dir.path(), &stable).unwrap();
+        assert!(!cached.is_success());
+        assert_eq!(cached.check.unwrap().duration, Duration::from_secs(3));
+        assert_eq!(cached.base_features, vec!["default".to_string()]);
+        assert!(dir.path().join("image-0.25.8").join("rgb-0.8.50-rustc_1.83.0_90b35a623_2024-11-26.json").is_file());
+
+        // Another toolchain has to rebuild
+        assert!(load(dir.path(), &key("rustc 1.84.0 (9fc6b4312 2025-01-07)")).is_none());
+    }
+
+    #[test]
+    fn test_failed_fetch_is_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        store(dir.path(), &key("rustc 1.83.0"), &baseline(false));
+        assert!(load(dir.path(), &key("rustc 1.83.0")).is_none());
+    }
+}
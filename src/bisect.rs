@@ -0,0 +1,170 @@
+//! Finding the first published version that breaks a dependent
+//!
+//! With `--bisect <from>..<to>`, every dependent that regressed is retested
+//! against the published versions of the base crate between the two bounds
+//! (inclusive, yanked versions skipped). `from` is expected to work and
+//! `to` to break it; a binary search between them needs about log2(n)
+//! builds to name the first version that breaks.
+
+use std::fmt;
+use semver::Version;
+
+/// Inclusive range of base crate versions to search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BisectRange {
+    pub from: Version,
+    pub to: Version,
+}
+
+impl BisectRange {
+    /// Parse `0.8.40..0.8.91`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (from, to) = s.split_once("..")
+            .ok_or_else(|| format!("expected <from>..<to>, got '{}'", s))?;
+        let parse = |v: &str| Version::parse(v.trim()).map_err(|e| format!("invalid version '{}': {}", v, e));
+        let range = BisectRange { from: parse(from)?, to: parse(to)? };
+        if range.from >= range.to {
+            return Err(format!("{} must be older than {}", range.from, range.to));
+        }
+        Ok(range)
+    }
+
+    /// Non-yanked versions within the range, oldest first
+    ///
+    /// Pre-releases are only included when one of the bounds is a pre-release.
+    pub fn candidates(&self, versions: &[(Version, bool)]) -> Vec<Version> {
+        let prereleases = !self.from.pre.is_empty() || !self.to.pre.is_empty();
+        let mut candidates: Vec<Version> = versions.iter()
+            .filter(|(version, yanked)| !yanked && *version >= self.from && *version <= self.to)
+            .filter(|(version, _)| prereleases || version.pre.is_empty())
+            .map(|(version, _)| version.clone())
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl fmt::Display for BisectRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.from, self.to)
+    }
+}
+
+/// What a bisection found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BisectOutcome {
+    /// `first_bad` is the oldest version that breaks; `last_good` the one before it
+    FirstBreaking { first_bad: Version, last_good: Version },
+    /// Already broken at the lower bound
+    BrokenAtStart(Version),
+    /// Still works at the upper bound
+    NeverBroke(Version),
+    /// Fewer than two published versions in the range
+    NoCandidates,
+}
+
+/// Result of bisecting one dependent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bisection {
+    pub outcome: BisectOutcome,
+    /// Versions built to get there
+    pub tested: usize,
+}
+
+impl fmt::Display for Bisection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            BisectOutcome::FirstBreaking { first_bad, last_good } => {
+                write!(f, "first broken by {} (last good {})", first_bad, last_good)?
+            }
+            BisectOutcome::BrokenAtStart(version) => write!(f, "already broken at {}", version)?,
+            BisectOutcome::NeverBroke(version) => write!(f, "still works with {}", version)?,
+            BisectOutcome::NoCandidates => return write!(f, "fewer than two published versions in range"),
+        }
+        write!(f, ", {} versions tested", self.tested)
+    }
+}
+
+/// Binary search `candidates` (oldest first) for the first version `works` rejects
+///
+/// Assumes a dependent stays broken once broken; the bounds are checked
+/// first so a bad assumption shows up as `BrokenAtStart`/`NeverBroke`
+/// rather than a wrong answer.
+pub fn first_breaking(candidates: &[Version], mut works: impl FnMut(&Version) -> Result<bool, String>) -> Result<Bisection, String> {
+    if candidates.len() < 2 {
+        return Ok(Bisection { outcome: BisectOutcome::NoCandidates, tested: 0 });
+    }
+
+    let (mut good, mut bad) = (0, candidates.len() - 1);
+    if works(&candidates[bad])? {
+        return Ok(Bisection { outcome: BisectOutcome::NeverBroke(candidates[bad].clone()), tested: 1 });
+    }
+    if !works(&candidates[good])? {
+        return Ok(Bisection { outcome: BisectOutcome::BrokenAtStart(candidates[good].clone()), tested: 2 });
+    }
+
+    let mut tested = 2;
+    while bad - good > 1 {
+        let mid = good + (bad - good) / 2;
+        tested += 1;
+        if works(&candidates[mid])? {
+            good = mid;
+        } else {
+            bad = mid;
+        }
+    }
+
+    Ok(Bisection {
+        outcome: BisectOutcome::FirstBreaking {
+            first_bad: candidates[bad].clone(),
+            last_good: candidates[good].clone(),
+        },
+        tested,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(list: &[&str]) -> Vec<Version> {
+        list.iter().map(|v| Version::parse(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse_and_candidates() {
+        let range = BisectRange::parse("0.8.40..0.8.91").unwrap();
+        assert_eq!(range.to_string(), "0.8.40..0.8.91");
+        assert!(BisectRange::parse("0.8.91..0.8.40").is_err());
+        assert!(BisectRange::parse("0.8.40").is_err());
+
+        let published: Vec<(Version, bool)> = [
+            ("0.8.39", false), ("0.8.40", false), ("0.8.50", true), ("0.8.60", false),
+            ("0.8.91-alpha.1", false), ("0.8.91", false), ("0.9.0", false),
+        ].iter().map(|(v, yanked)| (Version::parse(v).unwrap(), *yanked)).collect();
+        assert_eq!(range.candidates(&published), versions(&["0.8.40", "0.8.60", "0.8.91"]));
+    }
+
+    #[test]
+    fn test_first_breaking() {
+        let candidates = versions(&["0.8.40", "0.8.41", "0.8.42", "0.8.43", "0.8.44", "0.8.45", "0.8.46"]);
+        let breaks_at = Version::parse("0.8.44").unwrap();
+        let mut built = Vec::new();
+        let bisection = first_breaking(&candidates, |v| { built.push(v.clone()); Ok(*v < breaks_at) }).unwrap();
+
+        assert_eq!(bisection.outcome, BisectOutcome::FirstBreaking {
+            first_bad: breaks_at.clone(),
+            last_good: Version::parse("0.8.43").unwrap(),
+        });
+        assert_eq!(bisection.tested, built.len());
+        assert!(bisection.tested <= 5);
+        assert_eq!(bisection.to_string(), format!("first broken by 0.8.44 (last good 0.8.43), {} versions tested", built.len()));
+
+        let never = first_breaking(&candidates, |_| Ok(true)).unwrap();
+        assert_eq!(never.outcome, BisectOutcome::NeverBroke(Version::parse("0.8.46").unwrap()));
+        let always = first_breaking(&candidates, |_| Ok(false)).unwrap();
+        assert_eq!(always.outcome, BisectOutcome::BrokenAtStart(Version::parse("0.8.40").unwrap()));
+        assert_eq!(first_breaking(&candidates[..1], |_| Ok(true)).unwrap().outcome, BisectOutcome::NoCandidates);
+    }
+}
@@ -0,0 +1,314 @@
+//! Limits on what a whole run may consume
+//!
+//! `--max-memory` and `--max-cpu-time` bound each build process; these bound
+//! the run, for shared or metered machines: CPU time of all builds
+//! (`--max-total-cpu-hours`), data downloaded by us and by cargo
+//! (`--max-network-mb`), and build processes started (`--max-processes`).
+//! `--max-regressions` (and `--fail-fast`, which is one regression) stops
+//! a run that has already shown what it needs to.
+//! They are checked before each dependent starts. Once one is used up,
+//! dependents already building finish, the rest are NOT_RUN, and the
+//! report names the limit.
+//!
+//! `--max-duration` bounds wall time: no dependent is started once the
+//! time left is less than the average dependent has taken so far. With
+//! `--grace-period`, builds still running that long after the budget ran
+//! out are killed (TIMEOUT) instead of finishing.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use log::debug;
+
+use crate::format::{HumanDuration, HumanSize};
+
+/// Budgets for one run; None is unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunLimits {
+    pub cpu_hours: Option<f64>,
+    pub network_mb: Option<u64>,
+    pub processes: Option<u64>,
+    pub regressions: Option<usize>,
+    pub duration: Option<Duration>,
+    /// How long builds may run on after `duration`; None lets them finish
+    pub grace_period: Option<Duration>,
+}
+
+/// What the run has consumed so far
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Usage {
+    /// User + system time of finished child processes
+    pub cpu: Duration,
+    /// Bytes we downloaded, plus growth of cargo's registry cache with `--max-network-mb`
+    pub network_bytes: u64,
+    /// cargo fetch/check/test processes started
+    pub processes: u64,
+    /// Dependents found regressed
+    pub regressions: usize,
+    /// Wall time since the run started
+    pub elapsed: Duration,
+    /// Wall time of the average dependent finished so far
+    pub average_dependent: Option<Duration>,
+}
+
+impl RunLimits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == RunLimits::default()
+    }
+
+    /// Description of the first limit `usage` has reached
+    pub fn exceeded(&self, usage: &Usage) -> Option<String> {
+        if let Some(hours) = self.cpu_hours {
+            if usage.cpu.as_secs_f64() >= hours * 3600.0 {
+                return Some(format!("--max-total-cpu-hours {} reached ({} of CPU time used)", hours, HumanDuration(usage.cpu)));
+            }
+        }
+        if let Some(mb) = self.network_mb {
+            if usage.network_bytes >= mb * 1024 * 1024 {
+                return Some(format!("--max-network-mb {} reached ({} downloaded)", mb, HumanSize(usage.network_bytes)));
+            }
+        }
+        if let Some(processes) = self.processes {
+            if usage.processes >= processes {
+                return Some(format!("--max-processes {} reached ({} build processes started)", processes, usage.processes));
+            }
+        }
+        if let Some(regressions) = self.regressions {
+            if usage.regressions >= regressions {
+                return Some(format!("--max-regressions {} reached ({} dependents regressed)", regressions, usage.regressions));
+            }
+        }
+        if let Some(duration) = self.duration {
+            if usage.elapsed >= duration {
+                return Some(format!("--max-duration {} reached", HumanDuration(duration)));
+            }
+            // Another dependent likely wouldn't finish in time
+            if let Some(average) = usage.average_dependent.filter(|average| usage.elapsed + *average > duration) {
+                return Some(format!("--max-duration {} nearly reached ({} elapsed, dependents take {} on average)",
+                    HumanDuration(duration), HumanDuration(usage.elapsed), HumanDuration(average)));
+            }
+        }
+        None
+    }
+}
+
+impl Usage {
+    pub fn format(&self) -> String {
+        format!("Resource usage: {} CPU, {} downloaded, {} build processes\n",
+            HumanDuration(self.cpu), HumanSize(self.network_bytes), self.processes)
+    }
+}
+
+/// Bytes downloaded outside cargo by this process, wherever the lookup came from
+static DOWNLOADED: AtomicU64 = AtomicU64::new(0);
+
+/// Count bytes downloaded outside cargo (crates.io API, index, .crate files)
+pub fn record_download(bytes: u64) {
+    DOWNLOADED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// A run's budgets and what it has consumed so far, shared by everything
+/// the run starts (see `compile::RunSettings`)
+#[derive(Debug)]
+pub struct Budget {
+    limits: RunLimits,
+    /// Registry cache directory and its size when the run started
+    registry_cache: Option<(PathBuf, u64)>,
+    started: Instant,
+    /// Set once a limit is reached, so every later dependent sees the same reason
+    exhausted: Mutex<Option<String>>,
+    /// Names of the regressed dependents; a set, since a dependent can be tested twice
+    regressed: Mutex<HashSet<String>>,
+    /// Wall time of each finished dependent
+    dependent_times: Mutex<Vec<Duration>>,
+    processes: AtomicU64,
+    skipped: AtomicUsize,
+}
+
+impl Default for Budget {
+    /// No limits, measuring nothing but the run's own counts
+    fn default() -> Self {
+        Budget {
+            limits: RunLimits::default(),
+            registry_cache: None,
+            started: Instant::now(),
+            exhausted: Mutex::new(None),
+            regressed: Mutex::new(HashSet::new()),
+            dependent_times: Mutex::new(Vec::new()),
+            processes: AtomicU64::new(0),
+            skipped: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Budget {
+    /// Start measuring a run with `limits`; the registry cache is only
+    /// walked when `--max-network-mb` is there to check its growth against
+    pub fn new(limits: RunLimits) -> Self {
+        let registry_cache = limits.network_mb.map(|_| {
+            let cache = registry_cache_dir();
+            let size = dir_size(&cache);
+            (cache, size)
+        });
+        Budget { limits, registry_cache, ..Budget::default() }
+    }
+
+    /// Count a build process being started
+    pub fn record_process(&self) {
+        self.processes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a dependent found regressed
+    pub fn record_regression(&self, dependent: &str) {
+        self.regressed.lock().unwrap().insert(dependent.to_string());
+    }
+
+    /// Count a dependent finishing after `wall_time`
+    pub fn record_dependent_time(&self, wall_time: Duration) {
+        self.dependent_times.lock().unwrap().push(wall_time);
+    }
+
+    /// `timeout` for a build step, shortened so the step is killed once the
+    /// `--grace-period` after `--max-duration` is over
+    pub fn cap_timeout(&self, timeout: Option<Duration>) -> Option<Duration> {
+        let (Some(duration), Some(grace)) = (self.limits.duration, self.limits.grace_period) else {
+            return timeout;
+        };
+        let left = (duration + grace).saturating_sub(self.started.elapsed());
+        Some(timeout.map_or(left, |timeout| timeout.min(left)))
+    }
+
+    /// Consumption so far
+    pub fn usage(&self) -> Usage {
+        let cargo_downloads = self.registry_cache.as_ref()
+            .map(|(dir, start)| dir_size(dir).saturating_sub(*start))
+            .unwrap_or(0);
+        Usage {
+            cpu: children_cpu_time(),
+            network_bytes: DOWNLOADED.load(Ordering::Relaxed) + cargo_downloads,
+            processes: self.processes.load(Ordering::Relaxed),
+            regressions: self.regressed.lock().unwrap().len(),
+            elapsed: self.started.elapsed(),
+            average_dependent: {
+                let times = self.dependent_times.lock().unwrap();
+                (!times.is_empty()).then(|| times.iter().sum::<Duration>() / times.len() as u32)
+            },
+        }
+    }
+
+    /// The limit that stopped the run, if one has been reached; checked before each dependent
+    pub fn exhausted(&self) -> Option<String> {
+        if self.limits.is_unlimited() {
+            return None;
+        }
+        let mut exhausted = self.exhausted.lock().unwrap();
+        if exhausted.is_none() {
+            *exhausted = self.limits.exceeded(&self.usage());
+        }
+        exhausted.clone()
+    }
+
+    /// Note a dependent skipped because the budget ran out
+    pub fn record_skipped(&self) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Dependents skipped because the budget ran out
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+}
+
+/// Where cargo keeps downloaded .crate files
+fn registry_cache_dir() -> PathBuf {
+    let cargo_home = crate::paths::cargo_home().unwrap_or_else(|| PathBuf::from(".cargo"));
+    cargo_home.join("registry").join("cache")
+}
+
+/// Total size of the files in `dir` and its subdirectories
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries.flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// CPU time of all finished (and waited-for) child processes and their descendants
+#[cfg(unix)]
+fn children_cpu_time() -> Duration {
+    // SAFETY: getrusage only writes into the zeroed struct we pass it
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } != 0 {
+        debug!("getrusage failed: {}", std::io::Error::last_os_error());
+        return Duration::ZERO;
+    }
+    let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+    time(usage.ru_utime) + time(usage.ru_stime)
+}
+
+#[cfg(not(unix))]
+fn children_cpu_time() -> Duration {
+    debug!("CPU time accounting is only supported on unix");
+    Duration::ZERO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeded_names_the_limit() {
+        let limits = RunLimits { cpu_hours: Some(0.5), network_mb: Some(100), processes: Some(50), ..RunLimits::default() };
+        let mut usage = Usage { cpu: Duration::from_secs(1200), network_bytes: 10 * 1024 * 1024, processes: 12, regressions: 3, ..Usage::default() };
+        assert_eq!(limits.exceeded(&usage), None);
+
+        usage.processes = 50;
+        assert_eq!(limits.exceeded(&usage).unwrap(), "--max-processes 50 reached (50 build processes started)");
+
+        usage.cpu = Duration::from_secs(1800);
+        assert!(limits.exceeded(&usage).unwrap().starts_with("--max-total-cpu-hours 0.5 reached"));
+
+        let fail_fast = RunLimits { regressions: Some(1), ..RunLimits::default() };
+        assert_eq!(fail_fast.exceeded(&Usage::default()), None);
+        assert_eq!(fail_fast.exceeded(&usage).unwrap(), "--max-regressions 1 reached (3 dependents regressed)");
+
+        let timed = RunLimits { duration: Some(Duration::from_secs(1800)), ..RunLimits::default() };
+        usage.elapsed = Duration::from_secs(1500);
+        assert_eq!(timed.exceeded(&usage), None);
+        // Too little time left for another average dependent
+        usage.average_dependent = Some(Duration::from_secs(400));
+        assert_eq!(timed.exceeded(&usage).unwrap(), "--max-duration 30m 00s nearly reached (25m 00s elapsed, dependents take 6m 40s on average)");
+        usage.elapsed = Duration::from_secs(1800);
+        assert_eq!(timed.exceeded(&usage).unwrap(), "--max-duration 30m 00s reached");
+
+        assert!(RunLimits::default().is_unlimited());
+        assert_eq!(RunLimits::default().exceeded(&usage), None);
+    }
+
+    #[test]
+    fn test_registry_cache_walked_only_for_network_limit() {
+        let fail_fast = Budget::new(RunLimits { regressions: Some(1), duration: Some(Duration::from_secs(60)), ..RunLimits::default() });
+        assert!(fail_fast.registry_cache.is_none());
+        let metered = Budget::new(RunLimits { network_mb: Some(100), ..RunLimits::default() });
+        assert!(metered.registry_cache.is_some());
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("index.crates.io-6f17d22bba15001f")).unwrap();
+        fs::write(dir.path().join("index.crates.io-6f17d22bba15001f").join("rgb-0.8.50.crate"), vec![0u8; 1000]).unwrap();
+        fs::write(dir.path().join("other"), vec![0u8; 24]).unwrap();
+        assert_eq!(dir_size(dir.path()), 1024);
+        assert_eq!(dir_size(&dir.path().join("missing")), 0);
+    }
+}
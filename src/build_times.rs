@@ -0,0 +1,93 @@
+//! Build-time regressions (`--slowdown-threshold`)
+//!
+//! The duration of every step is recorded for the baseline and for each
+//! offered version. A dependent can keep passing while its check or test
+//! build gets several times slower, e.g. after a generics-heavy rewrite or a
+//! new proc macro; such steps are listed under "Performance impact".
+//!
+//! Only steps that passed with both versions are compared, and short steps
+//! are too noisy to judge by ratio alone: a slowdown must also add at least
+//! MIN_SLOWDOWN.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::compile::{CompileStep, ThreeStepResult};
+use crate::format::HumanDuration;
+
+/// Ratio of offered to baseline duration reported by default
+pub const DEFAULT_THRESHOLD: f64 = 2.0;
+
+/// Smallest increase worth reporting, whatever the ratio
+const MIN_SLOWDOWN: Duration = Duration::from_secs(3);
+
+/// A step the offered version made significantly slower
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slowdown {
+    pub step: CompileStep,
+    pub baseline: Duration,
+    pub offered: Duration,
+}
+
+impl Slowdown {
+    pub fn ratio(&self) -> f64 {
+        self.offered.as_secs_f64() / self.baseline.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for Slowdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} → {} ({:.1}x)", self.step.as_str(), HumanDuration(self.baseline), HumanDuration(self.offered), self.ratio())
+    }
+}
+
+/// Compile steps whose time the offered version can change; fetch is network-bound
+fn timed_steps(result: &ThreeStepResult) -> impl Iterator<Item = (CompileStep, Duration)> + '_ {
+    result.steps().into_iter()
+        .filter(|step| !matches!(step.step, CompileStep::Fetch | CompileStep::Miri))
+        // Retried tests ran several times
+        .filter(|step| step.success && !step.flaky)
+        .map(|step| (step.step, step.duration))
+}
+
+/// Steps of `offered` at least `threshold` (`--slowdown-threshold`) times slower than in `baseline`
+pub fn diff(baseline: &ThreeStepResult, offered: &ThreeStepResult, threshold: f64) -> Vec<Slowdown> {
+    let before: Vec<(CompileStep, Duration)> = timed_steps(baseline).collect();
+    timed_steps(offered)
+        .filter_map(|(step, duration)| {
+            let &(_, baseline) = before.iter().find(|(s, _)| *s == step)?;
+            let slowdown = Slowdown { step, baseline, offered: duration };
+            (duration >= baseline + MIN_SLOWDOWN && slowdown.ratio() >= threshold).then_some(slowdown)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::CompileResult;
+
+    fn result(check: u64, test: u64) -> ThreeStepResult {
+        let step = |step, secs| CompileResult { duration: Duration::from_secs(secs), ..CompileResult::for_test(step, true) };
+        ThreeStepResult::from_steps(step(CompileStep::Fetch, 1), [step(CompileStep::Check, check), step(CompileStep::Test, test)])
+    }
+
+    #[test]
+    fn test_slowdowns_over_threshold() {
+        // Check 3x slower; test only 1s slower, though 2x
+        let changes = diff(&result(10, 1), &result(30, 2), 2.0);
+        assert_eq!(changes, vec![Slowdown {
+            step: CompileStep::Check,
+            baseline: Duration::from_secs(10),
+            offered: Duration::from_secs(30),
+        }]);
+        assert_eq!(changes[0].to_string(), "check: 10.0s → 30.0s (3.0x)");
+
+        assert!(diff(&result(10, 1), &result(30, 2), 4.0).is_empty());
+        assert!(diff(&result(30, 2), &result(10, 1), 2.0).is_empty());
+
+        let mut failed = result(30, 2);
+        failed.check.as_mut().unwrap().success = false;
+        assert!(diff(&result(10, 1), &failed, 2.0).is_empty());
+    }
+}
@@ -0,0 +1,593 @@
+//! Cache export/import for warm-starting CI runners
+//!
+//! Bundles the downloaded .crate files and the staging directory into a
+//! single zstd-compressed tarball so an ephemeral runner can restore the
+//! previous run's state instead of re-downloading and re-unpacking
+//! everything. Build output (each staging entry's `target/` directory) is
+//! left out; it is large and cheap for cargo to rebuild relative to its size.
+//!
+//! Entries are written under a per-entry lock (`lock_entry`) to a temporary
+//! name and moved into place, so parallel jobs and concurrent runs can share
+//! one cache; those lock and temporary files are left out of archives too.
+//!
+//! Neither directory shrinks on its own. Each .crate file and each top-level
+//! staging directory is an entry whose modification time is bumped when a run
+//! reuses it (`touch`); `cache clean` and `--max-cache-size` evict the least
+//! recently used entries first. Staging directories shared by many dependents
+//! (SHARED_DIRS, and the per-toolchain `channel-*` staging directories) aren't
+//! entries themselves; what's inside them is.
+//!
+//! .crate files are gzipped tarballs with everything under `{name}-{version}/`;
+//! they're read in-process (`unpack_crate`, `read_crate_file`) rather than
+//! with an external `tar`, whose flags differ between GNU and bsdtar.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use fs2::FileExt;
+use log::debug;
+
+/// zstd compression level for cache archives
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Archive prefix for the downloaded .crate files
+const CRATE_CACHE_PREFIX: &str = "crate-cache";
+
+/// Archive prefix for the staging directory
+const STAGING_PREFIX: &str = "staging";
+
+/// Directories skipped directly under a staging entry when bundling (rebuilt by cargo on demand)
+const SKIPPED_DIRS: &[&str] = &["target"];
+
+/// Staging directories holding one entry per dependent or build, rather than being one
+const SHARED_DIRS: &[&str] = &["baseline-cache", "git", "local", "rustdoc"];
+
+/// Suffix of the lock file next to a cache entry
+const LOCK_SUFFIX: &str = ".lock";
+
+/// Prefix of files and directories being written, before they're moved into place
+pub const TEMP_PREFIX: &str = ".tmp";
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(LOCK_SUFFIX);
+    PathBuf::from(lock_path)
+}
+
+/// Exclusive lock on a cache entry (`path.lock`), held until the returned file
+/// is dropped; serializes writers across jobs and concurrent runs sharing the cache
+pub fn lock_entry(path: &Path) -> io::Result<File> {
+    let lock = OpenOptions::new().create(true).truncate(false).write(true).open(lock_path(path))?;
+    lock.lock_exclusive()?;
+    Ok(lock)
+}
+
+/// Lock files and unfinished writes aren't part of the cache
+fn is_transient(name: &std::ffi::OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name.ends_with(LOCK_SUFFIX) || name.starts_with(TEMP_PREFIX)
+}
+
+/// Mark a cache entry as used now, for least-recently-used eviction
+///
+/// Best effort: an entry that can't be touched is just evicted earlier.
+pub fn touch(path: &Path) {
+    if let Err(e) = open_for_touch(path).and_then(|f| f.set_modified(SystemTime::now())) {
+        debug!("Failed to touch cache entry {:?}: {}", path, e);
+    }
+}
+
+#[cfg(not(windows))]
+fn open_for_touch(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+/// Setting times needs write access on Windows, and a directory only opens
+/// with backup semantics
+#[cfg(windows)]
+fn open_for_touch(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+/// A .crate file or top-level staging directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub last_used: SystemTime,
+}
+
+/// Every entry of the crate cache and staging directory, least recently used first
+pub fn cache_entries(crate_cache: &Path, staging_dir: &Path) -> io::Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    // crate-cache/<name>/<name>-<version>.crate
+    for dir in read_dir_if_exists(crate_cache)? {
+        if dir.is_dir() {
+            for file in read_dir_if_exists(&dir)? {
+                entries.push(cache_entry(file)?);
+            }
+        }
+    }
+    for path in staging_entries(staging_dir)? {
+        entries.push(cache_entry(path)?);
+    }
+    entries.sort_by(|a, b| a.last_used.cmp(&b.last_used).then_with(|| a.path.cmp(&b.path)));
+    Ok(entries)
+}
+
+/// Entries of a staging directory: its children, but those of shared directories
+fn staging_entries(staging_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+    for path in read_dir_if_exists(staging_dir)? {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if !path.is_dir() {
+            entries.push(path);
+        } else if name.starts_with("channel-") {
+            entries.extend(staging_entries(&path)?);
+        } else if SHARED_DIRS.contains(&name.as_str()) {
+            entries.extend(read_dir_if_exists(&path)?);
+        } else {
+            entries.push(path);
+        }
+    }
+    Ok(entries)
+}
+
+/// Children of `dir` that aren't locks or unfinished writes
+fn read_dir_if_exists(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !is_transient(&entry.file_name()) {
+            paths.push(entry.path());
+        }
+    }
+    Ok(paths)
+}
+
+fn cache_entry(path: PathBuf) -> io::Result<CacheEntry> {
+    let metadata = fs::symlink_metadata(&path)?;
+    Ok(CacheEntry {
+        bytes: disk_usage(&path)?,
+        last_used: metadata.modified()?,
+        path,
+    })
+}
+
+/// Total size of the files under `path` (symlinks aren't followed)
+fn disk_usage(path: &Path) -> io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += disk_usage(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// The least recently used entries to remove so the rest fit in `max_bytes`
+/// (everything with no limit)
+pub fn plan_eviction(entries: &[CacheEntry], max_bytes: Option<u64>) -> Vec<CacheEntry> {
+    let Some(max_bytes) = max_bytes else {
+        return entries.to_vec();
+    };
+    let mut total: u64 = entries.iter().map(|e| e.bytes).sum();
+    entries.iter()
+        .take_while(|entry| {
+            let over = total > max_bytes;
+            total -= entry.bytes;
+            over
+        })
+        .cloned()
+        .collect()
+}
+
+/// Remove entries, each under its lock so an in-flight download or unpack finishes first
+pub fn evict(entries: &[CacheEntry]) -> io::Result<CacheStats> {
+    let mut stats = CacheStats::default();
+    for entry in entries {
+        let _lock = lock_entry(&entry.path)?;
+        if entry.path.is_dir() {
+            fs::remove_dir_all(&entry.path)?;
+        } else {
+            fs::remove_file(&entry.path)?;
+        }
+        // Writers recreate the lock file; the entry's gone either way
+        let _ = fs::remove_file(lock_path(&entry.path));
+        debug!("Evicted {:?}", entry.path);
+        stats.files += 1;
+        stats.bytes += entry.bytes;
+    }
+    Ok(stats)
+}
+
+/// Entries of a .crate file, with its `{name}-{version}/` directory stripped
+fn crate_entries<R: Read>(
+    archive: &mut tar::Archive<flate2::read::GzDecoder<R>>,
+) -> io::Result<impl Iterator<Item = io::Result<(PathBuf, tar::Entry<'_, flate2::read::GzDecoder<R>>)>>> {
+    Ok(archive.entries()?.map(|entry| {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let rest: PathBuf = path.components().skip(1).collect();
+        // Refuse entries that would escape the destination directory, directly
+        // or through a link a later entry is written through (cargo package
+        // stores the targets of links as plain files)
+        let entry_type = entry.header().entry_type();
+        if !path.components().all(|c| matches!(c, Component::Normal(_)))
+            || entry_type.is_symlink() || entry_type.is_hard_link() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("refusing to unpack unsafe crate entry {:?}", path),
+            ));
+        }
+        Ok((rest, entry))
+    }))
+}
+
+/// Unpack a .crate file's contents directly into `dest`
+pub fn unpack_crate(crate_file: &Path, dest: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(crate_file)?));
+    fs::create_dir_all(dest)?;
+    for entry in crate_entries(&mut archive)? {
+        let (rest, mut entry) = entry?;
+        if rest.as_os_str().is_empty() {
+            continue;
+        }
+        let target = dest.join(&rest);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&target)?;
+    }
+    Ok(())
+}
+
+/// Contents of one file of a .crate (e.g. `Cargo.toml`), if it has it
+pub fn read_crate_file(crate_file: &Path, name: &Path) -> io::Result<Option<Vec<u8>>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(crate_file)?));
+    for entry in crate_entries(&mut archive)? {
+        let (rest, mut entry) = entry?;
+        if rest == name {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            return Ok(Some(content));
+        }
+    }
+    Ok(None)
+}
+
+/// Counts reported after an export or import
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Write the crate cache and staging directory to a .tar.zst archive
+pub fn export_cache(archive: &Path, crate_cache: &Path, staging_dir: &Path) -> io::Result<CacheStats> {
+    if let Some(parent) = archive.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    let file = File::create(archive)?;
+    let encoder = zstd::Encoder::new(file, COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    let mut stats = CacheStats::default();
+
+    // Build output sits directly under a staging entry; a `target` directory
+    // deeper down is part of the dependent's source
+    let build_roots = staging_entries(staging_dir)?;
+    for (dir, prefix, build_roots) in [(crate_cache, CRATE_CACHE_PREFIX, &[][..]), (staging_dir, STAGING_PREFIX, &build_roots[..])] {
+        if dir.is_dir() {
+            append_dir(&mut builder, dir, Path::new(prefix), build_roots, &mut stats)?;
+        } else {
+            debug!("Skipping missing cache directory {:?}", dir);
+        }
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(stats)
+}
+
+/// Restore the crate cache and staging directory from a .tar.zst archive
+///
+/// Existing files are overwritten; anything not in the archive is left alone.
+pub fn import_cache(archive: &Path, crate_cache: &Path, staging_dir: &Path) -> io::Result<CacheStats> {
+    let file = File::open(archive)?;
+    let decoder = zstd::Decoder::new(file)?;
+    let mut tar = tar::Archive::new(decoder);
+    let mut stats = CacheStats::default();
+
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        let (root, rest) = if let Ok(rest) = path.strip_prefix(CRATE_CACHE_PREFIX) {
+            (crate_cache, rest.to_path_buf())
+        } else if let Ok(rest) = path.strip_prefix(STAGING_PREFIX) {
+            (staging_dir, rest.to_path_buf())
+        } else {
+            debug!("Ignoring unexpected archive entry {:?}", path);
+            continue;
+        };
+
+        // Refuse entries that would escape the destination directory, directly
+        // or through a link a later entry is written through
+        let entry_type = entry.header().entry_type();
+        if !rest.components().all(|c| matches!(c, Component::Normal(_)))
+            || entry_type.is_symlink() || entry_type.is_hard_link() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("refusing to unpack unsafe archive entry {:?}", path),
+            ));
+        }
+
+        let dest = root.join(&rest);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&dest)?;
+
+        if entry.header().entry_type().is_file() {
+            stats.files += 1;
+            stats.bytes += entry.header().size()?;
+        }
+    }
+
+    Ok(stats)
+}
+
+fn append_dir<W: io::Write>(
+    builder: &mut tar::Builder<W>,
+    dir: &Path,
+    archive_path: &Path,
+    build_roots: &[PathBuf],
+    stats: &mut CacheStats,
+) -> io::Result<()> {
+    builder.append_dir(archive_path, dir)?;
+
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name();
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        let entry_archive_path = archive_path.join(&name);
+
+        if is_transient(&name) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if SKIPPED_DIRS.iter().any(|skip| name == *skip) && build_roots.iter().any(|root| root == dir) {
+                continue;
+            }
+            append_dir(builder, &path, &entry_archive_path, build_roots, stats)?;
+        } else if file_type.is_file() {
+            let mut file = File::open(&path)?;
+            builder.append_file(&entry_archive_path, &mut file)?;
+            stats.files += 1;
+            stats.bytes += entry.metadata()?.len();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_export_import_roundtrip_skips_target() {
+        let src = TempDir::new().unwrap();
+        let crate_cache = src.path().join("crate-cache");
+        let staging = src.path().join("staging");
+        fs::create_dir_all(crate_cache.join("image")).unwrap();
+        fs::write(crate_cache.join("image/image-0.25.8.crate"), b"crate bytes").unwrap();
+        fs::write(crate_cache.join("image/image-0.25.8.crate.lock"), b"").unwrap();
+        fs::write(crate_cache.join("image/.tmpA1b2C3"), b"half a download").unwrap();
+        fs::create_dir_all(staging.join("image-0.25.8/target/debug")).unwrap();
+        fs::write(staging.join("image-0.25.8/Cargo.toml.original.txt"), b"[package]").unwrap();
+        fs::write(staging.join("image-0.25.8/target/debug/big.rlib"), b"build output").unwrap();
+        // A `target` module of the source (as in the cc crate) isn't build output
+        fs::create_dir_all(staging.join("image-0.25.8/src/target")).unwrap();
+        fs::write(staging.join("image-0.25.8/src/target/mod.rs"), b"mod apple;").unwrap();
+        fs::create_dir_all(staging.join("git/viewer/target/debug")).unwrap();
+        fs::write(staging.join("git/viewer/target/debug/big.rlib"), b"build output").unwrap();
+
+        let archive = src.path().join("out/cache.tar.zst");
+        let exported = export_cache(&archive, &crate_cache, &staging).unwrap();
+        assert_eq!(exported.files, 3);
+
+        let dst = TempDir::new().unwrap();
+        let restored_cache = dst.path().join("crate-cache");
+        let restored_staging = dst.path().join("staging");
+        let imported = import_cache(&archive, &restored_cache, &restored_staging).unwrap();
+        assert_eq!(imported, exported);
+
+        assert_eq!(fs::read(restored_cache.join("image/image-0.25.8.crate")).unwrap(), b"crate bytes");
+        assert!(restored_staging.join("image-0.25.8/Cargo.toml.original.txt").exists());
+        assert!(!restored_staging.join("image-0.25.8/target").exists());
+        assert!(!restored_staging.join("git/viewer/target").exists());
+        assert_eq!(fs::read(restored_staging.join("image-0.25.8/src/target/mod.rs")).unwrap(), b"mod apple;");
+    }
+
+    #[test]
+    fn test_import_refuses_links() {
+        let dir = TempDir::new().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+
+        // A link into `outside`, then a file written through it
+        let archive = dir.path().join("cache.tar.zst");
+        let mut builder = tar::Builder::new(zstd::Encoder::new(File::create(&archive).unwrap(), COMPRESSION_LEVEL).unwrap());
+        let mut link = tar::Header::new_gnu();
+        link.set_entry_type(tar::EntryType::Symlink);
+        link.set_size(0);
+        builder.append_link(&mut link, "staging/image-0.25.8/link", &outside).unwrap();
+        let mut file = tar::Header::new_gnu();
+        file.set_size(5);
+        file.set_mode(0o644);
+        file.set_cksum();
+        builder.append_data(&mut file, "staging/image-0.25.8/link/owned.txt", &b"owned"[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let err = import_cache(&archive, &dir.path().join("crate-cache"), &dir.path().join("staging")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!outside.join("owned.txt").exists());
+    }
+
+    #[test]
+    fn test_lock_entry_is_exclusive() {
+        let dir = TempDir::new().unwrap();
+        let entry = dir.path().join("image-0.25.8.crate");
+        let lock = lock_entry(&entry).unwrap();
+        assert!(dir.path().join("image-0.25.8.crate.lock").exists());
+
+        let other = File::open(dir.path().join("image-0.25.8.crate.lock")).unwrap();
+        assert!(other.try_lock_exclusive().is_err());
+        drop(lock);
+        assert!(other.try_lock_exclusive().is_ok());
+    }
+
+    #[test]
+    fn test_shared_staging_dirs_are_not_entries() {
+        let dir = TempDir::new().unwrap();
+        let staging = dir.path().join("staging");
+        for entry in ["image-0.25.8", "git/viewer", "local/viewer", "baseline-cache/image-0.25.8", "channel-1.70/image-0.25.8", "channel-1.70/git/viewer"] {
+            fs::create_dir_all(staging.join(entry)).unwrap();
+            fs::write(staging.join(entry).join("Cargo.toml"), b"").unwrap();
+        }
+
+        let entries = cache_entries(&dir.path().join("crate-cache"), &staging).unwrap();
+        let mut paths: Vec<_> = entries.iter().map(|e| e.path.strip_prefix(&staging).unwrap().to_path_buf()).collect();
+        paths.sort();
+        assert_eq!(paths, ["baseline-cache/image-0.25.8", "channel-1.70/git/viewer", "channel-1.70/image-0.25.8",
+                           "git/viewer", "image-0.25.8", "local/viewer"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn test_eviction_is_least_recently_used() {
+        let dir = TempDir::new().unwrap();
+        let crate_cache = dir.path().join("crate-cache");
+        let staging = dir.path().join("staging");
+        fs::create_dir_all(crate_cache.join("image")).unwrap();
+        fs::write(crate_cache.join("image/image-0.25.8.crate"), vec![0; 100]).unwrap();
+        fs::write(crate_cache.join("image/image-0.25.8.crate.lock"), b"").unwrap();
+        fs::create_dir_all(staging.join("image-0.25.8/target")).unwrap();
+        fs::write(staging.join("image-0.25.8/Cargo.toml"), vec![0; 50]).unwrap();
+        fs::write(staging.join("image-0.25.8/target/big.rlib"), vec![0; 400]).unwrap();
+        fs::create_dir_all(staging.join("base-rgb-0.8.50")).unwrap();
+        fs::write(staging.join("base-rgb-0.8.50/lib.rs"), vec![0; 200]).unwrap();
+
+        let age = |path: &Path, secs: u64| {
+            let time = SystemTime::now() - std::time::Duration::from_secs(secs);
+            open_for_touch(path).unwrap().set_modified(time).unwrap();
+        };
+        age(&crate_cache.join("image/image-0.25.8.crate"), 300);
+        age(&staging.join("base-rgb-0.8.50"), 200);
+        age(&staging.join("image-0.25.8"), 100);
+
+        let entries = cache_entries(&crate_cache, &staging).unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, vec!["image-0.25.8.crate", "base-rgb-0.8.50", "image-0.25.8"]);
+        assert_eq!(entries.iter().map(|e| e.bytes).collect::<Vec<_>>(), vec![100, 200, 450]);
+
+        assert!(plan_eviction(&entries, Some(750)).is_empty());
+        assert_eq!(plan_eviction(&entries, Some(600)).len(), 2);
+        assert_eq!(plan_eviction(&entries, Some(0)).len(), 3);
+        assert_eq!(plan_eviction(&entries, None).len(), 3);
+
+        // Using an entry makes it the most recent
+        touch(&crate_cache.join("image/image-0.25.8.crate"));
+        let evicted = plan_eviction(&cache_entries(&crate_cache, &staging).unwrap(), Some(600));
+        let stats = evict(&evicted).unwrap();
+        assert_eq!(stats, CacheStats { files: 1, bytes: 200 });
+        assert!(!staging.join("base-rgb-0.8.50").exists());
+        assert!(!staging.join("base-rgb-0.8.50.lock").exists());
+        assert!(crate_cache.join("image/image-0.25.8.crate").exists());
+    }
+
+    fn write_crate(path: &Path, files: &[(&str, &[u8])]) {
+        let encoder = flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_unpack_crate_strips_top_directory() {
+        let dir = TempDir::new().unwrap();
+        let crate_file = dir.path().join("rgb-0.8.50.crate");
+        write_crate(&crate_file, &[
+            ("rgb-0.8.50/Cargo.toml", b"[package]\nname = \"rgb\"\n"),
+            ("rgb-0.8.50/src/lib.rs", b"pub struct Rgb;"),
+            ("rgb-0.8.50/examples/Cargo.toml", b"[package]\nname = \"example\"\n"),
+        ]);
+
+        let dest = dir.path().join("staging/rgb-0.8.50");
+        unpack_crate(&crate_file, &dest).unwrap();
+        assert_eq!(fs::read(dest.join("src/lib.rs")).unwrap(), b"pub struct Rgb;");
+        assert!(dest.join("Cargo.toml").exists());
+
+        let manifest = read_crate_file(&crate_file, Path::new("Cargo.toml")).unwrap().unwrap();
+        assert_eq!(manifest, b"[package]\nname = \"rgb\"\n");
+        assert_eq!(read_crate_file(&crate_file, Path::new("build.rs")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unpack_crate_refuses_links() {
+        let dir = TempDir::new().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+
+        // A link out of the package, then a file written through it
+        let crate_file = dir.path().join("evil-0.1.0.crate");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(File::create(&crate_file).unwrap(), flate2::Compression::default()));
+        let mut link = tar::Header::new_gnu();
+        link.set_entry_type(tar::EntryType::Symlink);
+        link.set_size(0);
+        builder.append_link(&mut link, "evil-0.1.0/link", &outside).unwrap();
+        let mut file = tar::Header::new_gnu();
+        file.set_size(5);
+        file.set_mode(0o644);
+        file.set_cksum();
+        builder.append_data(&mut file, "evil-0.1.0/link/owned.txt", &b"owned"[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let err = unpack_crate(&crate_file, &dir.path().join("staging/evil-0.1.0")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!outside.join("owned.txt").exists());
+    }
+
+    #[test]
+    fn test_export_with_missing_dirs() {
+        let dir = TempDir::new().unwrap();
+        let archive = dir.path().join("cache.tar.zst");
+        let stats = export_cache(&archive, &dir.path().join("nope"), &dir.path().join("nada")).unwrap();
+        assert_eq!(stats, CacheStats::default());
+        assert!(archive.exists());
+    }
+}
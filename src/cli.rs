@@ -1,51 +1,363 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::git_source::GitSource;
+
+/// First argument cargo passes when run as `cargo copter`, or as `cargo crusader`
+/// through a `cargo-crusader` link to this binary (the tool's former name)
+const SUBCOMMAND_NAMES: [&str; 2] = ["copter", "crusader"];
+
+/// Narrowest --table-width: the fixed columns, borders and a 20-column Dependent
+const MIN_TABLE_WIDTH: usize = 118;
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "cargo-copter")]
 #[command(about = "Test the downstream impact of crate changes before publishing")]
 #[command(version)]
 pub struct CliArgs {
-    /// Path to the crate to test (directory or Cargo.toml file)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path to the crate to test (directory or Cargo.toml file). Repeat it for crates of
+    /// the same workspace released together: the first is the one whose dependents are
+    /// tested, the others are patched alongside it whenever the local version is offered
     #[arg(long, short = 'p', value_name = "PATH")]
-    pub path: Option<PathBuf>,
+    pub path: Vec<PathBuf>,
 
-    /// Name of the crate to test (for testing published crates without local source)
+    /// Name of the crate to test (for testing published crates without local source);
+    /// repeated, each names the crate at the --path in the same position
     #[arg(long = "crate", visible_alias = "crate-name", short = 'c', value_name = "CRATE")]
-    pub crate_name: Option<String>,
+    pub crate_name: Vec<String>,
 
     /// Test top N reverse dependencies by download count
     #[arg(long, default_value = "5")]
     pub top_dependents: usize,
 
+    /// Skip discovered dependents whose version has fewer downloads than this
+    #[arg(long, value_name = "N", default_value = "0")]
+    pub min_downloads: u64,
+
+    /// Skip discovered dependents without a release in the last DAYS days
+    #[arg(long, value_name = "DAYS")]
+    pub updated_within: Option<u64>,
+
+    /// Annotate dependents as stale (no release in a year) or abandoned (none in five
+    /// years, or an archived GitHub repository), from crates.io and GitHub
+    #[arg(long)]
+    pub maintenance: bool,
+
+    /// Skip stale and abandoned dependents (implies --maintenance)
+    #[arg(long)]
+    pub only_active: bool,
+
     /// Explicitly test these crates from crates.io (supports "name:version" syntax)
     /// Examples: "image", "image:0.25.8"
     /// Can specify multiple: --dependents image serde tokio
     #[arg(long, value_name = "CRATE[:VERSION]", num_args = 1..)]
     pub dependents: Vec<String>,
 
+    /// Whether a dependent's newest version may be a pre-release (without a pinned version):
+    /// exclude, include, or only (test dependents' release candidates)
+    #[arg(long, value_name = "POLICY", value_enum, default_value_t = crate::api::PrereleasePolicy::Include)]
+    pub dependent_prerelease: crate::api::PrereleasePolicy,
+
     /// Test local crates at these paths
     /// Can specify multiple: --dependent-paths ./crate1 ./crate2
     #[arg(long, value_name = "PATH", num_args = 1..)]
     pub dependent_paths: Vec<PathBuf>,
 
+    /// Find dependents under these directories (a workspace or monorepo): every package
+    /// depending on the crate is tested like a --dependent-paths entry
+    #[arg(long, value_name = "PATH", num_args = 1..)]
+    pub workspace_dependents: Vec<PathBuf>,
+
+    /// Test dependents from their git repository instead of crates.io
+    /// Format: [NAME=]URL[#BRANCH]; NAME defaults to the repository name
+    /// Can specify multiple: --dependents-git https://github.com/image-rs/image#main
+    #[arg(long, value_name = "[NAME=]URL[#BRANCH]", num_args = 1..)]
+    pub dependents_git: Vec<String>,
+
+    /// Also test up to N unpublished dependents found by GitHub code search,
+    /// cloned from their repositories (requires GITHUB_TOKEN)
+    #[arg(long, value_name = "N", default_value = "0")]
+    pub github_dependents: usize,
+
     /// Test against specific versions of the base crate (e.g., "0.3.0 4.1.1")
     /// When specified with --path, includes "this" (WIP version) automatically
     /// Supports versions with hyphens: "0.8.0 1.0.0-rc.1 1.0.0-alpha.2"
     #[arg(long, value_name = "VERSION", num_args = 1..)]
     pub test_versions: Vec<String>,
 
+    /// Enable features of specific dependents during check/test
+    /// Format: name=feat1,feat2 (overrides `features` in copter.toml)
+    /// Can specify multiple: --dependent-features image=png,jpeg serde=derive
+    #[arg(long, value_name = "NAME=FEATURES", num_args = 1..)]
+    pub dependent_features: Vec<String>,
+
     /// Number of parallel test jobs
     #[arg(long, short = 'j', default_value = "1")]
     pub jobs: usize,
 
+    /// Number of concurrent .crate downloads while prefetching dependents and offered
+    /// versions, before testing starts (0 skips prefetching)
+    #[arg(long, value_name = "N", default_value = "8")]
+    pub download_jobs: usize,
+
     /// HTML report output path
     #[arg(long, default_value = "copter-report.html")]
     pub output: PathBuf,
 
+    /// Render the HTML report's dependency graph with mermaid loaded from
+    /// cdn.jsdelivr.net (without it the graph source is shown as text)
+    #[arg(long)]
+    pub mermaid_cdn: bool,
+
     /// Directory for staging unpacked crates (enables caching across runs)
-    #[arg(long, default_value = ".copter/staging")]
-    pub staging_dir: PathBuf,
+    /// [default: $COPTER_HOME/staging]
+    #[arg(long, value_name = "PATH")]
+    pub staging_dir: Option<PathBuf>,
+
+    /// Before testing, evict least recently used crate-cache and staging entries
+    /// until both fit in this size, e.g. 20G
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_cache_size: Option<u64>,
+
+    /// Share one target directory across all dependents (builds into it are
+    /// serialized by cargo's lock; prefer --sccache with --jobs > 1)
+    #[arg(long, value_name = "PATH")]
+    pub shared_target_dir: Option<PathBuf>,
+
+    /// Set an environment variable for every cargo command run on a dependent (repeatable),
+    /// e.g. --env CARGO_PROFILE_DEV_DEBUG=0
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    pub env: Vec<(String, String)>,
+
+    /// Flags appended to RUSTFLAGS for every dependent build, e.g. --rustflags="-D warnings"
+    /// or --rustflags="--cfg my_cfg"; like any RUSTFLAGS, they replace cargo's build.rustflags
+    #[arg(long, value_name = "FLAGS", allow_hyphen_values = true)]
+    pub rustflags: Option<String>,
+
+    /// Fail the check step on warnings in dependents' own code, like CI that denies
+    /// warnings; those of dependencies, including a local base crate, don't count
+    #[arg(long)]
+    pub deny_warnings: bool,
+
+    /// Cache compiled dependencies across dependents with sccache (RUSTC_WRAPPER)
+    #[arg(long)]
+    pub sccache: bool,
+
+    /// Kill `cargo fetch` (and everything it spawned) after this long, e.g. 90s, 10m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub fetch_timeout: Option<Duration>,
+
+    /// Kill `cargo check` after this long and record the step as TIMEOUT
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub check_timeout: Option<Duration>,
+
+    /// Kill `cargo test` after this long and record the step as TIMEOUT
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub test_timeout: Option<Duration>,
+
+    /// Rerun a failed `cargo test` up to N more times; a dependent that passes on a retry is reported FLAKY
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub test_retries: u32,
+
+    /// Report steps (check, build, test, ...) this many times slower with an offered version
+    /// than with the baseline under "Performance impact"
+    #[arg(long, value_name = "RATIO", default_value_t = crate::build_times::DEFAULT_THRESHOLD, value_parser = parse_ratio)]
+    pub slowdown_threshold: f64,
+
+    /// Memory (address space) limit for each process of a build job, e.g. 4G, 512M
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_memory: Option<u64>,
+
+    /// CPU time limit for each process of a build job, e.g. 20m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_cpu_time: Option<Duration>,
+
+    /// Stop starting dependents once all builds together used this much CPU time
+    #[arg(long, value_name = "HOURS")]
+    pub max_total_cpu_hours: Option<f64>,
+
+    /// Stop starting dependents once this many MiB were downloaded (by us and by cargo)
+    #[arg(long, value_name = "MB")]
+    pub max_network_mb: Option<u64>,
+
+    /// Stop starting dependents once this many cargo fetch/check/test processes ran
+    #[arg(long, value_name = "N")]
+    pub max_processes: Option<u64>,
+
+    /// Stop starting dependents once this many have regressed, and report what was found
+    #[arg(long, value_name = "N")]
+    pub max_regressions: Option<usize>,
+
+    /// Stop starting dependents at the first regression (--max-regressions 1)
+    #[arg(long, conflicts_with = "max_regressions")]
+    pub fail_fast: bool,
+
+    /// Stop starting dependents when this much wall time is nearly used, e.g. 30m, 2h;
+    /// those not started are reported NOT_RUN
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_duration: Option<Duration>,
+
+    /// Kill builds still running this long after --max-duration ran out (default: let them finish)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "max_duration")]
+    pub grace_period: Option<Duration>,
+
+    /// Target triples for dependents that only build on another OS: they are
+    /// checked with `cargo check --target` instead of counted BROKEN
+    /// Example: --targets x86_64-pc-windows-msvc,aarch64-apple-darwin
+    #[arg(long, value_name = "TRIPLE", value_delimiter = ',')]
+    pub targets: Vec<String>,
+
+    /// Test every dependent with each of these rustup toolchains, each against its own
+    /// baseline, to catch breakage coming with the next rustc release
+    /// Example: --channels stable,beta,nightly
+    #[arg(long, value_name = "TOOLCHAIN", value_delimiter = ',', conflicts_with_all = ["serve", "worker"])]
+    pub channels: Vec<String>,
+
+    /// Only test dependents on this edition or newer
+    #[arg(long, value_name = "EDITION", value_parser = crate::manifest::EDITIONS)]
+    pub min_edition: Option<String>,
+
+    /// Use a local registry (index/ + .crate files, as from `cargo local-registry`)
+    /// for all lookups, downloads and builds instead of crates.io
+    #[arg(long, value_name = "PATH")]
+    pub registry_snapshot: Option<PathBuf>,
+
+    /// Discover dependents on, and patch over, this registry from cargo's config
+    /// (registries.<NAME>.index) instead of crates.io, e.g. a Kellnr or Artifactory instance
+    #[arg(long, value_name = "NAME", conflicts_with = "registry_snapshot")]
+    pub registry: Option<String>,
+
+    /// Sparse index of the registry to use instead of crates.io (sparse+https://...);
+    /// with --registry, overrides the configured index
+    #[arg(long, value_name = "URL", conflicts_with = "registry_snapshot")]
+    pub index_url: Option<String>,
+
+    /// Reuse cached crates.io responses (reverse dependencies, versions) younger
+    /// than this, e.g. 30m, 12h; 0 disables the cache
+    #[arg(long, value_name = "DURATION", default_value = "1h", value_parser = parse_cache_ttl)]
+    pub api_cache_ttl: Duration,
+
+    /// Forbid network access: use only cached .crate files, cached crates.io responses
+    /// (whatever their age) and local paths, and list what's missing from the cache
+    #[arg(long, conflicts_with_all = ["registry", "index_url", "github_dependents"])]
+    pub offline: bool,
+
+    /// Hand the dependents out over HTTP to workers (see --worker) instead of testing
+    /// them here, listening on ADDR, e.g. 0.0.0.0:7878; the report is written here as usual
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["worker", "strategy"])]
+    pub serve: Option<String>,
+
+    /// Test the dependents handed out by the coordinator at URL (see --serve) until none
+    /// are left; run from the same crate checkout with the same flags as the coordinator
+    #[arg(long, value_name = "URL")]
+    pub worker: Option<String>,
+
+    /// Dependents' committed Cargo.lock: delete it and resolve from scratch, respect it
+    /// (the baseline is fetched --locked), or run both ways and report where they differ
+    #[arg(long, value_name = "MODE", value_enum, default_value_t = crate::lockfile::LockfileMode::Delete)]
+    pub lockfile: crate::lockfile::LockfileMode,
+
+    /// Resolve each dependent with `-Z minimal-versions` (needs a nightly toolchain), to
+    /// catch lower bounds too loose for the offered version, in the base crate or its dependents
+    #[arg(long)]
+    pub minimal_versions: bool,
+
+    /// Rebuild baselines instead of reusing results cached in the staging dir
+    /// (cached per dependent version, base crate version and toolchain)
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Config file with per-dependent settings [default: ./copter.toml if present]
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Write zstd-compressed per-dependent logs and an index.json to this directory
+    /// [default: $COPTER_HOME/artifacts]
+    #[arg(long, value_name = "PATH", num_args = 0..=1)]
+    pub artifacts_dir: Option<Option<PathBuf>>,
+
+    /// Write regression diagnostics as SARIF (for code-scanning uploads)
+    #[arg(long, value_name = "PATH")]
+    pub sarif: Option<PathBuf>,
+
+    /// Save every dependent's outcome to FILE, for a later --compare-baseline
+    #[arg(long, value_name = "FILE")]
+    pub save_baseline: Option<PathBuf>,
+
+    /// Compare with the outcomes saved by --save-baseline: dependents that already failed
+    /// there the same way are reported but don't fail the run; new failures still do
+    #[arg(long, value_name = "FILE")]
+    pub compare_baseline: Option<PathBuf>,
+
+    /// Write the results as a markdown pull request comment: a status table plus a
+    /// collapsed section per dependent that didn't pass
+    #[arg(long, value_name = "PATH")]
+    pub pr_comment: Option<PathBuf>,
+
+    /// Start the PR comment with a hidden <!-- cargo-copter:ID --> line, so a bot can
+    /// find and update its earlier comment instead of posting another
+    #[arg(long, value_name = "ID", requires = "pr_comment")]
+    pub update_marker: Option<String>,
+
+    /// Exit nonzero on: none, regressed (regressions), broken (also already-broken dependents),
+    /// or error (also dependents that couldn't be tested)
+    #[arg(long, value_name = "WHEN", value_enum, default_value_t = crate::report::FailOn::Regressed)]
+    pub fail_on: crate::report::FailOn,
+
+    /// Color console output: auto (terminals, unless NO_COLOR is set), always, or never
+    #[arg(long, value_name = "WHEN", value_enum, default_value_t = crate::report::ColorChoice::Auto)]
+    pub color: crate::report::ColorChoice,
+
+    /// Draw the console table and status icons with plain ASCII (for limited terminals and log viewers)
+    #[arg(long)]
+    pub ascii: bool,
+
+    /// Console table width in columns [default: terminal width, or 120]
+    #[arg(long, value_name = "N")]
+    pub table_width: Option<usize>,
+
+    /// Soft-wrap long dependent names and error lines in the console table instead of truncating them
+    #[arg(long)]
+    pub wrap: bool,
+
+    /// Echo cargo output live, prefixed with the dependent: -v for stderr, -vv also stdout
+    #[arg(long, short = 'v', action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Hide the per-step status lines
+    #[arg(long, short = 'q')]
+    pub quiet: bool,
+
+    /// Write the full run log (status lines, debug details, cargo stderr) as JSON Lines,
+    /// whatever RUST_LOG says
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Failure log format: text (copter-failures.log) or json (copter-failures.jsonl, one failure
+    /// per line with its parsed diagnostics)
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = crate::compile::FailureLogFormat::Text)]
+    pub failure_log_format: crate::compile::FailureLogFormat,
+
+    /// POST a JSON summary of the run to this webhook when it completes
+    /// (Slack incoming webhooks accept it as is)
+    #[arg(long, value_name = "URL")]
+    pub notify_url: Option<String>,
+
+    /// Write the results as a JUnit XML test report, a test case per dependent and
+    /// version (for GitLab's artifacts:reports:junit and other CI test views)
+    #[arg(long, value_name = "PATH")]
+    pub junit: Option<PathBuf>,
+
+    /// Print TeamCity service messages as results arrive, a test per dependent and
+    /// version [default: on when TEAMCITY_VERSION is set]
+    #[arg(long)]
+    pub teamcity: bool,
 
     /// Skip cargo check (only run tests)
     #[arg(long)]
@@ -55,21 +367,217 @@ pub struct CliArgs {
     #[arg(long)]
     pub no_test: bool,
 
+    /// full: check and test every dependent; quick-then-deep: check all dependents first,
+    /// then fully test only those whose check outcome differs from the baseline
+    #[arg(long, value_name = "STRATEGY", value_enum, default_value_t = crate::schedule::Strategy::Full, conflicts_with = "no_test")]
+    pub strategy: crate::schedule::Strategy,
+
+    /// Also run `cargo build --all-targets` between check and test, to catch link and codegen failures;
+    /// the (debug profile) sizes of the dependent's libraries and binaries are compared with the baseline's
+    #[arg(long)]
+    pub full_build: bool,
+
+    /// Also build dependents' binaries the way `cargo install` does (`cargo build --release --bins`),
+    /// for CLI tools among the dependents
+    #[arg(long)]
+    pub install: bool,
+
+    /// Also run `cargo clippy` after check; new lint errors (lints the dependent denies) count as regressions
+    #[arg(long)]
+    pub clippy: bool,
+
+    /// Also run `cargo doc --no-deps` after check, denying broken intra-doc links
+    #[arg(long)]
+    pub doc: bool,
+
+    /// Run doctests as their own `cargo test --doc` step, with their own mark
+    #[arg(long)]
+    pub doctest: bool,
+
+    /// Also run these dependents' tests under Miri (`cargo +nightly miri test`) to catch
+    /// undefined behavior; slow, so only for dependents that rely on unsafe layout assumptions
+    #[arg(long, value_name = "NAME", num_args = 1..)]
+    pub miri: Vec<String>,
+
     /// Output results as JSON
     #[arg(long)]
     pub json: bool,
 
+    /// Fail the run on infrastructure warnings (version mismatches, unverifiable
+    /// or wildcard baselines, cargo tree failures) instead of just listing them
+    #[arg(long)]
+    pub strict: bool,
+
     /// Force testing specific versions, bypassing semver requirements
     /// Accepts multiple versions like --test-versions (e.g., "0.7.0 1.0.0-rc.1")
     /// These versions are tested even if they don't satisfy dependent's requirements
     #[arg(long, value_name = "VERSION", num_args = 0..)]
     pub force_versions: Vec<String>,
+
+    /// For each regressed dependent, binary-search the published versions in
+    /// this range for the first one that breaks it
+    /// Example: --bisect 0.8.40..0.8.91
+    #[arg(long, value_name = "FROM..TO", value_parser = crate::bisect::BisectRange::parse)]
+    pub bisect: Option<crate::bisect::BisectRange>,
+
+    /// Diff the base crate's public API (rustdoc JSON; needs a nightly toolchain) between each
+    /// regressed dependent's baseline and offered version, and name the removed or changed
+    /// item behind each regression
+    #[arg(long)]
+    pub api_diff: bool,
+}
+
+/// `argv` without the subcommand name cargo inserts (`cargo copter --top-dependents 5`
+/// runs `cargo-copter copter --top-dependents 5`), and whether there was one
+fn strip_cargo_subcommand(mut argv: Vec<OsString>) -> (Vec<OsString>, bool) {
+    let via_cargo = argv.get(1).and_then(|arg| arg.to_str()).is_some_and(|arg| SUBCOMMAND_NAMES.contains(&arg));
+    if via_cargo {
+        argv.remove(1);
+    }
+    (argv, via_cargo)
+}
+
+/// Parse `KEY=VALUE` for --env
+fn parse_env_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
+/// Parse a duration like "90", "90s", "10m" or "1h" (bare numbers are seconds)
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, unit_secs) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    let value: u64 = number.trim().parse()
+        .map_err(|_| format!("invalid duration '{}' (expected e.g. 90s, 10m, 1h)", s))?;
+    if value == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(Duration::from_secs(value * unit_secs))
+}
+
+/// Parse a slowdown ratio like "2" or "1.5"
+fn parse_ratio(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(ratio) if ratio > 1.0 => Ok(ratio),
+        Ok(_) => Err("ratio must be greater than 1".to_string()),
+        Err(_) => Err(format!("invalid ratio '{}' (expected e.g. 2 or 1.5)", s)),
+    }
+}
+
+/// Parse a cache TTL: a duration, or 0 to disable caching
+fn parse_cache_ttl(s: &str) -> Result<Duration, String> {
+    if s.trim() == "0" {
+        return Ok(Duration::ZERO);
+    }
+    parse_duration(s)
+}
+
+/// Parse a byte size like "4G", "512M", "512MiB" or "1048576" (binary units)
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits_end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits_end);
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("invalid size '{}' (expected e.g. 512M, 4G)", s)),
+    };
+    let value: u64 = number.parse()
+        .map_err(|_| format!("invalid size '{}' (expected e.g. 512M, 4G)", s))?;
+    if value == 0 {
+        return Err("size must be greater than zero".to_string());
+    }
+    value.checked_mul(multiplier).ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+/// Maintenance subcommands (the default, with no subcommand, runs the tests)
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Manage the local download/staging cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Compare two runs' copter-report.json files and show what changed
+    Diff {
+        /// Report from the earlier run
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+        /// Report from the later run
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+    },
+    /// Show past runs from the results database ($COPTER_HOME/results.db)
+    History {
+        /// Show one dependent's outcomes and when its current regression began
+        #[arg(long, value_name = "NAME")]
+        dependent: Option<String>,
+        /// Maximum number of runs to show
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+    /// Write a starter copter.toml for the crate (and optionally a GitHub
+    /// Actions workflow), then check the tools a run needs
+    Init {
+        /// Number of top dependents to suggest
+        #[arg(long, value_name = "N", default_value = "10")]
+        top: usize,
+        /// Also write .github/workflows/copter.yml, testing dependents on pull requests
+        #[arg(long)]
+        workflow: bool,
+        /// Replace existing files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print where staging, caches, the results database and artifacts live
+    Paths,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// Bundle the crate cache and staging directory into a .tar.zst archive
+    Export {
+        /// Archive to write (e.g. cache.tar.zst)
+        #[arg(value_name = "FILE")]
+        archive: PathBuf,
+    },
+    /// Restore the crate cache and staging directory from a .tar.zst archive
+    Import {
+        /// Archive produced by `cache export`
+        #[arg(value_name = "FILE")]
+        archive: PathBuf,
+    },
+    /// Remove crate-cache and staging entries, least recently used first
+    Clean {
+        /// Keep the most recently used entries that fit in this size, e.g. 20G
+        /// (without it, everything is removed)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 impl CliArgs {
     /// Parse command-line arguments
     pub fn parse_args() -> Self {
-        let mut args = CliArgs::parse();
+        let (argv, via_cargo) = strip_cargo_subcommand(std::env::args_os().collect());
+        let mut command = CliArgs::command();
+        if via_cargo {
+            command = command.bin_name("cargo copter");
+        }
+        let mut args = CliArgs::from_arg_matches(&command.get_matches_from(argv)).unwrap_or_else(|e| e.exit());
 
         // Split test_versions on whitespace to support quoted lists like '0.8.51 0.8.91-alpha.3'
         args.test_versions = args.test_versions
@@ -88,16 +596,42 @@ impl CliArgs {
 
     /// Validate argument combinations
     pub fn validate(&self) -> Result<(), String> {
+        // Subcommands don't test anything, so the test options don't apply
+        if self.command.is_some() {
+            return Ok(());
+        }
+
         // Can't skip both check and test
         if self.no_check && self.no_test {
             return Err("Cannot specify both --no-check and --no-test".to_string());
         }
 
-        // Need at least one of: top_dependents, dependents, or dependent_paths
+        // Need at least one of: top_dependents, dependents, dependents_git,
+        // github_dependents, dependent_paths or workspace_dependents
         if self.top_dependents == 0
             && self.dependents.is_empty()
-            && self.dependent_paths.is_empty() {
-            return Err("Must specify at least one of: --top-dependents, --dependents, or --dependent-paths".to_string());
+            && self.dependents_git.is_empty()
+            && self.github_dependents == 0
+            && self.dependent_paths.is_empty()
+            && self.workspace_dependents.is_empty() {
+            return Err("Must specify at least one of: --top-dependents, --dependents, --dependents-git, --github-dependents, --dependent-paths or --workspace-dependents".to_string());
+        }
+
+        self.parse_dependent_features()?;
+        self.parse_dependents_git()?;
+
+        // The committed Cargo.lock pins versions minimal-versions would lower
+        if self.minimal_versions && self.lockfile != crate::lockfile::LockfileMode::Delete {
+            return Err("--minimal-versions resolves from scratch and needs --lockfile delete".to_string());
+        }
+
+        // Snapshots carry neither download counts nor release dates
+        if self.registry_snapshot.is_some() && (self.min_downloads > 0 || self.updated_within.is_some()) {
+            return Err("--min-downloads and --updated-within need crates.io data and can't be used with --registry-snapshot".to_string());
+        }
+
+        if self.table_width.is_some_and(|width| width < MIN_TABLE_WIDTH) {
+            return Err(format!("--table-width must be at least {}", MIN_TABLE_WIDTH));
         }
 
         // Validate jobs >= 1
@@ -105,9 +639,16 @@ impl CliArgs {
             return Err("--jobs must be at least 1".to_string());
         }
 
+        if self.max_total_cpu_hours.is_some_and(|hours| hours.is_nan() || hours <= 0.0) {
+            return Err("--max-total-cpu-hours must be greater than 0".to_string());
+        }
+        if self.max_network_mb == Some(0) || self.max_processes == Some(0) || self.max_regressions == Some(0) {
+            return Err("--max-network-mb, --max-processes and --max-regressions must be at least 1".to_string());
+        }
+
         // Check if we have a way to determine the crate name
-        let has_path = self.path.is_some();
-        let has_crate = self.crate_name.is_some();
+        let has_path = !self.path.is_empty();
+        let has_crate = !self.crate_name.is_empty();
         let has_local_manifest = std::path::Path::new("./Cargo.toml").exists();
 
         if !has_path && !has_crate && !has_local_manifest {
@@ -117,15 +658,57 @@ impl CliArgs {
                     .to_string(),
             );
         }
+        if self.crate_name.len() > 1 && self.crate_name.len() != self.path.len() {
+            return Err("With several --crate, give each one a --path, in the same order".to_string());
+        }
 
         Ok(())
     }
 
+    /// Crates patched alongside the one under test: the --path entries after the
+    /// first, with the --crate name given for each, if any
+    pub fn companion_crates(&self) -> Vec<(Option<&str>, &Path)> {
+        self.path.iter().enumerate().skip(1)
+            .map(|(i, path)| (self.crate_name.get(i).map(String::as_str), path.as_path()))
+            .collect()
+    }
+
+    /// Parse --dependent-features into dependent name -> features
+    pub fn parse_dependent_features(&self) -> Result<HashMap<String, Vec<String>>, String> {
+        let mut map = HashMap::new();
+        for spec in &self.dependent_features {
+            let (name, features) = spec.split_once('=')
+                .ok_or_else(|| format!("--dependent-features expects NAME=FEAT1,FEAT2, got '{}'", spec))?;
+            let features: Vec<String> = features.split(',')
+                .map(|f| f.trim().to_string())
+                .filter(|f| !f.is_empty())
+                .collect();
+            map.insert(name.trim().to_string(), features);
+        }
+        Ok(map)
+    }
+
+    /// Parse --dependents-git into (dependent name, URL[#BRANCH]) pairs
+    pub fn parse_dependents_git(&self) -> Result<Vec<(String, String)>, String> {
+        self.dependents_git.iter().map(|spec| {
+            // A NAME= prefix never contains URL punctuation
+            let (name, source) = match spec.split_once('=') {
+                Some((name, source)) if !name.contains([':', '/']) => (name.trim().to_string(), source),
+                _ => (GitSource::parse(spec).repo_name().to_string(), spec.as_str()),
+            };
+            if name.is_empty() || source.is_empty() {
+                return Err(format!("--dependents-git expects [NAME=]URL[#BRANCH], got '{}'", spec));
+            }
+            Ok((name, source.to_string()))
+        }).collect()
+    }
+
     /// Check if we're testing local paths only (no network required)
+    #[allow(dead_code)]
     pub fn is_offline_mode(&self) -> bool {
         self.dependents.is_empty()
             && self.top_dependents == 0
-            && !self.dependent_paths.is_empty()
+            && !(self.dependent_paths.is_empty() && self.workspace_dependents.is_empty())
     }
 }
 
@@ -136,19 +719,93 @@ mod tests {
     #[test]
     fn test_validate_both_no_flags_fails() {
         let args = CliArgs {
-            path: None,
-            crate_name: None,
+            command: None,
+            path: vec![],
+            crate_name: vec![],
             top_dependents: 5,
+            min_downloads: 0,
+            updated_within: None,
+            maintenance: false,
+            only_active: false,
             dependents: vec![],
+            dependent_prerelease: crate::api::PrereleasePolicy::Include,
             dependent_paths: vec![],
+            workspace_dependents: vec![],
+            dependents_git: vec![],
+            github_dependents: 0,
             test_versions: vec![],
             force_versions: vec![],
+            bisect: None,
+            api_diff: false,
+            download_jobs: 8,
+            dependent_features: vec![],
             jobs: 1,
             output: PathBuf::from("report.html"),
-            staging_dir: PathBuf::from(".copter/staging"),
+            mermaid_cdn: false,
+            staging_dir: None,
+            max_cache_size: None,
+            shared_target_dir: None,
+            sccache: false,
+            env: vec![],
+            rustflags: None,
+            deny_warnings: false,
+            fetch_timeout: None,
+            check_timeout: None,
+            test_timeout: None,
+            test_retries: 0,
+            slowdown_threshold: 2.0,
+            max_memory: None,
+            max_cpu_time: None,
+            max_total_cpu_hours: None,
+            max_network_mb: None,
+            max_processes: None,
+            max_regressions: None,
+            fail_fast: false,
+            max_duration: None,
+            grace_period: None,
+            min_edition: None,
+            targets: vec![],
+            channels: vec![],
+            registry_snapshot: None,
+            registry: None,
+            index_url: None,
+            api_cache_ttl: Duration::from_secs(3600),
+            offline: false,
+            serve: None,
+            worker: None,
+            lockfile: crate::lockfile::LockfileMode::Delete,
+            minimal_versions: false,
+            no_cache: false,
+            config: None,
+            artifacts_dir: None,
+            sarif: None,
+            save_baseline: None,
+            compare_baseline: None,
+            pr_comment: None,
+            update_marker: None,
+            notify_url: None,
+            junit: None,
+            teamcity: false,
+            fail_on: crate::report::FailOn::Regressed,
+            color: crate::report::ColorChoice::Auto,
+            ascii: false,
+            table_width: None,
+            wrap: false,
+            verbose: 0,
+            quiet: false,
+            log_file: None,
+            failure_log_format: crate::compile::FailureLogFormat::Text,
             no_check: true,
             no_test: true,
+            strategy: crate::schedule::Strategy::Full,
+            full_build: false,
+            install: false,
+            clippy: false,
+            doc: false,
+            doctest: false,
+            miri: vec![],
             json: false,
+            strict: false,
         };
         assert!(args.validate().is_err());
     }
@@ -156,19 +813,93 @@ mod tests {
     #[test]
     fn test_validate_zero_jobs_fails() {
         let args = CliArgs {
-            path: None,
-            crate_name: None,
+            command: None,
+            path: vec![],
+            crate_name: vec![],
             top_dependents: 5,
+            min_downloads: 0,
+            updated_within: None,
+            maintenance: false,
+            only_active: false,
             dependents: vec![],
+            dependent_prerelease: crate::api::PrereleasePolicy::Include,
             dependent_paths: vec![],
+            workspace_dependents: vec![],
+            dependents_git: vec![],
+            github_dependents: 0,
             test_versions: vec![],
             force_versions: vec![],
+            bisect: None,
+            api_diff: false,
+            download_jobs: 8,
+            dependent_features: vec![],
             jobs: 0,
             output: PathBuf::from("report.html"),
-            staging_dir: PathBuf::from(".copter/staging"),
+            mermaid_cdn: false,
+            staging_dir: None,
+            max_cache_size: None,
+            shared_target_dir: None,
+            sccache: false,
+            env: vec![],
+            rustflags: None,
+            deny_warnings: false,
+            fetch_timeout: None,
+            check_timeout: None,
+            test_timeout: None,
+            test_retries: 0,
+            slowdown_threshold: 2.0,
+            max_memory: None,
+            max_cpu_time: None,
+            max_total_cpu_hours: None,
+            max_network_mb: None,
+            max_processes: None,
+            max_regressions: None,
+            fail_fast: false,
+            max_duration: None,
+            grace_period: None,
+            min_edition: None,
+            targets: vec![],
+            channels: vec![],
+            registry_snapshot: None,
+            registry: None,
+            index_url: None,
+            api_cache_ttl: Duration::from_secs(3600),
+            offline: false,
+            serve: None,
+            worker: None,
+            lockfile: crate::lockfile::LockfileMode::Delete,
+            minimal_versions: false,
+            no_cache: false,
+            config: None,
+            artifacts_dir: None,
+            sarif: None,
+            save_baseline: None,
+            compare_baseline: None,
+            pr_comment: None,
+            update_marker: None,
+            notify_url: None,
+            junit: None,
+            teamcity: false,
+            fail_on: crate::report::FailOn::Regressed,
+            color: crate::report::ColorChoice::Auto,
+            ascii: false,
+            table_width: None,
+            wrap: false,
+            verbose: 0,
+            quiet: false,
+            log_file: None,
+            failure_log_format: crate::compile::FailureLogFormat::Text,
             no_check: false,
             no_test: false,
+            strategy: crate::schedule::Strategy::Full,
+            full_build: false,
+            install: false,
+            clippy: false,
+            doc: false,
+            doctest: false,
+            miri: vec![],
             json: false,
+            strict: false,
         };
         assert!(args.validate().is_err());
     }
@@ -179,19 +910,93 @@ mod tests {
         std::fs::write("./Cargo.toml.test", "[package]\nname = \"test\"\nversion = \"0.1.0\"\n").ok();
 
         let args = CliArgs {
-            path: Some(PathBuf::from("./Cargo.toml.test")),
-            crate_name: None,
+            command: None,
+            path: vec![PathBuf::from("./Cargo.toml.test")],
+            crate_name: vec![],
             top_dependents: 5,
+            min_downloads: 0,
+            updated_within: None,
+            maintenance: false,
+            only_active: false,
             dependents: vec![],
+            dependent_prerelease: crate::api::PrereleasePolicy::Include,
             dependent_paths: vec![],
+            workspace_dependents: vec![],
+            dependents_git: vec![],
+            github_dependents: 0,
             test_versions: vec![],
             force_versions: vec![],
+            bisect: None,
+            api_diff: false,
+            download_jobs: 8,
+            dependent_features: vec![],
             jobs: 1,
             output: PathBuf::from("report.html"),
-            staging_dir: PathBuf::from(".copter/staging"),
+            mermaid_cdn: false,
+            staging_dir: None,
+            max_cache_size: None,
+            shared_target_dir: None,
+            sccache: false,
+            env: vec![],
+            rustflags: None,
+            deny_warnings: false,
+            fetch_timeout: None,
+            check_timeout: None,
+            test_timeout: None,
+            test_retries: 0,
+            slowdown_threshold: 2.0,
+            max_memory: None,
+            max_cpu_time: None,
+            max_total_cpu_hours: None,
+            max_network_mb: None,
+            max_processes: None,
+            max_regressions: None,
+            fail_fast: false,
+            max_duration: None,
+            grace_period: None,
+            min_edition: None,
+            targets: vec![],
+            channels: vec![],
+            registry_snapshot: None,
+            registry: None,
+            index_url: None,
+            api_cache_ttl: Duration::from_secs(3600),
+            offline: false,
+            serve: None,
+            worker: None,
+            lockfile: crate::lockfile::LockfileMode::Delete,
+            minimal_versions: false,
+            no_cache: false,
+            config: None,
+            artifacts_dir: None,
+            sarif: None,
+            save_baseline: None,
+            compare_baseline: None,
+            pr_comment: None,
+            update_marker: None,
+            notify_url: None,
+            junit: None,
+            teamcity: false,
+            fail_on: crate::report::FailOn::Regressed,
+            color: crate::report::ColorChoice::Auto,
+            ascii: false,
+            table_width: None,
+            wrap: false,
+            verbose: 0,
+            quiet: false,
+            log_file: None,
+            failure_log_format: crate::compile::FailureLogFormat::Text,
             no_check: false,
             no_test: false,
+            strategy: crate::schedule::Strategy::Full,
+            full_build: false,
+            install: false,
+            clippy: false,
+            doc: false,
+            doctest: false,
+            miri: vec![],
             json: false,
+            strict: false,
         };
         let result = args.validate();
         std::fs::remove_file("./Cargo.toml.test").ok();
@@ -201,19 +1006,93 @@ mod tests {
     #[test]
     fn test_is_offline_mode() {
         let args = CliArgs {
-            path: None,
-            crate_name: None,
+            command: None,
+            path: vec![],
+            crate_name: vec![],
             top_dependents: 0,
+            min_downloads: 0,
+            updated_within: None,
+            maintenance: false,
+            only_active: false,
             dependents: vec![],
+            dependent_prerelease: crate::api::PrereleasePolicy::Include,
             dependent_paths: vec![PathBuf::from("/tmp/crate")],
+            workspace_dependents: vec![],
+            dependents_git: vec![],
+            github_dependents: 0,
             test_versions: vec![],
             force_versions: vec![],
+            bisect: None,
+            api_diff: false,
+            download_jobs: 8,
+            dependent_features: vec![],
             jobs: 1,
             output: PathBuf::from("report.html"),
-            staging_dir: PathBuf::from(".copter/staging"),
+            mermaid_cdn: false,
+            staging_dir: None,
+            max_cache_size: None,
+            shared_target_dir: None,
+            sccache: false,
+            env: vec![],
+            rustflags: None,
+            deny_warnings: false,
+            fetch_timeout: None,
+            check_timeout: None,
+            test_timeout: None,
+            test_retries: 0,
+            slowdown_threshold: 2.0,
+            max_memory: None,
+            max_cpu_time: None,
+            max_total_cpu_hours: None,
+            max_network_mb: None,
+            max_processes: None,
+            max_regressions: None,
+            fail_fast: false,
+            max_duration: None,
+            grace_period: None,
+            min_edition: None,
+            targets: vec![],
+            channels: vec![],
+            registry_snapshot: None,
+            registry: None,
+            index_url: None,
+            api_cache_ttl: Duration::from_secs(3600),
+            offline: false,
+            serve: None,
+            worker: None,
+            lockfile: crate::lockfile::LockfileMode::Delete,
+            minimal_versions: false,
+            no_cache: false,
+            config: None,
+            artifacts_dir: None,
+            sarif: None,
+            save_baseline: None,
+            compare_baseline: None,
+            pr_comment: None,
+            update_marker: None,
+            notify_url: None,
+            junit: None,
+            teamcity: false,
+            fail_on: crate::report::FailOn::Regressed,
+            color: crate::report::ColorChoice::Auto,
+            ascii: false,
+            table_width: None,
+            wrap: false,
+            verbose: 0,
+            quiet: false,
+            log_file: None,
+            failure_log_format: crate::compile::FailureLogFormat::Text,
             no_check: false,
             no_test: false,
+            strategy: crate::schedule::Strategy::Full,
+            full_build: false,
+            install: false,
+            clippy: false,
+            doc: false,
+            doctest: false,
+            miri: vec![],
             json: false,
+            strict: false,
         };
         assert!(args.is_offline_mode());
     }
@@ -221,20 +1100,269 @@ mod tests {
     #[test]
     fn test_not_offline_mode_with_dependents() {
         let args = CliArgs {
-            path: None,
-            crate_name: None,
+            command: None,
+            path: vec![],
+            crate_name: vec![],
             top_dependents: 0,
+            min_downloads: 0,
+            updated_within: None,
+            maintenance: false,
+            only_active: false,
             dependents: vec!["serde".to_string()],
+            dependent_prerelease: crate::api::PrereleasePolicy::Include,
             dependent_paths: vec![],
+            workspace_dependents: vec![],
+            dependents_git: vec![],
+            github_dependents: 0,
             test_versions: vec![],
             force_versions: vec![],
+            bisect: None,
+            api_diff: false,
+            download_jobs: 8,
+            dependent_features: vec![],
             jobs: 1,
             output: PathBuf::from("report.html"),
-            staging_dir: PathBuf::from(".copter/staging"),
+            mermaid_cdn: false,
+            staging_dir: None,
+            max_cache_size: None,
+            shared_target_dir: None,
+            sccache: false,
+            env: vec![],
+            rustflags: None,
+            deny_warnings: false,
+            fetch_timeout: None,
+            check_timeout: None,
+            test_timeout: None,
+            test_retries: 0,
+            slowdown_threshold: 2.0,
+            max_memory: None,
+            max_cpu_time: None,
+            max_total_cpu_hours: None,
+            max_network_mb: None,
+            max_processes: None,
+            max_regressions: None,
+            fail_fast: false,
+            max_duration: None,
+            grace_period: None,
+            min_edition: None,
+            targets: vec![],
+            channels: vec![],
+            registry_snapshot: None,
+            registry: None,
+            index_url: None,
+            api_cache_ttl: Duration::from_secs(3600),
+            offline: false,
+            serve: None,
+            worker: None,
+            lockfile: crate::lockfile::LockfileMode::Delete,
+            minimal_versions: false,
+            no_cache: false,
+            config: None,
+            artifacts_dir: None,
+            sarif: None,
+            save_baseline: None,
+            compare_baseline: None,
+            pr_comment: None,
+            update_marker: None,
+            notify_url: None,
+            junit: None,
+            teamcity: false,
+            fail_on: crate::report::FailOn::Regressed,
+            color: crate::report::ColorChoice::Auto,
+            ascii: false,
+            table_width: None,
+            wrap: false,
+            verbose: 0,
+            quiet: false,
+            log_file: None,
+            failure_log_format: crate::compile::FailureLogFormat::Text,
             no_check: false,
             no_test: false,
+            strategy: crate::schedule::Strategy::Full,
+            full_build: false,
+            install: false,
+            clippy: false,
+            doc: false,
+            doctest: false,
+            miri: vec![],
             json: false,
+            strict: false,
         };
         assert!(!args.is_offline_mode());
     }
+
+    #[test]
+    fn test_parse_dependents_git() {
+        let args = CliArgs::parse_from([
+            "cargo-copter", "--dependents-git",
+            "https://github.com/image-rs/image.git#main",
+            "png=https://github.com/image-rs/image-png",
+        ]);
+        assert_eq!(args.parse_dependents_git().unwrap(), vec![
+            ("image".to_string(), "https://github.com/image-rs/image.git#main".to_string()),
+            ("png".to_string(), "https://github.com/image-rs/image-png".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("ten minutes").is_err());
+
+        let args = CliArgs::parse_from(["cargo-copter", "--test-timeout", "15m"]);
+        assert_eq!(args.test_timeout, Some(Duration::from_secs(900)));
+        assert_eq!(args.check_timeout, None);
+    }
+
+    #[test]
+    fn test_parse_cache_ttl() {
+        assert_eq!(parse_cache_ttl("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_cache_ttl("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(CliArgs::parse_from(["cargo-copter"]).api_cache_ttl, Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_parse_dependent_prerelease() {
+        use crate::api::PrereleasePolicy;
+        assert_eq!(CliArgs::parse_from(["cargo-copter"]).dependent_prerelease, PrereleasePolicy::Include);
+        let args = CliArgs::parse_from(["cargo-copter", "--dependent-prerelease", "only"]);
+        assert_eq!(args.dependent_prerelease, PrereleasePolicy::Only);
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--dependent-prerelease", "rc"]).is_err());
+    }
+
+    #[test]
+    fn test_companion_crates() {
+        let args = CliArgs::parse_from(["cargo-copter", "--crate", "foo", "--path", "foo",
+            "--crate", "foo-core", "--path", "core", "--path", "derive", "--dependents", "bar"]);
+        assert!(args.validate().is_err(), "three paths but only two names");
+        let args = CliArgs::parse_from(["cargo-copter", "--path", "foo", "--path", "core", "--path", "derive", "--dependents", "bar"]);
+        assert!(args.validate().is_ok());
+        assert_eq!(args.companion_crates(), vec![(None, Path::new("core")), (None, Path::new("derive"))]);
+        let args = CliArgs::parse_from(["cargo-copter", "-c", "foo", "-p", "foo", "-c", "foo-core", "-p", "core", "--dependents", "bar"]);
+        assert_eq!(args.companion_crates(), vec![(Some("foo-core"), Path::new("core"))]);
+    }
+
+    #[test]
+    fn test_minimal_versions_conflicts_with_lockfile() {
+        let parse = |lockfile: &str| CliArgs::parse_from(["cargo-copter", "--dependents", "image", "--minimal-versions", "--lockfile", lockfile]);
+        assert!(CliArgs::parse_from(["cargo-copter", "--minimal-versions"]).minimal_versions);
+        assert!(parse("delete").validate().is_ok());
+        assert!(parse("respect").validate().is_err());
+        assert!(parse("both").validate().is_err());
+    }
+
+    #[test]
+    fn test_offline_conflicts_with_network_sources() {
+        assert!(CliArgs::parse_from(["cargo-copter", "--offline", "--dependents", "image"]).offline);
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--offline", "--github-dependents", "5"]).is_err());
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--offline", "--registry", "kellnr"]).is_err());
+    }
+
+    #[test]
+    fn test_env_and_rustflags() {
+        let args = CliArgs::parse_from(["cargo-copter", "--env", "CARGO_INCREMENTAL=0", "--env", "FOO=a=b",
+            "--rustflags", "-D warnings", "--dependents", "image"]);
+        assert_eq!(args.env, vec![
+            ("CARGO_INCREMENTAL".to_string(), "0".to_string()),
+            ("FOO".to_string(), "a=b".to_string()),
+        ]);
+        assert_eq!(args.rustflags.as_deref(), Some("-D warnings"));
+        assert!(!args.deny_warnings);
+        assert!(CliArgs::parse_from(["cargo-copter", "--deny-warnings"]).deny_warnings);
+        assert_eq!(CliArgs::parse_from(["cargo-copter", "--slowdown-threshold", "1.5"]).slowdown_threshold, 1.5);
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--slowdown-threshold", "1"]).is_err());
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--env", "NOEQUALS"]).is_err());
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--env", "=value"]).is_err());
+    }
+
+    #[test]
+    fn test_strip_cargo_subcommand() {
+        let argv = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        let (stripped, via_cargo) = strip_cargo_subcommand(argv(&["/bin/cargo-copter", "copter", "--top-dependents", "5"]));
+        assert_eq!(stripped, argv(&["/bin/cargo-copter", "--top-dependents", "5"]));
+        assert!(via_cargo);
+        assert_eq!(CliArgs::parse_from(stripped).top_dependents, 5);
+
+        assert_eq!(strip_cargo_subcommand(argv(&["cargo-crusader", "crusader", "diff", "a.json", "b.json"])).0,
+            argv(&["cargo-crusader", "diff", "a.json", "b.json"]));
+        assert_eq!(strip_cargo_subcommand(argv(&["cargo-copter", "--top-dependents", "5"])),
+            (argv(&["cargo-copter", "--top-dependents", "5"]), false));
+        assert_eq!(strip_cargo_subcommand(argv(&["cargo-copter"])), (argv(&["cargo-copter"]), false));
+    }
+
+    #[test]
+    fn test_update_marker_requires_pr_comment() {
+        let args = CliArgs::parse_from(["cargo-copter", "--pr-comment", "comment.md", "--update-marker", "rgb"]);
+        assert_eq!(args.pr_comment, Some(PathBuf::from("comment.md")));
+        assert_eq!(args.update_marker.as_deref(), Some("rgb"));
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--update-marker", "rgb"]).is_err());
+    }
+
+    #[test]
+    fn test_serve_and_worker_conflict() {
+        let coordinator = CliArgs::parse_from(["cargo-copter", "--serve", "0.0.0.0:7878", "--top-dependents", "500"]);
+        assert_eq!(coordinator.serve.as_deref(), Some("0.0.0.0:7878"));
+        let worker = CliArgs::parse_from(["cargo-copter", "--worker", "http://build-1:7878", "--jobs", "8"]);
+        assert_eq!(worker.worker.as_deref(), Some("http://build-1:7878"));
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--serve", "0.0.0.0:7878", "--worker", "http://build-1:7878"]).is_err());
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--serve", "0.0.0.0:7878", "--strategy", "quick-then-deep"]).is_err());
+    }
+
+    #[test]
+    fn test_strategy() {
+        use crate::schedule::Strategy;
+        assert_eq!(CliArgs::parse_from(["cargo-copter"]).strategy, Strategy::Full);
+        assert_eq!(CliArgs::parse_from(["cargo-copter", "--strategy", "quick-then-deep"]).strategy, Strategy::QuickThenDeep);
+        // The deep phase is all tests
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--strategy", "quick-then-deep", "--no-test"]).is_err());
+    }
+
+    #[test]
+    fn test_validate_run_limits() {
+        let args = |extra: &[&str]| {
+            let mut argv = vec!["cargo-copter", "--crate", "rgb", "--dependents", "image"];
+            argv.extend_from_slice(extra);
+            CliArgs::parse_from(argv)
+        };
+        let valid = args(&["--max-total-cpu-hours", "1.5", "--max-network-mb", "500", "--max-processes", "200"]);
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.max_total_cpu_hours, Some(1.5));
+
+        assert!(args(&["--max-total-cpu-hours", "0"]).validate().is_err());
+        assert!(args(&["--max-processes", "0"]).validate().is_err());
+        assert!(args(&["--max-regressions", "0"]).validate().is_err());
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--fail-fast", "--max-regressions", "3"]).is_err());
+
+        let timed = args(&["--max-duration", "30m", "--grace-period", "2m"]);
+        assert_eq!(timed.max_duration, Some(Duration::from_secs(1800)));
+        assert_eq!(timed.grace_period, Some(Duration::from_secs(120)));
+        assert!(CliArgs::try_parse_from(["cargo-copter", "--grace-period", "2m"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576"), Ok(1 << 20));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("4G"), Ok(4 << 30));
+        assert_eq!(parse_size("4GiB"), Ok(4 << 30));
+        assert_eq!(parse_size("64kb"), Ok(64 << 10));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("4X").is_err());
+        assert!(parse_size("G").is_err());
+    }
+
+    #[test]
+    fn test_parse_dependent_features() {
+        let mut args = CliArgs::parse_from(["cargo-copter", "--dependent-features", "image=png, jpeg", "serde=derive"]);
+        let features = args.parse_dependent_features().unwrap();
+        assert_eq!(features["image"], vec!["png", "jpeg"]);
+        assert_eq!(features["serde"], vec!["derive"]);
+
+        args.dependent_features = vec!["image".to_string()];
+        assert!(args.parse_dependent_features().is_err());
+    }
 }
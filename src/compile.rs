@@ -1,51 +1,379 @@
-use std::fs::{self, File, OpenOptions};
+use std::cell::RefCell;
+use std::fs::{self, OpenOptions};
 use std::io::{Write, BufWriter};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::env;
 use std::time::{Duration, Instant};
-use std::sync::Mutex;
+use std::sync::Arc;
 // TempDir not needed since we use persistent staging directories
 use log::debug;
+use crate::budget::Budget;
+use crate::config_file::DependentConfig;
 use crate::error_extract::{Diagnostic, parse_cargo_json};
+use crate::lockfile::{self, CommittedLock, LockedOutcome, LockfileMode, LockPackage};
+use crate::manifest::TargetKinds;
+use crate::tree::{find_intermediates, Intermediate};
+use crate::format::HumanDuration;
 use fs2::FileExt;
-use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+/// Format of the failure log (`--failure-log-format`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FailureLogFormat {
+    /// Readable blocks with the full stdout and stderr (copter-failures.log)
+    #[default]
+    Text,
+    /// One JSON object per failure with its parsed diagnostics (copter-failures.jsonl)
+    Json,
+}
+
+impl FailureLogFormat {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            FailureLogFormat::Text => "copter-failures.log",
+            FailureLogFormat::Json => "copter-failures.jsonl",
+        }
+    }
+}
+
+/// A line of the JSON failure log
+#[derive(Debug, Serialize)]
+struct FailureEntry<'a> {
+    time: String,
+    dependent: &'a str,
+    dependent_version: &'a str,
+    base_crate: &'a str,
+    /// "baseline", "WIP", or the version tested
+    version: &'a str,
+    step: &'a str,
+    command: &'a str,
+    exit: Option<ProcessExit>,
+    timed_out: bool,
+    /// Seconds
+    duration: f64,
+    /// Errors parsed from cargo's JSON messages
+    diagnostics: Vec<&'a Diagnostic>,
+    /// stderr, when no diagnostics could be parsed (fetch and test failures)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr: Option<&'a str>,
+}
+
+impl<'a> FailureEntry<'a> {
+    fn new(dependent: &'a str, dependent_version: &'a str, base_crate: &'a str, test_label: &'a str,
+           command: &'a str, result: &'a CompileResult) -> Self {
+        let diagnostics: Vec<&Diagnostic> = result.diagnostics.iter()
+            .filter(|d| d.level == crate::error_extract::DiagnosticLevel::Error)
+            .collect();
+        let stderr = diagnostics.is_empty().then_some(result.stderr.as_str());
+        FailureEntry {
+            time: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            dependent,
+            dependent_version,
+            base_crate,
+            version: test_label,
+            step: result.step.as_str(),
+            command,
+            exit: result.exit,
+            timed_out: result.timed_out,
+            duration: result.duration.as_secs_f64(),
+            diagnostics,
+            stderr,
+        }
+    }
+}
+
+/// Build output shared across dependents, so common dependencies like serde
+/// and syn are compiled once per run instead of once per dependent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildCache {
+    /// Shared `CARGO_TARGET_DIR`; cargo's own build lock serializes concurrent
+    /// builds into it
+    pub target_dir: Option<PathBuf>,
+    /// `RUSTC_WRAPPER` such as sccache, which caches per compilation unit and
+    /// doesn't serialize parallel jobs
+    pub rustc_wrapper: Option<String>,
+}
+
+impl BuildCache {
+    fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+        if let Some(ref dir) = self.target_dir {
+            vars.push(("CARGO_TARGET_DIR", dir.display().to_string()));
+        }
+        if let Some(ref wrapper) = self.rustc_wrapper {
+            vars.push(("RUSTC_WRAPPER", wrapper.clone()));
+        }
+        vars
+    }
+}
+
+/// Environment for every cargo command run on a dependent (`--env`, `--rustflags`, `--deny-warnings`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CargoEnv {
+    pub vars: Vec<(String, String)>,
+    /// Appended to RUSTFLAGS from `vars` or our own environment
+    pub rustflags: Option<String>,
+    /// Fail the check step on warnings in the dependent's own code
+    pub deny_warnings: bool,
+}
+
+impl CargoEnv {
+    pub fn is_empty(&self) -> bool {
+        *self == CargoEnv::default()
+    }
+
+    /// `key` as cargo will see it: from `vars`, else inherited
+    fn var(&self, key: &str) -> Option<String> {
+        self.vars.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+            .or_else(|| env::var(key).ok())
+    }
+
+    fn env_vars(&self) -> Vec<(String, String)> {
+        let mut vars = self.vars.clone();
+        if let Some(ref flags) = self.rustflags {
+            let base = self.var("RUSTFLAGS").unwrap_or_default();
+            vars.push(("RUSTFLAGS".to_string(), format!("{} {}", base, flags).trim().to_string()));
+        }
+        vars
+    }
+}
+
+thread_local! {
+    /// Toolchain the current thread's dependent is tested with (`--channels`)
+    static TOOLCHAIN: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Test with `toolchain` on this thread until it's set again; None uses the default one
+pub fn set_toolchain(toolchain: Option<&str>) {
+    TOOLCHAIN.with(|t| *t.borrow_mut() = toolchain.map(str::to_string));
+}
+
+/// Selects this thread's toolchain through rustup's proxies, which also pass it to
+/// the rustc and rustdoc cargo runs; an explicit `+toolchain` still wins
+pub fn toolchain_env() -> Option<(&'static str, String)> {
+    TOOLCHAIN.with(|t| t.borrow().clone()).map(|toolchain| ("RUSTUP_TOOLCHAIN", toolchain))
+}
+
+/// Maximum wall-clock time of each step; None waits forever
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepTimeouts {
+    pub fetch: Option<Duration>,
+    pub check: Option<Duration>,
+    pub test: Option<Duration>,
+}
+
+impl StepTimeouts {
+    fn for_step(&self, step: CompileStep) -> Option<Duration> {
+        match step {
+            CompileStep::Fetch => self.fetch,
+            CompileStep::Check | CompileStep::Build | CompileStep::Install | CompileStep::Clippy | CompileStep::Doc => self.check,
+            CompileStep::Test | CompileStep::Doctest | CompileStep::Miri => self.test,
+        }
+    }
+}
+
+/// Limits applied to cargo and inherited by every rustc, build script and
+/// test binary it spawns, so one pathological dependent can't take down the
+/// host during a parallel run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Address space per process, in bytes (RLIMIT_AS)
+    pub memory_bytes: Option<u64>,
+    /// CPU time per process (RLIMIT_CPU); the kernel sends SIGXCPU when it runs out
+    pub cpu_time: Option<Duration>,
+}
+
+impl ResourceLimits {
+    #[cfg(unix)]
+    fn apply(&self, cmd: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        if self.memory_bytes.is_none() && self.cpu_time.is_none() {
+            return;
+        }
+        let limits = *self;
+        // SAFETY: the hook only calls setrlimit, which is async-signal-safe
+        unsafe {
+            cmd.pre_exec(move || {
+                let set = |resource, soft: u64, hard: u64| {
+                    let limit = libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                };
+                if let Some(bytes) = limits.memory_bytes {
+                    set(libc::RLIMIT_AS, bytes, bytes)?;
+                }
+                if let Some(cpu) = limits.cpu_time {
+                    // Hard limit a little later, so SIGXCPU (not SIGKILL) names the cause
+                    let secs = cpu.as_secs().max(1);
+                    set(libc::RLIMIT_CPU, secs, secs + 5)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn apply(&self, _cmd: &mut Command) {
+        if *self != ResourceLimits::default() {
+            debug!("resource limits are only supported on unix");
+        }
+    }
+}
+
+/// Steps run in addition to fetch/check/test (`--full-build`, `--install`, `--clippy`, `--doc`, `--doctest`)
+///
+/// A failed full build or install build counts like a failed check.
+/// Failures of the others only count when the baseline passed the same
+/// step, so a dependent's existing lint or doc problems don't make it BROKEN.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptionalSteps {
+    /// `cargo build --all-targets` between check and test
+    pub full_build: bool,
+    /// `cargo build --release --bins`, as `cargo install` does, for dependents with binaries
+    pub install: bool,
+    /// `cargo clippy` after check
+    pub clippy: bool,
+    /// `cargo doc --no-deps` after check, with broken intra-doc links denied
+    pub doc: bool,
+    /// Doctests as their own `cargo test --doc` step instead of part of test
+    pub doctest: bool,
+}
+
+/// How dependents are built and tested, from the command line; every cargo
+/// command of an ICT run gets it passed down
+#[derive(Debug, Clone, Default)]
+pub struct RunSettings {
+    /// Where failures are logged (`--failure-log-format`)
+    pub failure_log: Option<(PathBuf, FailureLogFormat)>,
+    pub build_cache: BuildCache,
+    pub cargo_env: CargoEnv,
+    pub step_timeouts: StepTimeouts,
+    pub resource_limits: ResourceLimits,
+    /// How many times a failed `cargo test` is rerun before it counts as failed
+    pub test_retries: u32,
+    /// Whether to delete, respect, or try both ways with the dependent's Cargo.lock
+    pub lockfile_mode: LockfileMode,
+    /// Resolve with `-Z minimal-versions` (`--minimal-versions`)
+    pub minimal_versions: bool,
+    pub optional_steps: OptionalSteps,
+    /// Budgets of the whole run, shared by every copy of the settings
+    pub budget: Arc<Budget>,
+}
 
-// Failure log file path
-lazy_static! {
-    static ref FAILURE_LOG: Mutex<Option<PathBuf>> = Mutex::new(None);
+/// Resource limit a killed process ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimitExceeded {
+    Memory,
+    CpuTime,
 }
 
-/// Initialize the failure log file
-pub fn init_failure_log(log_path: PathBuf) {
-    let mut log = FAILURE_LOG.lock().unwrap();
-    *log = Some(log_path);
+impl LimitExceeded {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitExceeded::Memory => "memory",
+            LimitExceeded::CpuTime => "CPU time",
+        }
+    }
+
+    /// Recognize a limit violation from cargo's stderr, which reports how
+    /// rustc or a test binary died
+    fn detect(stderr: &str) -> Option<Self> {
+        if stderr.contains("SIGXCPU") {
+            Some(LimitExceeded::CpuTime)
+        } else if (stderr.contains("memory allocation of") && stderr.contains("failed"))
+            || stderr.contains("Cannot allocate memory")
+            || stderr.contains("out of memory") {
+            Some(LimitExceeded::Memory)
+        } else {
+            None
+        }
+    }
+}
+
+/// How a cargo process ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcessExit {
+    /// Exited with this code
+    Code(i32),
+    /// Terminated by this signal (Unix), e.g. when killed after a timeout
+    Signal(i32),
+}
+
+impl ProcessExit {
+    fn from_status(status: &ExitStatus) -> Option<Self> {
+        if let Some(code) = status.code() {
+            return Some(ProcessExit::Code(code));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return Some(ProcessExit::Signal(signal));
+            }
+        }
+        None
+    }
+
+    pub fn is_success(&self) -> bool {
+        *self == ProcessExit::Code(0)
+    }
+}
+
+impl std::fmt::Display for ProcessExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessExit::Code(code) => write!(f, "{}", code),
+            ProcessExit::Signal(signal) => match signal_name(*signal) {
+                Some(name) => write!(f, "signal {} ({})", signal, name),
+                None => write!(f, "signal {}", signal),
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<&'static str> {
+    match signal {
+        libc::SIGABRT => Some("SIGABRT"),
+        libc::SIGBUS => Some("SIGBUS"),
+        libc::SIGINT => Some("SIGINT"),
+        libc::SIGKILL => Some("SIGKILL"),
+        libc::SIGSEGV => Some("SIGSEGV"),
+        libc::SIGTERM => Some("SIGTERM"),
+        libc::SIGXCPU => Some("SIGXCPU"),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_name(_signal: i32) -> Option<&'static str> {
+    None
 }
 
 /// Log a compilation failure to the failure log file with proper locking
-pub fn log_failure(
+fn log_failure(
+    settings: &RunSettings,
     dependent: &str,
     dependent_version: &str,
     base_crate: &str,
     test_label: &str,  // "baseline", "WIP", or version number
     command: &str,
-    exit_code: Option<i32>,
-    stdout: &str,
-    stderr: &str,
+    result: &CompileResult,
 ) {
-    let log_path = {
-        let log = FAILURE_LOG.lock().unwrap();
-        match &*log {
-            Some(path) => path.clone(),
-            None => return,  // Logging not initialized
-        }
+    let Some((ref log_path, format)) = settings.failure_log else {
+        return;  // Logging not enabled
     };
 
     // Open file with append mode
     let file = match OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&log_path)
+        .open(log_path)
     {
         Ok(f) => f,
         Err(e) => {
@@ -62,21 +390,30 @@ pub fn log_failure(
 
     // Write failure details
     let mut writer = BufWriter::new(&file);
-    let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
-
-    let exit_str = exit_code.map(|c| c.to_string()).unwrap_or_else(|| "N/A".to_string());
-
-    let _ = writeln!(writer, "\n{}", "=".repeat(100));
-    let _ = writeln!(writer, "[{}] FAILURE: {} {} testing {} {}",
-                     timestamp, dependent, dependent_version, base_crate, test_label);
-    let _ = writeln!(writer, "{}", "=".repeat(100));
-    let _ = writeln!(writer, "Command: {}", command);
-    let _ = writeln!(writer, "Exit code: {}", exit_str);
-    let _ = writeln!(writer, "\n--- STDOUT ---");
-    let _ = writeln!(writer, "{}", stdout);
-    let _ = writeln!(writer, "\n--- STDERR ---");
-    let _ = writeln!(writer, "{}", stderr);
-    let _ = writeln!(writer, "{}", "=".repeat(100));
+    match format {
+        FailureLogFormat::Text => {
+            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+            let exit_str = result.exit.map(|e| e.to_string()).unwrap_or_else(|| "N/A".to_string());
+
+            let _ = writeln!(writer, "\n{}", "=".repeat(100));
+            let _ = writeln!(writer, "[{}] FAILURE: {} {} testing {} {}",
+                             timestamp, dependent, dependent_version, base_crate, test_label);
+            let _ = writeln!(writer, "{}", "=".repeat(100));
+            let _ = writeln!(writer, "Command: {}", command);
+            let _ = writeln!(writer, "Exit code: {}", exit_str);
+            let _ = writeln!(writer, "\n--- STDOUT ---");
+            let _ = writeln!(writer, "{}", result.stdout);
+            let _ = writeln!(writer, "\n--- STDERR ---");
+            let _ = writeln!(writer, "{}", result.stderr);
+            let _ = writeln!(writer, "{}", "=".repeat(100));
+        }
+        FailureLogFormat::Json => {
+            let entry = FailureEntry::new(dependent, dependent_version, base_crate, test_label, command, result);
+            if let Ok(json) = serde_json::to_string(&entry) {
+                let _ = writeln!(writer, "{}", json);
+            }
+        }
+    }
 
     let _ = writer.flush();
 
@@ -98,14 +435,26 @@ pub fn restore_cargo_toml(staging_path: &Path) -> Result<(), String> {
 }
 
 /// The type of compilation step being performed
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum CompileStep {
     /// cargo fetch - download dependencies
     Fetch,
     /// cargo check - fast compilation check without code generation
     Check,
+    /// cargo build --all-targets - codegen and linking (`--full-build`)
+    Build,
+    /// cargo build --release --bins - what `cargo install` builds (`--install`)
+    Install,
+    /// cargo clippy - lints, with the dependent's own lint levels (`--clippy`)
+    Clippy,
+    /// cargo doc - documentation, with broken intra-doc links denied (`--doc`)
+    Doc,
     /// cargo test - full test suite execution
     Test,
+    /// cargo test --doc - doctests on their own (`--doctest`)
+    Doctest,
+    /// cargo +nightly miri test - tests under Miri's UB checks (`--miri`)
+    Miri,
 }
 
 impl CompileStep {
@@ -113,7 +462,13 @@ impl CompileStep {
         match self {
             CompileStep::Fetch => "fetch",
             CompileStep::Check => "check",
+            CompileStep::Build => "build",
+            CompileStep::Install => "install",
+            CompileStep::Clippy => "clippy",
+            CompileStep::Doc => "doc",
             CompileStep::Test => "test",
+            CompileStep::Doctest => "doctest",
+            CompileStep::Miri => "miri",
         }
     }
 
@@ -121,13 +476,17 @@ impl CompileStep {
         match self {
             CompileStep::Fetch => "fetch",
             CompileStep::Check => "check",
-            CompileStep::Test => "test",
+            CompileStep::Build | CompileStep::Install => "build",
+            CompileStep::Clippy => "clippy",
+            CompileStep::Doc => "doc",
+            CompileStep::Test | CompileStep::Doctest => "test",
+            CompileStep::Miri => "miri test",
         }
     }
 }
 
 /// Result of a compilation step
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompileResult {
     pub step: CompileStep,
     pub success: bool,
@@ -135,54 +494,205 @@ pub struct CompileResult {
     pub stderr: String,
     pub duration: Duration,
     pub diagnostics: Vec<Diagnostic>,
+    /// Killed after exceeding its step timeout (TIMEOUT); implies !success
+    pub timed_out: bool,
+    /// Failed because a process ran into a resource limit
+    pub limit_exceeded: Option<LimitExceeded>,
+    /// Exit code or terminating signal of cargo; None if it couldn't be determined
+    #[serde(default)]
+    pub exit: Option<ProcessExit>,
+    /// Failed at first but passed on a retry (FLAKY); see `--test-retries`
+    pub flaky: bool,
 }
 
 impl CompileResult {
     pub fn failed(&self) -> bool {
         !self.success
     }
+
+    /// A step that took a second without output, for tests to adjust
+    #[cfg(test)]
+    pub fn for_test(step: CompileStep, success: bool) -> Self {
+        CompileResult {
+            step,
+            success,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration: Duration::from_secs(1),
+            diagnostics: Vec::new(),
+            timed_out: false,
+            limit_exceeded: None,
+            exit: None,
+            flaky: false,
+        }
+    }
+
+    /// A fetch that failed because crates.io (or the network) couldn't be
+    /// reached, which says nothing about the dependent (NETWORK)
+    pub fn network_failure(&self) -> bool {
+        self.step == CompileStep::Fetch && !self.success && is_network_error(&self.stderr)
+    }
 }
 
-/// Verify that the correct version of a dependency is being used
-/// Returns the actual version found, or None if not found
-fn verify_dependency_version(
-    crate_path: &Path,
-    dep_name: &str,
-) -> Option<String> {
-    debug!("Verifying {} version in {:?}", dep_name, crate_path);
+/// Transport failures in cargo's output, plus the marker our own HTTP
+/// requests leave (see `api::NETWORK_MARKER`); "failed to download" alone
+/// also covers bad checksums and missing crates
+const NETWORK_ERRORS: &[&str] = &[
+    crate::api::NETWORK_MARKER,
+    // cargo's retries, which name the underlying error
+    "spurious network error",
+    "network failure seems to have happened",
+    // curl: DNS, connection, TLS and timeouts
+    "Could not resolve host",
+    "Couldn't resolve host",
+    "Couldn't connect to server",
+    "Connection refused",
+    "Connection reset by peer",
+    "Timeout was reached",
+    "Operation timed out",
+    "SSL connect error",
+    "SSL certificate problem",
+];
+
+/// Whether cargo's stderr shows a download problem rather than a problem with the crate
+pub fn is_network_error(stderr: &str) -> bool {
+    NETWORK_ERRORS.iter().any(|pattern| stderr.contains(pattern))
+}
+
+/// Output of a command run by `output_with_timeout`
+struct TimedOutput {
+    success: bool,
+    exit: Option<ProcessExit>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    timed_out: bool,
+}
+
+/// Run a command to completion, killing its whole process group if it
+/// outlives `timeout`
+///
+/// The command gets its own process group so rustc, build scripts and test
+/// binaries spawned by cargo die with it instead of holding the output pipes
+/// open (and the run hostage).
+fn output_with_timeout(cmd: &mut Command, timeout: Option<Duration>) -> std::io::Result<TimedOutput> {
+    // With -v or --log-file, cargo's output is read line by line as it comes
+    let echo = crate::run_log::wants_cargo_output().then(crate::run_log::current_dependent);
+    if timeout.is_none() && echo.is_none() {
+        let output = cmd.output()?;
+        return Ok(TimedOutput {
+            success: output.status.success(),
+            exit: ProcessExit::from_status(&output.status),
+            stdout: output.stdout,
+            stderr: output.stderr,
+            timed_out: false,
+        });
+    }
+
+    #[cfg(unix)]
+    if timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let mut child = cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain both pipes while waiting so a chatty child can't block on a full pipe
+    let mut stdout_pipe = child.stdout.take();
+    let mut stderr_pipe = child.stderr.take();
+    let stdout_echo = echo.clone().map(|dependent| (dependent, crate::run_log::Stream::Stdout));
+    let stderr_echo = echo.map(|dependent| (dependent, crate::run_log::Stream::Stderr));
+    let stdout_reader = std::thread::spawn(move || read_pipe(stdout_pipe.as_mut(), stdout_echo));
+    let stderr_reader = std::thread::spawn(move || read_pipe(stderr_pipe.as_mut(), stderr_echo));
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let (status, timed_out) = loop {
+        if let Some(status) = child.try_wait()? {
+            break (status, false);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            debug!("timeout of {:?} expired, killing {:?}", timeout.unwrap_or_default(), cmd);
+            kill_process_group(&mut child);
+            // Timed out because we killed it, whatever exit status that leaves
+            // (taskkill's is an exit code, not a signal)
+            break (child.wait()?, true);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+
+    Ok(TimedOutput {
+        success: status.success(),
+        exit: ProcessExit::from_status(&status),
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+        timed_out,
+    })
+}
 
-    // Try using cargo metadata which works better with path dependencies
+/// Read a pipe to the end, handing each line to the run log when `echo` is set
+fn read_pipe<R: std::io::Read>(pipe: Option<&mut R>, echo: Option<(Option<String>, crate::run_log::Stream)>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let Some(pipe) = pipe else {
+        return buf;
+    };
+    let Some((dependent, stream)) = echo else {
+        pipe.read_to_end(&mut buf).ok();
+        return buf;
+    };
+    let mut reader = std::io::BufReader::new(pipe);
+    loop {
+        let start = buf.len();
+        match std::io::BufRead::read_until(&mut reader, b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buf[start..]);
+                crate::run_log::cargo_line(dependent.as_deref(), stream, line.trim_end());
+            }
+        }
+    }
+    buf
+}
+
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) {
+    // The child leads its own group (process_group(0)), so its pid is the pgid
+    let killed = unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) } == 0;
+    if !killed {
+        child.kill().ok();
+    }
+}
+
+#[cfg(windows)]
+fn kill_process_group(child: &mut Child) {
+    // Windows has no process groups here; taskkill /T takes the whole tree (rustc, test binaries)
+    let killed = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &child.id().to_string()])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if !killed {
+        child.kill().ok();
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_process_group(child: &mut Child) {
+    child.kill().ok();
+}
+
+/// `cargo metadata` of the dependent, with its dependency graph resolved
+fn cargo_metadata(settings: &RunSettings, crate_path: &Path) -> Option<serde_json::Value> {
     // Don't use --no-deps because we need to see resolved dependencies
     let output = Command::new("cargo")
-        .args(&["metadata", "--format-version=1"])
+        .args(["metadata", "--format-version=1"])
+        .args(crate::api::cargo_source_args())
+        .envs(settings.cargo_env.env_vars())
+        .envs(toolchain_env())
         .current_dir(crate_path)
         .output()
         .ok()?;
-    // if output.status.success() {
-    //     let stdout = String::from_utf8_lossy(&output.stdout);
-    //     if let Ok(metadata) = serde_json::from_str::<serde_json::Value>(&stdout) {
-    //         // Check resolve.nodes for the dependency
-    //         if let Some(resolve) = metadata.get("resolve") {
-    //             if let Some(nodes) = resolve.get("nodes").and_then(|n| n.as_array()) {
-    //                 for node in nodes {
-    //                     if let Some(deps) = node.get("deps").and_then(|d| d.as_array()) {
-    //                         for dep in deps {
-    //                             if let Some(name) = dep.get("name").and_then(|n| n.as_str()) {
-    //                                 if name == dep_name {
-    //                                     if let Some(pkg) = dep.get("pkg").and_then(|p| p.as_str()) {
-    //                                         // pkg format: "rgb 0.8.52 (path+file://...)" or "rgb 0.8.52 (registry+...)"
-    //                                         let parts: Vec<&str> = pkg.split_whitespace().collect();
-    //                                         if parts.len() >= 2 {
-    //                                             let version = parts[1].to_string();
-    //                                             debug!("Found {} version: {}", dep_name, version);
-    //                                             return Some(version);
-    //                                         }
-    //                                     }
-    //                                 }
-    //                             }
-    //                         }
-    //                     }
-    //                 }
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         debug!("cargo metadata failed: {}", stderr.trim());
@@ -190,14 +700,51 @@ fn verify_dependency_version(
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let metadata = match serde_json::from_str::<serde_json::Value>(&stdout) {
-        Ok(m) => m,
+    match serde_json::from_str::<serde_json::Value>(&stdout) {
+        Ok(m) => Some(m),
         Err(e) => {
             debug!("Failed to parse metadata JSON: {}", e);
-            return None;
+            None
         }
+    }
+}
+
+/// Package id of the first `dep_name` found among resolved dependencies
+fn resolved_package_id<'a>(metadata: &'a serde_json::Value, dep_name: &str) -> Option<&'a str> {
+    metadata.get("resolve")?.get("nodes")?.as_array()?.iter()
+        .filter_map(|node| node.get("deps").and_then(|d| d.as_array()))
+        .flatten()
+        .find(|dep| dep.get("name").and_then(|n| n.as_str()) == Some(dep_name))
+        .and_then(|dep| dep.get("pkg"))
+        .and_then(|pkg| pkg.as_str())
+}
+
+/// Features of `dep_name` enabled in the resolved graph, sorted
+///
+/// Cargo unifies features, so this includes what other crates in the
+/// dependent's graph turn on, not just what the dependent asks for.
+fn enabled_features(metadata: &serde_json::Value, dep_name: &str) -> Vec<String> {
+    let Some(id) = resolved_package_id(metadata, dep_name) else {
+        return Vec::new();
     };
+    let mut features: Vec<String> = metadata.get("resolve")
+        .and_then(|r| r.get("nodes"))
+        .and_then(|n| n.as_array())
+        .and_then(|nodes| nodes.iter().find(|node| node.get("id").and_then(|i| i.as_str()) == Some(id)))
+        .and_then(|node| node.get("features"))
+        .and_then(|f| f.as_array())
+        .map(|f| f.iter().filter_map(|v| v.as_str()).map(str::to_string).collect())
+        .unwrap_or_default();
+    features.sort();
+    features
+}
 
+/// Verify that the correct version of a dependency is being used
+/// Returns the actual version found, or None if not found
+fn verify_dependency_version(
+    metadata: &serde_json::Value,
+    dep_name: &str,
+) -> Option<String> {
     // First try resolve.nodes to find the actually-used version (handles multiple versions correctly)
     if let Some(resolve) = metadata.get("resolve") {
         if let Some(nodes) = resolve.get("nodes").and_then(|n| n.as_array()) {
@@ -249,64 +796,6 @@ fn verify_dependency_version(
     None
 }
 
-/// Add [patch.crates-io] section to Cargo.toml to override a dependency
-/// This respects semver requirements - if the version doesn't match, cargo will fail
-fn add_cargo_patch(
-    crate_path: &Path,
-    dep_name: &str,
-    override_path: &Path,
-) -> Result<(), String> {
-    use std::io::{Read, Write};
-
-    // Convert to absolute path
-    let override_path = if override_path.is_absolute() {
-        override_path.to_path_buf()
-    } else {
-        env::current_dir()
-            .map_err(|e| format!("Failed to get current dir: {}", e))?
-            .join(override_path)
-    };
-
-    let cargo_toml_path = crate_path.join("Cargo.toml");
-    let mut content = String::new();
-
-    // Read original Cargo.toml
-    let mut file = fs::File::open(&cargo_toml_path)
-        .map_err(|e| format!("Failed to open Cargo.toml: {}", e))?;
-    file.read_to_string(&mut content)
-        .map_err(|e| format!("Failed to read Cargo.toml: {}", e))?;
-    drop(file);
-
-    // Parse as TOML
-    let mut doc: toml_edit::DocumentMut = content.parse()
-        .map_err(|e| format!("Failed to parse Cargo.toml: {}", e))?;
-
-    // Add or update [patch.crates-io] section
-    let patch_section = doc.entry("patch").or_insert(toml_edit::Item::Table(toml_edit::Table::new()));
-    let patch_table = patch_section.as_table_mut()
-        .ok_or_else(|| "patch is not a table".to_string())?;
-
-    let crates_io_section = patch_table.entry("crates-io").or_insert(toml_edit::Item::Table(toml_edit::Table::new()));
-    let crates_io_table = crates_io_section.as_table_mut()
-        .ok_or_else(|| "patch.crates-io is not a table".to_string())?;
-
-    // Add the patch entry for our dependency
-    let mut patch_entry = toml_edit::InlineTable::new();
-    patch_entry.insert("path", override_path.display().to_string().into());
-    crates_io_table.insert(dep_name, toml_edit::Item::Value(toml_edit::Value::InlineTable(patch_entry)));
-
-    debug!("Adding [patch.crates-io] for {} -> {:?}", dep_name, override_path);
-
-    // Write back
-    let mut file = fs::File::create(&cargo_toml_path)
-        .map_err(|e| format!("Failed to create Cargo.toml: {}", e))?;
-    file.write_all(doc.to_string().as_bytes())
-        .map_err(|e| format!("Failed to write Cargo.toml: {}", e))?;
-
-    debug!("Added patch to Cargo.toml: {} -> {}", dep_name, override_path.display());
-    Ok(())
-}
-
 /// Force-modify dependency specification to use exact path, bypassing semver
 /// This is used when --force-versions is specified
 fn force_dependency_spec(
@@ -372,6 +861,28 @@ fn force_dependency_spec(
                     }
                 }
 
+                // A workspace member's `{ workspace = true }` takes the rest from the root
+                let inherited = dep.as_table_like()
+                    .and_then(|t| t.get("workspace"))
+                    .and_then(|w| w.as_bool()) == Some(true);
+                if let Some(toml::Value::Table(root_dep)) = inherited.then(|| crate::workspace::inherited_dependency(crate_path, dep_name)).flatten() {
+                    for key in ["default-features", "features", "package"] {
+                        let Some(value) = root_dep.get(key).and_then(|v| v.to_string().parse::<toml_edit::Value>().ok()) else {
+                            continue;
+                        };
+                        match (new_dep.get_mut(key).and_then(|v| v.as_array_mut()), value) {
+                            (Some(features), toml_edit::Value::Array(root_features)) => {
+                                features.extend(root_features.iter().cloned());
+                            }
+                            (Some(_), _) => {}
+                            (None, value) => {
+                                new_dep.insert(key, value);
+                            }
+                        }
+                    }
+                    debug!("Merged {}'s workspace definition into the forced spec", dep_name);
+                }
+
                 *dep = toml_edit::Item::Value(toml_edit::Value::InlineTable(new_dep));
             }
         }
@@ -387,60 +898,237 @@ fn force_dependency_spec(
     Ok(())
 }
 
-pub fn compile_crate(
+/// `--config` arguments patching crates.io (or the `--registry`) to each (crate, path) of `patches`
+fn patch_config_args(patches: &[(&str, &Path)]) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for &(crate_name, override_path) in patches {
+        let config_str = format!(
+            "patch.{}.{}.path=\"{}\"",
+            crate::api::patch_registry(),
+            crate_name,
+            patch_path(override_path)?
+        );
+        debug!("using --config: {}", config_str);
+        args.extend(["--config".to_string(), config_str]);
+    }
+    Ok(args)
+}
+
+/// Absolute path of a patch for cargo config
+fn patch_path(override_path: &Path) -> Result<String, String> {
+    let override_path = if override_path.is_absolute() {
+        override_path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map_err(|e| format!("Failed to get current dir: {}", e))?
+            .join(override_path)
+    };
+    // Forward slashes work on Windows too, and backslashes would be TOML escapes
+    Ok(override_path.display().to_string().replace('\\', "/"))
+}
+
+/// `.cargo/config.toml` patching the base crate for a custom command, which
+/// may not pass `--config` on to the cargo it runs; restored when dropped
+///
+/// The dependent's own settings in the file are kept, and so are the
+/// `source_args` (`api::cargo_source_args`) cargo would have been given.
+struct PatchConfigFile {
+    path: PathBuf,
+    original: Option<String>,
+}
+
+impl PatchConfigFile {
+    fn write(crate_path: &Path, patches: &[(&str, &Path)], source_args: &[String]) -> Result<Self, String> {
+        let path = crate_path.join(".cargo").join("config.toml");
+        let original = fs::read_to_string(&path).ok();
+        let mut config: toml_edit::DocumentMut = original.as_deref().unwrap_or_default().parse()
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+        let mut args = source_args.iter();
+        while let Some(arg) = args.next() {
+            match (arg.as_str(), args.clone().next()) {
+                ("--config", Some(setting)) => {
+                    args.next();
+                    let setting: toml_edit::DocumentMut = setting.parse()
+                        .map_err(|e| format!("Invalid cargo setting {}: {}", setting, e))?;
+                    merge_tables(config.as_table_mut(), setting.as_table());
+                }
+                ("--offline", _) => config["net"]["offline"] = toml_edit::value(true),
+                _ => debug!("no config file equivalent of cargo argument {}", arg),
+            }
+        }
+
+        if !patches.is_empty() {
+            let patch = config["patch"].or_insert(toml_edit::table());
+            if let Some(patch) = patch.as_table_mut() {
+                patch.set_implicit(true);
+            }
+            let registry = &mut patch[crate::api::patch_registry().as_str()];
+            for &(crate_name, override_path) in patches {
+                let mut dependency = toml_edit::InlineTable::new();
+                dependency.insert("path", patch_path(override_path)?.into());
+                registry[crate_name] = toml_edit::value(dependency);
+            }
+        }
+
+        fs::create_dir_all(crate_path.join(".cargo"))
+            .and_then(|_| fs::write(&path, config.to_string()))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(PatchConfigFile { path, original })
+    }
+}
+
+/// Add the settings of `from` to `into`, replacing values both have
+fn merge_tables(into: &mut dyn toml_edit::TableLike, from: &dyn toml_edit::TableLike) {
+    for (key, item) in from.iter() {
+        match (into.get_mut(key).and_then(|i| i.as_table_like_mut()), item.as_table_like()) {
+            (Some(existing), Some(table)) => merge_tables(existing, table),
+            _ => {
+                into.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+impl Drop for PatchConfigFile {
+    fn drop(&mut self) {
+        let restored = match self.original {
+            Some(ref original) => fs::write(&self.path, original),
+            None => fs::remove_file(&self.path),
+        };
+        if let Err(e) = restored {
+            eprintln!("Warning: failed to restore {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Run one cargo step with the base crate (and companions) patched as in `patches`, with extra
+/// cargo arguments (e.g. a test filter or `--no-run`) and arguments for the
+/// test harness (passed after `--`)
+pub fn compile_crate_with_args(
+    settings: &RunSettings,
+    crate_path: &Path,
+    step: CompileStep,
+    patches: &[(&str, &Path)],
+    cargo_args: &[String],
+    harness_args: &[String],
+) -> Result<CompileResult, String> {
+    compile_crate_with_command(settings, crate_path, step, None, patches, cargo_args, harness_args)
+}
+
+/// Run one step like `compile_crate_with_args`, or with the dependent's own
+/// command for it from copter.toml (see `StepCommands`)
+///
+/// A `{cargo}` argument of a custom command is replaced by the cargo command
+/// the step would have run, so `["xvfb-run", "-a", "{cargo}"]` wraps it. Any
+/// other command replaces it, ignoring `cargo_args` and `harness_args`, and
+/// sees the patches in `.cargo/config.toml` for as long as it runs.
+pub fn compile_crate_with_command(
+    settings: &RunSettings,
     crate_path: &Path,
     step: CompileStep,
-    override_spec: Option<(&str, &Path)>,
+    custom: Option<&[String]>,
+    patches: &[(&str, &Path)],
+    cargo_args: &[String],
+    harness_args: &[String],
 ) -> Result<CompileResult, String> {
     debug!("compiling {:?} with step {:?}", crate_path, step);
+    crate::progress::step(step.as_str());
 
-    // Run the cargo command with JSON output for better error extraction
+    // The cargo command with JSON output for better error extraction
     let start = Instant::now();
-    let mut cmd = Command::new("cargo");
-    cmd.arg(step.cargo_subcommand());
+    let mut cargo = vec!["cargo".to_string()];
+    if step == CompileStep::Miri {
+        // Miri only ships with nightly; rustup's cargo proxy picks it with +nightly
+        cargo.extend(["+nightly", "miri", "test"].map(String::from));
+    } else {
+        cargo.push(step.cargo_subcommand().to_string());
+    }
 
     // Add --message-format=json for check and test (not fetch)
     if step != CompileStep::Fetch {
-        cmd.arg("--message-format=json");
+        cargo.push("--message-format=json".to_string());
     }
 
     // If override is provided, use --config flag instead of creating .cargo/config file
-    if let Some((crate_name, override_path)) = override_spec {
-        // Convert to absolute path if needed
-        let override_path = if override_path.is_absolute() {
-            override_path.to_path_buf()
-        } else {
-            env::current_dir()
-                .map_err(|e| format!("Failed to get current dir: {}", e))?
-                .join(override_path)
-        };
-
-        let config_str = format!(
-            "patch.crates-io.{}.path=\"{}\"",
-            crate_name,
-            override_path.display()
-        );
-        cmd.arg("--config").arg(&config_str);
-        debug!("using --config: {}", config_str);
+    cargo.extend(patch_config_args(patches)?);
+    cargo.extend(crate::api::cargo_source_args());
+    cargo.extend(cargo_args.iter().cloned());
+    if !harness_args.is_empty() {
+        cargo.push("--".to_string());
+        cargo.extend(harness_args.iter().cloned());
     }
 
+    let (argv, _patch_config) = match custom {
+        Some(command) if command.iter().any(|arg| arg == "{cargo}") => {
+            let argv = command.iter()
+                .flat_map(|arg| if arg == "{cargo}" { cargo.clone() } else { vec![arg.clone()] })
+                .collect();
+            (argv, None)
+        }
+        Some(command) => {
+            let source_args = crate::api::cargo_source_args();
+            let config = if patches.is_empty() && source_args.is_empty() {
+                None
+            } else {
+                Some(PatchConfigFile::write(crate_path, patches, &source_args)?)
+            };
+            (command.to_vec(), config)
+        }
+        None => (cargo, None),
+    };
+    let label = match custom {
+        Some(command) => command.join(" "),
+        None => format!("cargo {}", step.cargo_subcommand()),
+    };
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..]);
+
     cmd.current_dir(crate_path);
+    cmd.envs(settings.build_cache.env_vars());
+    let cargo_env = &settings.cargo_env;
+    cmd.envs(cargo_env.env_vars());
+    cmd.envs(toolchain_env());
+    if step == CompileStep::Doc {
+        // Broken links are what API changes break; rustdoc only warns about them by default
+        let mut rustdocflags = cargo_env.var("RUSTDOCFLAGS").unwrap_or_default();
+        rustdocflags.push_str(" -D rustdoc::broken_intra_doc_links");
+        cmd.env("RUSTDOCFLAGS", rustdocflags.trim_start());
+    }
+    let limits = settings.resource_limits;
+    limits.apply(&mut cmd);
 
-    debug!("running cargo: {:?}", cmd);
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to execute cargo: {}", e))?;
+    debug!("running {}: {:?}", argv[0], cmd);
+    let timeout = settings.budget.cap_timeout(settings.step_timeouts.for_step(step));
+    settings.budget.record_process();
+    let output = output_with_timeout(&mut cmd, timeout)
+        .map_err(|e| format!("Failed to execute {}: {}", argv[0], e))?;
 
     let duration = start.elapsed();
-    let success = output.status.success();
+    let mut success = output.success;
 
-    debug!("result: {:?}, duration: {:?}", success, duration);
+    debug!("result: {:?}, exit: {:?}, duration: {:?}, timed out: {}", success, output.exit, duration, output.timed_out);
 
     // Parse stdout for JSON messages (cargo writes JSON to stdout)
     let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
-    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let mut stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if output.timed_out {
+        stderr.push_str(&format!("\nTIMEOUT: {} killed after {}\n", label, HumanDuration(duration)));
+    }
+    let limit_exceeded = if !success && limits != ResourceLimits::default() {
+        // cargo itself can die of SIGXCPU, without a word on stderr
+        LimitExceeded::detect(&stderr)
+            .or(matches!(output.exit, Some(ProcessExit::Signal(signal)) if signal_name(signal) == Some("SIGXCPU"))
+                .then_some(LimitExceeded::CpuTime))
+    } else {
+        None
+    };
+    if let Some(limit) = limit_exceeded {
+        stderr.push_str(&format!("\nRESOURCE LIMIT: {} exceeded the {} limit\n", label, limit.as_str()));
+    }
 
     // Parse diagnostics from JSON output (only for check/test, not fetch)
-    let diagnostics = if step != CompileStep::Fetch {
+    let mut diagnostics = if step != CompileStep::Fetch {
         parse_cargo_json(&stdout)
     } else {
         Vec::new()
@@ -448,6 +1136,16 @@ pub fn compile_crate(
 
     debug!("parsed {} diagnostics", diagnostics.len());
 
+    // Like -D warnings, but only for the dependent's own code: RUSTFLAGS would
+    // also deny the warnings of a patched-in base crate, and rebuild everything
+    if cargo_env.deny_warnings && step == CompileStep::Check && success {
+        let denied = crate::warnings::deny_own_warnings(&mut diagnostics);
+        if denied > 0 {
+            success = false;
+            stderr.push_str(&format!("\nDENIED: {} warnings in the dependent's own code (--deny-warnings)\n", denied));
+        }
+    }
+
     Ok(CompileResult {
         step,
         success,
@@ -455,49 +1153,219 @@ pub fn compile_crate(
         stderr,
         duration,
         diagnostics,
+        timed_out: output.timed_out,
+        limit_exceeded,
+        exit: output.exit,
+        flaky: false,
     })
 }
 
-/// Emit a .cargo/config file to override a dependency with a local path
-fn emit_cargo_override_path(source_dir: &Path, override_path: &Path) -> Result<(), String> {
-    debug!("overriding cargo path in {:?} with {:?}", source_dir, override_path);
+/// Write a Cargo.lock with every dependency at the lowest version its requirement
+/// allows (`cargo +nightly generate-lockfile -Z minimal-versions`), then move the
+/// base crate to `pin` if given
+///
+/// Reported as the fetch step: a requirement nothing can satisfy fails here
+/// the way it would fail `cargo fetch`.
+fn resolve_minimal_versions(
+    settings: &RunSettings,
+    crate_path: &Path,
+    base_crate_name: &str,
+    patches: &[(&str, &Path)],
+    pin: Option<&str>,
+) -> Result<CompileResult, String> {
+    crate::progress::step("minimal-versions");
+    let start = Instant::now();
+    let mut commands = vec![vec!["+nightly", "generate-lockfile", "-Z", "minimal-versions"]];
+    if let Some(version) = pin {
+        commands.push(vec!["update", "-p", base_crate_name, "--precise", version]);
+    }
 
-    // Convert to absolute path if needed
-    let override_path = if override_path.is_absolute() {
-        override_path.to_path_buf()
-    } else {
-        env::current_dir()
-            .map_err(|e| format!("Failed to get current dir: {}", e))?
-            .join(override_path)
+    let timeout = settings.budget.cap_timeout(settings.step_timeouts.for_step(CompileStep::Fetch));
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let mut success = true;
+    let mut timed_out = false;
+    let mut exit = None;
+    for args in commands {
+        let mut cmd = Command::new("cargo");
+        cmd.args(&args)
+            .args(patch_config_args(patches)?)
+            .args(crate::api::cargo_source_args())
+            .envs(settings.cargo_env.env_vars())
+            .envs(toolchain_env())
+            .current_dir(crate_path);
+        debug!("running cargo: {:?}", cmd);
+        settings.budget.record_process();
+        let output = output_with_timeout(&mut cmd, timeout)
+            .map_err(|e| format!("Failed to execute cargo: {}", e))?;
+
+        stdout.push_str(&String::from_utf8_lossy(&output.stdout));
+        stderr.push_str(&String::from_utf8_lossy(&output.stderr));
+        let subcommand = args.iter().find(|arg| !arg.starts_with('+')).copied().unwrap_or_default();
+        if output.timed_out {
+            stderr.push_str(&format!("\nTIMEOUT: cargo {} killed after {}\n", subcommand, HumanDuration(start.elapsed())));
+        }
+        (success, timed_out, exit) = (output.success, output.timed_out, output.exit);
+        if !success {
+            break;
+        }
+    }
+
+    Ok(CompileResult {
+        step: CompileStep::Fetch,
+        success,
+        stdout,
+        stderr,
+        duration: start.elapsed(),
+        diagnostics: Vec::new(),
+        timed_out,
+        limit_exceeded: None,
+        exit,
+        flaky: false,
+    })
+}
+
+/// Run the test step, split according to the dependent's copter.toml settings
+///
+/// Without test shards this is a single `cargo test` (with `--features` and
+/// `--test-threads` if configured). With shards, the tests are built once with
+/// `--no-run`, then each filter runs as its own `cargo test <filter>` in
+/// parallel, plus a remainder shard that `--skip`s every filter. The shards
+/// are merged into one result whose duration is the wall-clock time of the
+/// whole step. `target_args` selects the targets to test (see `TargetKinds`).
+pub fn run_test_step(
+    settings: &RunSettings,
+    crate_path: &Path,
+    patches: &[(&str, &Path)],
+    dependent_config: Option<&DependentConfig>,
+    target_args: &[String],
+) -> Result<CompileResult, String> {
+    let default_config = DependentConfig::default();
+    let dependent_config = dependent_config.unwrap_or(&default_config);
+    let base_args = [target_args, &dependent_config.feature_args()].concat();
+
+    let thread_args: Vec<String> = dependent_config.test_threads
+        .map(|n| vec![format!("--test-threads={}", n)])
+        .unwrap_or_default();
+
+    // A custom test command runs the tests its own way, unsharded
+    let custom = dependent_config.commands.test.as_deref();
+    if dependent_config.test_shards.is_empty() || custom.is_some() {
+        return compile_crate_with_command(settings, crate_path, CompileStep::Test, custom, patches, &base_args, &thread_args);
+    }
+
+    let start = Instant::now();
+
+    // Build once so the shards don't serialize on cargo's build lock
+    let mut build_args = base_args.clone();
+    build_args.push("--no-run".to_string());
+    let build = compile_crate_with_args(settings, crate_path, CompileStep::Test, patches, &build_args, &[])?;
+    if build.failed() {
+        return Ok(build);
+    }
+
+    // One shard per filter, plus the remainder with every filter skipped
+    let mut shards: Vec<(String, Vec<String>, Vec<String>)> = dependent_config.test_shards.iter()
+        .map(|filter| {
+            let mut cargo_args = base_args.clone();
+            cargo_args.push(filter.clone());
+            (filter.clone(), cargo_args, thread_args.clone())
+        })
+        .collect();
+    let mut rest_args = thread_args.clone();
+    for filter in &dependent_config.test_shards {
+        rest_args.push("--skip".to_string());
+        rest_args.push(filter.clone());
+    }
+    shards.push(("(rest)".to_string(), base_args.clone(), rest_args));
+
+    let results: Vec<(String, Result<CompileResult, String>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = shards.iter()
+            .map(|(name, cargo_args, harness_args)| {
+                let handle = scope.spawn(move || {
+                    compile_crate_with_args(settings, crate_path, CompileStep::Test, patches, cargo_args, harness_args)
+                });
+                (name.clone(), handle)
+            })
+            .collect();
+        handles.into_iter()
+            .map(|(name, handle)| {
+                let result = handle.join()
+                    .unwrap_or_else(|_| Err(format!("test shard {} panicked", name)));
+                (name, result)
+            })
+            .collect()
+    });
+
+    let mut merged = CompileResult {
+        step: CompileStep::Test,
+        success: true,
+        stdout: build.stdout,
+        stderr: build.stderr,
+        duration: Duration::ZERO,
+        diagnostics: build.diagnostics,
+        timed_out: false,
+        limit_exceeded: None,
+        exit: build.exit,
+        flaky: false,
     };
+    for (name, result) in results {
+        let result = result?;
+        debug!("test shard {} for {:?}: success={}", name, crate_path, result.success);
+        merged.success &= result.success;
+        merged.timed_out |= result.timed_out;
+        merged.limit_exceeded = merged.limit_exceeded.or(result.limit_exceeded);
+        // Report the exit of the first shard that failed
+        if !result.success && merged.exit.is_none_or(|e| e.is_success()) {
+            merged.exit = result.exit;
+        }
+        merged.stdout.push_str(&result.stdout);
+        merged.stderr.push_str(&format!("\n=== test shard {} ===\n", name));
+        merged.stderr.push_str(&result.stderr);
+        merged.diagnostics.extend(result.diagnostics);
+    }
+    merged.duration = start.elapsed();
 
-    let cargo_dir = source_dir.join(".cargo");
-    fs::create_dir_all(&cargo_dir)
-        .map_err(|e| format!("Failed to create .cargo dir: {}", e))?;
+    Ok(merged)
+}
 
-    let config_path = cargo_dir.join("config.toml");
-    let mut file = File::create(&config_path)
-        .map_err(|e| format!("Failed to create config.toml: {}", e))?;
+/// Rerun a failed test step up to `retries` more times
+///
+/// Stops at the first passing attempt, which is then marked flaky. Timeouts
+/// and resource limits aren't retried since another attempt would only hit
+/// them again. The result's duration covers every attempt, and its stderr
+/// keeps the output of the failed ones.
+fn retry_failed_tests(
+    retries: u32,
+    mut run: impl FnMut() -> Result<CompileResult, String>,
+) -> Result<CompileResult, String> {
+    let mut result = run()?;
+    let mut duration = result.duration;
+    let mut stderr = String::new();
 
-    let config_content = format!(
-        r#"[patch.crates-io]
-# This is a temporary override for cargo-copter testing
-# Any crate at this path will override the published version
-paths = ["{}"]
-"#,
-        override_path.display()
-    );
+    for attempt in 1..=retries {
+        if result.success || result.timed_out || result.limit_exceeded.is_some() {
+            break;
+        }
+        debug!("cargo test failed, retry {} of {}", attempt, retries);
+        stderr.push_str(&result.stderr);
+        stderr.push_str(&format!("\n=== retry {} of {} ===\n", attempt, retries));
 
-    file.write_all(config_content.as_bytes())
-        .map_err(|e| format!("Failed to write config: {}", e))?;
-    file.flush()
-        .map_err(|e| format!("Failed to flush config: {}", e))?;
+        result = run()?;
+        duration += result.duration;
+        result.flaky = result.success;
+    }
 
-    Ok(())
+    result.duration = duration;
+    if !stderr.is_empty() {
+        stderr.push_str(&result.stderr);
+        result.stderr = stderr;
+    }
+    Ok(result)
 }
 
 /// Source of a version being tested
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VersionSource {
     /// Published version from crates.io
     Published(String),
@@ -519,14 +1387,39 @@ impl VersionSource {
 }
 
 /// Three-step ICT (Install/Check/Test) result for a single version
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThreeStepResult {
     /// Install step (cargo fetch) - always runs
     pub fetch: CompileResult,
     /// Check step (cargo check) - only if fetch succeeds
     pub check: Option<CompileResult>,
-    /// Test step (cargo test) - only if check succeeds
+    /// Full build step (cargo build --all-targets) - only with `--full-build`, if check succeeds
+    #[serde(default)]
+    pub build: Option<CompileResult>,
+    /// Install build (cargo build --release --bins) - only with `--install`, for
+    /// dependents with binaries, if check (and the full build) succeeds
+    #[serde(default)]
+    pub install: Option<CompileResult>,
+    /// Test step (cargo test) - only if check and the builds succeed
     pub test: Option<CompileResult>,
+    /// Lint step (cargo clippy) - only with `--clippy`, after check succeeds.
+    /// Not part of `is_success`: lint failures are only compared against the baseline's
+    #[serde(default)]
+    pub clippy: Option<CompileResult>,
+    /// Docs step (cargo doc) - only with `--doc`, after check succeeds; compared like clippy
+    #[serde(default)]
+    pub doc: Option<CompileResult>,
+    /// Doctest step (cargo test --doc) - only with `--doctest`, for libraries, where
+    /// test runs; compared like clippy
+    #[serde(default)]
+    pub doctest: Option<CompileResult>,
+    /// Miri step (cargo +nightly miri test) - only for dependents selected with `--miri`,
+    /// where test runs; compared like clippy
+    #[serde(default)]
+    pub miri: Option<CompileResult>,
+    /// Optional steps requested for this run (those that ran are Some above)
+    #[serde(default)]
+    pub optional_steps: OptionalSteps,
     /// Actual version resolved (from cargo tree), if verification succeeded
     pub actual_version: Option<String>,
     /// Expected version being tested
@@ -535,9 +1428,80 @@ pub struct ThreeStepResult {
     pub forced_version: bool,
     /// Original requirement from dependent (e.g., "^0.8.52"), if known
     pub original_requirement: Option<String>,
+    /// Dependent features enabled for check/test
+    pub features: Vec<String>,
+    /// Crates pulling in other versions of the base crate (from cargo tree)
+    pub intermediates: Vec<Intermediate>,
+    /// Target the check ran for instead of the host (test is skipped)
+    pub cross_target: Option<String>,
+    /// Cargo.lock as resolved by fetch
+    pub lock_packages: Vec<LockPackage>,
+    /// Features of the base crate enabled in the dependent's graph (after unification)
+    pub base_features: Vec<String>,
+    /// `--lockfile` mode of the run
+    #[serde(default)]
+    pub lockfile_mode: LockfileMode,
+    /// The dependent's committed Cargo.lock, unless the mode is delete or it has none
+    #[serde(default)]
+    pub committed_lock: Option<CommittedLock>,
+    /// Resolved with `-Z minimal-versions` (`--minimal-versions`)
+    #[serde(default)]
+    pub minimal_versions: bool,
+    /// Libraries and binaries of the dependent after the full build (`--full-build`)
+    #[serde(default)]
+    pub artifact_sizes: Vec<crate::sizes::ArtifactSize>,
+    /// rust-versions of the dependent and of the base crate version it resolved
+    #[serde(default)]
+    pub rust_versions: crate::msrv::RustVersions,
 }
 
 impl ThreeStepResult {
+    /// Result of `fetch` and the `later` steps that ran after it, with nothing
+    /// known about the version resolved; the rest is filled in with struct
+    /// update syntax
+    pub fn from_steps(fetch: CompileResult, later: impl IntoIterator<Item = CompileResult>) -> Self {
+        let mut result = ThreeStepResult {
+            fetch,
+            check: None,
+            build: None,
+            install: None,
+            test: None,
+            clippy: None,
+            doc: None,
+            doctest: None,
+            miri: None,
+            optional_steps: OptionalSteps::default(),
+            actual_version: None,
+            expected_version: None,
+            forced_version: false,
+            original_requirement: None,
+            features: vec![],
+            intermediates: vec![],
+            cross_target: None,
+            lock_packages: vec![],
+            base_features: vec![],
+            lockfile_mode: LockfileMode::Delete,
+            committed_lock: None,
+            minimal_versions: false,
+            artifact_sizes: vec![],
+            rust_versions: Default::default(),
+        };
+        for step in later {
+            match step.step {
+                CompileStep::Fetch => result.fetch = step,
+                CompileStep::Check => result.check = Some(step),
+                CompileStep::Build => result.build = Some(step),
+                CompileStep::Install => result.install = Some(step),
+                CompileStep::Test => result.test = Some(step),
+                CompileStep::Clippy => result.clippy = Some(step),
+                CompileStep::Doc => result.doc = Some(step),
+                CompileStep::Doctest => result.doctest = Some(step),
+                CompileStep::Miri => result.miri = Some(step),
+            }
+        }
+        result
+    }
+
     /// Determine if all executed steps succeeded
     pub fn is_success(&self) -> bool {
         if !self.fetch.success {
@@ -548,6 +1512,16 @@ impl ThreeStepResult {
                 return false;
             }
         }
+        if let Some(ref build) = self.build {
+            if !build.success {
+                return false;
+            }
+        }
+        if let Some(ref install) = self.install {
+            if !install.success {
+                return false;
+            }
+        }
         if let Some(ref test) = self.test {
             if !test.success {
                 return false;
@@ -556,6 +1530,20 @@ impl ThreeStepResult {
         true
     }
 
+    /// Every step that ran, in order
+    pub fn steps(&self) -> Vec<&CompileResult> {
+        std::iter::once(&self.fetch)
+            .chain(self.check.iter())
+            .chain(self.build.iter())
+            .chain(self.install.iter())
+            .chain(self.clippy.iter())
+            .chain(self.doc.iter())
+            .chain(self.test.iter())
+            .chain(self.doctest.iter())
+            .chain(self.miri.iter())
+            .collect()
+    }
+
     /// Get the first failed step, if any
     pub fn first_failure(&self) -> Option<&CompileResult> {
         if !self.fetch.success {
@@ -566,6 +1554,16 @@ impl ThreeStepResult {
                 return Some(check);
             }
         }
+        if let Some(ref build) = self.build {
+            if !build.success {
+                return Some(build);
+            }
+        }
+        if let Some(ref install) = self.install {
+            if !install.success {
+                return Some(install);
+            }
+        }
         if let Some(ref test) = self.test {
             if !test.success {
                 return Some(test);
@@ -574,6 +1572,11 @@ impl ThreeStepResult {
         None
     }
 
+    /// The step that was killed for exceeding its timeout, if any
+    pub fn timed_out_step(&self) -> Option<CompileStep> {
+        self.first_failure().filter(|f| f.timed_out).map(|f| f.step)
+    }
+
     /// Format ICT marks for display (e.g., "✓✓✓", "✓✗-", "✗--")
     /// Shows cumulative failure: after first failure, show dashes
     pub fn format_ict_marks(&self) -> String {
@@ -603,31 +1606,36 @@ impl ThreeStepResult {
     }
 }
 
-/// Result of testing a dependent against a single version
-#[derive(Debug, Clone)]
-pub struct VersionTestResult {
-    pub version_source: VersionSource,
-    pub result: ThreeStepResult,
-}
-
 /// Run three-step ICT (Install/Check/Test) test with early stopping
 ///
 /// # Arguments
 /// * `crate_path` - Path to the dependent crate
 /// * `base_crate_name` - Name of the crate being overridden (e.g., "rgb")
 /// * `override_path` - Optional path to override a dependency (None for published baseline)
+/// * `companions` - Other crates of the base crate's workspace, patched to these paths alongside it
 /// * `skip_check` - Skip cargo check step
 /// * `skip_test` - Skip cargo test step
+/// * `dependent_config` - Per-dependent features and test splitting (see `run_test_step`)
+/// * `targets` - The dependent's target kinds; bin-only dependents check and test `--bins`
+/// * `cross_target` - Check for this target triple instead of the host, and skip test
 ///
 /// # Returns
 /// ThreeStepResult with cumulative early stopping:
 /// - Fetch always runs
 /// - Check only runs if fetch succeeds (and !skip_check)
-/// - Test only runs if check succeeds (and !skip_test)
+/// - With `--full-build`, build only runs if check succeeds (and not for a cross target)
+/// - Test only runs if check and build succeed (and !skip_test)
+///
+/// With `--lockfile respect` the dependent's committed Cargo.lock is used
+/// instead of resolving from scratch; with `--lockfile both` this runs twice
+/// and the result is the fresh one, with how the locked run went alongside.
+#[allow(clippy::too_many_arguments)]
 pub fn run_three_step_ict(
+    settings: &RunSettings,
     crate_path: &Path,
     base_crate_name: &str,
     override_path: Option<&Path>,
+    companions: &[(String, PathBuf)],
     skip_check: bool,
     skip_test: bool,
     expected_version: Option<String>,
@@ -636,15 +1644,74 @@ pub fn run_three_step_ict(
     dependent_name: Option<&str>,  // For failure logging
     dependent_version: Option<&str>,  // For failure logging
     test_label: Option<&str>,  // For failure logging: "baseline", "WIP", or version
+    dependent_config: Option<&DependentConfig>,  // Per-dependent features/test splitting
+    targets: Option<TargetKinds>,
+    cross_target: Option<&str>,
 ) -> Result<ThreeStepResult, String> {
-    debug!("running three-step ICT for {:?} (force={}, expected_version={:?})", crate_path, force_versions, expected_version);
+    let mode = settings.lockfile_mode;
+    let mut committed = match mode {
+        LockfileMode::Delete => None,
+        LockfileMode::Respect | LockfileMode::Both => CommittedLock::load(crate_path, base_crate_name),
+    };
+    let run = |respect_lock: bool, label: Option<&str>| run_ict(
+        settings, crate_path, base_crate_name, override_path, companions, skip_check, skip_test,
+        expected_version.clone(), force_versions, original_requirement.clone(),
+        dependent_name, dependent_version, label, dependent_config, targets, cross_target, respect_lock,
+    );
+
+    let mut result = match (mode, &mut committed) {
+        (LockfileMode::Respect, Some(_)) => run(true, test_label)?,
+        (LockfileMode::Both, Some(lock)) => {
+            let fresh = run(false, test_label)?;
+            let locked_label = test_label.map(|label| format!("{} with Cargo.lock", label));
+            let locked = run(true, locked_label.as_deref())?;
+            lock.locked = Some(match locked.first_failure() {
+                Some(failure) => LockedOutcome::Failed(failure.step),
+                None => LockedOutcome::Passed,
+            });
+            fresh
+        }
+        _ => run(false, test_label)?,
+    };
+    result.lockfile_mode = mode;
+    result.committed_lock = committed;
+    result.minimal_versions = settings.minimal_versions;
+    Ok(result)
+}
+
+/// One ICT run, from a fresh resolution or from the committed Cargo.lock
+#[allow(clippy::too_many_arguments)]
+fn run_ict(
+    settings: &RunSettings,
+    crate_path: &Path,
+    base_crate_name: &str,
+    override_path: Option<&Path>,
+    companions: &[(String, PathBuf)],
+    skip_check: bool,
+    skip_test: bool,
+    expected_version: Option<String>,
+    force_versions: bool,
+    original_requirement: Option<String>,
+    dependent_name: Option<&str>,
+    dependent_version: Option<&str>,
+    test_label: Option<&str>,
+    dependent_config: Option<&DependentConfig>,
+    targets: Option<TargetKinds>,
+    cross_target: Option<&str>,
+    respect_lock: bool,
+) -> Result<ThreeStepResult, String> {
+    debug!("running three-step ICT for {:?} (force={}, expected_version={:?}, respect_lock={})",
+        crate_path, force_versions, expected_version, respect_lock);
 
     // Always restore Cargo.toml from original backup to prevent contamination
     restore_cargo_toml(crate_path)?;
 
-    // Always delete Cargo.lock to force fresh dependency resolution
+    // Start from the committed Cargo.lock, or delete it to force fresh dependency resolution
     let lock_file = crate_path.join("Cargo.lock");
-    if lock_file.exists() {
+    if respect_lock {
+        fs::copy(crate_path.join(lockfile::ORIGINAL_LOCK), &lock_file)
+            .map_err(|e| format!("Failed to restore the committed Cargo.lock: {}", e))?;
+    } else if lock_file.exists() {
         debug!("Deleting Cargo.lock to force dependency resolution");
         fs::remove_file(&lock_file)
             .map_err(|e| format!("Failed to remove Cargo.lock: {}", e))?;
@@ -666,7 +1733,6 @@ pub fn run_three_step_ict(
             (Some(backup), None) // Don't use --config when we modified Cargo.toml
         } else {
             // PATCH MODE: Use --config flag (clean, no file modifications)
-            // Build override_spec for --config flag
             let abs_path = if override_path.is_absolute() {
                 override_path.to_path_buf()
             } else {
@@ -682,92 +1748,207 @@ pub fn run_three_step_ict(
         (None, None) // No override (baseline test)
     };
 
-    // Build override_spec for compile_crate calls
-    let override_spec = override_path_buf.as_ref().map(|path| (base_crate_name, path.as_path()));
-
-    // Step 1: Fetch (always runs)
-    let fetch = compile_crate(crate_path, CompileStep::Fetch, override_spec)?;
-
-    // Verify the actual version after fetch
-    let actual_version = if fetch.success {
-        verify_dependency_version(crate_path, base_crate_name)
+    // --config patches: the base crate in patch mode, its companions in either mode
+    let mut patches: Vec<(&str, &Path)> = override_path_buf.iter()
+        .map(|path| (base_crate_name, path.as_path()))
+        .collect();
+    patches.extend(companions.iter().map(|(name, path)| (name.as_str(), path.as_path())));
+
+    let optional = settings.optional_steps;
+    let commands = dependent_config.map(|c| c.commands.clone()).unwrap_or_default();
+    // What a step's failure log says was run
+    let command_label = |custom: &Option<Vec<String>>, default: &str| custom.as_ref()
+        .map(|command| command.join(" "))
+        .unwrap_or_else(|| default.to_string());
+
+    // Step 1: Fetch (always runs). The baseline must build from the committed lock
+    // as is; an offered version has to move the base crate in it
+    let fetch_args: Vec<String> = if respect_lock && override_path.is_none() {
+        vec!["--locked".to_string()]
+    } else {
+        Vec::new()
+    };
+    // With --minimal-versions, the lock is generated first and fetch follows it
+    let resolved = if settings.minimal_versions && !respect_lock {
+        // A published baseline stays at the version it's meant to test
+        let pin = expected_version.as_deref().filter(|_| override_path.is_none());
+        Some(resolve_minimal_versions(settings, crate_path, base_crate_name, &patches, pin)?)
     } else {
         None
     };
+    let fetch = match resolved {
+        Some(resolved) if resolved.failed() => resolved,
+        _ => compile_crate_with_command(settings, crate_path, CompileStep::Fetch, commands.fetch.as_deref(), &patches, &fetch_args, &[])?,
+    };
+
+    // Verify the actual version after fetch, and which of its features ended up enabled
+    debug!("Verifying {} version in {:?}", base_crate_name, crate_path);
+    let metadata = if fetch.success { cargo_metadata(settings, crate_path) } else { None };
+    let actual_version = metadata.as_ref().and_then(|m| verify_dependency_version(m, base_crate_name));
+    let base_features = metadata.as_ref().map(|m| enabled_features(m, base_crate_name)).unwrap_or_default();
+    let rust_versions = metadata.as_ref()
+        .map(|m| crate::msrv::from_metadata(m, base_crate_name, actual_version.as_deref()))
+        .unwrap_or_default();
+
+    // Keep the Cargo.lock fetch left for comparing versions
+    let lock_packages = lockfile::read_packages(crate_path);
+
+    // Find out why other versions of the base crate are in the graph
+    let intermediates = match dependent_name {
+        Some(dependent) if fetch.success => {
+            find_intermediates(crate_path, base_crate_name, dependent, &patch_config_args(&patches)?)
+        }
+        _ => Vec::new(),
+    };
+
+    let features: Vec<String> = dependent_config.map(|c| c.features.clone()).unwrap_or_default();
+    // A run ended early by a failed step, with what's known about the version so far
+    let stopped = |fetch: CompileResult, later: Vec<CompileResult>| ThreeStepResult {
+        optional_steps: optional,
+        actual_version: actual_version.clone(),
+        expected_version: expected_version.clone(),
+        forced_version: force_versions,
+        original_requirement: original_requirement.clone(),
+        features: features.clone(),
+        intermediates: intermediates.clone(),
+        cross_target: cross_target.map(str::to_string),
+        lock_packages: lock_packages.clone(),
+        base_features: base_features.clone(),
+        ..ThreeStepResult::from_steps(fetch, later)
+    };
 
     if fetch.failed() {
         // Log failure
         if let (Some(dep_name), Some(dep_ver), Some(label)) = (dependent_name, dependent_version, test_label) {
             log_failure(
+                settings,
                 dep_name,
                 dep_ver,
                 base_crate_name,
                 label,
-                &format!("cargo fetch"),
-                None,
-                &fetch.stdout,
-                &fetch.stderr,
+                &command_label(&commands.fetch, "cargo fetch"),
+                &fetch,
             );
         }
 
         // Fetch failed - stop here with dashes for remaining steps
-        return Ok(ThreeStepResult {
-            fetch,
-            check: None,
-            test: None,
-            actual_version,
-            expected_version,
-            forced_version: force_versions,
-            original_requirement: original_requirement.clone(),
-        });
+        return Ok(stopped(fetch, vec![]));
+    }
+
+    let target_args = targets.map(|t| t.cargo_args()).unwrap_or_default();
+    let mut check_args = [target_args.clone(), dependent_config.map(|c| c.feature_args()).unwrap_or_default()].concat();
+    if let Some(triple) = cross_target {
+        check_args.extend(["--target".to_string(), triple.to_string()]);
     }
 
+    // Run an extra step, logging its failure like the others
+    let run_logged = |step: CompileStep, args: &[String]| -> Result<CompileResult, String> {
+        let result = compile_crate_with_args(settings, crate_path, step, &patches, args, &[])?;
+        if result.failed() {
+            if let (Some(dep_name), Some(dep_ver), Some(label)) = (dependent_name, dependent_version, test_label) {
+                let command = match step {
+                    CompileStep::Doctest => "cargo test --doc".to_string(),
+                    CompileStep::Miri => "cargo +nightly miri test".to_string(),
+                    CompileStep::Install => "cargo build --release --bins".to_string(),
+                    _ => format!("cargo {}", step.cargo_subcommand()),
+                };
+                log_failure(settings, dep_name, dep_ver, base_crate_name, label, &command, &result);
+            }
+        }
+        Ok(result)
+    };
+
     // Step 2: Check (only if fetch succeeded and not skipped)
     let check = if !skip_check {
-        let result = compile_crate(crate_path, CompileStep::Check, override_spec)?;
+        let result = compile_crate_with_command(settings, crate_path, CompileStep::Check, commands.check.as_deref(), &patches, &check_args, &[])?;
         if result.failed() {
             // Log failure
             if let (Some(dep_name), Some(dep_ver), Some(label)) = (dependent_name, dependent_version, test_label) {
                 log_failure(
+                    settings,
                     dep_name,
                     dep_ver,
                     base_crate_name,
                     label,
-                    &format!("cargo check"),
-                    None,
-                    &result.stdout,
-                    &result.stderr,
+                    &command_label(&commands.check, "cargo check"),
+                    &result,
                 );
             }
 
             // Check failed - stop here with dash for test
-            return Ok(ThreeStepResult {
-                fetch,
-                check: Some(result),
-                test: None,
-                actual_version: actual_version.clone(),
-                expected_version: expected_version.clone(),
-                forced_version: force_versions,
-                original_requirement: original_requirement.clone(),
-            });
+            return Ok(stopped(fetch, vec![result]));
         }
         Some(result)
     } else {
         None
     };
 
-    // Step 3: Test (only if check succeeded or was skipped, and not skip_test)
-    let test = if !skip_test {
-        let should_run = match &check {
-            Some(c) => c.success,
-            None => true, // check was skipped, proceed
-        };
+    // Lints (--clippy) and docs (--doc): only once the code compiles, with the same targets and features as check
+    let compiles = check.as_ref().is_none_or(|c| c.success);
+    let clippy = if optional.clippy && compiles {
+        Some(run_logged(CompileStep::Clippy, &check_args)?)
+    } else {
+        None
+    };
+    let doc = if optional.doc && compiles {
+        let doc_args = [check_args.clone(), vec!["--no-deps".to_string()]].concat();
+        Some(run_logged(CompileStep::Doc, &doc_args)?)
+    } else {
+        None
+    };
 
-        if should_run {
-            Some(compile_crate(crate_path, CompileStep::Test, override_spec)?)
-        } else {
-            None
+    // Full build (--full-build): codegen, linking and build scripts that check doesn't exercise
+    let build = if optional.full_build && cross_target.is_none() {
+        let build_args = [dependent_config.map(|c| c.feature_args()).unwrap_or_default(), vec!["--all-targets".to_string()]].concat();
+        let result = run_logged(CompileStep::Build, &build_args)?;
+        if result.failed() {
+            return Ok(stopped(fetch, check.into_iter().chain(clippy).chain(doc).chain([result]).collect()));
+        }
+        Some(result)
+    } else {
+        None
+    };
+    // Measured now, before the next version's build replaces the files
+    let artifact_sizes = build.as_ref().map(|b| crate::sizes::measure(&b.stdout, crate_path)).unwrap_or_default();
+
+    // Install build (--install): the release build of the binaries `cargo install` users get
+    let install = if optional.install && cross_target.is_none() && targets.is_some_and(|t| t.bins) {
+        let install_args = [dependent_config.map(|c| c.feature_args()).unwrap_or_default(), vec!["--release".to_string(), "--bins".to_string()]].concat();
+        let result = run_logged(CompileStep::Install, &install_args)?;
+        if result.failed() {
+            return Ok(stopped(fetch, check.into_iter().chain(clippy).chain(doc).chain(build).chain([result]).collect()));
         }
+        Some(result)
+    } else {
+        None
+    };
+
+    // Step 3: Test (only if check succeeded or was skipped, and not skip_test)
+    // Binaries built for another target can't run here
+    let runs_tests = !skip_test && cross_target.is_none() && compiles;
+    // With --doctest, libraries' doctests get their own step: test selects
+    // every other target `cargo test` runs by default
+    let has_lib = targets.is_none_or(|t| t.lib);
+    let split_doctests = optional.doctest && has_lib;
+    let test_target_args = if split_doctests {
+        [target_args.clone(), ["--lib", "--bins", "--tests", "--examples"].map(String::from).to_vec()].concat()
+    } else {
+        target_args.clone()
+    };
+    let test = if runs_tests {
+        Some(retry_failed_tests(settings.test_retries, || run_test_step(settings, crate_path, &patches, dependent_config, &test_target_args))?)
+    } else {
+        None
+    };
+    let doctest = if runs_tests && split_doctests {
+        let doctest_args = [dependent_config.map(|c| c.feature_args()).unwrap_or_default(), vec!["--doc".to_string()]].concat();
+        Some(run_logged(CompileStep::Doctest, &doctest_args)?)
+    } else {
+        None
+    };
+    let miri = if runs_tests && dependent_config.is_some_and(|c| c.miri) {
+        let miri_args = [target_args.clone(), dependent_config.map(|c| c.feature_args()).unwrap_or_default()].concat();
+        Some(run_logged(CompileStep::Miri, &miri_args)?)
     } else {
         None
     };
@@ -777,14 +1958,13 @@ pub fn run_three_step_ict(
         if test_result.failed() {
             if let (Some(dep_name), Some(dep_ver), Some(label)) = (dependent_name, dependent_version, test_label) {
                 log_failure(
+                    settings,
                     dep_name,
                     dep_ver,
                     base_crate_name,
                     label,
-                    &format!("cargo test"),
-                    None,
-                    &test_result.stdout,
-                    &test_result.stderr,
+                    &command_label(&commands.test, "cargo test"),
+                    test_result,
                 );
             }
         }
@@ -801,11 +1981,28 @@ pub fn run_three_step_ict(
     Ok(ThreeStepResult {
         fetch,
         check,
+        build,
+        install,
         test,
+        clippy,
+        doc,
+        doctest,
+        miri,
+        optional_steps: optional,
         actual_version,
         expected_version,
         forced_version: force_versions,
         original_requirement,
+        features,
+        intermediates,
+        cross_target: cross_target.map(str::to_string),
+        lock_packages,
+        base_features,
+        lockfile_mode: LockfileMode::Delete,
+        committed_lock: None,
+        minimal_versions: false,
+        artifact_sizes,
+        rust_versions,
     })
 }
 
@@ -814,39 +2011,239 @@ pub fn run_three_step_ict(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_failure_entry_json() {
+        let stdout = r#"{"reason":"compiler-message","message":{"level":"error","code":{"code":"E0308"},"message":"mismatched types","rendered":"error[E0308]: mismatched types\n","spans":[]}}
+{"reason":"compiler-message","message":{"level":"warning","code":null,"message":"unused import","rendered":"warning: unused import\n","spans":[]}}"#;
+        let result = CompileResult {
+            step: CompileStep::Check,
+            success: false,
+            stdout: stdout.to_string(),
+            stderr: "error: could not compile `image`".to_string(),
+            duration: Duration::from_millis(1500),
+            diagnostics: parse_cargo_json(stdout),
+            timed_out: false,
+            limit_exceeded: None,
+            exit: Some(ProcessExit::Code(101)),
+            flaky: false,
+        };
+        let entry = FailureEntry::new("image", "0.25.8", "rgb", "0.8.91", "cargo check", &result);
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["dependent"], "image");
+        assert_eq!(json["version"], "0.8.91");
+        assert_eq!(json["step"], CompileStep::Check.as_str());
+        assert_eq!(json["duration"], 1.5);
+        assert_eq!(json["diagnostics"].as_array().unwrap().len(), 1);
+        assert_eq!(json["diagnostics"][0]["code"], "E0308");
+        assert!(json.get("stderr").is_none());
+    }
+
+    #[test]
+    fn test_build_cache_env_vars() {
+        assert!(BuildCache::default().env_vars().is_empty());
+
+        let cache = BuildCache {
+            target_dir: Some(PathBuf::from("/tmp/copter-target")),
+            rustc_wrapper: Some("sccache".to_string()),
+        };
+        assert_eq!(cache.env_vars(), vec![
+            ("CARGO_TARGET_DIR", "/tmp/copter-target".to_string()),
+            ("RUSTC_WRAPPER", "sccache".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_network_errors_are_transport_failures() {
+        assert!(is_network_error("warning: spurious network error (3 tries remaining): [28] Timeout was reached"));
+        assert!(is_network_error("error: failed to download from `https://static.crates.io/crates/rgb/0.8.50/download`\n\nCaused by:\n  [6] Could not resolve hostname (Could not resolve host: static.crates.io)"));
+        // The crate itself is the problem
+        assert!(!is_network_error("error: failed to download `rgb v0.8.50`\n\nCaused by:\n  failed to verify the checksum of `rgb v0.8.50`"));
+        assert!(!is_network_error("error: failed to get successful HTTP response from `https://index.crates.io/rg/b/rgb`, got 404"));
+    }
+
+    #[test]
+    fn test_deny_warnings_leaves_rustflags_alone() {
+        let cargo_env = CargoEnv {
+            vars: vec![("RUSTFLAGS".to_string(), "--cfg ci".to_string())],
+            rustflags: None,
+            deny_warnings: true,
+        };
+        assert_eq!(cargo_env.env_vars(), cargo_env.vars);
+        assert!(!cargo_env.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_process_group() {
+        // The background sleep inherits the output pipes; unless the whole
+        // group is killed, reading the output would block for 30s
+        let start = Instant::now();
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo started; sleep 30 & sleep 30"]);
+        let output = output_with_timeout(&mut cmd, Some(Duration::from_millis(300))).unwrap();
+        assert!(output.timed_out);
+        assert!(!output.success);
+        assert_eq!(output.exit, Some(ProcessExit::Signal(9)));
+        assert_eq!(output.exit.unwrap().to_string(), "signal 9 (SIGKILL)");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "started\n");
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo done"]);
+        let output = output_with_timeout(&mut cmd, Some(Duration::from_secs(10))).unwrap();
+        assert!(!output.timed_out);
+        assert!(output.success);
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "exit 101"]);
+        let output = output_with_timeout(&mut cmd, Some(Duration::from_secs(10))).unwrap();
+        assert!(!output.timed_out);
+        assert_eq!(output.exit, Some(ProcessExit::Code(101)));
+        assert_eq!(output.exit.unwrap().to_string(), "101");
+    }
+
+    #[test]
+    fn test_detect_limit_exceeded() {
+        let oom = "memory allocation of 4294967296 bytes failed\nerror: could not compile `image`";
+        assert_eq!(LimitExceeded::detect(oom), Some(LimitExceeded::Memory));
+        let cpu = "process didn't exit successfully: `rustc ...` (signal: 24, SIGXCPU: cpu time limit exceeded)";
+        assert_eq!(LimitExceeded::detect(cpu), Some(LimitExceeded::CpuTime));
+        assert_eq!(LimitExceeded::detect("error[E0308]: mismatched types"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resource_limits_apply_to_children() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "ulimit -v; ulimit -t"]);
+        ResourceLimits { memory_bytes: Some(512 * 1024 * 1024), cpu_time: Some(Duration::from_secs(60)) }
+            .apply(&mut cmd);
+        let output = cmd.output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "524288\n60\n");
+    }
+
+    #[test]
+    fn test_patch_config_args_quotes_windows_paths() {
+        assert!(patch_config_args(&[]).unwrap().is_empty());
+        let args = patch_config_args(&[("rgb", Path::new("/work\\rgb")), ("rgb-derive", Path::new("/work/derive"))]).unwrap();
+        assert_eq!(args, vec![
+            "--config".to_string(), "patch.crates-io.rgb.path=\"/work/rgb\"".to_string(),
+            "--config".to_string(), "patch.crates-io.rgb-derive.path=\"/work/derive\"".to_string(),
+        ]);
+    }
+
     #[test]
     fn test_compile_step_as_str() {
         assert_eq!(CompileStep::Check.as_str(), "check");
         assert_eq!(CompileStep::Test.as_str(), "test");
     }
 
+    #[test]
+    fn test_force_inherited_dependency() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\"]\n[workspace.dependencies]\nrgb = { version = \"0.8\", default-features = false, features = [\"bytemuck\"] }\n").unwrap();
+        let member = dir.path().join("app");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(member.join("Cargo.toml"),
+            "[package]\nname = \"app\"\n[dependencies]\nrgb = { workspace = true, features = [\"serde\"] }\n").unwrap();
+
+        force_dependency_spec(&member, "rgb", Path::new("/tmp/rgb-0.8.91")).unwrap();
+        let manifest: toml::Value = toml::from_str(&fs::read_to_string(member.join("Cargo.toml")).unwrap()).unwrap();
+        let rgb = &manifest["dependencies"]["rgb"];
+        assert_eq!(rgb["path"].as_str(), Some("/tmp/rgb-0.8.91"));
+        assert!(rgb.get("workspace").is_none());
+        assert_eq!(rgb["default-features"].as_bool(), Some(false));
+        let features: Vec<&str> = rgb["features"].as_array().unwrap().iter().filter_map(|f| f.as_str()).collect();
+        assert_eq!(features, vec!["serde", "bytemuck"]);
+    }
+
     #[test]
     fn test_compile_step_cargo_subcommand() {
         assert_eq!(CompileStep::Check.cargo_subcommand(), "check");
         assert_eq!(CompileStep::Test.cargo_subcommand(), "test");
+        assert_eq!(CompileStep::Clippy.cargo_subcommand(), "clippy");
+        assert_eq!(CompileStep::Doctest.as_str(), "doctest");
+        assert_eq!(CompileStep::Doctest.cargo_subcommand(), "test");
+        assert_eq!(CompileStep::Miri.cargo_subcommand(), "miri test");
     }
 
     #[test]
-    fn test_compile_result_failed() {
-        let result = CompileResult {
-            step: CompileStep::Check,
-            success: false,
-            stdout: String::new(),
-            stderr: String::new(),
-            duration: Duration::from_secs(1),
-            diagnostics: Vec::new(),
+    fn test_failed_full_build_fails_the_result() {
+        let step = CompileResult::for_test;
+        let mut result = ThreeStepResult {
+            optional_steps: OptionalSteps { full_build: true, ..OptionalSteps::default() },
+            ..ThreeStepResult::from_steps(step(CompileStep::Fetch, true), [step(CompileStep::Check, true), step(CompileStep::Build, false)])
         };
-        assert!(result.failed());
+        assert!(!result.is_success());
+        assert_eq!(result.first_failure().map(|f| f.step), Some(CompileStep::Build));
+
+        // A binary that doesn't build for `cargo install` fails it like a build
+        result.build = Some(step(CompileStep::Build, true));
+        result.install = Some(step(CompileStep::Install, false));
+        assert_eq!(result.first_failure().map(|f| f.step), Some(CompileStep::Install));
+
+        // Optional steps compared against the baseline don't fail it
+        result.install = Some(step(CompileStep::Install, true));
+        result.clippy = Some(step(CompileStep::Clippy, false));
+        assert!(result.is_success());
+        assert_eq!(result.first_failure().map(|f| f.step), None);
+    }
 
-        let result = CompileResult {
-            step: CompileStep::Check,
-            success: true,
-            stdout: String::new(),
-            stderr: String::new(),
-            duration: Duration::from_secs(1),
-            diagnostics: Vec::new(),
+    #[test]
+    fn test_compile_result_failed() {
+        assert!(CompileResult::for_test(CompileStep::Check, false).failed());
+        assert!(!CompileResult::for_test(CompileStep::Check, true).failed());
+    }
+
+    #[test]
+    fn test_retry_failed_tests() {
+        let attempt = |success: bool, stderr: &str| CompileResult {
+            stderr: stderr.to_string(),
+            duration: Duration::from_secs(10),
+            ..CompileResult::for_test(CompileStep::Test, success)
         };
-        assert!(!result.failed());
+
+        // Fails, then passes: flaky, with time and output of both attempts
+        let mut outcomes = vec![attempt(true, "ok"), attempt(false, "boom")];
+        let result = retry_failed_tests(2, || Ok(outcomes.pop().unwrap())).unwrap();
+        assert!(result.success && result.flaky);
+        assert_eq!(result.duration, Duration::from_secs(20));
+        assert_eq!(result.stderr, "boom\n=== retry 1 of 2 ===\nok");
+
+        // Fails every time: a real failure after 1 + 2 attempts
+        let mut runs = 0;
+        let result = retry_failed_tests(2, || { runs += 1; Ok(attempt(false, "boom")) }).unwrap();
+        assert!(!result.success && !result.flaky);
+        assert_eq!(runs, 3);
+
+        // Timeouts aren't retried
+        let mut runs = 0;
+        let result = retry_failed_tests(2, || {
+            runs += 1;
+            Ok(CompileResult { timed_out: true, ..attempt(false, "") })
+        }).unwrap();
+        assert!(result.timed_out);
+        assert_eq!(runs, 1);
+    }
+
+    #[test]
+    fn test_enabled_features_from_metadata() {
+        let metadata: serde_json::Value = serde_json::from_str(r#"{
+            "resolve": { "nodes": [
+                { "id": "path+file:///tmp/image#0.25.8", "features": ["default"], "deps": [
+                    { "name": "rgb", "pkg": "path+file:///home/me/rgb#0.8.91" },
+                    { "name": "ravif", "pkg": "registry+https://github.com/rust-lang/crates.io-index#ravif@0.11.0" }
+                ] },
+                { "id": "registry+https://github.com/rust-lang/crates.io-index#ravif@0.11.0", "features": [], "deps": [
+                    { "name": "rgb", "pkg": "path+file:///home/me/rgb#0.8.91" }
+                ] },
+                { "id": "path+file:///home/me/rgb#0.8.91", "features": ["serde", "bytemuck", "default"], "deps": [] }
+            ] }
+        }"#).unwrap();
+        // Unified across image and ravif, sorted
+        assert_eq!(enabled_features(&metadata, "rgb"), vec!["bytemuck", "default", "serde"]);
+        assert!(enabled_features(&metadata, "png").is_empty());
     }
 
     // TODO: Update tests for ThreeStepResult instead of FourStepResult
@@ -953,4 +2350,79 @@ mod tests {
         assert!(regressed.is_regressed());
         */
     }
+
+    #[test]
+    fn test_run_test_step_shards_cover_all_tests() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("Cargo.toml"),
+            "[package]\nname = \"shardme\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"),
+            "#[cfg(test)] mod alpha { #[test] fn one() {} }\n\
+             #[cfg(test)] mod beta { #[test] fn two() { panic!(\"beta fails\") } }\n").unwrap();
+
+        let sharding = DependentConfig {
+            test_threads: Some(1),
+            test_shards: vec!["alpha::".to_string()],
+            ..Default::default()
+        };
+        let result = run_test_step(&RunSettings::default(), dir.path(), &[], Some(&sharding), &[]).unwrap();
+
+        // The failing test isn't matched by any filter, so only the remainder shard catches it
+        assert!(result.failed());
+        assert!(result.stderr.contains("=== test shard alpha:: ==="));
+        assert!(result.stderr.contains("=== test shard (rest) ==="));
+        assert!(result.stdout.contains("beta fails") || result.stderr.contains("beta fails"));
+
+        // A custom test command replaces the sharded cargo test
+        let custom = DependentConfig {
+            commands: crate::config_file::StepCommands {
+                test: Some(["cargo", "test", "alpha::"].map(String::from).to_vec()),
+                ..Default::default()
+            },
+            ..sharding
+        };
+        let result = run_test_step(&RunSettings::default(), dir.path(), &[], Some(&custom), &[]).unwrap();
+        assert!(result.success);
+        assert!(!result.stderr.contains("=== test shard"));
+    }
+
+    #[test]
+    fn test_patch_config_file_is_restored() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".cargo/config.toml");
+        let patch = [("rgb", Path::new("/work/rgb"))];
+
+        let file = PatchConfigFile::write(dir.path(), &patch, &[]).unwrap();
+        let patched: toml::Value = toml::from_str(&fs::read_to_string(&config).unwrap()).unwrap();
+        assert_eq!(patched["patch"]["crates-io"]["rgb"]["path"].as_str(), Some("/work/rgb"));
+        drop(file);
+        assert!(!config.exists());
+
+        fs::write(&config, "[build]\njobs = 2\n").unwrap();
+        let file = PatchConfigFile::write(dir.path(), &patch, &[]).unwrap();
+        let patched = fs::read_to_string(&config).unwrap();
+        assert!(patched.starts_with("[build]\njobs = 2\n") && patched.contains("rgb = "));
+        drop(file);
+        assert_eq!(fs::read_to_string(&config).unwrap(), "[build]\njobs = 2\n");
+    }
+
+    #[test]
+    fn test_patch_config_file_merges_existing_settings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".cargo/config.toml");
+        fs::create_dir_all(config.parent().unwrap()).unwrap();
+        fs::write(&config, "[patch.crates-io]\nserde = { path = \"../serde\" }\n\n[source.crates-io]\nreplace-with = \"vendored\"\n").unwrap();
+
+        let source_args = ["--config", "source.crates-io.replace-with=\"snapshot\"", "--offline"].map(String::from);
+        let _file = PatchConfigFile::write(dir.path(), &[("rgb", Path::new("/work/rgb"))], &source_args).unwrap();
+        let text = fs::read_to_string(&config).unwrap();
+        assert_eq!(text.matches("crates-io]").count(), 2, "no duplicate tables in:\n{}", text);
+
+        let patched: toml::Value = toml::from_str(&text).unwrap();
+        assert_eq!(patched["patch"]["crates-io"]["serde"]["path"].as_str(), Some("../serde"));
+        assert_eq!(patched["patch"]["crates-io"]["rgb"]["path"].as_str(), Some("/work/rgb"));
+        assert_eq!(patched["source"]["crates-io"]["replace-with"].as_str(), Some("snapshot"));
+        assert_eq!(patched["net"]["offline"].as_bool(), Some(true));
+    }
 }
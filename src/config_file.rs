@@ -0,0 +1,318 @@
+//! copter.toml configuration file
+//!
+//! Per-dependent settings that don't fit on the command line. Example:
+//!
+//! ```toml
+//! # Where staging, caches and results live (COPTER_HOME takes precedence);
+//! # relative to this file
+//! home = "../.copter"
+//!
+//! [dependents.image]
+//! # Enabled during check/test (overridden by --dependent-features)
+//! features = ["png", "jpeg"]
+//! # Passed to the test harness as --test-threads
+//! test-threads = 4
+//! # Run `cargo test <filter>` for each entry in parallel, plus one
+//! # shard with every filter --skip'ed so no test is missed
+//! test-shards = ["codecs::", "imageops::"]
+//! # Test the repository's branch head instead of the published crate
+//! git = "https://github.com/image-rs/image#main"
+//! # Also run the tests under Miri to catch undefined behavior (or --miri image)
+//! miri = true
+//!
+//! [dependents.image.commands]
+//! # Run instead of cargo's command for the step, with the base crate patched
+//! test = ["cargo", "nextest", "run"]
+//! # "{cargo}" stands for the command the step would have run
+//! check = ["xvfb-run", "-a", "{cargo}"]
+//!
+//! # Shell commands around each dependent, with COPTER_DEPENDENT etc. set (see `hooks`)
+//! [hooks]
+//! pre-dependent = "docker compose up -d"
+//! post-dependent = "docker compose down"
+//! on-regression = "cp -r target/debug/deps \"$ARTIFACTS/$COPTER_DEPENDENT\""
+//!
+//! # Mail the summary with the HTML report attached when something regressed (see `email`)
+//! [email]
+//! to = ["maintainers@example.com"]
+//! from = "copter@ci.example.com"
+//! transport = "smtp"           # or "sendmail" (default)
+//! smtp-server = "localhost:25"
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::debug;
+use serde::Deserialize;
+
+use crate::Error;
+
+/// Default config file looked up in the current directory
+pub const DEFAULT_CONFIG_FILE: &str = "copter.toml";
+
+/// Parsed copter.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CopterConfig {
+    /// Home directory for staging, caches and results, relative to the config file
+    pub home: Option<PathBuf>,
+    /// Settings keyed by dependent crate name
+    #[serde(default)]
+    pub dependents: HashMap<String, DependentConfig>,
+    /// Shell commands run around each dependent
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Mail the results when a run finishes
+    pub email: Option<EmailConfig>,
+}
+
+/// Email notification settings (see `email`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EmailConfig {
+    pub to: Vec<String>,
+    pub from: String,
+    #[serde(default)]
+    pub transport: EmailTransport,
+    /// sendmail-compatible program [default: sendmail]
+    pub sendmail: Option<PathBuf>,
+    /// SMTP relay as `HOST[:PORT]` [default: localhost:25]
+    pub smtp_server: Option<String>,
+    /// Also mail runs without regressions
+    #[serde(default)]
+    pub always: bool,
+}
+
+/// How mail leaves this machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransport {
+    /// Pipe the message to `sendmail -t -i` (or msmtp and the like)
+    #[default]
+    Sendmail,
+    /// Hand it to an SMTP relay, without TLS or authentication
+    Smtp,
+}
+
+/// Shell hooks (see `hooks`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    /// Before a dependent's first build; failing makes it an ERROR
+    pub pre_dependent: Option<String>,
+    /// After each dependent, whatever the outcome
+    pub post_dependent: Option<String>,
+    /// After a dependent that regressed
+    pub on_regression: Option<String>,
+}
+
+/// Settings for a single dependent
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DependentConfig {
+    /// Features of the dependent to enable during check/test
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// `--test-threads` for the libtest harness
+    pub test_threads: Option<usize>,
+    /// Test name filters; each runs as its own `cargo test` shard
+    #[serde(default)]
+    pub test_shards: Vec<String>,
+    /// Repository to test from instead of crates.io, as `URL[#BRANCH]`
+    pub git: Option<String>,
+    /// Also run the tests under Miri (`cargo +nightly miri test`)
+    #[serde(default)]
+    pub miri: bool,
+    /// Commands replacing or wrapping cargo's for the fetch, check and test steps
+    #[serde(default)]
+    pub commands: StepCommands,
+}
+
+/// Custom commands of a dependent, as program and arguments
+///
+/// An argument `"{cargo}"` is replaced by the cargo command the step would
+/// have run; without one, the command replaces it (and test shards).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StepCommands {
+    #[serde(default, deserialize_with = "command")]
+    pub fetch: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "command")]
+    pub check: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "command")]
+    pub test: Option<Vec<String>>,
+}
+
+impl StepCommands {
+    pub fn is_empty(&self) -> bool {
+        *self == StepCommands::default()
+    }
+}
+
+/// A command needs at least a program to run
+fn command<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    let command = Vec::<String>::deserialize(deserializer)?;
+    if command.is_empty() {
+        return Err(serde::de::Error::custom("command is empty"));
+    }
+    Ok(Some(command))
+}
+
+impl CopterConfig {
+    /// Load config from `path`, or from ./copter.toml if it exists
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let path = match path {
+            Some(p) => p,
+            None => {
+                let default = Path::new(DEFAULT_CONFIG_FILE);
+                if !default.exists() {
+                    return Ok(CopterConfig::default());
+                }
+                default
+            }
+        };
+
+        debug!("Loading config from {:?}", path);
+        let content = fs::read_to_string(path)?;
+        let mut config: CopterConfig = toml::from_str(&content)?;
+        if let (Some(home), Some(dir)) = (config.home.as_mut(), path.parent()) {
+            *home = dir.join(&*home);
+        }
+        Ok(config)
+    }
+
+    /// Settings for a dependent, if any were configured
+    pub fn dependent(&self, name: &str) -> Option<&DependentConfig> {
+        self.dependents.get(name)
+    }
+}
+
+impl DependentConfig {
+    /// Cargo arguments enabling the configured features
+    pub fn feature_args(&self) -> Vec<String> {
+        if self.features.is_empty() {
+            Vec::new()
+        } else {
+            vec!["--features".to_string(), self.features.join(",")]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dependent_sharding() {
+        let config: CopterConfig = toml::from_str(r#"
+            [dependents.image]
+            test-threads = 4
+            test-shards = ["codecs::", "imageops::"]
+        "#).unwrap();
+
+        let image = config.dependent("image").unwrap();
+        assert_eq!(image.test_threads, Some(4));
+        assert_eq!(image.test_shards, vec!["codecs::", "imageops::"]);
+        assert!(image.feature_args().is_empty());
+        assert!(config.dependent("serde").is_none());
+    }
+
+    #[test]
+    fn test_feature_args() {
+        let config: CopterConfig = toml::from_str(r#"
+            [dependents.image]
+            features = ["png", "jpeg"]
+        "#).unwrap();
+        assert_eq!(config.dependent("image").unwrap().feature_args(), vec!["--features", "png,jpeg"]);
+        assert!(!config.dependent("image").unwrap().miri);
+    }
+
+    #[test]
+    fn test_parse_miri() {
+        let config: CopterConfig = toml::from_str(r#"
+            [dependents.bytemuck]
+            miri = true
+        "#).unwrap();
+        assert!(config.dependent("bytemuck").unwrap().miri);
+    }
+
+    #[test]
+    fn test_parse_git_source() {
+        let config: CopterConfig = toml::from_str(r#"
+            [dependents.image]
+            git = "https://github.com/image-rs/image#main"
+        "#).unwrap();
+        assert_eq!(config.dependent("image").unwrap().git.as_deref(), Some("https://github.com/image-rs/image#main"));
+    }
+
+    #[test]
+    fn test_parse_step_commands() {
+        let config: CopterConfig = toml::from_str(r#"
+            [dependents.image.commands]
+            test = ["wasm-pack", "test", "--node"]
+            check = ["xvfb-run", "{cargo}"]
+        "#).unwrap();
+        let commands = &config.dependent("image").unwrap().commands;
+        assert_eq!(commands.test, Some(vec!["wasm-pack".to_string(), "test".to_string(), "--node".to_string()]));
+        assert_eq!(commands.check, Some(vec!["xvfb-run".to_string(), "{cargo}".to_string()]));
+        assert_eq!(commands.fetch, None);
+        assert!(!commands.is_empty());
+
+        let empty: Result<CopterConfig, _> = toml::from_str(r#"
+            [dependents.image.commands]
+            test = []
+        "#);
+        assert!(empty.is_err());
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let config: CopterConfig = toml::from_str(r#"
+            [hooks]
+            pre-dependent = "docker compose up -d"
+            on-regression = "./collect.sh"
+        "#).unwrap();
+        assert_eq!(config.hooks.pre_dependent.as_deref(), Some("docker compose up -d"));
+        assert_eq!(config.hooks.post_dependent, None);
+        assert_eq!(config.hooks.on_regression.as_deref(), Some("./collect.sh"));
+    }
+
+    #[test]
+    fn test_parse_email() {
+        let config: CopterConfig = toml::from_str(r#"
+            [email]
+            to = ["a@example.com", "b@example.com"]
+            from = "copter@example.com"
+            transport = "smtp"
+            smtp-server = "mail.example.com:2525"
+        "#).unwrap();
+        let email = config.email.unwrap();
+        assert_eq!(email.to.len(), 2);
+        assert_eq!(email.transport, EmailTransport::Smtp);
+        assert_eq!(email.smtp_server.as_deref(), Some("mail.example.com:2525"));
+        assert!(!email.always);
+
+        let config: CopterConfig = toml::from_str("[email]\nto = [\"a@example.com\"]\nfrom = \"c@example.com\"\n").unwrap();
+        assert_eq!(config.email.unwrap().transport, EmailTransport::Sendmail);
+        assert!(CopterConfig::default().email.is_none());
+    }
+
+    #[test]
+    fn test_home_is_relative_to_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("copter.toml");
+        fs::write(&path, "home = \"cache/copter\"\n").unwrap();
+        let config = CopterConfig::load(Some(&path)).unwrap();
+        assert_eq!(config.home, Some(dir.path().join("cache/copter")));
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        let result: Result<CopterConfig, _> = toml::from_str(r#"
+            [dependents.image]
+            test-thread = 4
+        "#);
+        assert!(result.is_err());
+    }
+}
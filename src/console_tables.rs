@@ -147,7 +147,7 @@ pub fn format_separator_row(
         // Find indices of columns at this position
         let prev_idx = resolved_cols.iter().position(|c|
             c.from_above && pos >= c.offset && pos < c.offset + c.width);
-        let next_idx = resolved_cols.iter().position(|c|
+        let _next_idx = resolved_cols.iter().position(|c|
             !c.from_above && pos >= c.offset && pos < c.offset + c.width);
 
         // Check if there's a divider at this position
@@ -477,7 +477,7 @@ mod tests {
         ];
 
         // Top border (no row before)
-        result.push_str(&format_separator_row(&vec![], &row0));
+        result.push_str(&format_separator_row(&[], &row0));
 
         // Row 1: wants separation above for line 2, none below for line 3
         let row1 = vec![
@@ -0,0 +1,244 @@
+//! Compare two JSON reports
+//!
+//! Answers "what changed since the last run?" per dependent: newly
+//! regressed, newly fixed, and still failing. A dependent's status is the
+//! worst status across its offered (non-baseline) rows, so the offered
+//! labels themselves (which embed git hashes) don't need to match.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::json_report::{JsonReport, RowStatus};
+use crate::report;
+use crate::OfferedRow;
+
+/// Change in a dependent's status between two runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependentChange {
+    pub dependent: String,
+    pub old: Option<RowStatus>,
+    pub new: Option<RowStatus>,
+}
+
+/// Dependents grouped by how their status changed
+#[derive(Debug, Default)]
+pub struct RunDiff {
+    pub newly_regressed: Vec<DependentChange>,
+    pub newly_fixed: Vec<DependentChange>,
+    pub still_failing: Vec<DependentChange>,
+    pub added: Vec<DependentChange>,
+    pub removed: Vec<DependentChange>,
+    pub unchanged_passing: usize,
+}
+
+/// Worst status of each dependent's offered rows
+fn dependent_statuses(report: &JsonReport) -> BTreeMap<String, RowStatus> {
+    let mut statuses: BTreeMap<String, RowStatus> = BTreeMap::new();
+    for row in report.rows.iter().filter(|r| r.offered.is_some()) {
+        let entry = statuses.entry(row.dependent.clone()).or_insert(row.status);
+        if severity(row.status) > severity(*entry) {
+            *entry = row.status;
+        }
+    }
+    statuses
+}
+
+fn severity(status: RowStatus) -> u8 {
+    match status {
+        RowStatus::Passed | RowStatus::Flaky | RowStatus::Warned | RowStatus::Duplicated | RowStatus::Msrv | RowStatus::Network => 0,
+        RowStatus::Broken | RowStatus::Failed => 1,
+        RowStatus::Regressed => 2,
+    }
+}
+
+/// A run's rows and errors, less the failures a `--compare-baseline` snapshot already had
+#[derive(Debug)]
+pub struct NewFailures {
+    pub rows: Vec<OfferedRow>,
+    /// Dependents that errored before they could be tested
+    pub errors: usize,
+    /// Dependents left out for failing the same way in the snapshot
+    pub pre_existing: usize,
+}
+
+/// How each failing row of `report` failed: dependent, status and first failed step
+fn known_failures(report: &JsonReport) -> BTreeSet<(String, &'static str, Option<String>)> {
+    report.rows.iter()
+        .filter(|row| row.status.is_failure())
+        .map(|row| (row.dependent.clone(), row.status.as_str(), row.failed_step.clone()))
+        .collect()
+}
+
+/// Leave out of `rows` and `errors` the failures `baseline` already had; a dependent that
+/// fails differently now (another status, or another step) still counts
+pub fn exclude_known_failures(rows: &[OfferedRow], errors: usize, baseline: &JsonReport) -> NewFailures {
+    let known = known_failures(baseline);
+    let mut new = NewFailures { rows: Vec::new(), errors, pre_existing: 0 };
+    let mut pre_existing: BTreeSet<&str> = BTreeSet::new();
+    for row in rows {
+        let status = report::row_status(row);
+        let failure = (
+            row.primary.dependent_name.clone(),
+            status.as_str(),
+            report::failed_step(row).map(|step| step.as_str().to_string()),
+        );
+        if !status.is_failure() || !known.contains(&failure) {
+            new.rows.push(row.clone());
+            continue;
+        }
+        pre_existing.insert(&row.primary.dependent_name);
+        // The single row of a dependent that errored before testing (see `TestResult::to_offered_rows`)
+        if row.offered.is_none() && row.primary.spec == "ERROR" {
+            new.errors = new.errors.saturating_sub(1);
+        }
+    }
+    new.pre_existing = pre_existing.len();
+    new
+}
+
+/// Compare two runs
+pub fn diff_reports(old: &JsonReport, new: &JsonReport) -> RunDiff {
+    let old_statuses = dependent_statuses(old);
+    let new_statuses = dependent_statuses(new);
+    let mut diff = RunDiff::default();
+
+    for (dependent, &new_status) in &new_statuses {
+        let old_status = old_statuses.get(dependent).copied();
+        let change = DependentChange {
+            dependent: dependent.clone(),
+            old: old_status,
+            new: Some(new_status),
+        };
+
+        match old_status {
+            None => diff.added.push(change),
+            Some(old_status) => {
+                if new_status == RowStatus::Regressed && old_status != RowStatus::Regressed {
+                    diff.newly_regressed.push(change);
+                } else if old_status.is_failure() && !new_status.is_failure() {
+                    diff.newly_fixed.push(change);
+                } else if new_status.is_failure() {
+                    diff.still_failing.push(change);
+                } else {
+                    diff.unchanged_passing += 1;
+                }
+            }
+        }
+    }
+
+    for (dependent, &old_status) in &old_statuses {
+        if !new_statuses.contains_key(dependent) {
+            diff.removed.push(DependentChange {
+                dependent: dependent.clone(),
+                old: Some(old_status),
+                new: None,
+            });
+        }
+    }
+
+    diff
+}
+
+/// Render the diff for the console
+pub fn format_diff(diff: &RunDiff, old: &JsonReport, new: &JsonReport) -> String {
+    let mut out = format!(
+        "Comparing {} {} ({}) → {} ({})\n",
+        new.crate_name, old.crate_version, old.generated_at, new.crate_version, new.generated_at
+    );
+
+    let sections: [(&str, &str, &Vec<DependentChange>); 5] = [
+        ("✗", "Newly regressed", &diff.newly_regressed),
+        ("✓", "Newly fixed", &diff.newly_fixed),
+        ("⚠", "Still failing", &diff.still_failing),
+        ("+", "Added", &diff.added),
+        ("-", "Removed", &diff.removed),
+    ];
+
+    for (icon, title, changes) in sections {
+        if changes.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n{} {} ({}):\n", icon, title, changes.len()));
+        for change in changes {
+            out.push_str(&format!("  {:<30} {} → {}\n",
+                change.dependent, status_str(change.old), status_str(change.new)));
+        }
+    }
+
+    out.push_str(&format!("\n{} dependent(s) still passing\n", diff.unchanged_passing));
+    out
+}
+
+fn status_str(status: Option<RowStatus>) -> &'static str {
+    status.map(|s| s.as_str()).unwrap_or("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_report::JsonRow;
+    use crate::CommandType;
+
+    #[test]
+    fn test_diff_classifies_changes() {
+        let old = JsonReport::for_test(vec![
+            JsonRow::for_test("image", None, RowStatus::Passed),
+            JsonRow::for_test("image", Some("this(0.8.91+gaaaaaaa)"), RowStatus::Passed),
+            JsonRow::for_test("pixels", Some("this(0.8.91+gaaaaaaa)"), RowStatus::Regressed),
+            JsonRow::for_test("lodepng", Some("this(0.8.91+gaaaaaaa)"), RowStatus::Broken),
+            JsonRow::for_test("gone", Some("this(0.8.91+gaaaaaaa)"), RowStatus::Passed),
+            JsonRow::for_test("steady", Some("this(0.8.91+gaaaaaaa)"), RowStatus::Passed),
+        ]);
+        let new = JsonReport::for_test(vec![
+            JsonRow::for_test("image", None, RowStatus::Passed),
+            JsonRow::for_test("image", Some("this(0.8.91+gbbbbbbb)"), RowStatus::Regressed),
+            JsonRow::for_test("pixels", Some("this(0.8.91+gbbbbbbb)"), RowStatus::Passed),
+            JsonRow::for_test("lodepng", Some("this(0.8.91+gbbbbbbb)"), RowStatus::Broken),
+            JsonRow::for_test("fresh", Some("this(0.8.91+gbbbbbbb)"), RowStatus::Passed),
+            JsonRow::for_test("steady", Some("this(0.8.91+gbbbbbbb)"), RowStatus::Passed),
+        ]);
+
+        let diff = diff_reports(&old, &new);
+        let names = |changes: &[DependentChange]| changes.iter().map(|c| c.dependent.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&diff.newly_regressed), vec!["image"]);
+        assert_eq!(names(&diff.newly_fixed), vec!["pixels"]);
+        assert_eq!(names(&diff.still_failing), vec!["lodepng"]);
+        assert_eq!(names(&diff.added), vec!["fresh"]);
+        assert_eq!(names(&diff.removed), vec!["gone"]);
+        assert_eq!(diff.unchanged_passing, 1);
+
+        let text = format_diff(&diff, &old, &new);
+        assert!(text.contains("Newly regressed (1)"));
+        assert!(text.contains("PASSED → REGRESSED"));
+    }
+
+    #[test]
+    fn test_exclude_known_failures() {
+        let failed_at = |dependent: &str, offered: Option<&str>, status: RowStatus, step: &str| JsonRow {
+            failed_step: Some(step.to_string()),
+            ..JsonRow::for_test(dependent, offered, status)
+        };
+        let baseline = JsonReport::for_test(vec![
+            failed_at("lodepng", Some("0.8.90"), RowStatus::Broken, "check"),
+            failed_at("pixels", Some("0.8.90"), RowStatus::Regressed, "check"),
+            failed_at("gone", None, RowStatus::Failed, "fetch"),
+        ]);
+
+        // Still broken at check: left out
+        let broken = OfferedRow::for_test("lodepng", Some("0.8.91"), Some(false), false);
+        // Regressed again, but now at fetch: counts
+        let mut earlier = OfferedRow::for_test("pixels", Some("0.8.91"), Some(true), false);
+        earlier.test.commands[0].command = CommandType::Fetch;
+        // Errored before testing in both runs: left out of the errors too
+        let mut errored = OfferedRow::for_test("gone", None, None, false);
+        errored.primary.spec = "ERROR".to_string();
+        errored.test.commands[0].command = CommandType::Fetch;
+        let passing = OfferedRow::for_test("image", Some("0.8.91"), Some(true), true);
+
+        let new = exclude_known_failures(&[broken, earlier, errored, passing], 1, &baseline);
+        let names: Vec<&str> = new.rows.iter().map(|row| row.primary.dependent_name.as_str()).collect();
+        assert_eq!(names, vec!["pixels", "image"]);
+        assert_eq!(new.errors, 0);
+        assert_eq!(new.pre_existing, 2);
+        assert_eq!(report::summarize_offered_rows(&new.rows).regressed, 1);
+    }
+}
@@ -0,0 +1,500 @@
+//! Distributed runs (`--serve`, `--worker`)
+//!
+//! A coordinator (`--serve ADDR`) resolves the dependents as usual but,
+//! instead of testing them itself, hands them out over HTTP one at a time.
+//! Workers (`--worker URL`), started with the same crate, versions and
+//! flags, test what they are given and post the results back, where they
+//! are reported as if they had been tested locally.
+//!
+//! Workers keep no state, so they can be added or stopped at any time: a
+//! dependent whose worker hasn't reported within `LEASE` is also handed to
+//! the next idle worker, and the first result wins. Run limits reached on
+//! the coordinator (`--max-regressions`, `--max-duration`, ...) stop it from
+//! handing out more work; the rest are reported NOT_RUN.
+//!
+//! Endpoints, with JSON bodies:
+//! - `GET /work`: the next `WorkUnit`; 503 with `Retry-After` while the last
+//!   ones are still being tested, 204 once every dependent has a result
+//! - `POST /result`: a `WorkResult`
+//!
+//! There is no authentication: serve on a network only trusted machines can reach.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::compile::{ThreeStepResult, VersionSource};
+use crate::manifest::ManifestInfo;
+use crate::budget::Budget;
+use crate::{status, Error, RevDep, TestResult, TestResultData, TestResultReceiver, VersionTestOutcome};
+
+/// How long a worker may take on a dependent before another worker is given it too
+const LEASE: Duration = Duration::from_secs(2 * 3600);
+/// How long idle workers wait before asking again while the last dependents are tested
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Consecutive failed requests after which a worker gives up on the coordinator
+const MAX_FAILURES: u32 = 6;
+/// Largest request body the coordinator accepts (a result with long build logs)
+const MAX_BODY: usize = 256 * 1024 * 1024;
+
+/// A dependent to test, as handed to a worker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkUnit {
+    pub id: usize,
+    pub dependent: String,
+    /// Version requirement from `--dependents name:version`
+    pub version: Option<String>,
+    /// Repository of a dependent tested from git; those found with
+    /// `--github-dependents` aren't known to workers otherwise
+    pub git: Option<String>,
+}
+
+/// A worker's `TestResult` for a `WorkUnit`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkResult {
+    pub id: usize,
+    /// Host name of the worker, for the coordinator's log
+    pub worker: String,
+    pub dependent: String,
+    pub version: String,
+    pub resolved_version: Option<String>,
+    pub manifest: Option<ManifestInfo>,
+    pub outcome: Outcome,
+    pub warnings: Vec<String>,
+    pub wall_time: Duration,
+}
+
+/// `TestResultData` on the wire; errors are sent as their message
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Outcome {
+    Skipped(String),
+    PlatformUnsupported(String),
+    NotRun(String),
+    Error(String),
+    MultiVersion(Vec<(VersionSource, ThreeStepResult)>),
+}
+
+impl WorkResult {
+    pub fn new(id: usize, worker: &str, result: TestResult) -> Self {
+        let outcome = match result.data {
+            TestResultData::Skipped(reason) => Outcome::Skipped(reason),
+            TestResultData::PlatformUnsupported(reason) => Outcome::PlatformUnsupported(reason),
+            TestResultData::NotRun(reason) => Outcome::NotRun(reason),
+            TestResultData::Error(e) => Outcome::Error(e.to_string()),
+            TestResultData::MultiVersion(outcomes) => Outcome::MultiVersion(outcomes.into_iter()
+                .map(|o| (o.version_source, o.result))
+                .collect()),
+        };
+        WorkResult {
+            id,
+            worker: worker.to_string(),
+            dependent: result.rev_dep.name,
+            version: result.rev_dep.vers.to_string(),
+            resolved_version: result.rev_dep.resolved_version,
+            manifest: result.rev_dep.manifest,
+            outcome,
+            warnings: result.warnings,
+            wall_time: result.wall_time,
+        }
+    }
+
+    /// The result as if the dependent had been tested here; where the
+    /// worker unpacked it is of no use, so it reads as the published crate
+    pub fn into_test_result(self) -> Result<TestResult, String> {
+        let vers = Version::parse(&self.version)
+            .map_err(|e| format!("{} sent version '{}' for {}: {}", self.worker, self.version, self.dependent, e))?;
+        let data = match self.outcome {
+            Outcome::Skipped(reason) => TestResultData::Skipped(reason),
+            Outcome::PlatformUnsupported(reason) => TestResultData::PlatformUnsupported(reason),
+            Outcome::NotRun(reason) => TestResultData::NotRun(reason),
+            Outcome::Error(message) => TestResultData::Error(Error::ProcessError(message)),
+            Outcome::MultiVersion(outcomes) => TestResultData::MultiVersion(outcomes.into_iter()
+                .map(|(version_source, result)| VersionTestOutcome { version_source, result })
+                .collect()),
+        };
+        Ok(TestResult {
+            rev_dep: RevDep {
+                name: self.dependent,
+                vers,
+                resolved_version: self.resolved_version,
+                manifest: self.manifest,
+                source_dir: None,
+                channel: None,
+            },
+            data,
+            warnings: self.warnings,
+            wall_time: self.wall_time,
+        })
+    }
+}
+
+/// What a worker asking for work gets
+#[derive(Debug, PartialEq, Eq)]
+enum Next {
+    Unit(WorkUnit),
+    /// Everything left is being tested; ask again later
+    Wait,
+    /// Every dependent has a result
+    Done,
+}
+
+/// Dependents waiting to be tested and those being tested
+#[derive(Default)]
+struct Queue {
+    pending: VecDeque<WorkUnit>,
+    /// Handed out and not reported yet, with when they were last handed out
+    leased: HashMap<usize, (WorkUnit, Instant)>,
+    /// Where each dependent's result goes; taken when it arrives
+    senders: Vec<Option<Sender<TestResult>>>,
+}
+
+impl Queue {
+    fn add(&mut self, dependent: String, version: Option<String>, git: Option<String>, sender: Sender<TestResult>) {
+        let id = self.senders.len();
+        self.senders.push(Some(sender));
+        self.pending.push_back(WorkUnit { id, dependent, version, git });
+    }
+
+    fn next(&mut self, now: Instant) -> Next {
+        if let Some(unit) = self.pending.pop_front() {
+            self.leased.insert(unit.id, (unit.clone(), now));
+            return Next::Unit(unit);
+        }
+        if self.leased.is_empty() {
+            return Next::Done;
+        }
+        // Hand out the longest overdue dependent again; its worker may be gone
+        let overdue = self.leased.values_mut()
+            .filter(|lease| now.duration_since(lease.1) >= LEASE)
+            .min_by_key(|lease| lease.1);
+        match overdue {
+            Some(lease) => {
+                lease.1 = now;
+                Next::Unit(lease.0.clone())
+            }
+            None => Next::Wait,
+        }
+    }
+
+    /// Where the result for `id` goes; None if it was already reported
+    fn complete(&mut self, id: usize) -> Option<Sender<TestResult>> {
+        self.leased.remove(&id);
+        self.senders.get_mut(id).and_then(Option::take)
+    }
+
+    /// Dependents not handed out yet, which won't be once a run limit is reached
+    fn drain_pending(&mut self) -> Vec<(WorkUnit, Sender<TestResult>)> {
+        let units: Vec<WorkUnit> = self.pending.drain(..).collect();
+        units.into_iter()
+            .filter_map(|unit| {
+                let sender = self.senders.get_mut(unit.id).and_then(Option::take)?;
+                Some((unit, sender))
+            })
+            .collect()
+    }
+}
+
+/// Serves dependents to workers and collects their results (`--serve`)
+pub struct Coordinator {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl Coordinator {
+    /// Start answering workers on `addr` (e.g. 0.0.0.0:7878) in the background
+    pub fn serve(addr: &str, budget: Arc<Budget>) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        let local = listener.local_addr().map_err(|e| e.to_string())?;
+        status(&format!("Serving dependents to workers on {} (start them with --worker http://<this host>:{})", local, local.port()));
+        let queue = Arc::new(Mutex::new(Queue::default()));
+        let shared = Arc::clone(&queue);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let queue = Arc::clone(&shared);
+                let budget = Arc::clone(&budget);
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &queue, &budget) {
+                        debug!("worker request failed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Coordinator { queue })
+    }
+
+    /// Queue a dependent; its result arrives once a worker has tested it
+    pub fn submit(&self, dependent: String, version: Option<String>, git: Option<String>) -> TestResultReceiver {
+        let (result_tx, result_rx) = crate::new_result_receiver(dependent.clone());
+        self.queue.lock().unwrap().add(dependent, version, git, result_tx);
+        result_rx
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, queue: &Mutex<Queue>, budget: &Budget) -> Result<(), String> {
+    stream.set_read_timeout(Some(Duration::from_secs(60))).map_err(|e| e.to_string())?;
+    let (method, path, body) = read_request(&mut stream)?;
+    let (code, body) = match (method.as_str(), path.as_str()) {
+        ("GET", "/work") => next_work(queue, budget),
+        ("POST", "/result") => match accept_result(queue, budget, &body) {
+            Ok(()) => (200, String::new()),
+            Err(e) => {
+                eprintln!("Warning: Rejected a worker result: {}", e);
+                (400, e)
+            }
+        },
+        _ => (404, format!("no such endpoint: {} {}", method, path)),
+    };
+    write_response(&mut stream, code, &body)
+}
+
+/// Response to `GET /work`
+fn next_work(queue: &Mutex<Queue>, budget: &Budget) -> (u16, String) {
+    let mut queue = queue.lock().unwrap();
+    // Once a run limit is reached, nothing new starts here either
+    if let Some(limit) = budget.exhausted() {
+        for (unit, sender) in queue.drain_pending() {
+            budget.record_skipped();
+            let rev_dep = RevDep {
+                name: unit.dependent,
+                vers: Version::new(0, 0, 0),
+                resolved_version: None,
+                manifest: None,
+                source_dir: None,
+                channel: None,
+            };
+            let _ = sender.send(TestResult::not_run(rev_dep, limit.clone()));
+        }
+    }
+    match queue.next(Instant::now()) {
+        Next::Unit(unit) => (200, serde_json::to_string(&unit).unwrap()),
+        Next::Wait => (503, String::new()),
+        Next::Done => (204, String::new()),
+    }
+}
+
+/// Handle `POST /result`: pass the result on to the report
+fn accept_result(queue: &Mutex<Queue>, budget: &Budget, body: &[u8]) -> Result<(), String> {
+    let result: WorkResult = serde_json::from_slice(body).map_err(|e| format!("invalid result: {}", e))?;
+    let id = result.id;
+    let worker = result.worker.clone();
+    let result = result.into_test_result()?;
+    let Some(sender) = queue.lock().unwrap().complete(id) else {
+        debug!("{} reported {} again; keeping the first result", worker, result.rev_dep.name);
+        return Ok(());
+    };
+    debug!("{} tested {} {} in {:?}", worker, result.rev_dep.name, result.rev_dep.vers, result.wall_time);
+    if result.quick_str() == "regressed" {
+        budget.record_regression(&result.rev_dep.name);
+    }
+    if let TestResultData::MultiVersion(_) = result.data {
+        budget.record_dependent_time(result.wall_time);
+    }
+    let _ = sender.send(result);
+    Ok(())
+}
+
+/// Method, path and body of an HTTP/1.1 request
+fn read_request(stream: &mut TcpStream) -> Result<(String, String, Vec<u8>), String> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| e.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(format!("malformed request line: {:?}", request_line));
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| e.to_string())?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| format!("invalid Content-Length: {}", value.trim()))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(format!("request body of {} bytes is too large", content_length));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    Ok((method.to_string(), path.to_string(), body))
+}
+
+fn write_response(stream: &mut TcpStream, code: u16, body: &str) -> Result<(), String> {
+    let reason = match code {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Service Unavailable",
+    };
+    let retry_after = if code == 503 { format!("Retry-After: {}\r\n", POLL_INTERVAL.as_secs()) } else { String::new() };
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        code, reason, body.len(), retry_after, body)
+        .map_err(|e| e.to_string())
+}
+
+/// Test dependents handed out by the coordinator at `url` on `jobs` threads
+/// until it has none left (`--worker`); returns how many were tested here
+pub fn run_worker(url: &str, jobs: usize, test: impl Fn(WorkUnit) -> TestResult + Send + Sync) -> Result<usize, String> {
+    let url = url.trim_end_matches('/');
+    let worker = hostname();
+    status(&format!("Worker {} taking dependents from {}", worker, url));
+    let tested = Mutex::new(0);
+    thread::scope(|scope| {
+        let threads: Vec<_> = (0..jobs.max(1))
+            .map(|_| scope.spawn(|| -> Result<(), String> {
+                while let Some(unit) = fetch_unit(url)? {
+                    status(&format!("Testing {}", unit.dependent));
+                    let id = unit.id;
+                    let result = WorkResult::new(id, &worker, test(unit));
+                    send_result(url, &result)?;
+                    *tested.lock().unwrap() += 1;
+                }
+                Ok(())
+            }))
+            .collect();
+        threads.into_iter().try_for_each(|t| t.join().unwrap_or_else(|_| Err("worker thread panicked".to_string())))
+    })?;
+    Ok(tested.into_inner().unwrap())
+}
+
+/// The next dependent to test, waiting while the last ones are being tested;
+/// None once the coordinator is done
+fn fetch_unit(url: &str) -> Result<Option<WorkUnit>, String> {
+    let mut failures = 0;
+    // The coordinator exits once it has written the report, so it going away
+    // while the last dependents were tested elsewhere means it is done
+    let mut waited = false;
+    loop {
+        match ureq::get(&format!("{}/work", url)).call() {
+            Ok(resp) if resp.status() == 204 => return Ok(None),
+            Ok(resp) => {
+                let body = resp.into_string().map_err(|e| format!("Failed to read work unit from {}: {}", url, e))?;
+                return serde_json::from_str(&body).map(Some).map_err(|e| format!("Invalid work unit from {}: {}", url, e));
+            }
+            Err(ureq::Error::Status(503, _)) => {
+                failures = 0;
+                waited = true;
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(ureq::Error::Transport(_)) if waited => return Ok(None),
+            Err(e) => {
+                failures += 1;
+                if failures >= MAX_FAILURES {
+                    return Err(format!("Coordinator {} unreachable: {}", url, e));
+                }
+                debug!("asking {} for work failed ({}), retrying", url, e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+fn send_result(url: &str, result: &WorkResult) -> Result<(), String> {
+    let body = serde_json::to_string(result).map_err(|e| e.to_string())?;
+    let mut failures = 0;
+    loop {
+        match ureq::post(&format!("{}/result", url)).set("Content-Type", "application/json").send_string(&body) {
+            Ok(_) => return Ok(()),
+            Err(ureq::Error::Status(code, resp)) => {
+                let message = resp.into_string().unwrap_or_default();
+                return Err(format!("Coordinator rejected the result for {} ({}): {}", result.dependent, code, message));
+            }
+            Err(e) => {
+                failures += 1;
+                if failures >= MAX_FAILURES {
+                    return Err(format!("Failed to send the result for {} to {}: {}", result.dependent, url, e));
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Name this worker reports itself by
+fn hostname() -> String {
+    std::env::var("HOSTNAME").ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok().map(|name| name.trim().to_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("worker-{}", std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_queue_leases_and_reassigns() {
+        let mut queue = Queue::default();
+        let (tx, rx) = mpsc::channel();
+        queue.add("image".to_string(), None, None, tx.clone());
+        queue.add("ravif".to_string(), Some("0.11".to_string()), None, tx);
+
+        let start = Instant::now();
+        let Next::Unit(first) = queue.next(start) else { panic!("expected work") };
+        assert_eq!(first.dependent, "image");
+        let later = start + Duration::from_secs(60);
+        let Next::Unit(second) = queue.next(later) else { panic!("expected work") };
+        assert_eq!(second.version.as_deref(), Some("0.11"));
+        assert_eq!(queue.next(later), Next::Wait);
+
+        // A worker that went quiet loses its dependent to the next one asking
+        assert_eq!(queue.next(start + LEASE), Next::Unit(first.clone()));
+        assert_eq!(queue.next(start + LEASE), Next::Wait);
+
+        assert!(queue.complete(first.id).is_some());
+        assert!(queue.complete(first.id).is_none(), "second result for the same dependent");
+        assert!(queue.complete(second.id).is_some());
+        assert_eq!(queue.next(start + LEASE), Next::Done);
+        drop(rx);
+    }
+
+    #[test]
+    fn test_drain_pending() {
+        let mut queue = Queue::default();
+        let (tx, _rx) = mpsc::channel();
+        queue.add("image".to_string(), None, None, tx.clone());
+        queue.add("ravif".to_string(), None, None, tx);
+        let Next::Unit(_) = queue.next(Instant::now()) else { panic!("expected work") };
+
+        let drained: Vec<String> = queue.drain_pending().into_iter().map(|(unit, _)| unit.dependent).collect();
+        assert_eq!(drained, vec!["ravif"]);
+        assert_eq!(queue.next(Instant::now()), Next::Wait);
+    }
+
+    #[test]
+    fn test_work_result_round_trip() {
+        let rev_dep = RevDep {
+            name: "image".to_string(),
+            vers: Version::new(0, 25, 1),
+            resolved_version: Some("0.8.50".to_string()),
+            manifest: None,
+            source_dir: Some("/tmp/staging/image-0.25.1".into()),
+            channel: None,
+        };
+        let sent = WorkResult::new(3, "builder-1", TestResult::skipped(rev_dep, "no matching version".to_string()));
+        let json = serde_json::to_string(&sent).unwrap();
+        let received: WorkResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(received.id, 3);
+
+        let result = received.into_test_result().unwrap();
+        assert_eq!(result.rev_dep.vers, Version::new(0, 25, 1));
+        assert_eq!(result.rev_dep.resolved_version.as_deref(), Some("0.8.50"));
+        assert_eq!(result.rev_dep.source_dir, None);
+        assert!(matches!(result.data, TestResultData::Skipped(ref reason) if reason == "no matching version"));
+    }
+}
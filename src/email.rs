@@ -0,0 +1,307 @@
+//! Email notifications (`[email]` in copter.toml)
+//!
+//! When a run finishes with regressions (or always, with `always = true`),
+//! the summary also posted on pull requests is mailed as the message body,
+//! with the HTML report attached, for nightly runs nobody watches. Mail goes
+//! out through a sendmail-compatible program, or straight to an SMTP relay.
+//! The relay is spoken to in plain SMTP; for TLS or authentication use a
+//! sendmail replacement such as msmtp instead.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use log::debug;
+
+use crate::config_file::{EmailConfig, EmailTransport};
+use crate::OfferedRow;
+
+const DEFAULT_SENDMAIL: &str = "sendmail";
+const DEFAULT_SMTP_SERVER: &str = "localhost:25";
+const SMTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// An email with an optional attachment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    /// Plain text (markdown) body
+    pub body: String,
+    /// File name and contents of an HTML attachment
+    pub attachment: Option<(String, Vec<u8>)>,
+}
+
+impl Message {
+    /// The message as MIME, lines ending in CRLF
+    pub fn to_mime(&self) -> String {
+        let boundary = format!("copter-{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default());
+        let mut mime = String::new();
+        let mut line = |text: &str| {
+            mime.push_str(text);
+            mime.push_str("\r\n");
+        };
+        line(&format!("From: {}", self.from));
+        line(&format!("To: {}", self.to.join(", ")));
+        line(&format!("Subject: {}", self.subject));
+        line(&format!("Date: {}", chrono::Local::now().to_rfc2822()));
+        line("MIME-Version: 1.0");
+        line(&format!("Content-Type: multipart/mixed; boundary=\"{}\"", boundary));
+        line("");
+        line(&format!("--{}", boundary));
+        line("Content-Type: text/plain; charset=utf-8");
+        line("Content-Transfer-Encoding: base64");
+        line("");
+        line(&base64(self.body.as_bytes()));
+        if let Some((ref name, ref contents)) = self.attachment {
+            line(&format!("--{}", boundary));
+            line(&format!("Content-Type: text/html; charset=utf-8; name=\"{}\"", name));
+            line(&format!("Content-Disposition: attachment; filename=\"{}\"", name));
+            line("Content-Transfer-Encoding: base64");
+            line("");
+            line(&base64(contents));
+        }
+        line(&format!("--{}--", boundary));
+        mime
+    }
+}
+
+/// The summary of a run, with the HTML report at `html_report` attached if it was written
+pub fn summary_message(config: &EmailConfig, rows: &[OfferedRow], crate_name: &str, display_version: &str, notes: &[String], regressed: usize, html_report: &Path) -> Message {
+    let verdict = match regressed {
+        0 => "no regressions".to_string(),
+        1 => "1 dependent regressed".to_string(),
+        n => format!("{} dependents regressed", n),
+    };
+    let links = crate::pr_comment::Links {
+        run_url: crate::report::CiProvider::detect().and_then(|ci| ci.run_url()),
+        artifact_logs: false,
+    };
+    let attachment = std::fs::read(html_report).ok().map(|contents| {
+        let name = html_report.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "copter-report.html".to_string());
+        (name, contents)
+    });
+    Message {
+        from: config.from.clone(),
+        to: config.to.clone(),
+        subject: format!("cargo-copter: {} {} - {}", crate_name, display_version, verdict),
+        body: crate::pr_comment::render(rows, crate_name, display_version, notes, &links, None),
+        attachment,
+    }
+}
+
+/// Send `message` the way `config` says
+pub fn send(config: &EmailConfig, message: &Message) -> Result<(), String> {
+    match config.transport {
+        EmailTransport::Sendmail => {
+            let program = config.sendmail.as_deref().unwrap_or(Path::new(DEFAULT_SENDMAIL));
+            send_sendmail(program, message)
+        }
+        EmailTransport::Smtp => {
+            let server = config.smtp_server.as_deref().unwrap_or(DEFAULT_SMTP_SERVER);
+            send_smtp(server, message)
+        }
+    }
+}
+
+/// Pipe the message to `program -t -i`, which takes the recipients from its headers
+fn send_sendmail(program: &Path, message: &Message) -> Result<(), String> {
+    debug!("mailing {:?} with {}", message.to, program.display());
+    let mut child = Command::new(program)
+        .args(["-t", "-i"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program.display(), e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(message.to_mime().as_bytes())
+            .map_err(|e| format!("Failed to pass the message to {}: {}", program.display(), e))?;
+    }
+    let output = child.wait_with_output()
+        .map_err(|e| format!("Failed to run {}: {}", program.display(), e))?;
+    if !output.status.success() {
+        return Err(format!("{} failed ({}): {}", program.display(), output.status,
+            String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Deliver the message to an SMTP relay at `HOST[:PORT]`
+fn send_smtp(server: &str, message: &Message) -> Result<(), String> {
+    let address = if server.contains(':') { server.to_string() } else { format!("{}:25", server) };
+    debug!("mailing {:?} through {}", message.to, address);
+    let socket = address.to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve SMTP server {}: {}", address, e))?
+        .next()
+        .ok_or_else(|| format!("Failed to resolve SMTP server {}", address))?;
+    let stream = TcpStream::connect_timeout(&socket, SMTP_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to SMTP server {}: {}", address, e))?;
+    stream.set_read_timeout(Some(SMTP_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(SMTP_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+    let mut session = SmtpSession { reader: BufReader::new(stream.try_clone().map_err(|e| e.to_string())?), writer: stream };
+
+    let client = std::env::var("HOSTNAME").ok().filter(|name| !name.is_empty()).unwrap_or_else(|| "localhost".to_string());
+    session.expect(220)?;
+    session.command(&format!("EHLO {}", client), 250)?;
+    session.command(&format!("MAIL FROM:<{}>", message.from), 250)?;
+    for to in &message.to {
+        session.command(&format!("RCPT TO:<{}>", to), 250)?;
+    }
+    session.command("DATA", 354)?;
+    // A line starting with a dot is escaped by doubling it; a lone dot ends the message
+    let data: String = message.to_mime().split("\r\n")
+        .map(|line| if line.starts_with('.') { format!(".{}\r\n", line) } else { format!("{}\r\n", line) })
+        .collect();
+    session.send(&data)?;
+    session.command(".", 250)?;
+    session.command("QUIT", 221)?;
+    Ok(())
+}
+
+struct SmtpSession {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl SmtpSession {
+    fn send(&mut self, text: &str) -> Result<(), String> {
+        self.writer.write_all(text.as_bytes()).map_err(|e| format!("SMTP: {}", e))
+    }
+
+    /// Send a command and check its reply code (251 counts as 250)
+    fn command(&mut self, command: &str, code: u16) -> Result<(), String> {
+        self.send(&format!("{}\r\n", command))?;
+        self.expect(code).map_err(|e| format!("{} (after {})", e, command.split(':').next().unwrap_or(command)))
+    }
+
+    /// Read a reply, its continuation lines included, and check its code
+    fn expect(&mut self, code: u16) -> Result<(), String> {
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return Err("SMTP server closed the connection".to_string()),
+                Ok(_) => {}
+                Err(e) => return Err(format!("SMTP: {}", e)),
+            }
+            let reply: u16 = line.get(..3).and_then(|c| c.parse().ok())
+                .ok_or_else(|| format!("SMTP: unexpected reply '{}'", line.trim()))?;
+            if line.as_bytes().get(3) == Some(&b'-') {
+                continue;
+            }
+            return match reply == code || (code == 250 && reply == 251) {
+                true => Ok(()),
+                false => Err(format!("SMTP server replied '{}'", line.trim())),
+            };
+        }
+    }
+}
+
+/// Base64 in lines of 76 characters, as MIME wants
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len() * 4 / 3 + 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded.as_bytes()
+        .chunks(76)
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn message() -> Message {
+        Message {
+            from: "copter@example.com".to_string(),
+            to: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+            subject: "cargo-copter: rgb 0.8.91 - 1 dependent regressed".to_string(),
+            body: "### cargo-copter: rgb 0.8.91 - ❌ 1 dependent regressed\n".to_string(),
+            attachment: Some(("copter-report.html".to_string(), b"<html></html>".to_vec())),
+        }
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert!(base64(&[0u8; 100]).split("\r\n").all(|line| line.len() <= 76));
+    }
+
+    #[test]
+    fn test_mime() {
+        let mime = message().to_mime();
+        assert!(mime.starts_with("From: copter@example.com\r\nTo: a@example.com, b@example.com\r\nSubject: cargo-copter: rgb 0.8.91 - 1 dependent regressed\r\n"));
+        assert!(mime.contains("Content-Disposition: attachment; filename=\"copter-report.html\"\r\nContent-Transfer-Encoding: base64\r\n\r\nPGh0bWw+PC9odG1sPg==\r\n"));
+        assert!(mime.ends_with("--\r\n"));
+    }
+
+    #[test]
+    fn test_send_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut received = Vec::new();
+            writer.write_all(b"220 test ESMTP\r\n").unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                let reply: &[u8] = match line.as_str() {
+                    "." if in_data => { in_data = false; b"250 queued\r\n" }
+                    _ if in_data => { received.push(line); continue; }
+                    "DATA" => { in_data = true; b"354 go ahead\r\n" }
+                    "QUIT" => { writer.write_all(b"221 bye\r\n").unwrap(); break; }
+                    _ if line.starts_with("EHLO") => b"250-test\r\n250 8BITMIME\r\n",
+                    _ if line.starts_with("RCPT TO:<b@") => b"251 forwarding\r\n",
+                    _ => b"250 ok\r\n",
+                };
+                received.push(line);
+                writer.write_all(reply).unwrap();
+            }
+            received
+        });
+
+        send_smtp(&address, &message()).unwrap();
+        let received = server.join().unwrap();
+        assert_eq!(&received[1..5], ["MAIL FROM:<copter@example.com>", "RCPT TO:<a@example.com>", "RCPT TO:<b@example.com>", "DATA"]);
+        assert!(received.contains(&"Subject: cargo-copter: rgb 0.8.91 - 1 dependent regressed".to_string()));
+    }
+
+    #[test]
+    fn test_smtp_rejection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"554 no service\r\n").unwrap();
+        });
+        let error = send_smtp(&address, &message()).unwrap_err();
+        assert_eq!(error, "SMTP server replied '554 no service'");
+    }
+}
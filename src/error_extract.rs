@@ -1,7 +1,8 @@
-/// Error extraction module for parsing cargo JSON output
-///
-/// This module parses cargo's --message-format=json output to extract
-/// structured error information for better reporting.
+//! Error extraction module for parsing cargo JSON output
+//!
+//! This module parses cargo's --message-format=json output to extract
+//! structured error information for better reporting, and libtest's output
+//! to find which tests failed.
 
 use serde::{Deserialize, Serialize};
 // BufRead not needed for current implementation
@@ -55,7 +56,7 @@ pub struct SpanText {
 }
 
 /// A parsed diagnostic with extracted key information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Diagnostic {
     pub level: DiagnosticLevel,
     pub code: Option<String>,
@@ -64,7 +65,7 @@ pub struct Diagnostic {
     pub primary_span: Option<SpanInfo>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DiagnosticLevel {
     Error,
     Warning,
@@ -89,7 +90,7 @@ impl DiagnosticLevel {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpanInfo {
     pub file_name: String,
     pub line: usize,
@@ -177,14 +178,178 @@ fn format_diagnostic_text(msg: &CompilerMessage) -> String {
 
 /// Extract just error messages for quick display
 /// Uses the rendered field which contains the full formatted error with code snippets
+/// (prefixed with the error header and location when the rendered text lacks them)
 pub fn extract_error_summary(diagnostics: &[Diagnostic]) -> String {
     diagnostics.iter()
         .filter(|d| d.level.is_error())
-        .map(|d| d.rendered.clone())
+        .map(|d| {
+            let header = match &d.code {
+                Some(code) => format!("error[{}]: {}", code, d.message),
+                None => format!("error: {}", d.message),
+            };
+            if d.rendered.starts_with(&header) {
+                d.rendered.clone()
+            } else {
+                let mut text = header;
+                if let Some(span) = &d.primary_span {
+                    text.push_str(&format!("\n --> {}:{}:{}", span.file_name, span.line, span.column));
+                }
+                if !d.rendered.is_empty() {
+                    text.push('\n');
+                    text.push_str(&d.rendered);
+                }
+                text
+            }
+        })
         .collect::<Vec<_>>()
         .join("\n\n")
 }
 
+/// Error codes (e.g. "E0308") of every error, in order, one per occurrence
+pub fn error_codes(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics.iter()
+        .filter(|d| d.level.is_error())
+        .filter_map(|d| d.code.clone())
+        .collect()
+}
+
+/// An error with what varies between dependents (generic arguments, numbers)
+/// taken out, so the same API break groups together across dependents
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ErrorPattern {
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ErrorPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{} {}", code, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Patterns of every distinct error, in order; rustc's closing "aborting due to" is left out
+pub fn error_patterns(diagnostics: &[Diagnostic]) -> Vec<ErrorPattern> {
+    let mut patterns: Vec<ErrorPattern> = Vec::new();
+    for d in diagnostics.iter().filter(|d| d.level.is_error()) {
+        if d.code.is_none() && (d.message.starts_with("aborting due to") || d.message.starts_with("could not compile")) {
+            continue;
+        }
+        let pattern = ErrorPattern { code: d.code.clone(), message: normalize_message(&d.message) };
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+    patterns
+}
+
+/// `Rgb<u8>` -> `Rgb`, "expected 3 arguments" -> "expected N arguments"
+fn normalize_message(message: &str) -> String {
+    let mut out = String::new();
+    let mut depth = 0;
+    let mut prev: Option<char> = None;
+    for c in message.chars() {
+        match c {
+            // Generic arguments follow a type or path; a lone `<` is an operator
+            '<' if depth > 0 || prev.is_some_and(|p| p.is_alphanumeric() || p == '_' || p == ':') => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            '0'..='9' if !prev.is_some_and(|p| p.is_alphanumeric() || p == '_') => {
+                if !out.ends_with('N') || !prev.is_some_and(|p| p.is_ascii_digit()) {
+                    out.push('N');
+                }
+            }
+            _ => out.push(c),
+        }
+        if depth == 0 && out.ends_with("::") && c == '>' {
+            // `Vec::<u8>::new` -> `Vec::new`
+            out.truncate(out.len() - 2);
+        }
+        prev = Some(c);
+    }
+    if depth > 0 {
+        return message.to_string();
+    }
+    out
+}
+
+/// Link to the rustc error index entry for an error code
+pub fn error_code_url(code: &str) -> String {
+    format!("https://doc.rust-lang.org/error_codes/{}.html", code)
+}
+
+/// A test that failed in `cargo test` output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFailure {
+    pub name: String,
+    /// Panic location and first line of the panic message, if it was printed
+    pub message: Option<String>,
+}
+
+/// Find the failed tests in libtest output: the human format (`test foo ... FAILED`
+/// plus the `---- foo stdout ----` blocks) or `--format json` events
+pub fn parse_test_failures(output: &str) -> Vec<TestFailure> {
+    let mut failures: Vec<TestFailure> = Vec::new();
+    let mut add = |name: &str, message: Option<String>| {
+        match failures.iter_mut().find(|f| f.name == name) {
+            Some(failure) => {
+                if failure.message.is_none() {
+                    failure.message = message;
+                }
+            }
+            None => failures.push(TestFailure { name: name.to_string(), message }),
+        }
+    };
+
+    let mut lines = output.lines().peekable();
+    while let Some(line) = lines.next() {
+        if line.starts_with('{') {
+            if let Ok(event) = serde_json::from_str::<serde_json::Value>(line) {
+                if event["type"] == "test" && event["event"] == "failed" {
+                    if let Some(name) = event["name"].as_str() {
+                        add(name, event["stdout"].as_str().and_then(|out| panic_message(out.lines())));
+                    }
+                }
+            }
+        } else if let Some(name) = line.strip_prefix("test ").and_then(|l| l.strip_suffix(" ... FAILED")) {
+            add(name, None);
+        } else if let Some(name) = line.strip_prefix("---- ").and_then(|l| l.strip_suffix(" stdout ----")) {
+            let mut block = Vec::new();
+            while let Some(next) = lines.next_if(|l| !l.starts_with("---- ") && *l != "failures:") {
+                block.push(next);
+            }
+            add(name, panic_message(block.into_iter()));
+        }
+    }
+    failures
+}
+
+/// `panicked at src/lib.rs:10:5: assertion failed` from a test's captured stdout
+fn panic_message<'a>(mut lines: impl Iterator<Item = &'a str>) -> Option<String> {
+    let panic_line = lines.find(|l| l.contains(" panicked at "))?;
+    let location = panic_line.split(" panicked at ").nth(1)?.trim_end_matches(':');
+    // Since Rust 1.73 the message is on the next line; before that it followed the location
+    match lines.next().filter(|l| !l.starts_with("note: ") && !l.trim().is_empty()) {
+        Some(message) => Some(format!("panicked at {}: {}", location, message.trim())),
+        None => Some(format!("panicked at {}", location)),
+    }
+}
+
+/// One line per failed test, for the error panel and reports
+pub fn test_failure_summary(failures: &[TestFailure]) -> String {
+    let mut lines = vec![match failures.len() {
+        1 => "1 test failed:".to_string(),
+        n => format!("{} tests failed:", n),
+    }];
+    lines.extend(failures.iter().map(|f| match &f.message {
+        Some(message) => format!("{} - {}", f.name, message),
+        None => f.name.clone(),
+    }));
+    lines.join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,4 +417,105 @@ mod tests {
         assert!(summary.contains("src/main.rs:10:5"));
         assert!(!summary.contains("unused variable")); // Warnings excluded
     }
+
+    #[test]
+    fn test_error_codes_counts_each_error() {
+        let diag = |level, code: Option<&str>| Diagnostic {
+            level,
+            code: code.map(|c| c.to_string()),
+            message: String::new(),
+            rendered: String::new(),
+            primary_span: None,
+        };
+        let diagnostics = vec![
+            diag(DiagnosticLevel::Error, Some("E0308")),
+            diag(DiagnosticLevel::Warning, Some("E0308")),
+            diag(DiagnosticLevel::Error, None),
+            diag(DiagnosticLevel::Error, Some("E0308")),
+            diag(DiagnosticLevel::Error, Some("E0425")),
+        ];
+        assert_eq!(error_codes(&diagnostics), vec!["E0308", "E0308", "E0425"]);
+        assert_eq!(error_code_url("E0425"), "https://doc.rust-lang.org/error_codes/E0425.html");
+    }
+
+    #[test]
+    fn test_parse_test_failures_human() {
+        let output = "\
+running 3 tests
+test tests::passes ... ok
+test tests::compares ... FAILED
+test tests::unwraps ... FAILED
+
+failures:
+
+---- tests::compares stdout ----
+
+thread 'tests::compares' panicked at src/lib.rs:10:5:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+---- tests::unwraps stdout ----
+thread 'tests::unwraps' panicked at 'called `Option::unwrap()` on a `None` value', src/lib.rs:20:9
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::compares
+    tests::unwraps
+
+test result: FAILED. 1 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out
+";
+        let failures = parse_test_failures(output);
+        assert_eq!(failures, vec![
+            TestFailure {
+                name: "tests::compares".to_string(),
+                message: Some("panicked at src/lib.rs:10:5: assertion `left == right` failed".to_string()),
+            },
+            TestFailure {
+                name: "tests::unwraps".to_string(),
+                message: Some("panicked at 'called `Option::unwrap()` on a `None` value', src/lib.rs:20:9".to_string()),
+            },
+        ]);
+        assert_eq!(test_failure_summary(&failures).lines().next(), Some("2 tests failed:"));
+        assert_eq!(test_failure_summary(&failures).lines().nth(1),
+                   Some("tests::compares - panicked at src/lib.rs:10:5: assertion `left == right` failed"));
+    }
+
+    #[test]
+    fn test_parse_test_failures_json() {
+        let output = r#"{ "type": "test", "event": "started", "name": "it_works" }
+{ "type": "test", "name": "it_works", "event": "failed", "stdout": "\nthread 'it_works' panicked at src/lib.rs:3:5:\nboom\n" }
+{ "type": "suite", "event": "failed", "passed": 0, "failed": 1 }"#;
+        assert_eq!(parse_test_failures(output), vec![TestFailure {
+            name: "it_works".to_string(),
+            message: Some("panicked at src/lib.rs:3:5: boom".to_string()),
+        }]);
+        assert!(parse_test_failures("test result: ok. 3 passed").is_empty());
+    }
+
+    #[test]
+    fn test_error_patterns_group_across_dependents() {
+        let diag = |code: Option<&str>, message: &str| Diagnostic {
+            level: DiagnosticLevel::Error,
+            code: code.map(|c| c.to_string()),
+            message: message.to_string(),
+            rendered: String::new(),
+            primary_span: None,
+        };
+        let patterns = error_patterns(&[
+            diag(Some("E0599"), "no method named `as_slice` found for struct `Rgb<u8>` in the current scope"),
+            diag(Some("E0599"), "no method named `as_slice` found for struct `Rgb<f32>` in the current scope"),
+            diag(Some("E0061"), "this function takes 3 arguments but 2 arguments were supplied"),
+            diag(Some("E0369"), "binary operation `<` cannot be applied to type `Rgb<u16>`"),
+            diag(None, "aborting due to 4 previous errors"),
+        ]);
+        assert_eq!(patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>(), vec![
+            "E0599 no method named `as_slice` found for struct `Rgb` in the current scope",
+            "E0061 this function takes N arguments but N arguments were supplied",
+            "E0369 binary operation `<` cannot be applied to type `Rgb`",
+        ]);
+        assert_eq!(normalize_message("expected `u8`, found `Vec::<u16>::new`"), "expected `u8`, found `Vec::new`");
+    }
 }
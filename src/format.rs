@@ -0,0 +1,80 @@
+//! Human-readable formatting for durations and sizes
+//!
+//! Console, markdown and HTML output go through these wrappers so the same
+//! value always renders the same way. Machine-readable output (JSON) keeps
+//! raw numbers (seconds as f64, sizes in bytes) and never uses them.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Duration rendered compactly: `0.4s`, `12.3s`, `3m 05s`, `1h 02m`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HumanDuration(pub Duration);
+
+impl HumanDuration {
+    /// From fractional seconds, as stored in `CommandResult::duration`
+    pub fn from_secs_f64(secs: f64) -> Self {
+        HumanDuration(Duration::from_secs_f64(secs.max(0.0)))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs_f64();
+        if secs < 60.0 {
+            return write!(f, "{:.1}s", secs);
+        }
+
+        let total = self.0.as_secs();
+        let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+        if hours > 0 {
+            write!(f, "{}h {:02}m", hours, minutes)
+        } else {
+            write!(f, "{}m {:02}s", minutes, seconds)
+        }
+    }
+}
+
+/// Byte count rendered with binary units: `512 B`, `1.5 KiB`, `3.2 MiB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanSize(pub u64);
+
+impl fmt::Display for HumanSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_duration() {
+        assert_eq!(HumanDuration::from_secs_f64(0.42).to_string(), "0.4s");
+        assert_eq!(HumanDuration::from_secs_f64(12.34).to_string(), "12.3s");
+        assert_eq!(HumanDuration(Duration::from_secs(185)).to_string(), "3m 05s");
+        assert_eq!(HumanDuration(Duration::from_secs(3720)).to_string(), "1h 02m");
+        assert_eq!(HumanDuration::from_secs_f64(-1.0).to_string(), "0.0s");
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(HumanSize(512).to_string(), "512 B");
+        assert_eq!(HumanSize(1536).to_string(), "1.5 KiB");
+        assert_eq!(HumanSize(3 * 1024 * 1024 + 200 * 1024).to_string(), "3.2 MiB");
+        assert_eq!(HumanSize(5 * 1024 * 1024 * 1024).to_string(), "5.0 GiB");
+    }
+}
@@ -0,0 +1,200 @@
+//! Dependents tested from their git repository
+//!
+//! With `--dependents-git` (or `git = "..."` in copter.toml) a dependent is
+//! cloned from its repository instead of unpacked from its published
+//! `.crate`, to check whether upstream has already fixed compatibility.
+//! Clones live in `{staging_dir}/git/{name}` and are refreshed to the
+//! branch head on every run.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use log::debug;
+use semver::{BuildMetadata, Version};
+
+/// A repository URL with an optional branch, written `URL[#BRANCH]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    pub url: String,
+    pub branch: Option<String>,
+}
+
+/// A dependent checked out from git
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitCheckout {
+    /// Directory of the dependent's package within the clone
+    pub crate_dir: PathBuf,
+    /// Manifest version, with `git.{hash}` build metadata
+    pub version: Version,
+}
+
+impl GitSource {
+    pub fn parse(spec: &str) -> Self {
+        match spec.rsplit_once('#') {
+            Some((url, branch)) if !branch.is_empty() => GitSource {
+                url: url.to_string(),
+                branch: Some(branch.to_string()),
+            },
+            _ => GitSource { url: spec.trim_end_matches('#').to_string(), branch: None },
+        }
+    }
+
+    /// Where `checkout` clones the repository of dependent `name`
+    pub fn clone_dir(name: &str, staging_dir: &Path) -> PathBuf {
+        staging_dir.join("git").join(name)
+    }
+
+    /// Repository name, the last path segment of the URL without `.git`
+    pub fn repo_name(&self) -> &str {
+        let path = self.url.trim_end_matches('/');
+        let last = path.rsplit(['/', ':']).next().unwrap_or(path);
+        last.strip_suffix(".git").unwrap_or(last)
+    }
+
+    /// Clone (or refresh) the repository and locate package `name` in it;
+    /// with `--offline` an earlier clone is reused as it is
+    pub fn checkout(&self, name: &str, staging_dir: &Path) -> Result<GitCheckout, String> {
+        let clone_dir = Self::clone_dir(name, staging_dir);
+
+        if crate::api::offline() {
+            if !clone_dir.join(".git").exists() {
+                return Err(format!("The git checkout of {} ({}) is not cached (--offline)", name, self.url));
+            }
+            git(&clone_dir, &["reset", "--hard", "HEAD"])?;
+            git(&clone_dir, &["clean", "-fd"])?;
+        } else if clone_dir.join(".git").exists() {
+            debug!("Refreshing git checkout of {} in {:?}", name, clone_dir);
+            let refspec = self.branch.as_deref().unwrap_or("HEAD");
+            git(&clone_dir, &["fetch", "--depth", "1", "origin", refspec])?;
+            git(&clone_dir, &["reset", "--hard", "FETCH_HEAD"])?;
+            // Drop backups and lock files left by earlier runs; keep target/
+            git(&clone_dir, &["clean", "-fd"])?;
+        } else {
+            debug!("Cloning {} for {}", self.url, name);
+            fs::create_dir_all(staging_dir.join("git"))
+                .map_err(|e| format!("Failed to create {}: {}", staging_dir.display(), e))?;
+            let mut args = vec!["clone", "--depth", "1"];
+            if let Some(ref branch) = self.branch {
+                args.extend(["--branch", branch.as_str()]);
+            }
+            let dest = clone_dir.to_string_lossy().to_string();
+            args.extend([self.url.as_str(), dest.as_str()]);
+            git(staging_dir, &args)?;
+        }
+
+        let hash = git(&clone_dir, &["rev-parse", "--short", "HEAD"])?;
+        let crate_dir = find_package(&clone_dir, name)?;
+        // Fresh from the reset, so this is the committed lock
+        crate::lockfile::save_committed(&crate_dir)
+            .map_err(|e| format!("Failed to save Cargo.lock of {}: {}", name, e))?;
+        let mut version = package_version(&crate_dir, &clone_dir)?;
+        version.build = BuildMetadata::new(&format!("git.{}", hash))
+            .map_err(|e| format!("Invalid git hash '{}': {}", hash, e))?;
+
+        Ok(GitCheckout { crate_dir, version })
+    }
+}
+
+/// Run git in `dir`, returning trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Directory under `root` whose Cargo.toml declares package `name`
+pub fn find_package(root: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        if package_name(&dir).as_deref() == Some(name) {
+            return Ok(dir);
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            if file_name.to_string_lossy().starts_with('.') || file_name == "target" {
+                continue;
+            }
+            if entry.path().is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+    }
+
+    Err(format!("No package named {} in {}", name, root.display()))
+}
+
+fn read_manifest(dir: &Path) -> Option<toml::Value> {
+    let content = fs::read(dir.join("Cargo.toml")).ok()?;
+    crate::manifest::parse_manifest(&content).ok()
+}
+
+pub fn package_name(dir: &Path) -> Option<String> {
+    read_manifest(dir)?
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+/// `package.version`, following `version.workspace = true` to the workspace root
+pub fn package_version(crate_dir: &Path, root: &Path) -> Result<Version, String> {
+    let manifest = read_manifest(crate_dir)
+        .ok_or_else(|| format!("Failed to read {}", crate_dir.join("Cargo.toml").display()))?;
+
+    let version = match manifest.get("package").and_then(|p| p.get("version")) {
+        Some(toml::Value::String(s)) => s.clone(),
+        Some(toml::Value::Table(t)) if t.get("workspace").and_then(|w| w.as_bool()) == Some(true) => {
+            read_manifest(root)
+                .and_then(|m| m.get("workspace")?.get("package")?.get("version")?.as_str().map(|s| s.to_string()))
+                .ok_or_else(|| format!("No workspace.package.version in {}", root.join("Cargo.toml").display()))?
+        }
+        // Cargo's default for manifests without a version
+        _ => "0.0.0".to_string(),
+    };
+
+    Version::parse(&version).map_err(|e| format!("Invalid version '{}': {}", version, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_source() {
+        let source = GitSource::parse("https://github.com/image-rs/image.git#main");
+        assert_eq!(source.url, "https://github.com/image-rs/image.git");
+        assert_eq!(source.branch.as_deref(), Some("main"));
+        assert_eq!(source.repo_name(), "image");
+
+        let source = GitSource::parse("git@github.com:kornelski/ravif");
+        assert_eq!(source.branch, None);
+        assert_eq!(source.repo_name(), "ravif");
+    }
+
+    #[test]
+    fn test_find_workspace_member() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n[workspace.package]\nversion = \"0.26.0-dev\"\n").unwrap();
+        fs::create_dir_all(root.join("crates/image")).unwrap();
+        fs::write(root.join("crates/image/Cargo.toml"),
+            "[package]\nname = \"image\"\nversion.workspace = true\n").unwrap();
+
+        let crate_dir = find_package(root, "image").unwrap();
+        assert_eq!(crate_dir, root.join("crates/image"));
+        assert_eq!(package_version(&crate_dir, root).unwrap().to_string(), "0.26.0-dev");
+        assert!(find_package(root, "ravif").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Dependency graph export
+//!
+//! Links each base crate version that was offered to the dependents it was
+//! tested against, with edges colored by result, so clusters of breakage
+//! stand out. Transitive dependencies that resolved to a different version
+//! hang off their dependent as dashed edges. Rendered as Graphviz DOT
+//! (`copter-graph.dot`) and as Mermaid for embedding in the markdown and
+//! HTML reports.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::json_report::RowStatus;
+use crate::report::row_status;
+use crate::OfferedRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Base,
+    Dependent,
+    Transitive,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Node {
+    id: String,
+    label: String,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Edge {
+    from: usize,
+    to: usize,
+    /// None for transitive edges, which aren't tested on their own
+    status: Option<RowStatus>,
+}
+
+/// Base versions, dependents and the test results between them
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl DependencyGraph {
+    pub fn from_rows(rows: &[OfferedRow], crate_name: &str) -> Self {
+        let mut graph = DependencyGraph::default();
+
+        for row in rows {
+            let base_label = match &row.offered {
+                Some(offered) => format!("{} {}", crate_name, offered.version),
+                None => format!("{} {}", crate_name, row.primary.resolved_version),
+            };
+            let base = graph.node(base_label, NodeKind::Base);
+            let dependent = graph.node(
+                format!("{} {}", row.primary.dependent_name, row.primary.dependent_version),
+                NodeKind::Dependent,
+            );
+            graph.edge(base, dependent, Some(row_status(row)));
+
+            for transitive in &row.transitive {
+                let dep = graph.node(
+                    format!("{} {}", transitive.dependency.dependent_name, transitive.dependency.resolved_version),
+                    NodeKind::Transitive,
+                );
+                graph.edge(dependent, dep, None);
+            }
+        }
+
+        graph
+    }
+
+    fn node(&mut self, label: String, kind: NodeKind) -> usize {
+        if let Some(index) = self.nodes.iter().position(|n| n.label == label) {
+            return index;
+        }
+        self.nodes.push(Node { id: format!("n{}", self.nodes.len()), label, kind });
+        self.nodes.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, status: Option<RowStatus>) {
+        let edge = Edge { from, to, status };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    /// Graphviz DOT
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph copter {\n    rankdir=LR;\n    node [fontname=\"monospace\"];\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Base => "box, style=bold",
+                NodeKind::Dependent => "ellipse",
+                NodeKind::Transitive => "ellipse, style=dashed",
+            };
+            out.push_str(&format!("    {} [label=\"{}\", shape={}];\n", node.id, escape(&node.label), shape));
+        }
+        for edge in &self.edges {
+            let from = &self.nodes[edge.from].id;
+            let to = &self.nodes[edge.to].id;
+            match edge.status {
+                Some(status) => out.push_str(&format!(
+                    "    {} -> {} [color=\"{}\", label=\"{}\"];\n", from, to, color(status), status.as_str())),
+                None => out.push_str(&format!("    {} -> {} [style=dashed];\n", from, to)),
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Mermaid flowchart (without the surrounding code fence)
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for node in &self.nodes {
+            let label = escape(&node.label);
+            match node.kind {
+                NodeKind::Base => out.push_str(&format!("    {}[\"{}\"]\n", node.id, label)),
+                NodeKind::Dependent | NodeKind::Transitive => out.push_str(&format!("    {}([\"{}\"])\n", node.id, label)),
+            }
+        }
+        for (index, edge) in self.edges.iter().enumerate() {
+            let from = &self.nodes[edge.from].id;
+            let to = &self.nodes[edge.to].id;
+            match edge.status {
+                Some(status) => {
+                    out.push_str(&format!("    {} -->|{}| {}\n", from, status.as_str(), to));
+                    out.push_str(&format!("    linkStyle {} stroke:{}\n", index, color(status)));
+                }
+                None => out.push_str(&format!("    {} -.-> {}\n", from, to)),
+            }
+        }
+        out
+    }
+
+    /// Write the DOT graph to `path`
+    pub fn write_dot(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_dot())
+    }
+}
+
+fn color(status: RowStatus) -> &'static str {
+    match status {
+        RowStatus::Passed => "green",
+        RowStatus::Regressed => "red",
+        RowStatus::Broken => "orange",
+        RowStatus::Failed => "gray",
+        RowStatus::Flaky => "purple",
+        RowStatus::Warned => "gold",
+        RowStatus::Duplicated => "darkorange",
+        RowStatus::Msrv => "orangered",
+        RowStatus::Network => "lightgray",
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('"', "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DependencyRef, TransitiveTest};
+
+    #[test]
+    fn test_graph_links_versions_to_dependents() {
+        let mut regressed = OfferedRow::for_test("image", Some("this(0.8.91)"), Some(true), false);
+        regressed.transitive.push(TransitiveTest { dependency: DependencyRef::for_test("ravif", "0.8.91"), depth: 1 });
+        let rows = vec![OfferedRow::for_test("image", None, None, true), regressed];
+
+        let graph = DependencyGraph::from_rows(&rows, "rgb");
+        assert_eq!(graph.nodes.len(), 4);  // rgb 0.8.50, image, rgb this(...), ravif
+        assert_eq!(graph.edges.len(), 3);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("n0 [label=\"rgb 0.8.50\", shape=box, style=bold];"));
+        assert!(dot.contains("n2 -> n1 [color=\"red\", label=\"REGRESSED\"];"));
+        assert!(dot.contains("n1 -> n3 [style=dashed];"));
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("n0 -->|PASSED| n1"));
+        assert!(mermaid.contains("linkStyle 1 stroke:red"));
+        assert!(mermaid.contains("n1 -.-> n3"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Persistent results database
+//!
+//! Every run's rows are appended to a SQLite database (`results.db` in the
+//! copter home, see `paths`), keyed by the base crate version and git hash and
+//! by dependent version. The `history` subcommand reads it back for trend
+//! views and "regressed since when?" questions.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use rusqlite::{params, Connection};
+
+use crate::json_report::{JsonReport, RowStatus};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    crate_name    TEXT NOT NULL,
+    crate_version TEXT NOT NULL,
+    git_hash      TEXT,
+    dirty         INTEGER NOT NULL,
+    started_at    TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS outcomes (
+    run_id            INTEGER NOT NULL REFERENCES runs(id),
+    dependent         TEXT NOT NULL,
+    dependent_version TEXT NOT NULL,
+    offered           TEXT,
+    status            TEXT NOT NULL,
+    duration_secs     REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS outcomes_dependent ON outcomes(dependent, run_id);
+";
+
+/// Identity of the base crate build a run tested
+pub struct RunKey<'a> {
+    pub crate_name: &'a str,
+    pub crate_version: &'a str,
+    pub git_hash: Option<&'a str>,
+    pub dirty: bool,
+}
+
+/// One row of the `history` run listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub id: i64,
+    pub crate_version: String,
+    pub git_hash: Option<String>,
+    pub dirty: bool,
+    pub started_at: String,
+    pub passed: usize,
+    pub regressed: usize,
+    pub broken: usize,
+}
+
+/// One dependent's outcome in a past run
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependentHistory {
+    pub run_id: i64,
+    pub crate_version: String,
+    pub git_hash: Option<String>,
+    pub started_at: String,
+    pub dependent_version: String,
+    pub offered: String,
+    pub status: String,
+}
+
+pub struct ResultsDb {
+    conn: Connection,
+}
+
+impl ResultsDb {
+    /// Open (creating if needed) the database at `path`
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        Self::init(conn)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to initialize results database: {}", e))?;
+        Ok(ResultsDb { conn })
+    }
+
+    /// Record every row of a finished run; returns the new run id
+    pub fn record_run(&mut self, key: &RunKey, report: &JsonReport) -> Result<i64, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO runs (crate_name, crate_version, git_hash, dirty, started_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![key.crate_name, key.crate_version, key.git_hash, key.dirty, report.generated_at],
+        ).map_err(|e| e.to_string())?;
+        let run_id = tx.last_insert_rowid();
+
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO outcomes (run_id, dependent, dependent_version, offered, status, duration_secs)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            ).map_err(|e| e.to_string())?;
+            for row in &report.rows {
+                let duration: f64 = row.steps.iter().map(|s| s.duration_secs).sum();
+                insert.execute(params![
+                    run_id,
+                    row.dependent,
+                    row.dependent_version,
+                    row.offered,
+                    status_key(row.status),
+                    duration,
+                ]).map_err(|e| e.to_string())?;
+            }
+        }
+
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(run_id)
+    }
+
+    /// Most recent runs of `crate_name` (all crates if None), newest first
+    pub fn runs(&self, crate_name: Option<&str>, limit: usize) -> Result<Vec<RunSummary>, String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.id, r.crate_version, r.git_hash, r.dirty, r.started_at,
+                    SUM(o.offered IS NOT NULL AND o.status = 'passed'),
+                    SUM(o.offered IS NOT NULL AND o.status = 'regressed'),
+                    SUM(o.offered IS NOT NULL AND o.status IN ('broken', 'failed'))
+             FROM runs r LEFT JOIN outcomes o ON o.run_id = r.id
+             WHERE ?1 IS NULL OR r.crate_name = ?1
+             GROUP BY r.id ORDER BY r.id DESC LIMIT ?2",
+        ).map_err(|e| e.to_string())?;
+
+        let rows = stmt.query_map(params![crate_name, limit as i64], |row| {
+            Ok(RunSummary {
+                id: row.get(0)?,
+                crate_version: row.get(1)?,
+                git_hash: row.get(2)?,
+                dirty: row.get(3)?,
+                started_at: row.get(4)?,
+                passed: row.get::<_, Option<i64>>(5)?.unwrap_or(0) as usize,
+                regressed: row.get::<_, Option<i64>>(6)?.unwrap_or(0) as usize,
+                broken: row.get::<_, Option<i64>>(7)?.unwrap_or(0) as usize,
+            })
+        }).map_err(|e| e.to_string())?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Offered-version outcomes of one dependent against `crate_name` (all crates if None), newest first
+    pub fn dependent_history(&self, dependent: &str, crate_name: Option<&str>, limit: usize) -> Result<Vec<DependentHistory>, String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.id, r.crate_version, r.git_hash, r.started_at, o.dependent_version, o.offered, o.status
+             FROM outcomes o JOIN runs r ON o.run_id = r.id
+             WHERE o.dependent = ?1 AND o.offered IS NOT NULL AND (?2 IS NULL OR r.crate_name = ?2)
+             ORDER BY r.id DESC LIMIT ?3",
+        ).map_err(|e| e.to_string())?;
+
+        let rows = stmt.query_map(params![dependent, crate_name, limit as i64], |row| {
+            Ok(DependentHistory {
+                run_id: row.get(0)?,
+                crate_version: row.get(1)?,
+                git_hash: row.get(2)?,
+                started_at: row.get(3)?,
+                dependent_version: row.get(4)?,
+                offered: row.get(5)?,
+                status: row.get(6)?,
+            })
+        }).map_err(|e| e.to_string())?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Seconds each dependent of `crate_name` took in total (all versions
+    /// tested) in the latest run that included it
+    pub fn estimated_durations(&self, crate_name: &str) -> Result<HashMap<String, f64>, String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT o.dependent, SUM(o.duration_secs)
+             FROM outcomes o
+             WHERE o.run_id = (SELECT MAX(o2.run_id) FROM outcomes o2 JOIN runs r ON o2.run_id = r.id
+                               WHERE o2.dependent = o.dependent AND r.crate_name = ?1)
+             GROUP BY o.dependent",
+        ).map_err(|e| e.to_string())?;
+
+        let rows = stmt.query_map(params![crate_name], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+
+        rows.collect::<Result<HashMap<_, _>, _>>().map_err(|e| e.to_string())
+    }
+}
+
+fn status_key(status: RowStatus) -> &'static str {
+    match status {
+        RowStatus::Passed => "passed",
+        RowStatus::Regressed => "regressed",
+        RowStatus::Broken => "broken",
+        RowStatus::Failed => "failed",
+        RowStatus::Flaky => "flaky",
+        RowStatus::Warned => "warned",
+        RowStatus::Duplicated => "duplicated",
+        RowStatus::Msrv => "msrv",
+        RowStatus::Network => "network",
+    }
+}
+
+/// The run a dependent's current regression streak started in, if it is regressed now
+///
+/// `history` is newest first, as returned by `dependent_history`.
+pub fn regressed_since(history: &[DependentHistory]) -> Option<&DependentHistory> {
+    if history.first().map(|h| h.status != "regressed").unwrap_or(true) {
+        return None;
+    }
+    history.iter().take_while(|h| h.status == "regressed").last()
+}
+
+/// Render the run listing
+pub fn format_runs(runs: &[RunSummary]) -> String {
+    let mut out = format!("{:>5}  {:<25}  {:<12}  {:>6}  {:>9}  {:>6}\n",
+        "Run", "Started", "Version", "Passed", "Regressed", "Broken");
+    for run in runs {
+        let version = match (&run.git_hash, run.dirty) {
+            (Some(hash), true) => format!("{} {}*", run.crate_version, hash),
+            (Some(hash), false) => format!("{} {}", run.crate_version, hash),
+            (None, true) => format!("{}*", run.crate_version),
+            (None, false) => run.crate_version.clone(),
+        };
+        out.push_str(&format!("{:>5}  {:<25}  {:<12}  {:>6}  {:>9}  {:>6}\n",
+            run.id, run.started_at, version, run.passed, run.regressed, run.broken));
+    }
+    out
+}
+
+/// Render one dependent's history, with when its current regression began
+pub fn format_dependent_history(dependent: &str, history: &[DependentHistory]) -> String {
+    let mut out = format!("History of {}:\n", dependent);
+    for h in history {
+        out.push_str(&format!("{:>5}  {:<25}  {:<10}  {:<30}  {}\n",
+            h.run_id, h.started_at, h.dependent_version, h.offered, h.status.to_uppercase()));
+    }
+    if let Some(since) = regressed_since(history) {
+        out.push_str(&format!("\nRegressed since run {} ({} {})\n",
+            since.run_id, since.crate_version, since.git_hash.as_deref().unwrap_or("")));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_report::{JsonRow, JsonStep};
+
+    fn report(status: RowStatus) -> JsonReport {
+        JsonReport::for_test(vec![
+            JsonRow::for_test("image", None, RowStatus::Passed),
+            JsonRow::for_test("image", Some("this(0.8.91)"), status),
+        ])
+    }
+
+    #[test]
+    fn test_record_and_query_history() {
+        let mut db = ResultsDb::init(Connection::open_in_memory().unwrap()).unwrap();
+        let key = |hash| RunKey { crate_name: "rgb", crate_version: "0.8.91", git_hash: Some(hash), dirty: false };
+
+        db.record_run(&key("aaaaaaa"), &report(RowStatus::Passed)).unwrap();
+        let second = db.record_run(&key("bbbbbbb"), &report(RowStatus::Regressed)).unwrap();
+        db.record_run(&key("ccccccc"), &report(RowStatus::Regressed)).unwrap();
+
+        let runs = db.runs(Some("rgb"), 10).unwrap();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0].git_hash.as_deref(), Some("ccccccc"));
+        assert_eq!((runs[0].passed, runs[0].regressed), (0, 1));
+        assert_eq!((runs[2].passed, runs[2].regressed), (1, 0));
+
+        let history = db.dependent_history("image", Some("rgb"), 10).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(regressed_since(&history).unwrap().run_id, second);
+        assert!(format_dependent_history("image", &history).contains(&format!("Regressed since run {}", second)));
+    }
+
+    #[test]
+    fn test_dependent_history_filters_by_base_crate() {
+        let mut db = ResultsDb::init(Connection::open_in_memory().unwrap()).unwrap();
+        let rgb = RunKey { crate_name: "rgb", crate_version: "0.8.91", git_hash: None, dirty: false };
+        let png = RunKey { crate_name: "png", crate_version: "0.17.0", git_hash: None, dirty: false };
+
+        let rgb_run = db.record_run(&rgb, &report(RowStatus::Regressed)).unwrap();
+        db.record_run(&png, &report(RowStatus::Passed)).unwrap();
+
+        let history = db.dependent_history("image", Some("rgb"), 10).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].run_id, rgb_run);
+        assert_eq!(db.dependent_history("image", None, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_estimated_durations_use_latest_run() {
+        let mut db = ResultsDb::init(Connection::open_in_memory().unwrap()).unwrap();
+        let key = RunKey { crate_name: "rgb", crate_version: "0.8.91", git_hash: None, dirty: true };
+        let timed = |secs: f64| {
+            let mut report = report(RowStatus::Passed);
+            for row in &mut report.rows {
+                row.steps = vec![JsonStep { command: "check".to_string(), passed: true, duration_secs: secs, features: vec![], exit_code: None, signal: None, failed_tests: vec![], log: None }];
+            }
+            report
+        };
+
+        db.record_run(&key, &timed(100.0)).unwrap();
+        db.record_run(&key, &timed(30.0)).unwrap();
+
+        // Baseline and offered rows of the latest run
+        let estimates = db.estimated_durations("rgb").unwrap();
+        assert_eq!(estimates.get("image"), Some(&60.0));
+        assert!(db.estimated_durations("serde").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! Shell hooks around each dependent (`[hooks]` in copter.toml)
+//!
+//! `pre-dependent` runs once a dependent is unpacked, before its first build;
+//! if it fails, the dependent is reported as ERROR instead of being tested.
+//! `post-dependent` runs after every dependent whatever its outcome, then
+//! `on-regression` if it regressed; their failures are only warnings.
+//!
+//! Hooks run with `sh -c` (`cmd /C` on Windows) in the dependent's unpacked
+//! source, or the current directory before it's unpacked, with:
+//!
+//! - `COPTER_CRATE`: the crate under test
+//! - `COPTER_DEPENDENT`, `COPTER_DEPENDENT_VERSION`: the dependent
+//! - `COPTER_DEPENDENT_DIR`: its unpacked source, if it got that far
+//! - `COPTER_OUTCOME` (after it): passed, regressed, broken, network,
+//!   skipped, unsupported, not_run or error
+//! - `COPTER_DURATION` (after it): seconds it took
+//!
+//! Their output goes to a temporary file rather than the console, so a
+//! service a hook leaves running in the background can't hold up the run;
+//! it's shown when the hook fails.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use log::debug;
+
+use crate::config_file::Hooks;
+use crate::TestResult;
+
+/// Hook output kept in error messages
+const MAX_OUTPUT: usize = 2000;
+
+/// Variables describing a dependent, for every hook
+pub fn dependent_vars(crate_name: &str, dir: Option<&Path>, dependent: &str, version: &str) -> Vec<(&'static str, String)> {
+    let mut vars = vec![
+        ("COPTER_CRATE", crate_name.to_string()),
+        ("COPTER_DEPENDENT", dependent.to_string()),
+        ("COPTER_DEPENDENT_VERSION", version.to_string()),
+    ];
+    if let Some(dir) = dir {
+        // Hooks run inside it, where a relative path means something else
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        vars.push(("COPTER_DEPENDENT_DIR", dir.display().to_string()));
+    }
+    vars
+}
+
+/// Run the `name` hook `command` in `dir`; errors carry the end of its output
+pub fn run(name: &str, command: &str, vars: &[(&'static str, String)], dir: Option<&Path>) -> Result<(), String> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    let (mut output, stdout, stderr) = output_files()
+        .map_err(|e| format!("Failed to create the output file of the {} hook: {}", name, e))?;
+    cmd.envs(vars.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr);
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+
+    debug!("running {} hook: {:?}", name, cmd);
+    let status = cmd.status()
+        .map_err(|e| format!("Failed to run the {} hook: {}", name, e))?;
+    if status.success() {
+        return Ok(());
+    }
+
+    let mut text = String::new();
+    let _ = output.seek(SeekFrom::Start(0)).and_then(|_| output.read_to_string(&mut text));
+    let tail = match text.char_indices().rev().nth(MAX_OUTPUT) {
+        Some((start, _)) => &text[start..],
+        None => &text,
+    };
+    Err(format!("{} hook failed ({}): {}", name, status, tail.trim()))
+}
+
+/// One temporary file, and handles to it for stdout and stderr
+fn output_files() -> io::Result<(File, File, File)> {
+    let output = tempfile::tempfile()?;
+    Ok((output.try_clone()?, output.try_clone()?, output))
+}
+
+/// `post-dependent`, then `on-regression` if `result` regressed
+pub fn after_dependent(hooks: &Hooks, crate_name: &str, result: &TestResult, dir: &Path) {
+    if hooks.post_dependent.is_none() && hooks.on_regression.is_none() {
+        return;
+    }
+    let dir = dir.exists().then_some(dir);
+    let mut vars = dependent_vars(crate_name, dir, &result.rev_dep.name, &result.rev_dep.vers.to_string());
+    vars.push(("COPTER_OUTCOME", result.quick_str().to_string()));
+    vars.push(("COPTER_DURATION", format!("{:.1}", result.wall_time.as_secs_f64())));
+
+    let regressed = result.quick_str() == "regressed";
+    let hooks = [("post-dependent", &hooks.post_dependent, true), ("on-regression", &hooks.on_regression, regressed)];
+    for (name, command, runs) in hooks {
+        if let (Some(command), true) = (command, runs) {
+            if let Err(e) = run(name, command, &vars, dir) {
+                eprintln!("Warning: {} {}: {}", result.rev_dep.name, result.rev_dep.vers, e);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let vars = dependent_vars("rgb", Some(dir.path()), "ravif", "0.11.0");
+        run("pre-dependent", "echo \"$COPTER_CRATE $COPTER_DEPENDENT $COPTER_DEPENDENT_VERSION\" > hook.txt", &vars, Some(dir.path())).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join("hook.txt")).unwrap(), "rgb ravif 0.11.0\n");
+
+        let error = run("pre-dependent", "echo 'no database'; exit 3", &vars, Some(dir.path())).unwrap_err();
+        assert!(error.starts_with("pre-dependent hook failed"));
+        assert!(error.ends_with("no database"));
+    }
+}
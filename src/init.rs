@@ -0,0 +1,290 @@
+//! `cargo-copter init`: setting a crate up for downstream testing
+//!
+//! Asks crates.io for the crate's most downloaded dependents and writes a
+//! starter copter.toml: the suggested command line (jobs from the CPU
+//! count, step timeouts, report path) as a comment, the home directory,
+//! and an empty table per dependent, ready for features or test shards.
+//! With `--workflow` it also writes a GitHub Actions workflow running the
+//! same command on pull requests. Finally it checks that the tools a run
+//! needs are installed. Existing files are only replaced with `--force`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::api::ReverseDependency;
+
+/// Where `--workflow` writes the GitHub Actions workflow
+pub const WORKFLOW_FILE: &str = ".github/workflows/copter.yml";
+
+/// Dependents suggested when crates.io knows none (e.g. unpublished crates)
+const FALLBACK_TOP_DEPENDENTS: usize = 5;
+
+/// What `init` suggests for a crate
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub crate_name: String,
+    /// Most downloaded dependents first
+    pub dependents: Vec<ReverseDependency>,
+    pub jobs: usize,
+}
+
+impl Suggestion {
+    pub fn new(crate_name: &str, dependents: Vec<ReverseDependency>, cpus: usize) -> Self {
+        Suggestion {
+            crate_name: crate_name.to_string(),
+            dependents,
+            jobs: suggested_jobs(cpus),
+        }
+    }
+
+    /// The run this configuration is meant for
+    pub fn command_line(&self) -> String {
+        let top = if self.dependents.is_empty() { FALLBACK_TOP_DEPENDENTS } else { self.dependents.len() };
+        format!(
+            "cargo-copter --top-dependents {} --jobs {} --check-timeout 20m --test-timeout 30m --output copter-report.html",
+            top, self.jobs
+        )
+    }
+
+    /// Starter copter.toml
+    pub fn copter_toml(&self) -> String {
+        let mut out = format!(
+            "# cargo-copter settings for {}, generated by `cargo-copter init`\n\
+             #\n\
+             # Suggested run:\n\
+             #   {}\n\
+             \n\
+             # Staging, caches and results (COPTER_HOME takes precedence); relative to this file\n\
+             home = \".copter\"\n",
+            self.crate_name,
+            self.command_line()
+        );
+        if self.dependents.is_empty() {
+            out.push_str("\n# No dependents found on crates.io. Per-dependent settings go in tables like:\n");
+            out.push_str("# [dependents.image]\n# features = [\"png\"]\n");
+            return out;
+        }
+        out.push_str("\n# Top dependents by downloads. Enable their features or split slow test suites, e.g.\n");
+        out.push_str("#   features = [\"png\", \"jpeg\"]\n#   test-shards = [\"codecs::\"]\n");
+        for dependent in &self.dependents {
+            out.push_str(&format!("\n[dependents.{}]  # {} downloads\n", toml_key(&dependent.name), dependent.downloads));
+        }
+        out
+    }
+
+    /// GitHub Actions workflow testing the dependents on every pull request;
+    /// `crate_dir` is the crate's directory relative to the repository root
+    pub fn workflow(&self, crate_dir: &Path) -> String {
+        let (prefix, working_directory) = if crate_dir.as_os_str().is_empty() {
+            (String::new(), String::new())
+        } else {
+            let dir = crate_dir.to_string_lossy().replace('\\', "/");
+            (format!("{}/", dir), format!("\n        working-directory: {}", dir))
+        };
+        let reports = ["copter-report.html", "copter-report.md", "copter-report.json"].iter()
+            .map(|file| format!("{}{}", prefix, file))
+            .collect::<Vec<_>>()
+            .join("\n            ");
+        format!(
+            r#"# Generated by `cargo-copter init`: tests {crate_name}'s dependents against each pull request
+name: Downstream impact
+on:
+  pull_request:
+  workflow_dispatch:
+
+permissions:
+  contents: read
+
+jobs:
+  copter:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+        with:
+          persist-credentials: false
+
+      - uses: dtolnay/rust-toolchain@stable
+
+      - name: Install cargo-copter
+        run: cargo install --git https://github.com/imazen/cargo-copter
+
+      - name: Restore copter cache
+        uses: actions/cache@v4
+        with:
+          path: {prefix}.copter
+          key: copter-${{{{ runner.os }}}}-${{{{ github.sha }}}}
+          restore-keys: copter-${{{{ runner.os }}}}-
+
+      - name: Test dependents
+        run: {command}{working_directory}
+
+      - name: Upload reports
+        if: always()
+        uses: actions/upload-artifact@v4
+        with:
+          name: copter-report
+          path: |
+            {reports}
+"#,
+            crate_name = self.crate_name,
+            command = self.command_line(),
+        )
+    }
+}
+
+/// Parallel dependents for a machine with `cpus` cores; each cargo build
+/// already uses several cores, so a quarter of them, 1 to 8
+pub fn suggested_jobs(cpus: usize) -> usize {
+    (cpus / 4).clamp(1, 8)
+}
+
+/// Top of the git checkout containing `crate_dir`, if it is in one
+pub fn repo_root(crate_dir: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(crate_dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Dependent names are crate names, but quote anything TOML wouldn't take bare
+fn toml_key(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        name.to_string()
+    } else {
+        format!("{:?}", name)
+    }
+}
+
+/// Write `content` to `path` unless it exists and `force` is off; true if written
+pub fn write_file(path: &Path, content: &str, force: bool) -> Result<bool, String> {
+    if path.exists() && !force {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+/// One environment check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvCheck {
+    pub what: String,
+    /// Version or other detail when it passed, the problem when it didn't
+    pub detail: String,
+    pub ok: bool,
+    /// Only needed by some options; a failure is reported but not fatal
+    pub optional: bool,
+}
+
+/// Check for the tools a run needs, from `crate_dir`
+///
+/// `crates_io` is the outcome of looking up the dependents, which doubles
+/// as the network check.
+pub fn check_environment(crate_dir: &Path, crates_io: Result<usize, String>) -> Vec<EnvCheck> {
+    let tool = |what: &str, program: &str, args: &[&str], optional: bool| {
+        let output = Command::new(program).args(args).current_dir(crate_dir).output();
+        let (ok, detail) = match output {
+            Ok(o) if o.status.success() => {
+                (true, String::from_utf8_lossy(&o.stdout).lines().next().unwrap_or("").trim().to_string())
+            }
+            Ok(o) => (false, String::from_utf8_lossy(&o.stderr).lines().next().unwrap_or("failed").trim().to_string()),
+            Err(e) => (false, format!("{} not found: {}", program, e)),
+        };
+        EnvCheck { what: what.to_string(), detail, ok, optional }
+    };
+
+    vec![
+        tool("cargo", "cargo", &["--version"], false),
+        tool("rustc", "rustc", &["--version"], false),
+        tool("git", "git", &["--version"], false),
+        tool("clippy (for --clippy)", "cargo", &["clippy", "--version"], true),
+        tool("miri (for --miri)", "cargo", &["+nightly", "miri", "--version"], true),
+        tool("nightly rustdoc (for --api-diff)", "cargo", &["+nightly", "rustdoc", "--version"], true),
+        tool("nightly cargo (for --minimal-versions)", "cargo", &["+nightly", "--version"], true),
+        match crates_io {
+            Ok(n) => EnvCheck { what: "crates.io".to_string(), detail: format!("reachable, {} dependents found", n), ok: true, optional: false },
+            Err(e) => EnvCheck { what: "crates.io".to_string(), detail: e, ok: false, optional: false },
+        },
+    ]
+}
+
+/// Checklist for the console
+pub fn format_checks(checks: &[EnvCheck]) -> String {
+    let mut out = String::from("Environment:\n");
+    for check in checks {
+        let mark = match (check.ok, check.optional) {
+            (true, _) => "✓",
+            (false, true) => "-",
+            (false, false) => "✗",
+        };
+        out.push_str(&format!("  {} {}: {}\n", mark, check.what, check.detail));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_file::CopterConfig;
+
+    fn dependent(name: &str, downloads: u64) -> ReverseDependency {
+        ReverseDependency { name: name.to_string(), downloads, published_at: None }
+    }
+
+    #[test]
+    fn test_suggested_jobs() {
+        assert_eq!(suggested_jobs(1), 1);
+        assert_eq!(suggested_jobs(16), 4);
+        assert_eq!(suggested_jobs(128), 8);
+    }
+
+    #[test]
+    fn test_copter_toml_is_valid_config() {
+        let suggestion = Suggestion::new("rgb", vec![dependent("image", 12_000_000), dependent("ravif", 900_000)], 16);
+        assert!(suggestion.command_line().starts_with("cargo-copter --top-dependents 2 --jobs 4 "));
+
+        let toml_text = suggestion.copter_toml();
+        let config: CopterConfig = toml::from_str(&toml_text).unwrap();
+        assert_eq!(config.home.as_deref(), Some(Path::new(".copter")));
+        assert!(config.dependent("image").is_some());
+        assert!(config.dependent("ravif").is_some());
+
+        // Nothing to list yet: still a valid config
+        let empty: CopterConfig = toml::from_str(&Suggestion::new("new-crate", vec![], 2).copter_toml()).unwrap();
+        assert!(empty.dependents.is_empty());
+    }
+
+    #[test]
+    fn test_workflow_runs_suggested_command() {
+        let suggestion = Suggestion::new("rgb", vec![dependent("image", 1)], 8);
+        let workflow = suggestion.workflow(Path::new(""));
+        assert!(workflow.contains(&format!("run: {}\n", suggestion.command_line())));
+        assert!(workflow.contains("key: copter-${{ runner.os }}-${{ github.sha }}"));
+        assert!(workflow.contains("            copter-report.json\n"));
+
+        // A crate inside a workspace runs from its own directory
+        let workflow = suggestion.workflow(Path::new("crates/rgb"));
+        assert!(workflow.contains("working-directory: crates/rgb\n"));
+        assert!(workflow.contains("path: crates/rgb/.copter\n"));
+        assert!(workflow.contains("            crates/rgb/copter-report.html\n"));
+    }
+
+    #[test]
+    fn test_write_file_keeps_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".github").join("workflows").join("copter.yml");
+        assert!(write_file(&path, "first", false).unwrap());
+        assert!(!write_file(&path, "second", false).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert!(write_file(&path, "second", true).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+    }
+}
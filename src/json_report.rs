@@ -0,0 +1,321 @@
+//! Machine-readable JSON report
+//!
+//! Written next to the markdown report after every run so runs can be
+//! compared later (see `diff`). Durations are raw seconds, not formatted.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::compile::ProcessExit;
+use crate::report::{failed_step, row_status, TestSummary};
+use crate::{OfferedRow, VersionSource};
+
+/// Status of a single row, as shown in the Result column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowStatus {
+    Passed,
+    Regressed,
+    Broken,
+    Failed,
+    /// Passed, but only after a failed `cargo test` was retried
+    Flaky,
+    /// Passed, with new warnings compared to the baseline (see `warnings`)
+    Warned,
+    /// Passed, but with two semver-incompatible versions of the base crate in the graph
+    Duplicated,
+    /// Passed, but the offered version needs a newer Rust than the dependent's rust-version
+    Msrv,
+    /// Couldn't be fetched because crates.io or the network kept failing
+    Network,
+}
+
+impl RowStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RowStatus::Passed => "PASSED",
+            RowStatus::Regressed => "REGRESSED",
+            RowStatus::Broken => "BROKEN",
+            RowStatus::Failed => "FAILED",
+            RowStatus::Flaky => "FLAKY",
+            RowStatus::Warned => "WARNED",
+            RowStatus::Duplicated => "DUPLICATED",
+            RowStatus::Msrv => "MSRV",
+            RowStatus::Network => "NETWORK",
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, RowStatus::Passed | RowStatus::Flaky | RowStatus::Warned | RowStatus::Duplicated | RowStatus::Msrv)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonReport {
+    pub crate_name: String,
+    pub crate_version: String,
+    pub generated_at: String,
+    pub summary: JsonSummary,
+    pub rows: Vec<JsonRow>,
+    /// Versions of the crate published while the run was in progress
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub published_during_run: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JsonSummary {
+    pub passed: usize,
+    pub regressed: usize,
+    pub broken: usize,
+    #[serde(default)]
+    pub flaky: usize,
+    #[serde(default)]
+    pub network: usize,
+    pub total: usize,
+    /// rustc error code -> occurrences across regressed and broken rows
+    #[serde(default)]
+    pub error_codes: BTreeMap<String, usize>,
+    /// Errors of regressed rows grouped by pattern, most dependents first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub error_groups: Vec<JsonErrorGroup>,
+    /// Base-crate items behind regressions (`--api-diff`): cause -> regressed dependents
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub api_breaks: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonErrorGroup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Error message with generic arguments and numbers replaced
+    pub message: String,
+    pub dependents: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRow {
+    pub dependent: String,
+    pub dependent_version: String,
+    /// Offered version label; None for the baseline row
+    pub offered: Option<String>,
+    pub forced: bool,
+    pub spec: String,
+    pub resolved_version: String,
+    pub resolved_source: String,
+    pub status: RowStatus,
+    /// First failed step ("fetch", "check", "test", or an optional step such as "doctest"),
+    /// for telling compile breaks from test failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_step: Option<String>,
+    pub steps: Vec<JsonStep>,
+    /// Dependent's edition and resolver, if its manifest was read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver: Option<String>,
+    /// Target kinds checked and tested: "lib", "bin" or "lib+bin"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub targets: Option<String>,
+    /// Target triple checked instead of the host (`--targets`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cross_target: Option<String>,
+    /// Toolchain the dependent was tested with (`--channels`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// "active", "stale" or "abandoned" (`--maintenance`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<String>,
+    /// Features of the base crate enabled in the dependent's graph, after unification
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub base_features: Vec<String>,
+    /// Base-crate items likely behind a regression (`--api-diff`), e.g. "removed `Rgb::new`"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub likely_causes: Vec<String>,
+    /// What the dependent's committed Cargo.lock changed (`--lockfile respect|both`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lockfile_notes: Vec<String>,
+    /// Lints the offered version warns about more often than the baseline, e.g. "deprecated: 0 → 2 (...)"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_warnings: Vec<String>,
+    /// Steps `--slowdown-threshold` times slower than the baseline's, e.g. "check: 10.0s → 30.0s (3.0x)"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slowdowns: Vec<String>,
+    /// Libraries and binaries that changed size in the dev-profile `--full-build`, e.g. "libimage.rlib: 1.0 MiB → 1.2 MiB (+25.0%)"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub size_changes: Vec<String>,
+    /// Semver-incompatible versions of the base crate in the graph, e.g. ["0.8.50", "0.9.0 (path)"]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_versions: Vec<String>,
+    /// e.g. "needs Rust 1.70, dependent's rust-version is 1.60"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msrv_conflict: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonStep {
+    pub command: String,
+    pub passed: bool,
+    pub duration_secs: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// cargo's exit code, or the signal that terminated it (e.g. 9 after a timeout)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// Names of the tests that failed in this step
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_tests: Vec<String>,
+    /// File with the complete output of this step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+}
+
+impl JsonReport {
+    pub fn from_rows(rows: &[OfferedRow], summary: &TestSummary, crate_name: &str, crate_version: &str) -> Self {
+        JsonReport {
+            crate_name: crate_name.to_string(),
+            crate_version: crate_version.to_string(),
+            generated_at: chrono::Local::now().to_rfc3339(),
+            summary: JsonSummary {
+                passed: summary.passed,
+                regressed: summary.regressed,
+                broken: summary.broken,
+                flaky: summary.flaky,
+                network: summary.network,
+                total: summary.total,
+                error_codes: summary.error_codes.iter().cloned().collect(),
+                error_groups: summary.error_groups.iter()
+                    .map(|group| JsonErrorGroup {
+                        code: group.pattern.code.clone(),
+                        message: group.pattern.message.clone(),
+                        dependents: group.dependents.clone(),
+                    })
+                    .collect(),
+                api_breaks: summary.api_breaks.iter()
+                    .map(|brk| (brk.cause.clone(), brk.dependents.clone()))
+                    .collect(),
+            },
+            rows: rows.iter().map(JsonRow::from_row).collect(),
+            published_during_run: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// An rgb 0.8.91 report with an empty summary
+    #[cfg(test)]
+    pub fn for_test(rows: Vec<JsonRow>) -> Self {
+        JsonReport {
+            crate_name: "rgb".to_string(),
+            crate_version: "0.8.91".to_string(),
+            generated_at: "2025-01-01T00:00:00Z".to_string(),
+            summary: JsonSummary::default(),
+            rows,
+            published_during_run: vec![],
+        }
+    }
+}
+
+impl JsonRow {
+    /// A row for `dependent 0.25.8` with no steps
+    #[cfg(test)]
+    pub fn for_test(dependent: &str, offered: Option<&str>, status: RowStatus) -> Self {
+        JsonRow {
+            dependent: dependent.to_string(),
+            dependent_version: "0.25.8".to_string(),
+            offered: offered.map(|o| o.to_string()),
+            forced: false,
+            spec: "^0.8".to_string(),
+            resolved_version: "0.8.91".to_string(),
+            resolved_source: "local".to_string(),
+            status,
+            failed_step: None,
+            steps: vec![],
+            edition: None,
+            resolver: None,
+            targets: None,
+            cross_target: None,
+            channel: None,
+            maintenance: None,
+            base_features: vec![],
+            likely_causes: vec![],
+            lockfile_notes: vec![],
+            new_warnings: vec![],
+            slowdowns: vec![],
+            size_changes: vec![],
+            duplicate_versions: vec![],
+            msrv_conflict: None,
+        }
+    }
+
+    fn from_row(row: &OfferedRow) -> Self {
+        JsonRow {
+            dependent: row.primary.dependent_name.clone(),
+            dependent_version: row.primary.dependent_version.clone(),
+            offered: row.offered.as_ref().map(|o| o.version.clone()),
+            forced: row.offered.as_ref().map(|o| o.forced).unwrap_or(false),
+            spec: row.primary.spec.clone(),
+            resolved_version: row.primary.resolved_version.clone(),
+            resolved_source: match row.primary.resolved_source {
+                VersionSource::CratesIo => "crates.io",
+                VersionSource::Local => "local",
+                VersionSource::Git => "git",
+            }.to_string(),
+            status: row_status(row),
+            failed_step: failed_step(row).map(|step| step.as_str().to_string()),
+            steps: row.test.commands.iter()
+                .map(|cmd| JsonStep {
+                    command: cmd.command.as_str().to_string(),
+                    passed: cmd.result.passed,
+                    duration_secs: cmd.result.duration,
+                    features: cmd.features.clone(),
+                    exit_code: match cmd.result.exit {
+                        Some(ProcessExit::Code(code)) => Some(code),
+                        _ => None,
+                    },
+                    signal: match cmd.result.exit {
+                        Some(ProcessExit::Signal(signal)) => Some(signal),
+                        _ => None,
+                    },
+                    failed_tests: cmd.result.failures.iter().flat_map(|f| f.failed_tests.iter().cloned()).collect(),
+                    log: cmd.result.log.as_ref().map(|log| log.display().to_string()),
+                })
+                .collect(),
+            edition: row.primary.edition.clone(),
+            resolver: row.primary.resolver.clone(),
+            targets: row.primary.targets.clone(),
+            cross_target: row.primary.cross_target.clone(),
+            channel: row.primary.channel.clone(),
+            maintenance: row.primary.maintenance.map(|m| m.as_str().to_string()),
+            base_features: row.primary.base_features.clone(),
+            likely_causes: {
+                let mut causes: Vec<String> = Vec::new();
+                for cause in row.test.commands.iter().flat_map(|cmd| &cmd.result.failures).flat_map(|f| &f.likely_causes) {
+                    if !causes.contains(cause) {
+                        causes.push(cause.clone());
+                    }
+                }
+                causes
+            },
+            lockfile_notes: row.lockfile_notes.clone(),
+            new_warnings: row.warning_diff.iter().map(ToString::to_string).collect(),
+            slowdowns: row.slowdowns.iter().map(ToString::to_string).collect(),
+            size_changes: row.size_diff.iter().map(ToString::to_string).collect(),
+            duplicate_versions: row.duplicate_versions.clone(),
+            msrv_conflict: row.msrv_conflict.as_ref().map(ToString::to_string),
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Dependents tested from a local checkout
+//!
+//! `--dependent-paths` points at crates on disk, such as in-house dependents
+//! that were never published. Each one is copied to
+//! `{staging_dir}/local/{name}` and tested there like a git checkout, so the
+//! patching, lock handling and backups of an ICT run never touch the
+//! original. `.git/` and `target/` aren't copied; the copy's own `target/`
+//! survives the refresh on every run. A workspace member is copied with its
+//! whole workspace, so fields it inherits from the root still resolve, and
+//! relative `path` dependencies leading out of the copy are pointed back at
+//! the originals.
+//!
+//! `--workspace-dependents` finds such paths itself, by walking a workspace
+//! or monorepo for every package that depends on the crate under test.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use log::debug;
+use semver::{BuildMetadata, Version};
+
+/// Directories never copied
+const SKIPPED: [&str; 2] = [".git", "target"];
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A dependent copied from a local path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalCopy {
+    /// Directory of the dependent's package within the copy
+    pub crate_dir: PathBuf,
+    /// Manifest version, with `local` build metadata
+    pub version: Version,
+}
+
+/// Package directory for a `--dependent-paths` entry (a directory or its Cargo.toml)
+fn package_dir(path: &Path) -> &Path {
+    match path.file_name() {
+        Some(name) if name == "Cargo.toml" => path.parent().unwrap_or(path),
+        _ => path,
+    }
+}
+
+/// Package name declared by the crate at `path`, None if there is no crate there
+pub fn package_name(path: &Path) -> Option<String> {
+    crate::git_source::package_name(package_dir(path))
+}
+
+/// Packages under `root` depending on `crate_name`, sorted by path
+///
+/// Hidden directories and `target/` aren't searched, and `crate_name`'s own
+/// package isn't one of its dependents.
+pub fn discover(root: &Path, crate_name: &str) -> Result<Vec<PathBuf>, String> {
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    let mut found = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let manifest = fs::read(dir.join("Cargo.toml")).ok()
+            .and_then(|content| crate::manifest::parse_manifest(&content).ok());
+        if let Some(manifest) = manifest {
+            let name = manifest.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str());
+            if name.is_some_and(|name| name != crate_name) && depends_on(&manifest, crate_name) {
+                debug!("{:?} depends on {}", dir, crate_name);
+                found.push(dir.clone());
+            }
+        }
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            if file_name.to_string_lossy().starts_with('.') || file_name == "target" {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                dirs.push(entry.path());
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// Whether any dependency table of `manifest`, platform-specific ones
+/// included, names `crate_name` (also as `package = "..."` of a renamed one)
+fn depends_on(manifest: &toml::Value, crate_name: &str) -> bool {
+    let names_crate = |item: &toml::Value| DEPENDENCY_TABLES.iter()
+        .filter_map(|table| item.get(table).and_then(|deps| deps.as_table()))
+        .flatten()
+        .any(|(name, dep)| name == crate_name || dep.get("package").and_then(|p| p.as_str()) == Some(crate_name));
+
+    names_crate(manifest)
+        || manifest.get("target").and_then(|t| t.as_table())
+            .is_some_and(|targets| targets.values().any(names_crate))
+}
+
+/// Copy (or refresh the copy of) the crate at `path` into the staging dir
+pub fn copy(name: &str, path: &Path, staging_dir: &Path) -> Result<LocalCopy, String> {
+    let source = fs::canonicalize(package_dir(path))
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+    let workspace = crate::workspace::find_root(&source);
+    let root = workspace.clone().unwrap_or_else(|| source.clone());
+    let member = source.strip_prefix(&root).unwrap_or(Path::new(""));
+
+    let dest = staging_dir.join("local").join(name);
+    fs::create_dir_all(&dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    // The staging dir may well sit inside the checkout (.copter/ by default)
+    let staging = fs::canonicalize(staging_dir)
+        .map_err(|e| format!("Failed to resolve {}: {}", staging_dir.display(), e))?;
+
+    debug!("Copying {} from {:?} to {:?}", name, root, dest);
+    clear(&dest).and_then(|_| copy_tree(&root, &dest, &root, &staging))
+        .map_err(|e| format!("Failed to copy {} to {}: {}", root.display(), dest.display(), e))?;
+
+    let crate_dir = dest.join(member);
+    // Copied as is, so this is the committed lock
+    crate::lockfile::save_committed(&crate_dir)
+        .map_err(|e| format!("Failed to save Cargo.lock of {}: {}", name, e))?;
+    let mut version = crate::git_source::package_version(&crate_dir, &dest)?;
+    version.build = BuildMetadata::new("local").expect("valid build metadata");
+    if workspace.is_none() {
+        // Keep a workspace around the staging dir from claiming the copy
+        crate::workspace::isolate_unpacked(&crate_dir, &version)?;
+    }
+
+    Ok(LocalCopy { crate_dir, version })
+}
+
+/// Remove everything from an earlier copy except `target/`
+fn clear(dest: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dest)? {
+        let entry = entry?;
+        if entry.file_name() == "target" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy `from` (within `root`) to `to`, anchoring the manifests on the way
+fn copy_tree(from: &Path, to: &Path, root: &Path, staging: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        // Follows symlinks: the copy has to build on its own
+        let metadata = fs::metadata(&path)?;
+        if metadata.is_dir() {
+            if SKIPPED.iter().any(|skipped| entry.file_name() == *skipped) || path == staging {
+                continue;
+            }
+            // A link back up the tree would be copied forever
+            if entry.file_type()?.is_symlink() && fs::canonicalize(from)?.starts_with(fs::canonicalize(&path)?) {
+                continue;
+            }
+            copy_tree(&path, &to.join(entry.file_name()), root, staging)?;
+        } else {
+            fs::copy(&path, to.join(entry.file_name()))?;
+            if entry.file_name() == "Cargo.toml" {
+                anchor_paths(&to.join("Cargo.toml"), from, root).map_err(io::Error::other)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite relative `path` dependencies of a copied manifest that point
+/// outside `root` to absolute paths of the originals; `original_dir` is
+/// where the manifest was copied from
+fn anchor_paths(manifest: &Path, original_dir: &Path, root: &Path) -> Result<(), String> {
+    let content = fs::read_to_string(manifest)
+        .map_err(|e| format!("Failed to read {}: {}", manifest.display(), e))?;
+    let Ok(mut doc) = content.parse::<toml_edit::DocumentMut>() else {
+        // Left for cargo to report
+        return Ok(());
+    };
+
+    let mut tables: Vec<&mut dyn toml_edit::TableLike> = Vec::new();
+    for (key, item) in doc.iter_mut() {
+        let key = key.get();
+        if DEPENDENCY_TABLES.contains(&key) {
+            tables.extend(item.as_table_like_mut());
+        } else if key == "workspace" {
+            tables.extend(item.get_mut("dependencies").and_then(|d| d.as_table_like_mut()));
+        } else if let Some(outer) = item.as_table_like_mut().filter(|_| key == "target" || key == "patch") {
+            for (_, inner) in outer.iter_mut() {
+                let Some(inner) = inner.as_table_like_mut() else { continue };
+                if key == "patch" {
+                    // [patch.crates-io] holds dependencies directly
+                    tables.push(inner);
+                    continue;
+                }
+                // [target.'cfg(..)'.dependencies]
+                for (name, deps) in inner.iter_mut() {
+                    if DEPENDENCY_TABLES.contains(&name.get()) {
+                        tables.extend(deps.as_table_like_mut());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut changed = false;
+    for deps in tables {
+        for (name, dep) in deps.iter_mut() {
+            let Some(dep) = dep.as_table_like_mut() else { continue };
+            let Some(path) = dep.get("path").and_then(|p| p.as_str()).map(PathBuf::from) else { continue };
+            if path.is_absolute() {
+                continue;
+            }
+            let Ok(target) = fs::canonicalize(original_dir.join(&path)) else { continue };
+            if !target.starts_with(root) {
+                debug!("Anchoring path dependency {} of {:?} at {:?}", name, manifest, target);
+                dep.insert("path", toml_edit::value(target.display().to_string()));
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        fs::write(manifest, doc.to_string())
+            .map_err(|e| format!("Failed to write {}: {}", manifest.display(), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_dependents() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let package = |path: &str, manifest: &str| {
+            fs::create_dir_all(root.join(path)).unwrap();
+            fs::write(root.join(path).join("Cargo.toml"), manifest).unwrap();
+        };
+        package("", "[workspace]\nmembers = [\"crates/*\", \"tools/*\"]\n[workspace.dependencies]\nrgb = \"0.8\"\n");
+        package("crates/rgb", "[package]\nname = \"rgb\"\n");
+        package("crates/viewer", "[package]\nname = \"viewer\"\n[dependencies]\nrgb = { workspace = true }\n");
+        package("crates/codec", "[package]\nname = \"codec\"\n[target.'cfg(unix)'.dev-dependencies]\npixels = { package = \"rgb\", version = \"0.8\" }\n");
+        package("tools/cli", "[package]\nname = \"cli\"\n[dependencies]\nclap = \"4\"\n");
+        package("crates/viewer/target/package/viewer-0.1.0", "[package]\nname = \"viewer\"\n[dependencies]\nrgb = \"0.8\"\n");
+
+        let found = discover(root, "rgb").unwrap();
+        assert_eq!(found, vec![root.join("crates/codec"), root.join("crates/viewer")]);
+        assert!(discover(&root.join("missing"), "rgb").is_err());
+    }
+
+    #[test]
+    fn test_copy_workspace_member() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("app");
+        fs::create_dir_all(root.join("crates/viewer/src")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n[workspace.package]\nversion = \"0.3.0\"\n").unwrap();
+        fs::write(root.join("Cargo.lock"), "version = 3\n").unwrap();
+        fs::write(root.join("crates/viewer/Cargo.toml"), concat!(
+            "[package]\nname = \"viewer\"\nversion.workspace = true\n",
+            "[dependencies]\ncodec = { path = \"../../../codec\" }\nshared = { path = \"../shared\" }\n",
+        )).unwrap();
+        fs::create_dir_all(dir.path().join("codec")).unwrap();
+        fs::create_dir_all(root.join("crates/shared")).unwrap();
+        fs::write(root.join("crates/viewer/src/lib.rs"), "").unwrap();
+        // Default staging dir, inside the checkout
+        let staging = root.join(".copter/staging");
+        fs::create_dir_all(&staging).unwrap();
+
+        let path = root.join("crates/viewer/Cargo.toml");
+        assert_eq!(package_name(&path).as_deref(), Some("viewer"));
+        let copied = copy("viewer", &path, &staging).unwrap();
+        let dest = staging.join("local/viewer");
+        assert_eq!(copied.crate_dir, dest.join("crates/viewer"));
+        assert_eq!(copied.version.to_string(), "0.3.0+local");
+        assert!(copied.crate_dir.join("src/lib.rs").exists());
+        assert!(!dest.join("target").exists());
+        assert!(!dest.join(".copter/staging").exists());
+        // Paths out of the copy lead back to the originals; those inside stay relative
+        let manifest = fs::read_to_string(copied.crate_dir.join("Cargo.toml")).unwrap();
+        let codec = fs::canonicalize(dir.path().join("codec")).unwrap();
+        assert!(manifest.contains(&format!("codec = {{ path = \"{}\" }}", codec.display())));
+        assert!(manifest.contains("shared = { path = \"../shared\" }"));
+
+        // A refresh drops files removed from the original, and keeps the copy's target/
+        fs::remove_file(root.join("crates/viewer/src/lib.rs")).unwrap();
+        fs::create_dir_all(dest.join("target")).unwrap();
+        copy("viewer", &path, &staging).unwrap();
+        assert!(!dest.join("crates/viewer/src/lib.rs").exists());
+        assert!(dest.join("target").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_follows_directory_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("viewer");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"viewer\"\nversion = \"0.3.0\"\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::create_dir_all(dir.path().join("shaders")).unwrap();
+        fs::write(dir.path().join("shaders/blit.wgsl"), "").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("shaders"), root.join("shaders")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("src/root")).unwrap();
+        let staging = dir.path().join("staging");
+        fs::create_dir_all(&staging).unwrap();
+
+        let copied = copy("viewer", &root.join("Cargo.toml"), &staging).unwrap();
+        assert!(copied.crate_dir.join("shaders/blit.wgsl").is_file());
+        assert!(!copied.crate_dir.join("src/root").exists());
+    }
+}
@@ -0,0 +1,276 @@
+//! What changed in a dependent's Cargo.lock between the baseline and an offered version
+//!
+//! Every run deletes Cargo.lock and lets fetch resolve from scratch, so the
+//! lock left behind records the whole graph for that version. Comparing the
+//! offered run's lock against the baseline's shows what actually changed
+//! beyond the base crate itself: new transitive dependencies, or other crates
+//! that moved because of it.
+//!
+//! Users of a dependent that commits its Cargo.lock don't resolve from
+//! scratch, though. `--lockfile respect` restores that lock before each run
+//! (fetching the baseline `--locked`), and `--lockfile both` runs each version
+//! both ways, so breakage deleting the lock masks or creates shows up.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::compile::CompileStep;
+
+/// The dependent's own Cargo.lock, saved when it's unpacked or checked out
+pub const ORIGINAL_LOCK: &str = "Cargo.lock.original.txt";
+
+/// What to do with a dependent's committed Cargo.lock (`--lockfile`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LockfileMode {
+    /// Delete it and resolve every version from scratch
+    #[default]
+    Delete,
+    /// Keep it: the baseline is fetched --locked, offered versions only move the base crate
+    Respect,
+    /// Resolve from scratch, then run again with the committed lock and report differences
+    Both,
+}
+
+/// Keep the dependent's committed Cargo.lock (if it has one) before any run replaces it
+pub fn save_committed(crate_dir: &Path) -> io::Result<()> {
+    let lock = crate_dir.join("Cargo.lock");
+    if lock.exists() {
+        fs::copy(&lock, crate_dir.join(ORIGINAL_LOCK))?;
+    }
+    Ok(())
+}
+
+/// How a run with the committed lockfile went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockedOutcome {
+    Passed,
+    Failed(CompileStep),
+}
+
+/// The dependent's committed Cargo.lock, for `--lockfile respect|both` runs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommittedLock {
+    /// Version of the base crate it pins (from the registry)
+    pub pinned: Option<String>,
+    /// `both`: the run with the committed lock, next to this fresh-resolution run
+    pub locked: Option<LockedOutcome>,
+}
+
+impl CommittedLock {
+    /// Read from `crate_dir`, if the dependent has a committed lock
+    pub fn load(crate_dir: &Path, base_crate: &str) -> Option<Self> {
+        let content = fs::read_to_string(crate_dir.join(ORIGINAL_LOCK)).ok()?;
+        let packages = toml::from_str::<LockFile>(&content).map(|lock| lock.package).unwrap_or_default();
+        let pinned = packages.into_iter()
+            .find(|p| p.name == base_crate && p.source.is_some())
+            .map(|p| p.version);
+        Some(CommittedLock { pinned, locked: None })
+    }
+
+    /// What stands out for a run of base crate `tested` whose first failure
+    /// was `fresh_failure` (None = passed): a pin the version can't satisfy,
+    /// or a different outcome with the committed lock
+    pub fn notes(&self, base_crate: &str, tested: Option<&str>, fresh_failure: Option<CompileStep>) -> Vec<String> {
+        let mut notes = Vec::new();
+        if let (Some(pinned), Some(tested)) = (&self.pinned, tested) {
+            let compatible = VersionReq::parse(&format!("^{}", pinned)).ok()
+                .zip(Version::parse(tested).ok())
+                .is_none_or(|(req, version)| req.matches(&version));
+            if !compatible {
+                notes.push(format!("Cargo.lock pins {} {}, incompatible with {}", base_crate, pinned, tested));
+            }
+        }
+        match (self.locked, fresh_failure) {
+            (Some(LockedOutcome::Passed), Some(step)) => {
+                notes.push(format!("passes with its Cargo.lock, fails {} resolved fresh", step.as_str()));
+            }
+            (Some(LockedOutcome::Failed(step)), None) => {
+                notes.push(format!("fails {} with its Cargo.lock, passes resolved fresh", step.as_str()));
+            }
+            _ => {}
+        }
+        notes
+    }
+}
+
+/// A `[[package]]` entry of Cargo.lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockPackage {
+    pub name: String,
+    pub version: String,
+    /// None for path dependencies (including a patched-in local version)
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl LockPackage {
+    fn label(&self) -> String {
+        match self.source {
+            Some(_) => self.version.clone(),
+            None => format!("{} (path)", self.version),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LockFile {
+    #[serde(default)]
+    package: Vec<LockPackage>,
+}
+
+/// Packages in `crate_path/Cargo.lock`; empty if it's missing or unreadable
+pub fn read_packages(crate_path: &Path) -> Vec<LockPackage> {
+    fs::read_to_string(crate_path.join("Cargo.lock")).ok()
+        .and_then(|content| toml::from_str::<LockFile>(&content).ok())
+        .map(|lock| lock.package)
+        .unwrap_or_default()
+}
+
+/// A package whose versions differ between two lock files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockChange {
+    pub name: String,
+    /// Versions in the baseline lock (empty = added)
+    pub before: Vec<String>,
+    /// Versions in the offered lock (empty = removed)
+    pub after: Vec<String>,
+}
+
+impl fmt::Display for LockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.before.is_empty(), self.after.is_empty()) {
+            (true, _) => write!(f, "+ {} {}", self.name, self.after.join(", ")),
+            (_, true) => write!(f, "- {} {}", self.name, self.before.join(", ")),
+            _ => write!(f, "~ {} {} → {}", self.name, self.before.join(", "), self.after.join(", ")),
+        }
+    }
+}
+
+fn versions_of(packages: &[LockPackage], name: &str) -> Vec<String> {
+    let mut versions: Vec<String> = packages.iter().filter(|p| p.name == name).map(LockPackage::label).collect();
+    versions.sort();
+    versions.dedup();
+    versions
+}
+
+/// Versions cargo can't unify: the major version, or the first non-zero component for 0.x
+fn compatibility_line(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+/// Versions of `crate_name` in the graph when they're semver-incompatible, so
+/// two copies of it get built whose types don't mix; empty otherwise
+pub fn incompatible_versions(packages: &[LockPackage], crate_name: &str) -> Vec<String> {
+    let mut lines: Vec<(u64, u64, u64)> = packages.iter()
+        .filter(|p| p.name == crate_name)
+        .filter_map(|p| Version::parse(&p.version).ok())
+        .map(|v| compatibility_line(&v))
+        .collect();
+    lines.sort();
+    lines.dedup();
+    if lines.len() < 2 {
+        return Vec::new();
+    }
+    versions_of(packages, crate_name)
+}
+
+/// Packages that differ, `base_crate` first, then by name
+pub fn diff(baseline: &[LockPackage], offered: &[LockPackage], base_crate: &str) -> Vec<LockChange> {
+    let mut names: Vec<&str> = baseline.iter().chain(offered).map(|p| p.name.as_str()).collect();
+    names.sort_by_key(|name| (*name != base_crate, *name));
+    names.dedup();
+
+    names.into_iter()
+        .filter_map(|name| {
+            let before = versions_of(baseline, name);
+            let after = versions_of(offered, name);
+            (before != after).then(|| LockChange { name: name.to_string(), before, after })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, registry: bool) -> LockPackage {
+        LockPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: registry.then(|| "registry+https://github.com/rust-lang/crates.io-index".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_incompatible_versions() {
+        let graph = |versions: &[(&str, bool)]| -> Vec<LockPackage> {
+            versions.iter().map(|(version, registry)| package("rgb", version, *registry))
+                .chain([package("image", "0.25.8", false)])
+                .collect()
+        };
+        assert!(incompatible_versions(&graph(&[("0.8.50", true)]), "rgb").is_empty());
+        assert!(incompatible_versions(&graph(&[("0.8.50", true), ("0.8.91", false)]), "rgb").is_empty());
+        assert!(incompatible_versions(&graph(&[("1.0.0", true), ("1.2.0", false)]), "rgb").is_empty());
+        assert_eq!(incompatible_versions(&graph(&[("0.8.50", true), ("0.9.0", false)]), "rgb"), vec!["0.8.50", "0.9.0 (path)"]);
+        assert_eq!(incompatible_versions(&graph(&[("0.0.1", true), ("0.0.2", true)]), "rgb"), vec!["0.0.1", "0.0.2"]);
+    }
+
+    #[test]
+    fn test_read_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.lock"), "version = 3\n\n\
+            [[package]]\nname = \"image\"\nversion = \"0.25.8\"\ndependencies = [\"rgb\"]\n\n\
+            [[package]]\nname = \"rgb\"\nversion = \"0.8.50\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n").unwrap();
+        assert_eq!(read_packages(dir.path()), vec![package("image", "0.25.8", false), package("rgb", "0.8.50", true)]);
+        assert!(read_packages(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_committed_lock_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(CommittedLock::load(dir.path(), "rgb"), None);
+        fs::write(dir.path().join("Cargo.lock"), "version = 3\n\n\
+            [[package]]\nname = \"rgb\"\nversion = \"0.7.3\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n").unwrap();
+        save_committed(dir.path()).unwrap();
+        fs::remove_file(dir.path().join("Cargo.lock")).unwrap();
+
+        let mut lock = CommittedLock::load(dir.path(), "rgb").unwrap();
+        assert_eq!(lock.pinned.as_deref(), Some("0.7.3"));
+        assert!(lock.notes("rgb", Some("0.7.9"), None).is_empty());
+        assert_eq!(lock.notes("rgb", Some("0.8.91"), None), vec!["Cargo.lock pins rgb 0.7.3, incompatible with 0.8.91"]);
+
+        lock.locked = Some(LockedOutcome::Failed(CompileStep::Check));
+        assert_eq!(lock.notes("rgb", Some("0.7.9"), None), vec!["fails check with its Cargo.lock, passes resolved fresh"]);
+        assert!(lock.notes("rgb", Some("0.7.9"), Some(CompileStep::Test)).is_empty());
+        lock.locked = Some(LockedOutcome::Passed);
+        assert_eq!(lock.notes("rgb", None, Some(CompileStep::Test)), vec!["passes with its Cargo.lock, fails test resolved fresh"]);
+    }
+
+    #[test]
+    fn test_diff_puts_base_crate_first() {
+        let baseline = vec![package("image", "0.25.8", false), package("bytemuck", "1.14.0", true), package("rgb", "0.8.50", true)];
+        let offered = vec![
+            package("image", "0.25.8", false),
+            package("bytemuck", "1.16.0", true),
+            package("rgb", "0.8.91", false),
+            package("zerocopy", "0.7.0", true),
+        ];
+
+        let changes: Vec<String> = diff(&baseline, &offered, "rgb").iter().map(|c| c.to_string()).collect();
+        assert_eq!(changes, vec![
+            "~ rgb 0.8.50 → 0.8.91 (path)",
+            "~ bytemuck 1.14.0 → 1.16.0",
+            "+ zerocopy 0.7.0",
+        ]);
+        assert!(diff(&baseline, &baseline, "rgb").is_empty());
+    }
+}
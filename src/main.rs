@@ -8,14 +8,58 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod alt_registry;
 mod api;
+mod api_cache;
+mod api_diff;
+mod artifacts;
+mod badge;
+mod baseline_cache;
+mod bisect;
+mod budget;
+mod build_times;
+mod cache;
 mod cli;
 mod compile;
+mod config_file;
+mod diff;
+mod email;
+mod distributed;
+#[cfg(test)]
 mod console_tables;
 mod error_extract;
+mod format;
+mod git_source;
+mod graph;
+mod history;
+mod hooks;
+mod init;
+mod maintenance;
+mod manifest;
+mod msrv;
+mod notify;
+mod paths;
+mod platform;
+mod pr_comment;
+mod progress;
+mod registry;
+mod json_report;
+mod local_source;
+mod lockfile;
 mod report;
+mod run_log;
+mod sarif;
+mod schedule;
+mod sink;
+mod sizes;
+mod step_logs;
+mod test_reports;
+mod tree;
+mod warnings;
+mod workspace;
 
 use semver::Version;
+use std::collections::HashMap;
 use std::env;
 use std::error::Error as StdError;
 use std::fmt;
@@ -24,39 +68,106 @@ use std::io::{self, Read, Write};
 use std::path::{PathBuf, Path};
 use std::process::Command;
 use std::string::FromUtf8Error;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, RwLock};
 use std::sync::mpsc::{self, Sender, Receiver, RecvError};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use threadpool::ThreadPool;
-use tempfile::TempDir;
-use crates_io_api::SyncClient;
 
 use lazy_static::lazy_static;
 use log::debug;
 
-const USER_AGENT: &str = "cargo-copter/0.1.1 (https://github.com/imazen/cargo-copter)";
-
 lazy_static! {
-    static ref CRATES_IO_CLIENT: SyncClient = {
-        SyncClient::new(USER_AGENT, Duration::from_millis(1000))
-            .expect("Failed to create crates.io API client")
-    };
+    /// Where downloaded .crate files are kept between runs
+    static ref CRATE_CACHE_DIR: RwLock<PathBuf> = RwLock::new(paths::CopterPaths::default().crate_cache);
+    /// `latest`/`latest-preview` as first resolved, so one run never mixes two answers
+    static ref PINNED_LATEST: Mutex<HashMap<(String, bool), String>> = Mutex::new(HashMap::new());
 }
 
 fn main() {
-    env_logger::init();
-
     // Parse CLI arguments
     let args = cli::CliArgs::parse_args();
 
+    let verbosity = run_log::Verbosity::from_flags(args.quiet, args.verbose);
+    if let Err(e) = run_log::init(verbosity, args.log_file.as_deref()) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    report::init_color(args.color);
+    report::init_ascii(args.ascii);
+    report::init_table(args.table_width, args.wrap);
+
     // Validate arguments
     if let Err(e) = args.validate() {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
 
+    // Options this OS can't honor are skipped rather than failing the run
+    for (flag, reason) in platform::unsupported_options(&args) {
+        eprintln!("Warning: {} skipped on {}: {}", flag, env::consts::OS, reason);
+    }
+
+    // Locations of the staging dir, caches and results database
+    let copter_paths = match resolve_paths(&args) {
+        Ok(p) => p,
+        Err(e) => {
+            report_error(e);
+            return;
+        }
+    };
+    *CRATE_CACHE_DIR.write().unwrap() = copter_paths.crate_cache.clone();
+
+    // Maintenance subcommands don't run any tests
+    if let Some(ref command) = args.command {
+        if let Err(e) = run_command(command, &args, &copter_paths) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Serve crates.io lookups and downloads from a local snapshot
+    if let Some(ref path) = args.registry_snapshot {
+        match registry::RegistrySnapshot::open(path) {
+            Ok(snapshot) => api::init_registry_snapshot(snapshot),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Discover dependents and patch on a private registry instead of crates.io
+    if args.registry.is_some() || args.index_url.is_some() {
+        let manifest = manifest_path(&args);
+        let crate_dir = manifest.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        match api::open_alt_registry(args.registry.as_deref(), args.index_url.as_deref(), crate_dir) {
+            Ok(registry) => api::init_alt_registry(registry),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Reuse recent crates.io responses across runs; offline, whatever their age
+    if args.offline {
+        api::init_offline();
+        api::init_api_cache(api_cache::ApiCache::new(&copter_paths.api_cache, Duration::MAX));
+    } else if !args.api_cache_ttl.is_zero() {
+        api::init_api_cache(api_cache::ApiCache::new(&copter_paths.api_cache, args.api_cache_ttl));
+    }
+
+    // Keep the cache within --max-cache-size
+    if let Some(max_bytes) = args.max_cache_size {
+        if let Err(e) = enforce_cache_size(&copter_paths, max_bytes) {
+            eprintln!("Warning: failed to shrink the cache to --max-cache-size: {}", e);
+        }
+    }
+
     // Get config
-    let config = match get_config(&args) {
+    let config = match get_config(&args, copter_paths) {
         Ok(c) => c,
         Err(e) => {
             report_error(e);
@@ -64,11 +175,142 @@ fn main() {
         }
     };
 
-    // Run tests and report results
+    // Run tests and report results; a worker's results went to its coordinator
     let results = run(args.clone(), config.clone());
+    if args.worker.is_some() && results.is_ok() {
+        return;
+    }
     report_results(results, &args, &config);
 }
 
+/// Where everything kept between runs lives, from COPTER_HOME, copter.toml and the path flags
+fn resolve_paths(args: &cli::CliArgs) -> Result<paths::CopterPaths, Error> {
+    let file_config = config_file::CopterConfig::load(args.config.as_deref())?;
+    let env_home = env::var_os(paths::HOME_ENV).filter(|h| !h.is_empty()).map(PathBuf::from);
+    Ok(paths::CopterPaths::resolve(
+        env_home,
+        file_config.home,
+        args.staging_dir.as_deref(),
+        args.artifacts_dir.as_ref().and_then(|dir| dir.as_deref()),
+    ))
+}
+
+/// Evict least recently used cache entries until the cache fits in `max_bytes`
+fn enforce_cache_size(copter_paths: &paths::CopterPaths, max_bytes: u64) -> io::Result<()> {
+    let entries = cache::cache_entries(&copter_paths.crate_cache, &copter_paths.staging)?;
+    let evicted = cache::plan_eviction(&entries, Some(max_bytes));
+    if evicted.is_empty() {
+        return Ok(());
+    }
+    let stats = cache::evict(&evicted)?;
+    status(&format!("Evicted {} least recently used cache entries ({}) to stay under {}",
+        stats.files, format::HumanSize(stats.bytes), format::HumanSize(max_bytes)));
+    Ok(())
+}
+
+/// Run a maintenance subcommand
+fn run_command(command: &cli::Command, args: &cli::CliArgs, copter_paths: &paths::CopterPaths) -> Result<(), Error> {
+    match command {
+        cli::Command::Cache { action } => {
+            let crate_cache = &copter_paths.crate_cache;
+            match action {
+                cli::CacheCommand::Export { archive } => {
+                    let stats = cache::export_cache(archive, crate_cache, &copter_paths.staging)?;
+                    println!("Exported {} files ({}) to {}", stats.files, format::HumanSize(stats.bytes), archive.display());
+                }
+                cli::CacheCommand::Import { archive } => {
+                    let stats = cache::import_cache(archive, crate_cache, &copter_paths.staging)?;
+                    println!("Imported {} files ({}) from {}", stats.files, format::HumanSize(stats.bytes), archive.display());
+                }
+                cli::CacheCommand::Clean { max_size, dry_run } => {
+                    let entries = cache::cache_entries(crate_cache, &copter_paths.staging)?;
+                    let evicted = cache::plan_eviction(&entries, *max_size);
+                    for entry in &evicted {
+                        println!("{:>10}  {}", format::HumanSize(entry.bytes).to_string(), entry.path.display());
+                    }
+                    let bytes: u64 = evicted.iter().map(|e| e.bytes).sum();
+                    if *dry_run {
+                        println!("Would remove {} entries ({})", evicted.len(), format::HumanSize(bytes));
+                    } else {
+                        let stats = cache::evict(&evicted)?;
+                        println!("Removed {} entries ({})", stats.files, format::HumanSize(stats.bytes));
+                    }
+                }
+            }
+        }
+        cli::Command::Diff { old, new } => {
+            let old_report = json_report::JsonReport::load(old)?;
+            let new_report = json_report::JsonReport::load(new)?;
+            let run_diff = diff::diff_reports(&old_report, &new_report);
+            print!("{}", diff::format_diff(&run_diff, &old_report, &new_report));
+            if !run_diff.newly_regressed.is_empty() {
+                std::process::exit(-2);
+            }
+        }
+        cli::Command::History { dependent, limit } => {
+            let db = history::ResultsDb::open(&copter_paths.results_db)
+                .map_err(Error::HistoryError)?;
+            match dependent {
+                Some(name) => {
+                    let entries = db.dependent_history(name, args.crate_name.first().map(String::as_str), *limit).map_err(Error::HistoryError)?;
+                    print!("{}", history::format_dependent_history(name, &entries));
+                }
+                None => {
+                    let runs = db.runs(args.crate_name.first().map(String::as_str), *limit).map_err(Error::HistoryError)?;
+                    print!("{}", history::format_runs(&runs));
+                }
+            }
+        }
+        cli::Command::Init { top, workflow, force } => {
+            init_crate(args, *top, *workflow, *force)?;
+        }
+        cli::Command::Paths => {
+            print!("{}", copter_paths.format());
+        }
+    }
+    Ok(())
+}
+
+/// `init`: suggest settings for the crate at --path (or here) and check the environment
+fn init_crate(args: &cli::CliArgs, top: usize, workflow: bool, force: bool) -> Result<(), Error> {
+    let manifest = manifest_path(args);
+    let (crate_name, _) = get_crate_info(&manifest)?;
+    let crate_dir = match manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    status(&format!("Looking up the top {} dependents of {}", top, crate_name));
+    let lookup = api::get_top_dependents(&crate_name, top, &api::DependentFilter::default());
+    let dependents = lookup.clone().unwrap_or_default();
+    let suggestion = init::Suggestion::new(&crate_name, dependents, num_cpus::get());
+
+    let mut files = vec![(crate_dir.join(config_file::DEFAULT_CONFIG_FILE), suggestion.copter_toml())];
+    if workflow {
+        let root = init::repo_root(&crate_dir).unwrap_or_else(|| crate_dir.clone());
+        let crate_dir_in_repo = crate_dir.canonicalize().ok()
+            .and_then(|dir| dir.strip_prefix(&root).ok().map(Path::to_path_buf))
+            .unwrap_or_default();
+        files.push((root.join(init::WORKFLOW_FILE), suggestion.workflow(&crate_dir_in_repo)));
+    }
+    for (path, content) in files {
+        if init::write_file(&path, &content, force).map_err(Error::InitError)? {
+            println!("Wrote {}", path.display());
+        } else {
+            println!("Kept existing {} (--force replaces it)", path.display());
+        }
+    }
+    println!("\nSuggested run:\n  {}\n", suggestion.command_line());
+
+    let checks = init::check_environment(&crate_dir, lookup.map(|d| d.len()));
+    print!("{}", init::format_checks(&checks));
+    let failed = checks.iter().filter(|c| !c.ok && !c.optional).count();
+    if failed > 0 {
+        return Err(Error::InitError(format!("{} environment check(s) failed", failed)));
+    }
+    Ok(())
+}
+
 /// Parse dependent spec in "name" or "name:version" format
 fn parse_dependent_spec(spec: &str) -> (String, Option<String>) {
     match spec.split_once(':') {
@@ -107,7 +349,7 @@ fn print_test_plan(
                 (v.clone(), force_versions.contains(v))
             }
             compile::VersionSource::Local(_) => {
-                ("this".to_string(), force_local)
+                (config.this_label(), force_local)
             }
         };
 
@@ -126,11 +368,20 @@ fn print_test_plan(
 
     // Print compact plan
     println!("\nTest Plan:");
-    println!("  {} × {} = {} tests",
-        rev_deps.len(),
-        versions_display.len(),
-        rev_deps.len() * versions_display.len()
-    );
+    if config.channels.is_empty() {
+        println!("  {} × {} = {} tests",
+            rev_deps.len(),
+            versions_display.len(),
+            rev_deps.len() * versions_display.len()
+        );
+    } else {
+        println!("  {} × {} × {} channels = {} tests",
+            rev_deps.len(),
+            versions_display.len(),
+            config.channels.len(),
+            rev_deps.len() * versions_display.len() * config.channels.len()
+        );
+    }
 
     // Show dependents (compact, comma-separated, max 80 chars per line)
     print!("  Dependents: ");
@@ -158,16 +409,101 @@ fn print_test_plan(
 
     // Show versions (compact, comma-separated)
     println!("  Versions: {}", versions_display.join(", "));
+    if !config.channels.is_empty() {
+        println!("  Channels: {}", config.channels.join(", "));
+    }
+    if !config.companions.is_empty() {
+        let names: Vec<&str> = config.companions.iter().map(|(name, _)| name.as_str()).collect();
+        println!("  Patched with {}: {}", config.this_label(), names.join(", "));
+    }
     println!();
 }
 
-fn run(args: cli::CliArgs, config: Config) -> Result<Vec<TestResult>, Error> {
-    // Initialize failure log
+/// How dependents are built and tested, from the command line
+fn run_settings(args: &cli::CliArgs) -> Result<compile::RunSettings, Error> {
     let log_path = std::env::current_dir()
         .unwrap_or_else(|_| PathBuf::from("."))
-        .join("copter-failures.log");
-    compile::init_failure_log(log_path.clone());
-    debug!("Failure log initialized at: {:?}", log_path);
+        .join(args.failure_log_format.file_name());
+    debug!("Failure log at: {:?}", log_path);
+
+    // Share compiled dependencies across dependents
+    let rustc_wrapper = if args.sccache {
+        let available = Command::new("sccache").arg("--version").output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        if !available {
+            return Err(Error::ProcessError("--sccache requires sccache on PATH".to_string()));
+        }
+        Some("sccache".to_string())
+    } else {
+        None
+    };
+    let target_dir = match args.shared_target_dir {
+        Some(ref dir) => {
+            fs::create_dir_all(dir)?;
+            Some(fs::canonicalize(dir)?)
+        }
+        None => None,
+    };
+
+    Ok(compile::RunSettings {
+        failure_log: Some((log_path, args.failure_log_format)),
+        build_cache: compile::BuildCache { target_dir, rustc_wrapper },
+        cargo_env: compile::CargoEnv {
+            vars: args.env.clone(),
+            rustflags: args.rustflags.clone(),
+            deny_warnings: args.deny_warnings,
+        },
+        step_timeouts: compile::StepTimeouts {
+            fetch: args.fetch_timeout,
+            check: args.check_timeout,
+            test: args.test_timeout,
+        },
+        resource_limits: compile::ResourceLimits {
+            memory_bytes: args.max_memory,
+            cpu_time: args.max_cpu_time,
+        },
+        test_retries: args.test_retries,
+        lockfile_mode: args.lockfile,
+        minimal_versions: args.minimal_versions,
+        optional_steps: compile::OptionalSteps {
+            full_build: args.full_build,
+            install: args.install,
+            clippy: args.clippy,
+            doc: args.doc,
+            doctest: args.doctest,
+        },
+        budget: Arc::new(budget::Budget::new(budget::RunLimits {
+            cpu_hours: args.max_total_cpu_hours,
+            network_mb: args.max_network_mb,
+            processes: args.max_processes,
+            regressions: if args.fail_fast { Some(1) } else { args.max_regressions },
+            duration: args.max_duration,
+            grace_period: args.grace_period,
+        })),
+    })
+}
+
+fn run(args: cli::CliArgs, mut config: Config) -> Result<RunResults, Error> {
+    if args.minimal_versions {
+        let available = Command::new("cargo").args(["+nightly", "--version"]).output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        if !available {
+            return Err(Error::ProcessError("--minimal-versions requires a nightly toolchain (rustup toolchain install nightly)".to_string()));
+        }
+    }
+    for channel in &config.channels {
+        let available = Command::new("cargo").arg(format!("+{}", channel)).arg("--version").output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        if !available {
+            return Err(Error::ProcessError(format!("--channels {} requires that toolchain (rustup toolchain install {})", channel, channel)));
+        }
+    }
+    if !config.channels.is_empty() && args.strategy == schedule::Strategy::QuickThenDeep {
+        return Err(Error::ProcessError("--channels can't be combined with --strategy quick-then-deep".to_string()));
+    }
 
     // Phase 5: Check if we're doing multi-version testing
     let use_multi_version = !args.test_versions.is_empty() || !args.force_versions.is_empty();
@@ -332,125 +668,315 @@ fn run(args: cli::CliArgs, config: Config) -> Result<Vec<TestResult>, Error> {
         None
     };
 
+    // Always use multi-version testing (legacy path removed)
+    // If --test-versions not specified, build vec with just "this" - baseline will be auto-inferred
+    let versions = test_versions.clone().unwrap_or_else(|| {
+        let mut versions = Vec::new();
+        // Add "this" (local WIP) or "latest" if no local version
+        if let CrateOverride::Source(ref manifest_path) = config.next_override {
+            versions.push(compile::VersionSource::Local(manifest_path.clone()));
+        } else {
+            // No local version (only --crate), add "latest" as final version
+            if let Ok(ver) = resolve_latest_version(&config.crate_name, false) {
+                versions.push(compile::VersionSource::Published(ver));
+            }
+        }
+        versions
+    });
+
+    // Workers test what the coordinator hands out; it picks the dependents and reports
+    if let Some(ref url) = args.worker {
+        run_worker(url, &config, &versions, force_local, args.jobs)?;
+        return Ok(RunResults::default());
+    }
+
     // Determine which dependents to test (returns Vec<(name, optional_version)>)
-    let rev_deps: Vec<(RevDepName, Option<String>)> = if !args.dependent_paths.is_empty() {
-        // Local paths mode - convert to rev dep names (no version spec)
-        args.dependent_paths
-            .iter()
-            .map(|p| {
-                p.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| (s.to_string(), None))
-                    .ok_or_else(|| Error::InvalidPath(p.clone()))
-            })
-            .collect::<Result<Vec<_>, _>>()?
-    } else if !args.dependents.is_empty() {
-        // Explicit crate names from crates.io (parse name:version syntax)
+    let mut rev_deps: Vec<(RevDepName, Option<String>)> = if !config.dependent_paths.is_empty() {
+        // Local paths mode - package names from their manifests (no version spec)
+        config.dependent_paths.iter().map(|(name, _)| (name.clone(), None)).collect()
+    } else if !args.dependents.is_empty() || !args.dependents_git.is_empty() {
+        // Explicit crate names from crates.io (parse name:version syntax),
+        // then those cloned from git (resolved from their checkout)
+        let git_names = args.parse_dependents_git().map_err(Error::ProcessError)?;
         args.dependents.iter()
             .map(|spec| parse_dependent_spec(spec))
+            .chain(git_names.into_iter().map(|(name, _)| (name, None)))
             .collect()
     } else {
         // Top N by downloads (no version spec)
-        let api_deps = api::get_top_dependents(&config.crate_name, args.top_dependents)
-            .map_err(|e| Error::CratesIoApiError(e))?;
+        let filter = api::DependentFilter {
+            min_downloads: args.min_downloads,
+            updated_within_days: args.updated_within,
+        };
+        let api_deps = api::get_top_dependents(&config.crate_name, args.top_dependents, &filter)
+            .map_err(Error::CratesIoApiError)?;
         api_deps.into_iter().map(|d| (d.name, None)).collect()
     };
 
+    // Unpublished dependents from GitHub, tested from their repositories
+    if args.github_dependents > 0 {
+        let token = env::var("GITHUB_TOKEN").map_err(|_| Error::ProcessError(
+            "--github-dependents requires GITHUB_TOKEN (GitHub code search needs authentication)".to_string()))?;
+        let found = api::search_github_dependents(&config.crate_name, args.github_dependents, &token)
+            .map_err(Error::ProcessError)?;
+        status(&format!("Found {} dependents of {} on GitHub", found.len(), config.crate_name));
+        for dependent in found {
+            if rev_deps.iter().any(|(name, _)| *name == dependent.name) {
+                continue;
+            }
+            config.dependents_git.entry(dependent.name.clone()).or_insert(dependent.repo_url);
+            rev_deps.push((dependent.name, None));
+        }
+    }
+
+    // How actively each dependent is maintained, to annotate rows and skip inactive ones
+    let maintenance = match args.maintenance || args.only_active {
+        true => lookup_maintenance(&rev_deps, &config),
+        false => HashMap::new(),
+    };
+    if args.only_active {
+        let inactive: Vec<String> = rev_deps.iter()
+            .filter_map(|(name, _)| maintenance.get(name).filter(|m| **m != maintenance::Maintenance::Active).map(|m| format!("{} ({})", name, m)))
+            .collect();
+        if !inactive.is_empty() {
+            status(&format!("Skipping {} inactive dependents (--only-active): {}", inactive.len(), inactive.join(", ")));
+            rev_deps.retain(|(name, _)| maintenance.get(name).is_none_or(|m| *m == maintenance::Maintenance::Active));
+        }
+    }
+
     // Run all the tests in a thread pool and create a list of result
     // receivers.
     let mut result_rxs = Vec::new();
-    let ref mut pool = ThreadPool::new(args.jobs);
-
-    // Build version list for display (same logic as per-dependent)
-    let versions_to_test = test_versions.clone().unwrap_or_else(|| {
-        let mut versions = Vec::new();
-        if let CrateOverride::Source(ref manifest_path) = config.next_override {
-            versions.push(compile::VersionSource::Local(manifest_path.clone()));
-        } else {
-            if let Ok(ver) = resolve_latest_version(&config.crate_name, false) {
-                versions.push(compile::VersionSource::Published(ver));
-            }
-        }
-        versions
-    });
+    let pool = &mut ThreadPool::new(args.jobs);
 
     // Print test plan
-    print_test_plan(&rev_deps, &versions_to_test, &config.force_versions, force_local, &config);
-
-    for (rev_dep, version) in rev_deps {
-        // Always use multi-version testing (legacy path removed)
-        // If --test-versions not specified, build vec with just "this" - baseline will be auto-inferred
-        let versions = test_versions.clone().unwrap_or_else(|| {
-            let mut versions = Vec::new();
-            // Add "this" (local WIP) or "latest" if no local version
-            if let CrateOverride::Source(ref manifest_path) = config.next_override {
-                versions.push(compile::VersionSource::Local(manifest_path.clone()));
-            } else {
-                // No local version (only --crate), add "latest" as final version
-                if let Ok(ver) = resolve_latest_version(&config.crate_name, false) {
-                    versions.push(compile::VersionSource::Published(ver));
-                }
-            }
-            versions
+    print_test_plan(&rev_deps, &versions, &config.force_versions, force_local, &config);
+
+    // A coordinator leaves the downloads to its workers
+    if args.offline {
+        check_offline_cache(&rev_deps, &versions, &config)?;
+    } else if args.download_jobs > 0 && config.dependent_paths.is_empty() && args.serve.is_none() {
+        prefetch_crates(&rev_deps, &versions, &config, args.download_jobs);
+    }
+
+    // Start the dependents that took longest last time first; results are
+    // still shown in the requested order
+    let estimates = history::ResultsDb::open(&config.paths.results_db)
+        .and_then(|db| db.estimated_durations(&config.crate_name))
+        .unwrap_or_else(|e| {
+            debug!("No duration estimates from the results database: {}", e);
+            HashMap::new()
         });
+    let names: Vec<RevDepName> = rev_deps.iter().map(|(name, _)| name.clone()).collect();
+    let mut slots: Vec<Vec<TestResultReceiver>> = rev_deps.iter().map(|_| Vec::new()).collect();
+
+    // Versions known when testing starts, to notice releases made during the run
+    let known_versions = api::list_versions(&config.crate_name).ok();
+
+    let coordinator = match args.serve {
+        Some(ref addr) => Some(distributed::Coordinator::serve(addr, Arc::clone(&config.settings.budget)).map_err(Error::ProcessError)?),
+        None => None,
+    };
 
-        let result = run_test_multi_version(pool, config.clone(), rev_dep, version, versions, force_local);
-        result_rxs.push(result);
+    let run_started = Instant::now();
+    let order = schedule::lpt_order(&names, &estimates);
+    let mut quick_results: Vec<Option<TestResult>> = match args.strategy {
+        schedule::Strategy::QuickThenDeep => quick_scan(pool, &config, &rev_deps, &order, &versions, force_local, args.jobs),
+        schedule::Strategy::Full => rev_deps.iter().map(|_| None).collect(),
+    };
+    // One job per channel (--channels), each against its own baseline
+    let channels: Vec<Option<String>> = match config.channels.is_empty() {
+        true => vec![None],
+        false => config.channels.iter().cloned().map(Some).collect(),
+    };
+    for idx in order {
+        let (rev_dep, version) = rev_deps[idx].clone();
+        // Dependents the quick scan found nothing new in keep their check-only result
+        slots[idx] = match quick_results[idx].take() {
+            // Nor are they retested once the run has stopped early
+            Some(quick) if !quick.check_changed() || config.settings.budget.exhausted().is_some() => {
+                hooks::after_dependent(&config.file_config.hooks, &config.crate_name, &quick, &quick.rev_dep.source_path(&config.paths.staging));
+                vec![ready_result(quick)]
+            }
+            _ => match coordinator {
+                Some(ref coordinator) => {
+                    let git = config.dependents_git.get(&rev_dep).cloned();
+                    vec![coordinator.submit(rev_dep, version, git)]
+                }
+                None => channels.iter()
+                    .map(|channel| run_test_multi_version(pool, config.clone(), rev_dep.clone(), version.clone(), channel.clone(), versions.clone(), force_local))
+                    .collect(),
+            },
+        };
     }
+    result_rxs.extend(slots.into_iter().flatten());
 
     // Print table header for streaming output
     let total = result_rxs.len();
     report::print_table_header(&config.crate_name, &config.display_version(), total);
+    progress::start(total, args.jobs);
+
+    // Compress per-dependent artifacts on a background thread as results arrive
+    let artifact_writer = match args.artifacts_dir {
+        Some(_) => match artifacts::ArtifactWriter::new(&config.paths.artifacts) {
+            Ok(writer) => Some(writer),
+            Err(e) => {
+                eprintln!("Warning: Failed to create artifacts directory {}: {}", config.paths.artifacts.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let mut sarif_builder = args.sarif.as_ref().map(|_| sarif::SarifBuilder::new());
+
+    if let Some(ref url) = args.notify_url {
+        let report = report::CiProvider::detect()
+            .and_then(|ci| ci.run_url())
+            .unwrap_or_else(|| std::path::absolute(&args.output).unwrap_or_else(|_| args.output.clone()).display().to_string());
+        sink::register_report_sink(Box::new(notify::WebhookSink::new(url, &config.crate_name, &config.display_version(), &report)));
+    }
+    if let Some(ref path) = args.junit {
+        sink::register_report_sink(Box::new(test_reports::JunitSink::new(path, &config.crate_name, &config.display_version())));
+    }
+    if args.teamcity || test_reports::in_teamcity() {
+        sink::register_report_sink(Box::new(test_reports::TeamcitySink::new(&config.crate_name, &config.display_version())));
+    }
 
     // Stream results as they arrive
+    let this_label = config.this_label();
     let mut all_rows = Vec::new();
+    let mut infra_warnings = Vec::new();
+    let mut job_times = Vec::new();
+    let mut errors = 0;  // Dependents that couldn't be tested at all
+    let mut regressed = Vec::new();  // Dependents for --bisect, with the target they were checked for
     for (i, result_rx) in result_rxs.into_iter().enumerate() {
         let result = result_rx.recv();
+        job_times.push(result.wall_time);
+        if matches!(result.data, TestResultData::Error(_)) {
+            errors += 1;
+        }
+        for warning in &result.warnings {
+            infra_warnings.push(format!("{} {}: {}", result.rev_dep.name, result.rev_dep.vers, warning));
+        }
 
-        // Status line removed - redundant with table output
-        // report_quick_result(i + 1, total, &result);
+        step_logs::write(&config.paths.logs, &result);
+        if let Some(ref writer) = artifact_writer {
+            writer.submit(&result, &this_label, &config.crate_name);
+        }
+        if let Some(ref mut builder) = sarif_builder {
+            builder.add_test_result(&result, &this_label);
+        }
 
-        // Convert to OfferedRows and stream print
-        let rows = result.to_offered_rows();
-        for (j, row) in rows.iter().enumerate() {
-            let is_last_in_group = j == rows.len() - 1;
-            report::print_offered_row(row, is_last_in_group);
+        // Convert to OfferedRows and stream print, collapsible per dependent in CI logs
+        let group_title = format!("{} {}", result.rev_dep.name, result.rev_dep.vers);
+        let mut rows = result.to_offered_rows(&config);
+        for row in &mut rows {
+            row.primary.maintenance = maintenance.get(&result.rev_dep.name).copied();
         }
+        progress::suspend(|| {
+            report::print_group_start(&group_title);
+            for (j, row) in rows.iter().enumerate() {
+                let is_last_in_group = j == rows.len() - 1;
+                report::print_offered_row(row, is_last_in_group);
+                sink::write_row(row);
+            }
+            report::print_group_end(&group_title);
 
-        // Print separator after each dependent
-        if i < total - 1 {
-            report::print_separator_line();
+            // Print separator after each dependent
+            if i < total - 1 {
+                report::print_separator_line();
+            }
+        });
+        if let Some(row) = rows.iter().find(|r| report::row_status(r) == json_report::RowStatus::Regressed) {
+            regressed.push((result.rev_dep.clone(), row.primary.cross_target.clone()));
         }
 
         all_rows.extend(rows);
     }
 
     // Print table footer
+    progress::stop();
     report::print_table_footer();
+    let makespan = run_started.elapsed();
 
-    // Print summary
-    let summary = report::summarize_offered_rows(&all_rows);
-    report::print_summary(&summary);
+    if args.jobs > 1 {
+        print!("{}", schedule::ScheduleStats::new(args.jobs, makespan, &job_times).format());
+    }
+    if args.max_total_cpu_hours.is_some() || args.max_network_mb.is_some() || args.max_processes.is_some() {
+        print!("{}", config.settings.budget.usage().format());
+    }
 
-    // Generate markdown report
-    let markdown_path = PathBuf::from("copter-report.md");
-    match report::export_markdown_table_report(&all_rows, &markdown_path, &config.crate_name, &config.display_version(), total) {
-        Ok(_) => {
-            println!("Markdown report: {}", markdown_path.display());
+    if !infra_warnings.is_empty() {
+        println!("\nInfrastructure warnings:");
+        for warning in &infra_warnings {
+            println!("  {}", warning);
         }
-        Err(e) => {
-            eprintln!("Warning: Failed to generate markdown report: {}", e);
+    }
+
+    if let Some(ref range) = args.bisect {
+        bisect_regressions(&config, range, &regressed);
+    }
+    let api_changes = args.api_diff.then(|| attribute_regressions(&mut all_rows, &config));
+
+    // A release during the run changes what `latest` and fresh baselines resolve to
+    let published_during_run = match known_versions.map(|known| (known, api::refresh_versions(&config.crate_name))) {
+        Some((known, Ok(current))) => newly_published(&known, &current),
+        Some((_, Err(e))) => {
+            debug!("Failed to recheck published versions: {}", e);
+            Vec::new()
         }
+        None => Vec::new(),  // Unpublished crate, or crates.io was unreachable at the start
+    };
+    let mut notes = Vec::new();
+    if let Some(limit) = config.settings.budget.exhausted() {
+        let note = format!("run stopped early: {}; {} dependents not run", limit, config.settings.budget.skipped());
+        println!("\nNote: {}", note);
+        notes.push(note);
+    }
+    if !published_during_run.is_empty() {
+        let note = format!("{} {} was published during this run; re-runs may resolve different baselines and `latest`",
+            config.crate_name, published_during_run.join(", "));
+        println!("\nNote: {}", note);
+        notes.push(note);
     }
 
-    // Exit with error code if there were regressions
-    if summary.regressed > 0 {
-        std::process::exit(-2);
+    if let Some(writer) = artifact_writer {
+        match writer.finish(&config.crate_name, &config.display_version()) {
+            Ok(index_path) => println!("Artifacts index: {}", index_path.display()),
+            Err(e) => eprintln!("Warning: Failed to write artifacts index: {}", e),
+        }
     }
 
-    // For now, still return TestResults for compatibility
-    // TODO: Eventually remove this and just work with OfferedRows
-    Ok(vec![])
+    // Generate SARIF for code-scanning uploads
+    if let (Some(builder), Some(sarif_path)) = (sarif_builder, args.sarif.as_ref()) {
+        match builder.write(sarif_path, &config.paths.staging) {
+            Ok(_) => println!("SARIF report: {}", sarif_path.display()),
+            Err(e) => eprintln!("Warning: Failed to generate SARIF report: {}", e),
+        }
+    }
+
+    Ok(RunResults {
+        rows: all_rows,
+        total,
+        notes,
+        published_during_run,
+        infra_warnings: infra_warnings.len(),
+        errors,
+        api_changes,
+    })
+}
+
+/// What a finished run reports; the console table was already streamed
+#[derive(Default)]
+struct RunResults {
+    rows: Vec<OfferedRow>,
+    total: usize,  // Dependents tested
+    notes: Vec<String>,  // Caveats repeated in the reports
+    published_during_run: Vec<String>,
+    infra_warnings: usize,  // Fatal with --strict
+    errors: usize,  // Dependents that errored before they could be tested
+    api_changes: Option<Vec<api_diff::ApiChange>>,  // Public API diff, with --api-diff
 }
 
 #[derive(Clone)]
@@ -459,14 +985,72 @@ struct Config {
     version: String,
     git_hash: Option<String>,
     is_dirty: bool,
-    staging_dir: PathBuf,
-    base_override: CrateOverride,
+    paths: paths::CopterPaths,  // Staging dir, caches, results database
     next_override: CrateOverride,
-    limit: Option<usize>,
     force_versions: Vec<String>,  // List of versions to force (bypass semver)
+    file_config: config_file::CopterConfig,  // Settings from copter.toml
+    dependent_features: HashMap<String, Vec<String>>,  // From --dependent-features
+    dependents_git: HashMap<String, String>,  // From --dependents-git
+    dependent_paths: Vec<(String, PathBuf)>,  // From --dependent-paths, by package name
+    companions: Vec<(String, PathBuf)>,  // Further --path crates, patched along with "this"
+    miri: Vec<String>,  // From --miri: dependents to also test under Miri
+    dependent_prerelease: api::PrereleasePolicy,  // Which versions of a dependent count as its newest
+    min_edition: Option<String>,  // Skip dependents on older editions
+    targets: Vec<String>,  // From --targets, for dependents needing another OS
+    channels: Vec<String>,  // From --channels: toolchains each dependent is tested with
+    no_cache: bool,  // --no-cache: always rebuild baselines
+    skip_check: bool,  // --no-check
+    skip_test: bool,  // --no-test, or the quick scan of --strategy quick-then-deep
+    quick_scan: bool,  // The quick scan's results may still be retested; hooks wait for the final one
+    settings: compile::RunSettings,  // How each dependent is built and tested
+    slowdown_threshold: f64,  // --slowdown-threshold
 }
 
 impl Config {
+    /// Settings for a dependent: copter.toml, with --dependent-features and
+    /// --dependents-git taking precedence
+    fn dependent_config(&self, name: &str) -> Option<config_file::DependentConfig> {
+        let mut dependent = self.file_config.dependent(name).cloned();
+        if let Some(features) = self.dependent_features.get(name) {
+            dependent.get_or_insert_with(Default::default).features = features.clone();
+        }
+        if let Some(git) = self.dependents_git.get(name) {
+            dependent.get_or_insert_with(Default::default).git = Some(git.clone());
+        }
+        if self.miri.iter().any(|n| n == name) {
+            dependent.get_or_insert_with(Default::default).miri = true;
+        }
+        dependent
+    }
+
+    /// Where dependents tested with `channel` are unpacked; each channel patches
+    /// and builds its own copies, so they can be tested at the same time
+    fn staging_for(&self, channel: Option<&str>) -> PathBuf {
+        match channel {
+            Some(channel) => self.paths.staging.join(format!("channel-{}", channel)),
+            None => self.paths.staging.clone(),
+        }
+    }
+
+    /// Label for the local work-in-progress offer, e.g. `this(0.8.91+g1a2b3c4.dirty)`
+    ///
+    /// The git hash and dirty flag go in semver build metadata so reports from
+    /// different checkouts of the same version can be told apart.
+    fn this_label(&self) -> String {
+        let mut build = Vec::new();
+        if let Some(ref hash) = self.git_hash {
+            build.push(format!("g{}", hash));
+        }
+        if self.is_dirty {
+            build.push("dirty".to_string());
+        }
+        if build.is_empty() {
+            format!("this({})", self.version)
+        } else {
+            format!("this({}+{})", self.version, build.join("."))
+        }
+    }
+
     /// Get formatted version string for display
     /// Examples: "1.0.0 abc123f*", "1.0.0 abc123f", "1.0.0*", "1.0.0"
     fn display_version(&self) -> String {
@@ -488,7 +1072,7 @@ enum CrateOverride {
 /// Get short git hash (7 chars) if in a git repository
 fn get_git_hash() -> Option<String> {
     Command::new("git")
-        .args(&["rev-parse", "--short", "HEAD"])
+        .args(["rev-parse", "--short", "HEAD"])
         .output()
         .ok()
         .filter(|output| output.status.success())
@@ -499,7 +1083,7 @@ fn get_git_hash() -> Option<String> {
 /// Check if git working directory is dirty (has uncommitted changes)
 fn is_git_dirty() -> bool {
     Command::new("git")
-        .args(&["status", "--porcelain"])
+        .args(["status", "--porcelain"])
         .output()
         .ok()
         .filter(|output| output.status.success())
@@ -508,18 +1092,36 @@ fn is_git_dirty() -> bool {
         .unwrap_or(false)
 }
 
-fn get_config(args: &cli::CliArgs) -> Result<Config, Error> {
-    let limit = env::var("COPTER_LIMIT")
-        .ok()
-        .and_then(|s| s.parse::<usize>().ok());
+/// Maintenance status of the dependents tested from crates.io; those that
+/// can't be classified are left out
+fn lookup_maintenance(rev_deps: &[(RevDepName, Option<String>)], config: &Config) -> HashMap<RevDepName, maintenance::Maintenance> {
+    let mut statuses = HashMap::new();
+    if !config.dependent_paths.is_empty() {
+        return statuses;
+    }
+    let token = env::var("GITHUB_TOKEN").ok();
+    let now = chrono::Utc::now();
+    for (name, _) in rev_deps.iter().filter(|(name, _)| !config.dependents_git.contains_key(name)) {
+        match api::crate_activity(name, token.as_deref()) {
+            Ok(activity) => {
+                if let Some(status) = maintenance::Maintenance::classify(&activity, now) {
+                    statuses.insert(name.clone(), status);
+                }
+            }
+            Err(e) => eprintln!("Warning: Maintenance status of {} unknown: {}", name, e),
+        }
+    }
+    statuses
+}
 
+fn get_config(args: &cli::CliArgs, paths: paths::CopterPaths) -> Result<Config, Error> {
     // Determine crate name and version based on --crate and --path
-    let (crate_name, version, next_override) = if let Some(ref crate_name) = args.crate_name {
+    let (crate_name, version, next_override) = if let Some(crate_name) = args.crate_name.first() {
         // --crate specified: use that name
         debug!("Using crate name from --crate: {}", crate_name);
 
         // Check if --path is also specified (for "this" version)
-        let (version, next_override) = if let Some(ref path) = args.path {
+        let (version, next_override) = if let Some(path) = args.path.first() {
             let manifest = if path.is_dir() {
                 path.join("Cargo.toml")
             } else {
@@ -561,16 +1163,7 @@ fn get_config(args: &cli::CliArgs) -> Result<Config, Error> {
         (crate_name.clone(), version, next_override)
     } else {
         // No --crate, use --path or ./Cargo.toml
-        let manifest = if let Some(ref path) = args.path {
-            if path.is_dir() {
-                path.join("Cargo.toml")
-            } else {
-                path.clone()
-            }
-        } else {
-            let env_manifest = env::var("COPTER_MANIFEST");
-            PathBuf::from(env_manifest.unwrap_or_else(|_| "./Cargo.toml".to_string()))
-        };
+        let manifest = manifest_path(args);
         debug!("Using manifest {:?}", manifest);
 
         let (crate_name, version) = get_crate_info(&manifest)?;
@@ -580,20 +1173,94 @@ fn get_config(args: &cli::CliArgs) -> Result<Config, Error> {
     // Get git information for display (only if we have a local source)
     let git_hash = get_git_hash();
     let is_dirty = git_hash.is_none() || is_git_dirty();
+    let dependent_paths = dependent_paths(args, &crate_name)?;
+    let companions = companion_crates(args, &crate_name)?;
 
     Ok(Config {
         crate_name,
         version,
         git_hash,
         is_dirty,
-        staging_dir: args.staging_dir.clone(),
-        base_override: CrateOverride::Default,
+        paths,
         next_override,
-        limit,
         force_versions: args.force_versions.clone(),
+        file_config: config_file::CopterConfig::load(args.config.as_deref())?,
+        dependent_features: args.parse_dependent_features().map_err(Error::ProcessError)?,
+        dependents_git: args.parse_dependents_git().map_err(Error::ProcessError)?.into_iter().collect(),
+        dependent_paths,
+        companions,
+        miri: args.miri.clone(),
+        dependent_prerelease: args.dependent_prerelease,
+        min_edition: args.min_edition.clone(),
+        targets: args.targets.clone(),
+        channels: args.channels.clone(),
+        no_cache: args.no_cache,
+        skip_check: args.no_check,
+        skip_test: args.no_test,
+        quick_scan: false,
+        settings: run_settings(args)?,
+        slowdown_threshold: args.slowdown_threshold,
     })
 }
 
+/// Crates of the workspace released with the one under test (the --path entries after
+/// the first), by package name, with their directories
+fn companion_crates(args: &cli::CliArgs, crate_name: &str) -> Result<Vec<(String, PathBuf)>, Error> {
+    let mut companions: Vec<(String, PathBuf)> = Vec::new();
+    for (name, path) in args.companion_crates() {
+        let manifest = if path.is_dir() { path.join("Cargo.toml") } else { path.to_path_buf() };
+        let (manifest_name, _) = get_crate_info(&manifest)?;
+        if let Some(name) = name.filter(|name| *name != manifest_name) {
+            return Err(Error::ProcessError(format!(
+                "Crate name mismatch: --crate specifies '{}' but {} contains '{}'",
+                name, manifest.display(), manifest_name
+            )));
+        }
+        if manifest_name == crate_name || companions.iter().any(|(known, _)| *known == manifest_name) {
+            return Err(Error::ProcessError(format!("{} is given twice with --path", manifest_name)));
+        }
+        let dir = manifest.parent().map(Path::to_path_buf).unwrap_or_default();
+        companions.push((manifest_name, dir));
+    }
+    Ok(companions)
+}
+
+/// Local dependents by package name: --dependent-paths, then those --workspace-dependents finds
+fn dependent_paths(args: &cli::CliArgs, crate_name: &str) -> Result<Vec<(String, PathBuf)>, Error> {
+    let mut paths: Vec<(String, PathBuf)> = args.dependent_paths.iter()
+        .map(|p| local_source::package_name(p).map(|name| (name, p.clone())).ok_or_else(|| Error::InvalidPath(p.clone())))
+        .collect::<Result<_, _>>()?;
+
+    for root in &args.workspace_dependents {
+        let found = local_source::discover(root, crate_name).map_err(Error::ProcessError)?;
+        status(&format!("Found {} dependents of {} in {}", found.len(), crate_name, root.display()));
+        for path in found {
+            let name = local_source::package_name(&path).ok_or_else(|| Error::InvalidPath(path.clone()))?;
+            if !paths.iter().any(|(known, _)| *known == name) {
+                paths.push((name, path));
+            }
+        }
+    }
+    if paths.is_empty() && !args.workspace_dependents.is_empty() {
+        return Err(Error::ProcessError(format!("No dependents of {} found under --workspace-dependents", crate_name)));
+    }
+    Ok(paths)
+}
+
+/// Manifest of the crate under test: --path (directory or Cargo.toml), COPTER_MANIFEST or ./Cargo.toml
+fn manifest_path(args: &cli::CliArgs) -> PathBuf {
+    if let Some(path) = args.path.first() {
+        if path.is_dir() {
+            path.join("Cargo.toml")
+        } else {
+            path.clone()
+        }
+    } else {
+        let env_manifest = env::var("COPTER_MANIFEST");
+        PathBuf::from(env_manifest.unwrap_or_else(|_| "./Cargo.toml".to_string()))
+    }
+}
+
 fn get_crate_info(manifest_path: &Path) -> Result<(String, String), Error> {
     let toml_str = load_string(manifest_path)?;
     let value: toml::Value = toml::from_str(&toml_str)?;
@@ -616,11 +1283,6 @@ fn get_crate_info(manifest_path: &Path) -> Result<(String, String), Error> {
     }
 }
 
-// Legacy function for compatibility
-fn get_crate_name(manifest_path: &Path) -> Result<String, Error> {
-    get_crate_info(manifest_path).map(|(name, _)| name)
-}
-
 fn load_string(path: &Path) -> Result<String, Error> {
     let mut file = File::open(path)?;
     let mut s = String::new();
@@ -630,75 +1292,41 @@ fn load_string(path: &Path) -> Result<String, Error> {
 
 type RevDepName = String;
 
-fn crate_url(krate: &str, call: Option<&str>) -> String {
-    crate_url_with_parms(krate, call, &[])
-}
-
-fn crate_url_with_parms(krate: &str, call: Option<&str>, parms: &[(&str, &str)]) -> String {
-    let url = format!("https://crates.io/api/v1/crates/{}", krate);
-    let s = match call {
-        Some(c) => format!("{}/{}", url, c),
-        None => url
-    };
-
-    if !parms.is_empty() {
-        let parms: Vec<String> = parms.iter().map(|&(k, v)| format!("{}={}", k, v)).collect();
-        let parms: String = parms.join("&");
-        format!("{}?{}", s, parms)
-    } else {
-        s
-    }
-}
-
-fn get_rev_deps(crate_name: &str, limit: Option<usize>) -> Result<Vec<RevDepName>, Error> {
-    status(&format!("downloading reverse deps for {}", crate_name));
-
-    let deps = CRATES_IO_CLIENT.crate_reverse_dependencies(crate_name)
-        .map_err(|e| Error::CratesIoApiError(e.to_string()))?;
-
-    let mut all_deps: Vec<String> = deps.dependencies
-        .into_iter()
-        .map(|d| d.dependency.crate_id)
-        .collect();
-
-    // Apply limit if specified
-    if let Some(lim) = limit {
-        all_deps.truncate(lim);
-    }
-
-    status(&format!("{} reverse deps", all_deps.len()));
-
-    Ok(all_deps)
-}
-
-fn http_get_bytes(url: &str) -> Result<Vec<u8>, Error> {
-    let resp = ureq::get(url)
-        .set("User-Agent", USER_AGENT)
-        .call()?;
-    let len = resp.header("Content-Length")
-        .and_then(|s| s.parse::<usize>().ok())
-        .unwrap_or(0);
-    let mut data: Vec<u8> = Vec::with_capacity(len);
-    resp.into_reader().read_to_end(&mut data)?;
-    Ok(data)
-}
-
 #[derive(Debug, Clone)]
 struct RevDep {
     name: RevDepName,
     vers: Version,
     resolved_version: Option<String>, // Exact version from dependent's Cargo.lock
+    manifest: Option<manifest::ManifestInfo>, // Edition/resolver, once unpacked
+    source_dir: Option<PathBuf>, // Local copy or git checkout to test instead of the published crate
+    channel: Option<String>, // Rust toolchain it was tested with (--channels); None for the default one
+}
+
+impl RevDep {
+    /// Directory the dependent is built in: its local copy or git checkout, else the unpacked .crate
+    fn source_path(&self, staging_dir: &Path) -> PathBuf {
+        match self.source_dir {
+            Some(ref dir) => dir.clone(),
+            None => staging_dir.join(format!("{}-{}", self.name, self.vers)),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct TestResult {
     rev_dep: RevDep,
-    data: TestResultData
+    data: TestResultData,
+    /// Problems that make the result less trustworthy (fatal with --strict)
+    warnings: Vec<String>,
+    /// Wall time of the job that produced this result
+    wall_time: Duration,
 }
 
 #[derive(Debug)]
 enum TestResultData {
     Skipped(String), // Skipped with reason (e.g., version incompatibility)
+    PlatformUnsupported(String), // Only builds on another OS; reason names it
+    NotRun(String), // Not started because a run limit was reached; reason names it
     Error(Error),
     // Phase 5: Multi-version result
     MultiVersion(Vec<VersionTestOutcome>),
@@ -719,8 +1347,9 @@ impl VersionTestOutcome {
         } else {
             // Failed - determine if REGRESSED or BROKEN
             if let Some(baseline) = baseline_outcome {
-                if baseline.result.is_success() {
-                    VersionStatus::Regressed
+                if baseline.result.is_success() && !self.result.fetch.network_failure() {
+                    let step = self.result.first_failure().map(|f| f.step).unwrap_or(compile::CompileStep::Fetch);
+                    VersionStatus::Regressed(step)
                 } else {
                     VersionStatus::Broken
                 }
@@ -736,7 +1365,9 @@ impl VersionTestOutcome {
 pub enum VersionStatus {
     Passed,
     Broken,
-    Regressed,
+    /// Baseline passed; the step the offered version failed at tells a
+    /// compile break (fetch/check) from a behavior change caught by tests
+    Regressed(compile::CompileStep),
 }
 
 // ============================================================================
@@ -760,6 +1391,57 @@ pub struct OfferedRow {
 
     /// Transitive dependencies using different versions (depth > 0)
     pub transitive: Vec<TransitiveTest>,
+
+    /// Cargo.lock packages that differ from the baseline run (empty for baseline rows)
+    pub lock_diff: Vec<lockfile::LockChange>,
+
+    /// Lints warned about more often than in the baseline run (empty for baseline rows)
+    pub warning_diff: Vec<warnings::WarningChange>,
+
+    /// Steps significantly slower than in the baseline run (empty for baseline rows)
+    pub slowdowns: Vec<build_times::Slowdown>,
+
+    /// Libraries and binaries that changed size since the baseline run (`--full-build`)
+    pub size_diff: Vec<sizes::SizeChange>,
+
+    /// Semver-incompatible versions of the base crate the offered version put in the
+    /// graph, where the baseline's had only one (empty for baseline rows)
+    pub duplicate_versions: Vec<String>,
+
+    /// The offered version needs a newer Rust than the dependent's rust-version (None for baseline rows)
+    pub msrv_conflict: Option<msrv::MsrvConflict>,
+
+    /// What the dependent's committed Cargo.lock changed (`--lockfile respect|both`)
+    pub lockfile_notes: Vec<String>,
+}
+
+#[cfg(test)]
+impl OfferedRow {
+    /// A row for `dependent` (resolving 0.8.50) with a single check step
+    pub fn for_test(dependent: &str, offered: Option<&str>, baseline_passed: Option<bool>, passed: bool) -> Self {
+        let mut primary = DependencyRef::for_test(dependent, "0.8.50");
+        primary.used_offered_version = offered.is_some();
+        OfferedRow {
+            baseline_passed,
+            primary,
+            offered: offered.map(|v| OfferedVersion { version: v.to_string(), forced: false }),
+            test: TestExecution {
+                commands: vec![TestCommand {
+                    command: CommandType::Check,
+                    features: vec![],
+                    result: CommandResult { passed, duration: 1.0, failures: vec![], flaky: false, exit: None, log: None },
+                }],
+            },
+            transitive: vec![],
+            lock_diff: vec![],
+            warning_diff: vec![],
+            slowdowns: vec![],
+            size_diff: vec![],
+            duplicate_versions: vec![],
+            msrv_conflict: None,
+            lockfile_notes: vec![],
+        }
+    }
 }
 
 /// Reference to a dependency (primary or transitive)
@@ -771,12 +1453,49 @@ pub struct DependencyRef {
     pub resolved_version: String,     // "0.8.91" (what cargo chose)
     pub resolved_source: VersionSource,  // CratesIo | Local | Git
     pub used_offered_version: bool,   // true if resolved == offered
+    pub edition: Option<String>,      // "2021" (from the dependent's manifest, if read)
+    pub resolver: Option<String>,     // "2"
+    pub targets: Option<String>,      // "lib", "bin" or "lib+bin" (target kinds checked/tested)
+    pub cross_target: Option<String>, // Triple checked instead of the host (--targets)
+    pub channel: Option<String>,      // "beta" (toolchain it was tested with, --channels)
+    pub maintenance: Option<maintenance::Maintenance>,  // Stale, abandoned, ... (--maintenance)
+    pub base_features: Vec<String>,   // ["default", "serde"] (base crate features enabled, after unification)
+}
+
+impl DependencyRef {
+    /// `image 0.25.8`, or `image 0.25.8 [beta]` when tested with one of --channels
+    pub fn dependent_label(&self) -> String {
+        match self.channel {
+            Some(ref channel) => format!("{} {} [{}]", self.dependent_name, self.dependent_version, channel),
+            None => format!("{} {}", self.dependent_name, self.dependent_version),
+        }
+    }
+
+    /// `name 0.25.8` requiring `^0.8` and resolving `resolved` from crates.io
+    #[cfg(test)]
+    pub fn for_test(name: &str, resolved: &str) -> Self {
+        DependencyRef {
+            dependent_name: name.to_string(),
+            dependent_version: "0.25.8".to_string(),
+            spec: "^0.8".to_string(),
+            resolved_version: resolved.to_string(),
+            resolved_source: VersionSource::CratesIo,
+            used_offered_version: false,
+            edition: None,
+            resolver: None,
+            targets: None,
+            cross_target: None,
+            channel: None,
+            maintenance: None,
+            base_features: vec![],
+        }
+    }
 }
 
 /// Version offered for testing
 #[derive(Debug, Clone)]
 pub struct OfferedVersion {
-    pub version: String,  // "this(0.8.91)" or "0.8.51"
+    pub version: String,  // "this(0.8.91+g1a2b3c4)" or "0.8.51"
     pub forced: bool,     // true shows [≠→!] suffix
 }
 
@@ -799,7 +1518,34 @@ pub struct TestCommand {
 pub enum CommandType {
     Fetch,
     Check,
+    Build,
+    Install,
+    Clippy,
+    Doc,
     Test,
+    Doctest,
+    Miri,
+}
+
+impl CommandType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandType::Fetch => "fetch",
+            CommandType::Check => "check",
+            CommandType::Build => "build",
+            CommandType::Install => "install",
+            CommandType::Clippy => "clippy",
+            CommandType::Doc => "doc",
+            CommandType::Test => "test",
+            CommandType::Doctest => "doctest",
+            CommandType::Miri => "miri",
+        }
+    }
+
+    /// Enabled by --clippy/--doc/--doctest/--miri; only compared against the baseline's result
+    pub fn is_optional(&self) -> bool {
+        matches!(self, CommandType::Clippy | CommandType::Doc | CommandType::Doctest | CommandType::Miri)
+    }
 }
 
 /// Result of executing a command
@@ -808,6 +1554,9 @@ pub struct CommandResult {
     pub passed: bool,
     pub duration: f64,
     pub failures: Vec<CrateFailure>,  // Which crate(s) failed
+    pub flaky: bool,  // Passed only on a retry (--test-retries)
+    pub exit: Option<compile::ProcessExit>,  // cargo's exit code or terminating signal, if known
+    pub log: Option<PathBuf>,  // Complete output of the step (see step_logs)
 }
 
 /// A crate that failed during testing
@@ -815,6 +1564,10 @@ pub struct CommandResult {
 pub struct CrateFailure {
     pub crate_name: String,
     pub error_message: String,
+    pub error_codes: Vec<String>,  // rustc error codes, one per error ("E0308")
+    pub error_patterns: Vec<error_extract::ErrorPattern>,  // Distinct errors, for grouping across dependents
+    pub failed_tests: Vec<String>,  // Names of the tests that failed, for test steps
+    pub likely_causes: Vec<String>,  // Base-crate items behind the errors ("removed `Rgb::new`"), with --api-diff
 }
 
 /// Transitive dependency test (depth > 0)
@@ -848,8 +1601,24 @@ impl TestResult {
     }
     */
 
+    fn edition(&self) -> Option<String> {
+        self.rev_dep.manifest.as_ref().map(|m| m.edition.clone())
+    }
+
+    fn resolver(&self) -> Option<String> {
+        self.rev_dep.manifest.as_ref().map(|m| m.resolver.clone())
+    }
+
+    fn targets(&self) -> Option<String> {
+        self.rev_dep.manifest.as_ref().map(|m| m.targets.label().to_string())
+    }
+
     /// Convert TestResult to OfferedRows for streaming output
-    fn to_offered_rows(&self) -> Vec<OfferedRow> {
+    ///
+    /// Local offers are labelled with `Config::this_label`, and the base crate
+    /// is listed first in Cargo.lock diffs.
+    fn to_offered_rows(&self, config: &Config) -> Vec<OfferedRow> {
+        let (this_label, crate_name) = (config.this_label(), config.crate_name.as_str());
         match &self.data {
             TestResultData::MultiVersion(outcomes) => {
                 let mut rows = Vec::new();
@@ -861,10 +1630,11 @@ impl TestResult {
                     let is_baseline = idx == 0;
 
                     // Determine baseline_passed for this row
+                    // A baseline that couldn't be fetched says nothing either way
                     let baseline_passed = if is_baseline {
                         None  // This IS the baseline
                     } else {
-                        baseline.map(|b| b.result.is_success())
+                        baseline.filter(|b| !b.result.fetch.network_failure()).map(|b| b.result.is_success())
                     };
 
                     // Convert compile::VersionSource to main::VersionSource
@@ -883,106 +1653,152 @@ impl TestResult {
                             .unwrap_or_else(|| "?".to_string()),
                         resolved_source,
                         used_offered_version: outcome.result.expected_version == outcome.result.actual_version,
+                        edition: self.edition(),
+                        resolver: self.resolver(),
+                        targets: self.targets(),
+                        cross_target: outcome.result.cross_target.clone(),
+                        channel: self.rev_dep.channel.clone(),
+                        maintenance: None,
+                        base_features: outcome.result.base_features.clone(),
                     };
 
                     // Build OfferedVersion (None for baseline)
                     let offered = if is_baseline {
                         None
                     } else {
+                        let version = match &outcome.version_source {
+                            compile::VersionSource::Local(_) => this_label.to_string(),
+                            compile::VersionSource::Published(v) => v.clone(),
+                        };
                         Some(OfferedVersion {
-                            version: outcome.version_source.label(),
+                            version,
                             forced: outcome.result.forced_version,
                         })
                     };
 
                     // Build TestExecution from ThreeStepResult
                     let mut commands = Vec::new();
+                    let offered_dir = step_logs::offered_dir(is_baseline, &outcome.version_source, outcome.result.cross_target.as_deref());
+                    let log = |step: compile::CompileStep| {
+                        Some(step_logs::path(&config.paths.logs, &self.rev_dep.name, &self.rev_dep.vers.to_string(), self.rev_dep.channel.as_deref(), &offered_dir, step))
+                    };
 
-                    // Fetch command
-                    commands.push(TestCommand {
-                        command: CommandType::Fetch,
-                        features: vec![],  // TODO: track features
+                    // One command's outcome; failures carry the diagnostics of the step
+                    let step_command = |command: CommandType, features: Vec<String>, result: &compile::CompileResult, flaky: bool| TestCommand {
+                        command,
+                        features,
                         result: CommandResult {
-                            passed: outcome.result.fetch.success,
-                            duration: outcome.result.fetch.duration.as_secs_f64(),
-                            failures: if !outcome.result.fetch.success {
-                                let error_msg = error_extract::extract_error_summary(&outcome.result.fetch.diagnostics);
-                                let error_msg = if !error_msg.is_empty() {
-                                    error_msg
-                                } else {
-                                    // Fallback to stderr if no error diagnostics
-                                    outcome.result.fetch.stderr.clone()
-                                };
+                            passed: result.success,
+                            duration: result.duration.as_secs_f64(),
+                            failures: if !result.success {
                                 vec![CrateFailure {
                                     crate_name: self.rev_dep.name.clone(),
-                                    error_message: error_msg,
+                                    error_message: failure_message(result),
+                                    error_codes: error_extract::error_codes(&result.diagnostics),
+                                    error_patterns: error_extract::error_patterns(&result.diagnostics),
+                                    failed_tests: failed_test_names(result),
+                                    likely_causes: vec![],
                                 }]
                             } else {
                                 vec![]
                             },
+                            flaky,
+                            exit: result.exit,
+                            log: log(result.step),
                         },
-                    });
+                    };
+
+                    // Fetch command (fetch doesn't take features)
+                    commands.push(step_command(CommandType::Fetch, vec![], &outcome.result.fetch, false));
 
                     // Check command (if ran)
                     if let Some(ref check) = outcome.result.check {
-                        commands.push(TestCommand {
-                            command: CommandType::Check,
-                            features: vec![],
-                            result: CommandResult {
-                                passed: check.success,
-                                duration: check.duration.as_secs_f64(),
-                                failures: if !check.success {
-                                    let error_msg = error_extract::extract_error_summary(&check.diagnostics);
-                                    let error_msg = if !error_msg.is_empty() {
-                                        error_msg
-                                    } else {
-                                        // Fallback to stderr if no error diagnostics
-                                        check.stderr.clone()
-                                    };
-                                    vec![CrateFailure {
-                                        crate_name: self.rev_dep.name.clone(),
-                                        error_message: error_msg,
-                                    }]
-                                } else {
-                                    vec![]
-                                },
-                            },
-                        });
+                        commands.push(step_command(CommandType::Check, outcome.result.features.clone(), check, false));
                     }
 
+                    // Full build and install build commands (--full-build, --install, if ran)
+                    let builds = [(CommandType::Build, &outcome.result.build), (CommandType::Install, &outcome.result.install)];
+                    for (command, build) in builds.into_iter().filter_map(|(c, b)| b.as_ref().map(|b| (c, b))) {
+                        commands.push(step_command(command, outcome.result.features.clone(), build, false));
+                    }
+
+                    // Optional steps (--clippy, --doc, --doctest, --miri): failures the baseline already
+                    // has aren't the offered version's, so those rows leave the step out
+                    let optional_command = |command: CommandType, step: fn(&compile::ThreeStepResult) -> Option<&compile::CompileResult>| {
+                        let baseline_clean = baseline.and_then(|b| step(&b.result)).is_none_or(|r| r.success);
+                        step(&outcome.result).filter(|_| is_baseline || baseline_clean)
+                            .map(|result| step_command(command, outcome.result.features.clone(), result, false))
+                    };
+                    commands.extend(optional_command(CommandType::Clippy, |r| r.clippy.as_ref()));
+                    commands.extend(optional_command(CommandType::Doc, |r| r.doc.as_ref()));
+
                     // Test command (if ran)
                     if let Some(ref test) = outcome.result.test {
-                        commands.push(TestCommand {
-                            command: CommandType::Test,
-                            features: vec![],
-                            result: CommandResult {
-                                passed: test.success,
-                                duration: test.duration.as_secs_f64(),
-                                failures: if !test.success {
-                                    let error_msg = error_extract::extract_error_summary(&test.diagnostics);
-                                    let error_msg = if !error_msg.is_empty() {
-                                        error_msg
-                                    } else {
-                                        // Fallback to stderr if no error diagnostics
-                                        test.stderr.clone()
-                                    };
-                                    vec![CrateFailure {
-                                        crate_name: self.rev_dep.name.clone(),
-                                        error_message: error_msg,
-                                    }]
-                                } else {
-                                    vec![]
-                                },
-                            },
-                        });
+                        commands.push(step_command(CommandType::Test, outcome.result.features.clone(), test, test.flaky));
                     }
+                    commands.extend(optional_command(CommandType::Doctest, |r| r.doctest.as_ref()));
+                    commands.extend(optional_command(CommandType::Miri, |r| r.miri.as_ref()));
 
                     rows.push(OfferedRow {
                         baseline_passed,
                         primary,
                         offered,
                         test: TestExecution { commands },
-                        transitive: vec![],  // TODO: extract from cargo tree
+                        transitive: outcome.result.intermediates.iter()
+                            .map(|i| TransitiveTest {
+                                dependency: DependencyRef {
+                                    dependent_name: i.name.clone(),
+                                    dependent_version: i.version.clone(),
+                                    spec: "?".to_string(),  // cargo tree doesn't show requirements
+                                    resolved_version: i.base_version.clone(),
+                                    resolved_source: if i.base_is_path { VersionSource::Local } else { VersionSource::CratesIo },
+                                    used_offered_version: false,
+                                    edition: None,
+                                    resolver: None,
+                                    targets: None,
+                                    base_features: vec![],
+                                    cross_target: None,
+                                    channel: None,
+                                    maintenance: None,
+                                },
+                                depth: i.depth,
+                            })
+                            .collect(),
+                        lock_diff: match baseline {
+                            Some(b) if !is_baseline => lockfile::diff(
+                                &b.result.lock_packages, &outcome.result.lock_packages, crate_name,
+                            ),
+                            _ => vec![],
+                        },
+                        warning_diff: match baseline {
+                            Some(b) if !is_baseline => warnings::diff(&b.result, &outcome.result),
+                            _ => vec![],
+                        },
+                        slowdowns: match baseline {
+                            Some(b) if !is_baseline => build_times::diff(&b.result, &outcome.result, config.slowdown_threshold),
+                            _ => vec![],
+                        },
+                        size_diff: match baseline {
+                            Some(b) if !is_baseline => sizes::diff(&b.result.artifact_sizes, &outcome.result.artifact_sizes),
+                            _ => vec![],
+                        },
+                        duplicate_versions: match baseline {
+                            Some(b) if !is_baseline && lockfile::incompatible_versions(&b.result.lock_packages, crate_name).is_empty() => {
+                                lockfile::incompatible_versions(&outcome.result.lock_packages, crate_name)
+                            }
+                            _ => vec![],
+                        },
+                        msrv_conflict: match baseline {
+                            Some(b) if !is_baseline => msrv::conflict(&b.result.rust_versions, &outcome.result.rust_versions),
+                            _ => None,
+                        },
+                        lockfile_notes: outcome.result.committed_lock.as_ref()
+                            .map(|lock| lock.notes(
+                                crate_name,
+                                outcome.result.expected_version.as_deref().or(outcome.result.actual_version.as_deref()),
+                                outcome.result.first_failure().map(|f| f.step),
+                            ))
+                            .unwrap_or_default(),
                     });
                 }
 
@@ -999,6 +1815,13 @@ impl TestResult {
                         resolved_version: "ERROR".to_string(),
                         resolved_source: VersionSource::CratesIo,
                         used_offered_version: false,
+                        edition: self.edition(),
+                        resolver: self.resolver(),
+                        targets: self.targets(),
+                        base_features: vec![],
+                        cross_target: None,
+                        channel: self.rev_dep.channel.clone(),
+                        maintenance: None,
                     },
                     offered: None,
                     test: TestExecution {
@@ -1011,28 +1834,61 @@ impl TestResult {
                                 failures: vec![CrateFailure {
                                     crate_name: self.rev_dep.name.clone(),
                                     error_message: msg.to_string(),
+                                    error_codes: vec![],
+                                    error_patterns: vec![],
+                                    failed_tests: vec![],
+                                    likely_causes: vec![],
                                 }],
+                                flaky: false,
+                                exit: None,
+                                log: None,
                             },
                         }],
                     },
                     transitive: vec![],
+                    lock_diff: vec![],
+                    warning_diff: vec![],
+                    slowdowns: vec![],
+                    size_diff: vec![],
+                    duplicate_versions: vec![],
+                    msrv_conflict: None,
+                    lockfile_notes: vec![],
                 }]
             }
-            TestResultData::Skipped(reason) => {
+            TestResultData::Skipped(reason) | TestResultData::PlatformUnsupported(reason) | TestResultData::NotRun(reason) => {
                 // Create a single row for skipped
+                let spec = match self.data {
+                    TestResultData::PlatformUnsupported(_) => "UNSUPPORTED",
+                    TestResultData::NotRun(_) => "NOT_RUN",
+                    _ => "SKIPPED",
+                };
                 vec![OfferedRow {
                     baseline_passed: None,
                     primary: DependencyRef {
                         dependent_name: self.rev_dep.name.clone(),
                         dependent_version: self.rev_dep.vers.to_string(),
-                        spec: "SKIPPED".to_string(),
+                        spec: spec.to_string(),
                         resolved_version: reason.clone(),
                         resolved_source: VersionSource::CratesIo,
                         used_offered_version: false,
+                        edition: self.edition(),
+                        resolver: self.resolver(),
+                        targets: self.targets(),
+                        base_features: vec![],
+                        cross_target: None,
+                        channel: self.rev_dep.channel.clone(),
+                        maintenance: None,
                     },
                     offered: None,
                     test: TestExecution { commands: vec![] },
                     transitive: vec![],
+                    lock_diff: vec![],
+                    warning_diff: vec![],
+                    slowdowns: vec![],
+                    size_diff: vec![],
+                    duplicate_versions: vec![],
+                    msrv_conflict: None,
+                    lockfile_notes: vec![],
                 }]
             }
         }
@@ -1044,28 +1900,68 @@ impl TestResult {
     fn skipped(rev_dep: RevDep, reason: String) -> TestResult {
         TestResult {
             rev_dep,
-            data: TestResultData::Skipped(reason)
+            data: TestResultData::Skipped(reason),
+            warnings: Vec::new(),
+            wall_time: Duration::ZERO,
+        }
+    }
+
+    fn platform_unsupported(rev_dep: RevDep, reason: String) -> TestResult {
+        TestResult {
+            rev_dep,
+            data: TestResultData::PlatformUnsupported(reason),
+            warnings: Vec::new(),
+            wall_time: Duration::ZERO,
+        }
+    }
+
+    fn not_run(rev_dep: RevDep, reason: String) -> TestResult {
+        TestResult {
+            rev_dep,
+            data: TestResultData::NotRun(reason),
+            warnings: Vec::new(),
+            wall_time: Duration::ZERO,
         }
     }
 
     fn error(rev_dep: RevDep, e: Error) -> TestResult {
         TestResult {
             rev_dep,
-            data: TestResultData::Error(e)
+            data: TestResultData::Error(e),
+            warnings: Vec::new(),
+            wall_time: Duration::ZERO,
+        }
+    }
+
+    /// Whether an offered version fetches and checks differently than the
+    /// baseline (`--strategy quick-then-deep` tests only these)
+    fn check_changed(&self) -> bool {
+        let TestResultData::MultiVersion(ref outcomes) = self.data else {
+            return false;
+        };
+        let checks = |o: &VersionTestOutcome| o.result.fetch.success && o.result.check.as_ref().is_none_or(|c| c.success);
+        match outcomes.split_first() {
+            Some((baseline, offered)) => offered.iter().any(|o| checks(o) != checks(baseline)),
+            None => false,
         }
     }
 
     fn quick_str(&self) -> &'static str {
         match self.data {
             TestResultData::Skipped(_) => "skipped",
+            TestResultData::PlatformUnsupported(_) => "unsupported",
+            TestResultData::NotRun(_) => "not_run",
             TestResultData::Error(_) => "error",
             TestResultData::MultiVersion(ref outcomes) => {
-                // For multi-version, return worst status
-                let has_regressed = outcomes.iter().any(|o| {
-                    matches!(o.classify(None), VersionStatus::Regressed)
+                // For multi-version, return worst status (first outcome is the baseline)
+                let baseline = outcomes.first();
+                let has_regressed = outcomes.iter().skip(1).any(|o| {
+                    matches!(o.classify(baseline), VersionStatus::Regressed(_))
                 });
                 if has_regressed {
                     "regressed"
+                } else if outcomes.iter().any(|o| o.result.fetch.network_failure()) {
+                    "network"
                 } else if outcomes.iter().any(|o| !o.result.is_success()) {
                     "broken"
                 } else {
@@ -1074,21 +1970,58 @@ impl TestResult {
             }
         }
     }
+}
 
-    fn html_class(&self) -> &'static str {
-        self.quick_str()
+/// Error message shown for a failed step: TIMEOUT, RESOURCE LIMIT or NETWORK,
+/// else the first compiler errors, else raw stderr
+fn failure_message(result: &compile::CompileResult) -> String {
+    if result.timed_out {
+        return format!("TIMEOUT: cargo {} killed after {}",
+            result.step.cargo_subcommand(), format::HumanDuration(result.duration));
+    }
+    if let Some(limit) = result.limit_exceeded {
+        return format!("RESOURCE LIMIT: cargo {} exceeded the {} limit",
+            result.step.cargo_subcommand(), limit.as_str());
+    }
+    if result.network_failure() && !result.stderr.contains(api::NETWORK_MARKER) {
+        return format!("{} {}", api::NETWORK_MARKER, result.stderr.trim());
+    }
+    let error_msg = error_extract::extract_error_summary(&result.diagnostics);
+    if !error_msg.is_empty() {
+        return error_msg;
+    }
+    let test_failures = test_failures(result);
+    if !test_failures.is_empty() {
+        error_extract::test_failure_summary(&test_failures)
+    } else {
+        // Fallback to stderr if no error diagnostics
+        result.stderr.clone()
     }
+}
 
-    fn html_anchor(&self) -> String {
-        sanitize_link(&format!("{}-{}", self.rev_dep.name, self.rev_dep.vers))
+/// Tests that failed in a test step's output (libtest prints to stdout, shard headers go to stderr)
+fn test_failures(result: &compile::CompileResult) -> Vec<error_extract::TestFailure> {
+    if !matches!(result.step, compile::CompileStep::Test | compile::CompileStep::Doctest | compile::CompileStep::Miri) {
+        return Vec::new();
+    }
+    let mut failures = error_extract::parse_test_failures(&result.stdout);
+    for failure in error_extract::parse_test_failures(&result.stderr) {
+        if !failures.iter().any(|f| f.name == failure.name) {
+            failures.push(failure);
+        }
     }
+    failures
+}
+
+fn failed_test_names(result: &compile::CompileResult) -> Vec<String> {
+    test_failures(result).into_iter().map(|f| f.name).collect()
 }
 
 fn sanitize_link(s: &str) -> String {
     s.chars().map(|c| {
         let c = c.to_lowercase().collect::<Vec<_>>()[0];
-        if c != '-' && (c < 'a' || c > 'z')
-            && (c < '0' || c > '9') {
+        if c != '-' && !c.is_ascii_lowercase()
+            && !c.is_ascii_digit() {
             '_'
         } else {
             c
@@ -1110,6 +2043,9 @@ impl TestResultReceiver {
                     name: self.rev_dep,
                     vers: Version::parse("0.0.0").unwrap(),
                     resolved_version: None,
+                    manifest: None,
+                    source_dir: None,
+                    channel: None,
                 };
                 TestResult::error(r, Error::from(e))
             }
@@ -1121,8 +2057,8 @@ fn new_result_receiver(rev_dep: RevDepName) -> (Sender<TestResult>, TestResultRe
     let (tx, rx) = mpsc::channel();
 
     let fut = TestResultReceiver {
-        rev_dep: rev_dep,
-        rx: rx
+        rev_dep,
+        rx
     };
 
     (tx, fut)
@@ -1135,16 +2071,122 @@ fn run_test_multi_version(
     config: Config,
     rev_dep: RevDepName,
     version: Option<String>,
+    channel: Option<String>,
     test_versions: Vec<compile::VersionSource>,
     force_local: bool,
 ) -> TestResultReceiver {
     let (result_tx, result_rx) = new_result_receiver(rev_dep.clone());
+    let mut config = config;
+    config.paths.staging = config.staging_for(channel.as_deref());
     pool.execute(move || {
-        let res = run_multi_version_test(&config, rev_dep, version, test_versions, force_local);
+        let started = Instant::now();
+        match channel {
+            Some(ref channel) => progress::begin(&format!("{} [{}]", rev_dep, channel), test_versions.len()),
+            None => progress::begin(&rev_dep, test_versions.len()),
+        }
+        run_log::set_dependent(Some(&rev_dep));
+        compile::set_toolchain(channel.as_deref());
+        let mut res = run_multi_version_test(&config, rev_dep, version, test_versions, force_local);
+        compile::set_toolchain(None);
+        res.rev_dep.channel = channel;
+        res.wall_time = started.elapsed();
+        if !config.quick_scan {
+            hooks::after_dependent(&config.file_config.hooks, &config.crate_name, &res, &res.rev_dep.source_path(&config.paths.staging));
+        }
+        if res.quick_str() == "regressed" {
+            config.settings.budget.record_regression(&res.rev_dep.name);
+        }
+        if let TestResultData::MultiVersion(_) = res.data {
+            config.settings.budget.record_dependent_time(res.wall_time);
+        }
+        progress::end();
+        run_log::set_dependent(None);
         result_tx.send(res).unwrap();
     });
 
-    return result_rx;
+    result_rx
+}
+
+/// `--worker`: test the dependents the coordinator at `url` hands out, `jobs` at a time
+fn run_worker(url: &str, config: &Config, versions: &[compile::VersionSource], force_local: bool, jobs: usize) -> Result<(), Error> {
+    let tested = distributed::run_worker(url, jobs, |unit| {
+        let mut config = config.clone();
+        if let Some(git) = unit.git {
+            config.dependents_git.insert(unit.dependent.clone(), git);
+        }
+        let started = Instant::now();
+        run_log::set_dependent(Some(&unit.dependent));
+        let mut res = run_multi_version_test(&config, unit.dependent, unit.version, versions.to_vec(), force_local);
+        res.wall_time = started.elapsed();
+        hooks::after_dependent(&config.file_config.hooks, &config.crate_name, &res, &res.rev_dep.source_path(&config.paths.staging));
+        run_log::set_dependent(None);
+        res
+    }).map_err(Error::ProcessError)?;
+    status(&format!("Coordinator has no dependents left; {} tested here", tested));
+    Ok(())
+}
+
+/// A receiver that already holds `result`
+fn ready_result(result: TestResult) -> TestResultReceiver {
+    let (result_tx, result_rx) = new_result_receiver(result.rev_dep.name.clone());
+    result_tx.send(result).unwrap();
+    result_rx
+}
+
+/// First phase of `--strategy quick-then-deep`: check every dependent against
+/// every version, without tests or optional steps, and wait for all of them
+fn quick_scan(
+    pool: &mut ThreadPool,
+    config: &Config,
+    rev_deps: &[(RevDepName, Option<String>)],
+    order: &[usize],
+    versions: &[compile::VersionSource],
+    force_local: bool,
+    jobs: usize,
+) -> Vec<Option<TestResult>> {
+    status(&format!("Quick scan: checking {} dependents without running tests", rev_deps.len()));
+    let started = Instant::now();
+    let mut quick_config = config.clone();
+    quick_config.skip_test = true;
+    quick_config.quick_scan = true;
+    quick_config.settings.optional_steps = compile::OptionalSteps::default();
+
+    let mut receivers: Vec<Option<TestResultReceiver>> = rev_deps.iter().map(|_| None).collect();
+    progress::start(rev_deps.len(), jobs);
+    for &idx in order {
+        let (rev_dep, version) = rev_deps[idx].clone();
+        receivers[idx] = Some(run_test_multi_version(pool, quick_config.clone(), rev_dep, version, None, versions.to_vec(), force_local));
+    }
+    let results: Vec<Option<TestResult>> = receivers.into_iter().map(|rx| rx.map(TestResultReceiver::recv)).collect();
+    progress::stop();
+
+    let changed = results.iter().flatten().filter(|r| r.check_changed()).count();
+    status(&format!("Quick scan took {}: {} of {} dependents check differently than with their baseline; testing those",
+        format::HumanDuration(started.elapsed()), changed, rev_deps.len()));
+    results
+}
+
+/// Version of `crate_name` that `cargo metadata`'s resolve.nodes depend on
+fn resolved_in_nodes(metadata: &serde_json::Value, crate_name: &str) -> Option<String> {
+    let Some(nodes) = metadata["resolve"]["nodes"].as_array() else {
+        debug!("No resolve nodes in metadata");
+        return None;
+    };
+    // Deps are named like the library target: base-crate is base_crate here
+    let lib_name = crate_name.replace('-', "_");
+    let deps = nodes.iter().flat_map(|node| node["deps"].as_array().into_iter().flatten());
+    for dep in deps.filter(|dep| dep["name"] == lib_name.as_str()) {
+        // pkg format: "SOURCE#crate-name@version", e.g.
+        //   "registry+https://github.com/rust-lang/crates.io-index#rgb@0.8.52"
+        //   "path+file:///home/user/crate#rgb@0.8.91"
+        let Some(pkg) = dep["pkg"].as_str() else { continue };
+        debug!("pkg field: {}", pkg);
+        if let Some((_, version)) = pkg.rsplit_once('@') {
+            debug!("Resolved {} to version: {}", crate_name, version);
+            return Some(version.to_string());
+        }
+    }
+    None
 }
 
 /// Extract the resolved version of a dependency using cargo metadata
@@ -1153,17 +2195,21 @@ fn extract_resolved_version(rev_dep: &RevDep, crate_name: &str, staging_dir: &Pa
     // Create staging directory if it doesn't exist
     fs::create_dir_all(staging_dir)?;
 
-    // Staging path: staging_dir/{crate-name}-{version}/
-    let staging_path = staging_dir.join(format!("{}-{}", rev_dep.name, rev_dep.vers));
+    // Staging path: staging_dir/{crate-name}-{version}/, or the git checkout
+    let staging_path = rev_dep.source_path(staging_dir);
 
     // Check if already unpacked
     if !staging_path.exists() {
         debug!("Unpacking {} to staging dir", rev_dep.name);
         let crate_handle = get_crate_handle(rev_dep)?;
         fs::create_dir_all(&staging_path)?;
-        crate_handle.unpack_source_to(&staging_path)?;
+        crate_handle.unpack_source_to(&staging_path, &rev_dep.vers)?;
     } else {
         debug!("Using cached staging dir for {}", rev_dep.name);
+        // Git and local copies are cache entries too; --dependent-paths aren't ours
+        if staging_path.starts_with(staging_dir) {
+            cache::touch(&staging_path);
+        }
     }
 
     // The crate is unpacked directly into staging_path (--strip-components=1)
@@ -1184,16 +2230,20 @@ fn extract_resolved_version(rev_dep: &RevDep, crate_name: &str, staging_dir: &Pa
         // Run cargo metadata to get resolved dependencies
         // Try with --locked first, fallback to generating Cargo.lock if needed
         let mut output = Command::new("cargo")
-            .args(&["metadata", "--format-version=1", "--locked"])
-            .current_dir(&crate_dir)
+            .args(["metadata", "--format-version=1", "--locked"])
+            .args(api::cargo_source_args())
+            .envs(compile::toolchain_env())
+            .current_dir(crate_dir)
             .output()?;
 
         if !output.status.success() {
             // If --locked failed (no Cargo.lock), try without it to generate one
             debug!("cargo metadata --locked failed, trying without --locked");
             output = Command::new("cargo")
-                .args(&["metadata", "--format-version=1"])
-                .current_dir(&crate_dir)
+                .args(["metadata", "--format-version=1"])
+                .args(api::cargo_source_args())
+                .envs(compile::toolchain_env())
+                .current_dir(crate_dir)
                 .output()?;
         }
 
@@ -1205,44 +2255,8 @@ fn extract_resolved_version(rev_dep: &RevDep, crate_name: &str, staging_dir: &Pa
             if let Ok(metadata) = serde_json::from_str::<serde_json::Value>(&stdout) {
                 debug!("Successfully parsed metadata JSON");
                 // Look through resolve.nodes for our dependency
-                if let Some(resolve) = metadata.get("resolve") {
-                    debug!("Found resolve section");
-                    if let Some(nodes) = resolve.get("nodes").and_then(|n| n.as_array()) {
-                        debug!("Found {} nodes in resolve", nodes.len());
-                        for node in nodes {
-                            if let Some(deps) = node.get("deps").and_then(|d| d.as_array()) {
-                                for dep in deps {
-                                    if let Some(name) = dep.get("name").and_then(|n| n.as_str()) {
-                                        if name == crate_name {
-                                            debug!("Found {} in resolve.nodes!", crate_name);
-                                            if let Some(pkg) = dep.get("pkg").and_then(|p| p.as_str()) {
-                                                // pkg format: "SOURCE#crate-name@version"
-                                                // Examples:
-                                                //   "registry+https://github.com/rust-lang/crates.io-index#rgb@0.8.52"
-                                                //   "path+file:///home/user/crate#rgb@0.8.91"
-                                                debug!("pkg field: {}", pkg);
-
-                                                // Extract version from after @
-                                                if let Some(at_pos) = pkg.rfind('@') {
-                                                    let resolved_version = pkg[at_pos + 1..].to_string();
-                                                    debug!("Resolved {} to version: {}", crate_name, resolved_version);
-                                                    return Ok(resolved_version);
-                                                } else {
-                                                    debug!("No '@' found in pkg field");
-                                                }
-                                            } else {
-                                                debug!("No 'pkg' field in dep");
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        debug!("No nodes array in resolve");
-                    }
-                } else {
-                    debug!("No resolve section in metadata");
+                if let Some(resolved_version) = resolved_in_nodes(&metadata, crate_name) {
+                    return Ok(resolved_version);
                 }
 
                 // Fallback: check packages array for version requirement
@@ -1301,41 +2315,99 @@ fn run_multi_version_test(
     // Status line removed - redundant with table output
     // status(&format!("testing crate {} (multi-version)", rev_dep));
 
-    // Resolve dependent version
-    let mut rev_dep = match resolve_rev_dep_version(rev_dep.clone(), dependent_version) {
+    // Resolve dependent version, from its local copy or git checkout if it has one
+    let local_path = config.dependent_paths.iter().find(|(name, _)| *name == rev_dep).map(|(_, path)| path);
+    let git_source = config.dependent_config(&rev_dep)
+        .and_then(|d| d.git)
+        .map(|spec| git_source::GitSource::parse(&spec));
+    let resolved = match (local_path, git_source) {
+        (Some(path), _) => local_source::copy(&rev_dep, path, &config.paths.staging)
+            .map(|copy| RevDep {
+                name: rev_dep.clone(),
+                vers: copy.version,
+                resolved_version: None,
+                manifest: None,
+                source_dir: Some(copy.crate_dir),
+                channel: None,
+            })
+            .map_err(Error::ProcessError),
+        (None, Some(source)) => source.checkout(&rev_dep, &config.paths.staging)
+            .map(|checkout| RevDep {
+                name: rev_dep.clone(),
+                vers: checkout.version,
+                resolved_version: None,
+                manifest: None,
+                source_dir: Some(checkout.crate_dir),
+                channel: None,
+            })
+            .map_err(Error::ProcessError),
+        (None, None) => resolve_rev_dep_version(rev_dep.clone(), dependent_version, config.dependent_prerelease),
+    };
+    let mut rev_dep = match resolved {
         Ok(r) => r,
         Err(e) => {
             let rev_dep = RevDep {
                 name: rev_dep,
                 vers: Version::parse("0.0.0").unwrap(),
                 resolved_version: None,
+                manifest: None,
+                source_dir: None,
+                channel: None,
             };
+            // --dependent-prerelease only: a dependent without a release candidate has nothing to test
+            if matches!(e, Error::NoCrateVersions) && config.dependent_prerelease == api::PrereleasePolicy::Only {
+                return TestResult::skipped(rev_dep, "no pre-release published".to_string());
+            }
             return TestResult::error(rev_dep, e);
         }
     };
 
+    // Once a run-wide limit is reached, nothing new starts
+    if let Some(limit) = config.settings.budget.exhausted() {
+        config.settings.budget.record_skipped();
+        return TestResult::not_run(rev_dep, limit);
+    }
+
+    // Infrastructure problems that don't stop the test but weaken its result
+    let mut warnings = Vec::new();
+
+    // Extract the original requirement spec from the dependent's Cargo.toml
+    let original_requirement = extract_dependency_requirement(&rev_dep, &config.crate_name);
+
     // Extract resolved baseline version for this specific dependent
-    let baseline_version = match extract_resolved_version(&rev_dep, &config.crate_name, &config.staging_dir) {
+    let baseline_version = match extract_resolved_version(&rev_dep, &config.crate_name, &config.paths.staging) {
         Ok(resolved) => {
             debug!("Baseline version for {} -> {}: {}", rev_dep.name, config.crate_name, resolved);
-            rev_dep.resolved_version = Some(resolved.clone());
             Some(resolved)
         }
         Err(e) => {
             debug!("Failed to extract resolved version for {}: {}", rev_dep.name, e);
-            None
+            // Fall back to what cargo would pick for the requirement, so the
+            // dependent still gets a baseline to classify against
+            match infer_baseline_version(original_requirement.as_deref(), &config.crate_name) {
+                Ok(inferred) => {
+                    status(&format!("Inferred baseline {} {} for {} from its requirement",
+                        config.crate_name, inferred, rev_dep.name));
+                    Some(inferred)
+                }
+                Err(e) => {
+                    debug!("Failed to infer baseline version for {}: {}", rev_dep.name, e);
+                    warnings.push(format!("could not determine the baseline version of {}", config.crate_name));
+                    None
+                }
+            }
         }
     };
-
-    // Extract the original requirement spec from the dependent's Cargo.toml
-    let original_requirement = extract_dependency_requirement(&rev_dep, &config.crate_name);
+    rev_dep.resolved_version = baseline_version.clone();
 
     // Add baseline at the front (always non-forced)
     // IMPORTANT: Don't remove duplicates - if user specified same version in --force-versions,
     // we want to test it twice: once as baseline (non-forced), once as forced
     if let Some(ref baseline) = baseline_version {
         // Skip wildcard or star baselines
-        if baseline != "*" && !baseline.is_empty() {
+        if baseline == "*" || baseline.is_empty() {
+            warnings.push(format!("baseline of {} is a wildcard and was not tested", config.crate_name));
+        } else {
             // Always insert baseline at position 0
             // Even if the same version appears later in the list (from --force-versions),
             // this baseline will be tested in non-forced mode
@@ -1345,7 +2417,7 @@ fn run_multi_version_test(
     }
 
     // Check version compatibility
-    match check_version_compatibility(&rev_dep, &config) {
+    match check_version_compatibility(&rev_dep, config) {
         Ok(true) => {}, // Compatible
         Ok(false) => {
             let reason = format!(
@@ -1354,13 +2426,18 @@ fn run_multi_version_test(
             );
             return TestResult::skipped(rev_dep, reason);
         }
+        // Cargo can't build what can't be parsed; that's no regression
+        Err(Error::ManifestUnparsable(reason)) => {
+            return TestResult::skipped(rev_dep, format!("ManifestUnparsable: {}", reason));
+        }
         Err(e) => {
             debug!("Failed to check version compatibility: {}, testing anyway", e);
+            warnings.push(format!("could not check version compatibility: {}", e));
         }
     }
 
     // Unpack the dependent crate once (cached)
-    let staging_path = config.staging_dir.join(format!("{}-{}", rev_dep.name, rev_dep.vers));
+    let staging_path = rev_dep.source_path(&config.paths.staging);
     if !staging_path.exists() {
         debug!("Unpacking {} to staging for multi-version test", rev_dep.name);
         match get_crate_handle(&rev_dep) {
@@ -1368,7 +2445,7 @@ fn run_multi_version_test(
                 if let Err(e) = fs::create_dir_all(&staging_path) {
                     return TestResult::error(rev_dep, Error::IoError(e));
                 }
-                if let Err(e) = handle.unpack_source_to(&staging_path) {
+                if let Err(e) = handle.unpack_source_to(&staging_path, &rev_dep.vers) {
                     return TestResult::error(rev_dep, e);
                 }
             }
@@ -1376,11 +2453,38 @@ fn run_multi_version_test(
         }
     }
 
+    // Services and fixtures its tests need; a dependent without them can't be tested
+    if let Some(ref command) = config.file_config.hooks.pre_dependent {
+        let vars = hooks::dependent_vars(&config.crate_name, Some(&staging_path), &rev_dep.name, &rev_dep.vers.to_string());
+        if let Err(e) = hooks::run("pre-dependent", command, &vars, Some(&staging_path)) {
+            return TestResult::error(rev_dep, Error::ProcessError(e));
+        }
+    }
+
+    // Record edition/resolver for segmenting results, and apply --min-edition
+    match manifest::ManifestInfo::load(&staging_path) {
+        Ok(info) => rev_dep.manifest = Some(info),
+        Err(e) => debug!("Failed to read manifest of {}: {}", rev_dep.name, e),
+    }
+    if let (Some(min_edition), Some(info)) = (config.min_edition.as_deref(), rev_dep.manifest.as_ref()) {
+        if !info.edition_at_least(min_edition) {
+            let reason = format!("edition {} < --min-edition {}", info.edition, min_edition);
+            return TestResult::skipped(rev_dep, reason);
+        }
+    }
+
     // Run ICT tests for each version
     let mut outcomes = Vec::new();
+    // Set once the baseline turns out to need another OS and --targets has one
+    let mut cross_target: Option<String> = None;
     debug!("Total versions to test: {}", test_versions.len());
-    for (idx, version_source) in test_versions.iter().enumerate() {
+    let mut next = 0;
+    while next < test_versions.len() {
+        let idx = next;
+        next += 1;
+        let version_source = &test_versions[idx];
         debug!("[{}/{}] Testing {} against version {}", idx + 1, test_versions.len(), rev_dep.name, version_source.label());
+        progress::version(idx, test_versions.len(), &version_source.label());
 
         // Check if this is the baseline (first version and matches baseline_version)
         let is_baseline = idx == 0 && baseline_version.is_some() && {
@@ -1414,7 +2518,7 @@ fn run_multi_version_test(
                     match download_and_unpack_base_crate_version(
                     &config.crate_name,
                     version,
-                    &config.staging_dir,
+                    &config.paths.staging,
                 ) {
                     Ok(path) => Some(path),
                     Err(e) => {
@@ -1423,21 +2527,26 @@ fn run_multi_version_test(
                         // version is already validated as concrete semver at input time
                         let is_forced = config.force_versions.contains(version);
 
+                        let fetch = compile::CompileResult {
+                            step: compile::CompileStep::Fetch,
+                            success: false,
+                            stdout: String::new(),
+                            stderr: format!("Failed to download base crate: {}", e),
+                            duration: Duration::from_secs(0),
+                            diagnostics: Vec::new(),
+                            timed_out: false,
+                            limit_exceeded: None,
+                            exit: None,
+                            flaky: false,
+                        };
                         let failed_result = compile::ThreeStepResult {
-                            fetch: compile::CompileResult {
-                                step: compile::CompileStep::Fetch,
-                                success: false,
-                                stdout: String::new(),
-                                stderr: format!("Failed to download base crate: {}", e),
-                                duration: Duration::from_secs(0),
-                                diagnostics: Vec::new(),
-                            },
-                            check: None,
-                            test: None,
-                            actual_version: None,
+                            optional_steps: config.settings.optional_steps,
                             expected_version: Some(version.to_string()),
                             forced_version: is_forced,
                             original_requirement: original_requirement.clone(),
+                            lockfile_mode: config.settings.lockfile_mode,
+                            minimal_versions: config.settings.minimal_versions,
+                            ..compile::ThreeStepResult::from_steps(fetch, [])
                         };
                         outcomes.push(VersionTestOutcome {
                             version_source: version_source.clone(),
@@ -1450,8 +2559,15 @@ fn run_multi_version_test(
             }
         };
 
-        let skip_check = false; // TODO: Get from args
-        let skip_test = false;  // TODO: Get from args
+        // The rest of the workspace is patched along with the local version only
+        let companions: &[(String, PathBuf)] = match version_source {
+            compile::VersionSource::Local(_) if !is_baseline => &config.companions,
+            _ => &[],
+        };
+
+        let dependent_config = config.dependent_config(&rev_dep.name);
+        let skip_check = config.skip_check;
+        let skip_test = config.skip_test;
 
         // Determine expected version for verification and if it's forced
         // IMPORTANT: Baseline is NEVER forced, even if it's in --force-versions list
@@ -1486,29 +2602,101 @@ fn run_multi_version_test(
             }
         };
 
-        match compile::run_three_step_ict(
-            &staging_path,
-            &config.crate_name,
-            override_path.as_deref(),
-            skip_check,
-            skip_test,
-            expected_version,
-            is_forced,
-            original_requirement.clone(),
-            Some(&rev_dep.name),
-            Some(&rev_dep.vers.to_string()),
-            Some(&test_label),
-        ) {
+        // Baselines of registry dependents are reused from earlier runs unless --no-cache
+        let cache_dir = config.paths.staging.join("baseline-cache");
+        let cache_key = match (is_baseline, version_source) {
+            // Cached baselines were built by cargo's own commands, without --env, --rustflags and --deny-warnings
+            (true, compile::VersionSource::Published(version)) if !config.no_cache && rev_dep.source_dir.is_none() && config.settings.cargo_env.is_empty()
+                && dependent_config.as_ref().is_none_or(|c| c.commands.is_empty()) => {
+                baseline_cache::toolchain(&staging_path).map(|toolchain| baseline_cache::BaselineKey {
+                    dependent: rev_dep.name.clone(),
+                    dependent_version: rev_dep.vers.to_string(),
+                    crate_name: config.crate_name.clone(),
+                    base_version: version.clone(),
+                    toolchain,
+                })
+            }
+            _ => None,
+        };
+        let features: Vec<String> = dependent_config.as_ref().map(|c| c.features.clone()).unwrap_or_default();
+        let cached = cache_key.as_ref()
+            .and_then(|key| baseline_cache::load(&cache_dir, key))
+            .filter(|result| result.features == features && result.cross_target == cross_target)
+            // Entries from runs with other optional steps have different results to compare against
+            .filter(|result| result.optional_steps == config.settings.optional_steps)
+            .filter(|result| result.lockfile_mode == config.settings.lockfile_mode)
+            .filter(|result| result.minimal_versions == config.settings.minimal_versions)
+            .filter(|result| {
+                let wants_miri = dependent_config.as_ref().is_some_and(|c| c.miri);
+                skip_test || result.miri.is_some() == wants_miri || result.first_failure().is_some_and(|f| f.step < compile::CompileStep::Test)
+            })
+            // A full baseline serves a run without tests once its test steps are dropped
+            .map(|mut result| {
+                if skip_test {
+                    (result.test, result.doctest, result.miri) = (None, None, None);
+                }
+                result
+            });
+
+        let result = match cached {
+            Some(result) => {
+                debug!("Reusing cached baseline of {} {} against {}", rev_dep.name, rev_dep.vers, version_source.label());
+                Ok(result)
+            }
+            None => compile::run_three_step_ict(
+                &config.settings,
+                &staging_path,
+                &config.crate_name,
+                override_path.as_deref(),
+                companions,
+                skip_check,
+                skip_test,
+                expected_version,
+                is_forced,
+                original_requirement.clone(),
+                Some(&rev_dep.name),
+                Some(&rev_dep.vers.to_string()),
+                Some(&test_label),
+                dependent_config.as_ref(),
+                rev_dep.manifest.as_ref().map(|m| m.targets),
+                cross_target.as_deref(),
+            ).inspect(|result| {
+                // Results missing steps would be taken for complete ones later
+                if let Some(key) = cache_key.as_ref().filter(|_| !skip_check && !skip_test) {
+                    baseline_cache::store(&cache_dir, key, result);
+                }
+            }),
+        };
+
+        match result {
             Ok(result) => {
+                // A baseline that can't build on this OS says nothing about the offered versions
+                if is_baseline && cross_target.is_none() && !result.is_success() {
+                    let declared = rev_dep.manifest.as_ref().map(|m| m.platforms.clone()).unwrap_or_default();
+                    if let Some(unsupported) = platform::detect(&result, &declared, std::env::consts::OS) {
+                        match platform::matching_target(&unsupported, &config.targets) {
+                            Some(triple) => {
+                                status(&format!("{} is {}; checking for {} instead", rev_dep.name, unsupported, triple));
+                                cross_target = Some(triple.clone());
+                                next = idx;  // Retest the baseline for that target
+                                continue;
+                            }
+                            None => return TestResult::platform_unsupported(rev_dep, unsupported.to_string()),
+                        }
+                    }
+                }
+
                 // Version mismatch is shown in table with [≠→!] suffix, no need for separate warning
                 if let (Some(ref expected), Some(ref actual)) = (&result.expected_version, &result.actual_version) {
                     if actual != expected {
                         debug!("⚠️  VERSION MISMATCH: Expected {} but cargo resolved to {}!", expected, actual);
+                        warnings.push(format!("expected {} {} but cargo resolved {}", config.crate_name, expected, actual));
                     } else {
                         debug!("✓ Version verified: {} = {}", expected, actual);
                     }
-                } else if result.expected_version.is_some() && result.actual_version.is_none() {
+                } else if let (Some(ref expected), None) = (&result.expected_version, &result.actual_version) {
                     debug!("⚠️  Could not verify version for {} (cargo tree failed)", config.crate_name);
+                    warnings.push(format!("could not verify {} {} was used (cargo tree failed)", config.crate_name, expected));
                 }
 
                 outcomes.push(VersionTestOutcome {
@@ -1526,40 +2714,161 @@ fn run_multi_version_test(
     TestResult {
         rev_dep,
         data: TestResultData::MultiVersion(outcomes),
+        warnings,
+        wall_time: Duration::ZERO,
     }
 }
 
-fn check_version_compatibility(rev_dep: &RevDep, config: &Config) -> Result<bool, Error> {
-    debug!("checking version compatibility for {} {}", rev_dep.name, rev_dep.vers);
+/// Name the base-crate items behind each regression from the public API diff
+/// between its baseline and offered version (`--api-diff`). Returns every
+/// change found, including the newest baseline against the first offered
+/// version, for the version bump recommendation.
+fn attribute_regressions(rows: &mut [OfferedRow], config: &Config) -> Vec<api_diff::ApiChange> {
+    let baselines: HashMap<(String, String), String> = rows.iter()
+        .filter(|row| row.offered.is_none())
+        .map(|row| ((row.primary.dependent_name.clone(), row.primary.dependent_version.clone()), row.primary.resolved_version.clone()))
+        .collect();
+    let offered_source = |row: &OfferedRow| match (&row.primary.resolved_source, &config.next_override) {
+        (VersionSource::Local, CrateOverride::Source(_)) => Some("this".to_string()),
+        (VersionSource::CratesIo, _) => Some(row.primary.resolved_version.clone()),
+        _ => None,  // git sources aren't diffed
+    };
+    let target_dir = config.paths.staging.join("rustdoc");
+
+    // Rustdoc JSON per source ("0.8.50" or "this"), built once
+    let mut apis: HashMap<String, Option<api_diff::ApiItems>> = HashMap::new();
+    let mut public_api = |version: &str| {
+        apis.entry(version.to_string()).or_insert_with(|| {
+            let crate_dir = if version == "this" {
+                match &config.next_override {
+                    CrateOverride::Source(manifest_path) => manifest_path.parent().map(Path::to_path_buf),
+                    CrateOverride::Default => None,
+                }
+            } else {
+                download_and_unpack_base_crate_version(&config.crate_name, version, &config.paths.staging)
+                    .map_err(|e| eprintln!("Warning: --api-diff: {}", e))
+                    .ok()
+            }?;
+            status(&format!("Building rustdoc JSON for {} {}", config.crate_name, version));
+            api_diff::public_api(&crate_dir, &config.crate_name, &target_dir.join(version))
+                .map_err(|e| eprintln!("Warning: --api-diff: {}", e))
+                .ok()
+        }).clone()
+    };
+    let mut diffs: HashMap<(String, String), Vec<api_diff::ApiChange>> = HashMap::new();
+    let mut diff = |baseline: &str, offered: &str| {
+        diffs.entry((baseline.to_string(), offered.to_string()))
+            .or_insert_with(|| match (public_api(baseline), public_api(offered)) {
+                (Some(old), Some(new)) => api_diff::diff(&old, &new),
+                _ => Vec::new(),
+            })
+            .clone()
+    };
+
+    let mut all_changes = Vec::new();
+    let newest_baseline = baselines.values().filter_map(|v| Version::parse(v).ok()).max();
+    if let (Some(baseline), Some(offered)) = (newest_baseline, rows.iter().filter(|r| r.offered.is_some()).find_map(offered_source)) {
+        all_changes.extend(diff(&baseline.to_string(), &offered));
+    }
+
+    for row in rows.iter_mut().filter(|row| report::row_status(row) == json_report::RowStatus::Regressed) {
+        let key = (row.primary.dependent_name.clone(), row.primary.dependent_version.clone());
+        let (Some(baseline), Some(offered)) = (baselines.get(&key), offered_source(row)) else { continue };
+        let changes = diff(baseline, &offered);
+        for failure in row.test.commands.iter_mut().flat_map(|cmd| cmd.result.failures.iter_mut()) {
+            failure.likely_causes = api_diff::attribute(&changes, &failure.error_patterns)
+                .iter()
+                .map(|change| change.to_string())
+                .collect();
+        }
+        all_changes.extend(changes);
+    }
+
+    all_changes.sort();
+    all_changes.dedup();
+    all_changes
+}
+
+/// Find the release that broke each regressed dependent (`--bisect`) and print it
+fn bisect_regressions(config: &Config, range: &bisect::BisectRange, regressed: &[(RevDep, Option<String>)]) {
+    if regressed.is_empty() {
+        return;
+    }
+    let candidates = match api::list_versions(&config.crate_name) {
+        Ok(versions) => range.candidates(&versions),
+        Err(e) => {
+            eprintln!("Warning: Can't bisect, failed to list versions of {}: {}", config.crate_name, e);
+            return;
+        }
+    };
+
+    println!("\nBisect {} {} ({} published versions):", config.crate_name, range, candidates.len());
+    for (rev_dep, cross_target) in regressed {
+        status(&format!("bisecting {} {}", rev_dep.name, rev_dep.vers));
+        let staging_path = rev_dep.source_path(&config.staging_for(rev_dep.channel.as_deref()));
+        compile::set_toolchain(rev_dep.channel.as_deref());
+        let dependent_config = config.dependent_config(&rev_dep.name);
+        let original_requirement = extract_dependency_requirement(rev_dep, &config.crate_name);
+
+        // Versions outside the dependent's requirement are forced, like --force-versions
+        let bisection = bisect::first_breaking(&candidates, |version| {
+            let version = version.to_string();
+            let base_path = download_and_unpack_base_crate_version(&config.crate_name, &version, &config.paths.staging)
+                .map_err(|e| format!("failed to download {} {}: {}", config.crate_name, version, e))?;
+            let result = compile::run_three_step_ict(
+                &config.settings,
+                &staging_path,
+                &config.crate_name,
+                Some(&base_path),
+                &[],
+                false,
+                false,
+                Some(version.clone()),
+                true,
+                original_requirement.clone(),
+                Some(&rev_dep.name),
+                Some(&rev_dep.vers.to_string()),
+                Some(&format!("bisect ({})", version)),
+                dependent_config.as_ref(),
+                rev_dep.manifest.as_ref().map(|m| m.targets),
+                cross_target.as_deref(),
+            )?;
+            debug!("bisect {} against {}: {}", rev_dep.name, version, if result.is_success() { "works" } else { "broken" });
+            Ok(result.is_success())
+        });
+
+        compile::set_toolchain(None);
+        match bisection {
+            Ok(bisection) => println!("  {} {}: {}", rev_dep.name, rev_dep.vers, bisection),
+            Err(e) => println!("  {} {}: bisect failed: {}", rev_dep.name, rev_dep.vers, e),
+        }
+    }
+}
+
+/// Parse the dependent's Cargo.toml, from its local copy, git checkout or .crate file
+fn load_dependent_manifest(rev_dep: &RevDep) -> Result<toml::Value, Error> {
+    if let Some(ref dir) = rev_dep.source_dir {
+        let content = fs::read(dir.join("Cargo.toml"))?;
+        let mut value = manifest::parse_manifest(&content).map_err(Error::ManifestUnparsable)?;
+        // Requirements of a workspace member may be defined by the root
+        if let Some(root) = workspace::member_root(dir) {
+            workspace::resolve(&mut value, &root);
+        }
+        return Ok(value);
+    }
 
     // Download and cache the dependent's .crate file
     let crate_handle = get_crate_handle(rev_dep)?;
 
-    // Create temp directory to extract Cargo.toml
-    let temp_dir = TempDir::new()?;
-    let extract_dir = temp_dir.path().join("extracted");
-    fs::create_dir(&extract_dir)?;
-
-    // Extract just the Cargo.toml
-    let mut cmd = Command::new("tar");
-    let cmd = cmd
-        .arg("xzf")
-        .arg(&crate_handle.0)
-        .arg("--strip-components=1")
-        .arg("-C")
-        .arg(&extract_dir)
-        .arg("--wildcards")
-        .arg("*/Cargo.toml");
-
-    let output = cmd.output()?;
-    if !output.status.success() {
-        return Err(Error::ProcessError("Failed to extract Cargo.toml".to_string()));
-    }
-
-    // Read and parse Cargo.toml
-    let toml_path = extract_dir.join("Cargo.toml");
-    let toml_str = load_string(&toml_path)?;
-    let value: toml::Value = toml::from_str(&toml_str)?;
+    let content = cache::read_crate_file(&crate_handle.0, Path::new("Cargo.toml"))?
+        .ok_or_else(|| Error::ProcessError(format!("No Cargo.toml in {}", crate_handle.0.display())))?;
+    manifest::parse_manifest(&content).map_err(Error::ManifestUnparsable)
+}
+
+fn check_version_compatibility(rev_dep: &RevDep, config: &Config) -> Result<bool, Error> {
+    debug!("checking version compatibility for {} {}", rev_dep.name, rev_dep.vers);
+
+    let value = load_dependent_manifest(rev_dep)?;
 
     // Look for our crate in dependencies
     let our_crate = &config.crate_name;
@@ -1599,7 +2908,7 @@ fn check_requirement(req: &toml::Value, wip_version: &Version) -> Result<bool, E
     debug!("Checking if version {} satisfies requirement '{}'", wip_version, req_str);
 
     let version_req = VersionReq::parse(&req_str)
-        .map_err(|e| Error::SemverError(e))?;
+        .map_err(Error::SemverError)?;
 
     Ok(version_req.matches(wip_version))
 }
@@ -1619,72 +2928,25 @@ fn extract_requirement_string(req: &toml::Value) -> String {
     }
 }
 
+/// Highest published version of `crate_name` matching the dependent's requirement
+fn infer_baseline_version(requirement: Option<&str>, crate_name: &str) -> Result<String, Error> {
+    let requirement = requirement
+        .ok_or_else(|| Error::ProcessError(format!("no requirement on {} found", crate_name)))?;
+    let req = semver::VersionReq::parse(requirement)?;
+    let version = api::resolve_max_matching_version(crate_name, &req)
+        .map_err(Error::CratesIoApiError)?;
+    Ok(version.to_string())
+}
+
 /// Extract the original requirement spec for our crate from a dependent's Cargo.toml
 /// Returns the requirement string (e.g., "^0.8.52") if found
 fn extract_dependency_requirement(rev_dep: &RevDep, crate_name: &str) -> Option<String> {
     debug!("Extracting dependency requirement for {} from {}", crate_name, rev_dep.name);
 
-    // Download and cache the dependent's .crate file
-    let crate_handle = match get_crate_handle(rev_dep) {
-        Ok(h) => h,
-        Err(e) => {
-            debug!("Failed to get crate handle for {}: {}", rev_dep.name, e);
-            return None;
-        }
-    };
-
-    // Create temp directory to extract Cargo.toml
-    let temp_dir = match TempDir::new() {
-        Ok(d) => d,
-        Err(e) => {
-            debug!("Failed to create temp dir: {}", e);
-            return None;
-        }
-    };
-
-    let extract_dir = temp_dir.path().join("extracted");
-    if fs::create_dir(&extract_dir).is_err() {
-        return None;
-    }
-
-    // Extract just the Cargo.toml
-    let mut cmd = Command::new("tar");
-    let cmd = cmd
-        .arg("xzf")
-        .arg(&crate_handle.0)
-        .arg("--strip-components=1")
-        .arg("-C")
-        .arg(&extract_dir)
-        .arg("--wildcards")
-        .arg("*/Cargo.toml");
-
-    let output = match cmd.output() {
-        Ok(o) => o,
-        Err(e) => {
-            debug!("Failed to run tar command: {}", e);
-            return None;
-        }
-    };
-
-    if !output.status.success() {
-        debug!("tar command failed for {}", rev_dep.name);
-        return None;
-    }
-
-    // Read and parse Cargo.toml
-    let toml_path = extract_dir.join("Cargo.toml");
-    let toml_str = match load_string(&toml_path) {
-        Ok(s) => s,
-        Err(e) => {
-            debug!("Failed to read Cargo.toml: {}", e);
-            return None;
-        }
-    };
-
-    let value: toml::Value = match toml::from_str(&toml_str) {
+    let value = match load_dependent_manifest(rev_dep) {
         Ok(v) => v,
         Err(e) => {
-            debug!("Failed to parse Cargo.toml: {}", e);
+            debug!("Failed to load Cargo.toml of {}: {}", rev_dep.name, e);
             return None;
         }
     };
@@ -1720,165 +2982,230 @@ fn extract_dependency_requirement(rev_dep: &RevDep, crate_name: &str) -> Option<
     None
 }
 
-fn resolve_rev_dep_version(name: RevDepName, version: Option<String>) -> Result<RevDep, Error> {
+fn resolve_rev_dep_version(name: RevDepName, version: Option<String>, prerelease: api::PrereleasePolicy) -> Result<RevDep, Error> {
     // If version is provided, use it directly
     if let Some(ver_str) = version {
         debug!("using pinned version {} for {}", ver_str, name);
         let vers = Version::parse(&ver_str)
-            .map_err(|e| Error::SemverError(e))?;
+            .map_err(Error::SemverError)?;
         return Ok(RevDep {
-            name: name,
-            vers: vers,
+            name,
+            vers,
             resolved_version: None,
+            manifest: None,
+            source_dir: None,
+            channel: None,
         });
     }
 
     // Otherwise, resolve latest version from crates.io
     debug!("resolving current version for {}", name);
 
-    let krate = CRATES_IO_CLIENT.get_crate(&name)
-        .map_err(|e| Error::CratesIoApiError(e.to_string()))?;
+    let vers = api::resolve_latest_version(&name, prerelease)
+        .map_err(Error::CratesIoApiError)?
+        .ok_or(Error::NoCrateVersions)?;
 
-    // Pull out the version numbers and sort them
-    let versions = krate.versions.iter()
-        .filter_map(|r| Version::parse(&r.num).ok());
-    let mut versions = versions.collect::<Vec<_>>();
-    versions.sort();
-
-    versions.pop().map(|v| {
-        RevDep {
-            name: name,
-            vers: v,
-            resolved_version: None,
-        }
-    }).ok_or(Error::NoCrateVersions)
+    Ok(RevDep {
+        name,
+        vers,
+        resolved_version: None,
+        manifest: None,
+        source_dir: None,
+        channel: None,
+    })
 }
 
 /// Resolve 'latest' or 'latest-preview' keyword to actual version
+///
+/// Pinned at the first resolution, so a release during the run can't make
+/// different dependents test different versions.
 fn resolve_latest_version(crate_name: &str, include_prerelease: bool) -> Result<String, Error> {
+    let key = (crate_name.to_string(), include_prerelease);
+    if let Some(pinned) = PINNED_LATEST.lock().unwrap().get(&key) {
+        return Ok(pinned.clone());
+    }
     debug!("Resolving latest version for {} (prerelease={})", crate_name, include_prerelease);
 
-    let krate = CRATES_IO_CLIENT.get_crate(crate_name)
-        .map_err(|e| Error::CratesIoApiError(e.to_string()))?;
+    let versions = api::list_versions(crate_name)
+        .map_err(Error::CratesIoApiError)?;
 
     // Filter and sort versions
-    let mut versions: Vec<Version> = krate.versions.iter()
-        .filter_map(|r| Version::parse(&r.num).ok())
+    let mut versions: Vec<Version> = versions.into_iter()
+        .map(|(version, _)| version)
         .filter(|v| include_prerelease || v.pre.is_empty()) // Filter pre-releases unless requested
         .collect();
 
     versions.sort();
 
-    versions.pop()
+    let latest = versions.pop()
         .map(|v| v.to_string())
-        .ok_or(Error::NoCrateVersions)
+        .ok_or(Error::NoCrateVersions)?;
+    PINNED_LATEST.lock().unwrap().insert(key, latest.clone());
+    Ok(latest)
 }
 
+/// Versions in `current` that weren't in `known`, oldest first
+fn newly_published(known: &[(Version, bool)], current: &[(Version, bool)]) -> Vec<String> {
+    let mut new: Vec<&Version> = current.iter()
+        .map(|(version, _)| version)
+        .filter(|version| !known.iter().any(|(k, _)| k == *version))
+        .collect();
+    new.sort();
+    new.into_iter().map(|v| v.to_string()).collect()
+}
 
-// CompileResult is now in compile module
-type CompileResult = compile::CompileResult;
 
-fn compile_with_custom_dep(
-    rev_dep: &RevDep,
-    krate: &CrateOverride,
-    crate_name: &str,
-    staging_dir: &Path
-) -> Result<CompileResult, Error> {
-    // Use staging directory instead of temp dir to cache build artifacts
-    fs::create_dir_all(staging_dir)?;
-    let staging_path = staging_dir.join(format!("{}-{}", rev_dep.name, rev_dep.vers));
+struct CrateHandle(PathBuf);
 
-    // Check if already unpacked, if not unpack it
-    if !staging_path.exists() {
-        debug!("Unpacking {} to staging for compilation", rev_dep.name);
-        let crate_handle = get_crate_handle(rev_dep)?;
-        fs::create_dir_all(&staging_path)?;
-        crate_handle.unpack_source_to(&staging_path)?;
+fn get_crate_handle(rev_dep: &RevDep) -> Result<CrateHandle, Error> {
+    let crate_dir = &CRATE_CACHE_DIR.read().unwrap().join(&rev_dep.name);
+    (fs::create_dir_all(crate_dir)?);
+    let crate_file = crate_dir.join(format!("{}-{}.crate", rev_dep.name, rev_dep.vers));
+    if !crate_file.exists() {
+        // One download per entry; whoever waited on the lock finds the file in place
+        let _lock = cache::lock_entry(&crate_file)?;
+        if !crate_file.exists() {
+            let body = api::download_crate(&rev_dep.name, &rev_dep.vers.to_string())
+                .map_err(Error::CratesIoApiError)?;
+            // Written aside and moved into place, so a concurrent reader never sees half a file
+            let mut file = tempfile::Builder::new().prefix(cache::TEMP_PREFIX).tempfile_in(crate_dir)?;
+            (file.write_all(&body)?);
+            (file.flush()?);
+            file.persist(&crate_file).map_err(|e| Error::IoError(e.error))?;
+        }
     } else {
-        debug!("Using cached staging dir for compilation of {}", rev_dep.name);
+        cache::touch(&crate_file);
     }
 
-    let source_dir = &staging_path;
-
-    // Restore Cargo.toml from original backup to prevent contamination
-    restore_cargo_toml(&staging_path)?;
+    Ok(CrateHandle(crate_file))
+}
 
-    // Clean up any existing .cargo/config from previous runs (old system)
-    let cargo_dir = source_dir.join(".cargo");
-    if cargo_dir.exists() {
-        fs::remove_dir_all(&cargo_dir).ok(); // Ignore errors
+/// Download every dependent's .crate and unpack the offered versions of the base
+/// crate, `jobs` at a time, so workers don't wait on the network one by one.
+/// Dependents are unpacked and resolved like the workers do, with the run's
+/// registry sources, so cargo's cache holds their dependencies too.
+/// Failures are left for the workers, which report them per dependent.
+fn prefetch_crates(rev_deps: &[(RevDepName, Option<String>)], versions: &[compile::VersionSource], config: &Config, jobs: usize) {
+    let dependents: Vec<(RevDepName, Option<String>)> = rev_deps.iter()
+        .filter(|(name, _)| config.dependent_config(name).and_then(|d| d.git).is_none())
+        .cloned()
+        .collect();
+    let offered: Vec<String> = versions.iter()
+        .filter_map(|v| match v {
+            compile::VersionSource::Published(version) => Some(version.clone()),
+            compile::VersionSource::Local(_) => None,
+        })
+        .collect();
+    if dependents.len() + offered.len() < 2 {
+        return;
+    }
+    status(&format!("Prefetching {} dependents and {} versions of {}, {} at a time",
+        dependents.len(), offered.len(), config.crate_name, jobs));
+
+    let started = Instant::now();
+    let pool = ThreadPool::new(jobs);
+    let failed = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    for (name, version) in dependents {
+        let (prerelease, failed) = (config.dependent_prerelease, failed.clone());
+        let (crate_name, staging) = (config.crate_name.clone(), config.paths.staging.clone());
+        pool.execute(move || {
+            let resolved = resolve_rev_dep_version(name.clone(), version, prerelease)
+                .and_then(|rev_dep| extract_resolved_version(&rev_dep, &crate_name, &staging));
+            if let Err(e) = resolved {
+                debug!("Prefetching {} failed: {}", name, e);
+                failed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
+    }
+    for version in offered {
+        let (crate_name, staging, failed) = (config.crate_name.clone(), config.paths.staging.clone(), failed.clone());
+        pool.execute(move || {
+            if let Err(e) = download_and_unpack_base_crate_version(&crate_name, &version, &staging) {
+                debug!("Prefetching {} {} failed: {}", crate_name, version, e);
+                failed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
     }
+    pool.join();
 
-    // Build override spec for new --config system
-    let override_spec = match krate {
-        CrateOverride::Default => None,
-        CrateOverride::Source(ref path) => {
-            // Extract directory from Cargo.toml path
-            let override_dir = if path.ends_with("Cargo.toml") {
-                path.parent().unwrap()
-            } else {
-                path.as_path()
-            };
-            Some((crate_name, override_dir))
+    let failed = failed.load(std::sync::atomic::Ordering::Relaxed);
+    let mut message = format!("Prefetched in {}", format::HumanDuration(started.elapsed()));
+    if failed > 0 {
+        message.push_str(&format!(" ({} failed; retried during testing)", failed));
+    }
+    status(&message);
+}
+
+/// With `--offline`, stop before testing anything unless every dependent's
+/// .crate (or git checkout), version list and baseline, and every offered
+/// version of the base crate is cached; the error lists all that isn't
+fn check_offline_cache(rev_deps: &[(RevDepName, Option<String>)], versions: &[compile::VersionSource], config: &Config) -> Result<(), Error> {
+    // Only lookups that needed the network are worth listing
+    fn miss(e: Error) -> Option<String> {
+        match e {
+            Error::CratesIoApiError(msg) => Some(msg),
+            _ => None,
         }
-    };
+    }
 
-    // Use cargo build with --config flag (legacy: still using build instead of check)
-    let start = std::time::Instant::now();
-    let mut cmd = Command::new("cargo");
-    cmd.arg("build").current_dir(source_dir);
+    let mut missing: Vec<String> = Vec::new();
+    let mut base_versions: Vec<String> = versions.iter()
+        .filter_map(|v| match v {
+            compile::VersionSource::Published(version) => Some(version.clone()),
+            compile::VersionSource::Local(_) => None,
+        })
+        .collect();
 
-    if let Some((name, path)) = override_spec {
-        // Convert to absolute path
-        let abs_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            env::current_dir()?.join(path)
+    for (name, version) in rev_deps {
+        if config.dependent_paths.iter().any(|(local, _)| local == name) {
+            continue;
+        }
+        if let Some(spec) = config.dependent_config(name).and_then(|d| d.git) {
+            if !git_source::GitSource::clone_dir(name, &config.paths.staging).join(".git").exists() {
+                missing.push(format!("The git checkout of {} ({})", name, spec));
+            }
+            continue;
+        }
+        let rev_dep = match resolve_rev_dep_version(name.clone(), version.clone(), config.dependent_prerelease) {
+            Ok(rev_dep) => rev_dep,
+            Err(e) => {
+                missing.extend(miss(e));
+                continue;
+            }
         };
-
-        let config_str = format!("patch.crates-io.{}.path=\"{}\"", name, abs_path.display());
-        cmd.arg("--config").arg(&config_str);
-        debug!("using --config: {}", config_str);
+        if let Err(e) = get_crate_handle(&rev_dep) {
+            missing.extend(miss(e));
+            continue;
+        }
+        let requirement = extract_dependency_requirement(&rev_dep, &config.crate_name);
+        match infer_baseline_version(requirement.as_deref(), &config.crate_name) {
+            Ok(baseline) => base_versions.push(baseline),
+            Err(e) => missing.extend(miss(e)),
+        }
     }
 
-    debug!("running cargo: {:?}", cmd);
-    let r = cmd.output()?;
-
-    let duration = start.elapsed();
-    let success = r.status.success();
-
-    debug!("result: {:?}", success);
-
-    Ok(CompileResult {
-        step: compile::CompileStep::Check, // Legacy: using Check for old build command
-        success,
-        stdout: String::from_utf8(r.stdout)?,
-        stderr: String::from_utf8(r.stderr)?,
-        duration,
-        diagnostics: Vec::new(), // Legacy path doesn't use JSON parsing
-    })
-}
-
-struct CrateHandle(PathBuf);
-
-fn get_crate_handle(rev_dep: &RevDep) -> Result<CrateHandle, Error> {
-    let cache_path = Path::new("./.copter/crate-cache");
-    let ref crate_dir = cache_path.join(&rev_dep.name);
-    (fs::create_dir_all(crate_dir)?);
-    let crate_file = crate_dir.join(format!("{}-{}.crate", rev_dep.name, rev_dep.vers));
-    // FIXME: Path::exists() is unstable so just opening the file
-    let crate_file_exists = File::open(&crate_file).is_ok();
-    if !crate_file_exists {
-        let url = crate_url(&rev_dep.name,
-                            Some(&format!("{}/download", rev_dep.vers)));
-        let body = http_get_bytes(&url)?;
-        // FIXME: Should move this into place atomically
-        let mut file = File::create(&crate_file)?;
-        (file.write_all(&body)?);
-        (file.flush()?);
+    base_versions.sort();
+    base_versions.dedup();
+    for version in base_versions {
+        if let Err(e) = download_and_unpack_base_crate_version(&config.crate_name, &version, &config.paths.staging) {
+            missing.extend(miss(e));
+        }
     }
 
-    return Ok(CrateHandle(crate_file));
+    // The base crate's version list is looked up once per dependent
+    let mut unique: Vec<String> = Vec::new();
+    for item in missing {
+        if !unique.contains(&item) {
+            unique.push(item);
+        }
+    }
+    let missing = unique;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(Error::ProcessError(format!(
+        "--offline: {} needed items are not cached; run once without --offline to fetch them:\n  {}",
+        missing.len(), missing.join("\n  "))))
 }
 
 /// Download and unpack a specific version of the base crate for patching
@@ -1893,54 +3220,57 @@ fn download_and_unpack_base_crate_version(
     // version is already validated as concrete semver at input time
     // Create a pseudo-RevDep for downloading
     let vers = Version::parse(version)
-        .map_err(|e| Error::SemverError(e))?;
+        .map_err(Error::SemverError)?;
     let pseudo_dep = RevDep {
         name: RevDepName::from(crate_name.to_string()),
         vers,
         resolved_version: None,
+        manifest: None,
+        source_dir: None,
+        channel: None,
     };
 
     // Download the crate
     let crate_handle = get_crate_handle(&pseudo_dep)?;
 
-    // Unpack to staging directory
+    // Unpack to staging directory, aside and then moved into place, so an
+    // interrupted unpack isn't mistaken for a cached one
     let unpack_path = staging_dir.join(format!("base-{}-{}", crate_name, version));
+    let _lock = cache::lock_entry(&unpack_path)?;
     if !unpack_path.exists() {
-        fs::create_dir_all(&unpack_path)?;
-        crate_handle.unpack_source_to(&unpack_path)?;
+        let temp_path = staging_dir.join(format!("{}-base-{}-{}-{}", cache::TEMP_PREFIX, crate_name, version, std::process::id()));
+        if temp_path.exists() {
+            fs::remove_dir_all(&temp_path)?;
+        }
+        fs::create_dir_all(&temp_path)?;
+        crate_handle.unpack_source_to(&temp_path, &pseudo_dep.vers)?;
+        fs::rename(&temp_path, &unpack_path)?;
         debug!("Unpacked {} {} to {:?}", crate_name, version, unpack_path);
     } else {
         debug!("Using cached base crate at {:?}", unpack_path);
+        cache::touch(&unpack_path);
     }
 
     Ok(unpack_path)
 }
 
 impl CrateHandle {
-    fn unpack_source_to(&self, path: &Path) -> Result<(), Error> {
-        debug!("unpackng {:?} to {:?}", self.0, path);
-        let mut cmd = Command::new("tar");
-        let cmd = cmd
-            .arg("xzf")
-            .arg(self.0.to_str().unwrap().to_owned())
-            .arg("--strip-components=1")
-            .arg("-C")
-            .arg(path.to_str().unwrap().to_owned());
-        let r = cmd.output()?;
-        if r.status.success() {
-            // Save original Cargo.toml if this is first unpack
-            save_original_cargo_toml(path)?;
-            Ok(())
-        } else {
-            // FIXME: Want to put r in this value but
-            // process::Output doesn't implement Debug
-            let s = String::from_utf8_lossy(&r.stderr).into_owned();
-            Err(Error::ProcessError(s))
+    fn unpack_source_to(&self, path: &Path, version: &Version) -> Result<(), Error> {
+        debug!("unpacking {:?} to {:?}", self.0, path);
+        cache::unpack_crate(&self.0, path)?;
+        // Keep cargo from treating it as part of a workspace it isn't in
+        let synthesized = workspace::isolate_unpacked(path, version).map_err(Error::ProcessError)?;
+        if !synthesized.is_empty() {
+            status(&format!("Synthesized workspace-inherited {} in {}", synthesized.join(", "), path.display()));
         }
+        // Save original Cargo.toml if this is first unpack
+        save_original_cargo_toml(path)?;
+        Ok(())
     }
 }
 
-/// Save a backup of Cargo.toml as Cargo.toml.original.txt (only if not already saved)
+/// Save a backup of Cargo.toml as Cargo.toml.original.txt (only if not already saved),
+/// and of the packaged Cargo.lock for `--lockfile respect|both`
 fn save_original_cargo_toml(staging_path: &Path) -> Result<(), Error> {
     let cargo_toml = staging_path.join("Cargo.toml");
     let original = staging_path.join("Cargo.toml.original.txt");
@@ -1949,29 +3279,18 @@ fn save_original_cargo_toml(staging_path: &Path) -> Result<(), Error> {
     if !original.exists() && cargo_toml.exists() {
         fs::copy(&cargo_toml, &original)?;
         debug!("Saved original Cargo.toml to {:?}", original);
-    }
-    Ok(())
-}
-
-/// Restore Cargo.toml from the original backup before testing
-fn restore_cargo_toml(staging_path: &Path) -> Result<(), Error> {
-    let cargo_toml = staging_path.join("Cargo.toml");
-    let original = staging_path.join("Cargo.toml.original.txt");
-
-    if original.exists() {
-        fs::copy(&original, &cargo_toml)?;
-        debug!("Restored Cargo.toml from original backup in {:?}", staging_path);
+        lockfile::save_committed(staging_path)?;
     }
     Ok(())
 }
 
 
-fn status_lock<F>(f: F) where F: FnOnce() -> () {
+fn status_lock<F>(f: F) where F: FnOnce() {
    lazy_static! {
         static ref LOCK: Mutex<()> = Mutex::new(());
     }
     let _guard = LOCK.lock();
-    f();
+    progress::suspend(f);
 }
 
 fn print_status_header() {
@@ -1985,83 +3304,180 @@ fn print_color(s: &str, fg: term::color::Color) {
 
     fn really_print_color(s: &str,
                           fg: term::color::Color) -> bool {
-        if let Some(ref mut t) = term::stdout() {
-            if t.fg(fg).is_err() { return false }
-            let _ = t.attr(term::Attr::Bold);
-            if write!(t, "{}", s).is_err() { return false }
-            let _ = t.reset();
-        }
+        let Some(ref mut t) = report::color_stdout() else { return false };
+        if t.fg(fg).is_err() { return false }
+        let _ = t.attr(term::Attr::Bold);
+        if write!(t, "{}", s).is_err() { return false }
+        let _ = t.reset();
 
         true
     }
 }
 
 fn status(s: &str) {
+    run_log::status(s);
+    if run_log::verbosity() == run_log::Verbosity::Quiet {
+        return;
+    }
     status_lock(|| {
         print_status_header();
         println!("{}", s);
     });
 }
 
-fn report_quick_result(current_num: usize, total: usize, result: &TestResult) {
-    status_lock(|| {
-        print_status_header();
-        print!("result {} of {}, {} {}: ",
-               current_num,
-               total,
-               result.rev_dep.name,
-               result.rev_dep.vers
-               );
-        let color = match result.data {
-            TestResultData::Skipped(_) => term::color::BRIGHT_CYAN,
-            TestResultData::Error(_) => term::color::BRIGHT_MAGENTA,
-            TestResultData::MultiVersion(_) => term::color::BRIGHT_GREEN, // TODO: Compute worst status
-        };
-        print_color(&format!("{}", result.quick_str()), color);
-        println!("");
+/// Render the summary and every report file from the rows of a finished run
+fn report_results(res: Result<RunResults, Error>, args: &cli::CliArgs, config: &Config) {
+    let results = match res {
+        Ok(results) => results,
+        Err(e) => return report_error(e),
+    };
 
-        // Print detailed error output immediately for failures
-        // TODO: Migrate to OfferedRow-based failure reporting
-        if matches!(result.data, TestResultData::Error(_)) {
-            report::print_immediate_failure(result);
+    // Print summary
+    let summary = report::summarize_offered_rows(&results.rows);
+    report::print_summary(&summary);
+    let bump = report::recommend_bump(&results.rows, results.api_changes.as_deref());
+    print!("{}", report::format_bump(&bump, report::newest_baseline(&results.rows).as_ref()));
+    sink::finish(&summary);
+
+    // Generate HTML report
+    match report::generate_html_report(&results.rows, &config.crate_name, &config.display_version(), results.total, &results.notes, args.mermaid_cdn, &args.output) {
+        Ok(_) => println!("HTML report: {}", args.output.display()),
+        Err(e) => eprintln!("Warning: Failed to generate HTML report: {}", e),
+    }
+
+    // Generate markdown report
+    let markdown_path = PathBuf::from("copter-report.md");
+    match report::export_markdown_table_report(&results.rows, &markdown_path, &config.crate_name, &config.display_version(), results.total, &results.notes) {
+        Ok(_) => {
+            println!("Markdown report: {}", markdown_path.display());
         }
-    });
-}
+        Err(e) => {
+            eprintln!("Warning: Failed to generate markdown report: {}", e);
+        }
+    }
 
-fn report_results(res: Result<Vec<TestResult>, Error>, args: &cli::CliArgs, config: &Config) {
-    match res {
-        Ok(results) => {
-            // Console table is already printed in streaming mode during run_tests()
-            // No need to print again here
+    // Generate markdown analysis (per-dependent details for a release PR)
+    let analysis_path = PathBuf::from("copter-analysis.md");
+    match report::export_markdown_report(&results.rows, &analysis_path, &config.crate_name, &config.display_version(), &results.notes) {
+        Ok(_) => println!("Markdown analysis: {}", analysis_path.display()),
+        Err(e) => eprintln!("Warning: Failed to generate markdown analysis: {}", e),
+    }
 
-            // Note: Markdown report generation disabled for now
-            // The table is already printed to console in streaming mode
-            // TODO: Capture console output and write to markdown file
+    // Generate the pull request comment
+    if let Some(ref comment_path) = args.pr_comment {
+        let links = pr_comment::Links {
+            run_url: report::CiProvider::detect().and_then(|ci| ci.run_url()),
+            artifact_logs: args.artifacts_dir.is_some(),
+        };
+        match pr_comment::write(comment_path, &results.rows, &config.crate_name, &config.display_version(), &results.notes, &links, args.update_marker.as_deref()) {
+            Ok(_) => println!("PR comment: {}", comment_path.display()),
+            Err(e) => eprintln!("Warning: Failed to write PR comment: {}", e),
         }
-        Err(e) => {
-            report_error(e);
+    }
+
+    // Generate README status badge, next to the HTML report
+    let badge_path = args.output.with_file_name("copter-badge.svg");
+    match badge::Badge::from_rows(&results.rows).write(&badge_path) {
+        Ok(_) => println!("Status badge: {}", badge_path.display()),
+        Err(e) => eprintln!("Warning: Failed to generate status badge: {}", e),
+    }
+
+    // Generate dependency graph (Graphviz DOT)
+    let graph_path = PathBuf::from("copter-graph.dot");
+    match graph::DependencyGraph::from_rows(&results.rows, &config.crate_name).write_dot(&graph_path) {
+        Ok(_) => println!("Dependency graph: {}", graph_path.display()),
+        Err(e) => eprintln!("Warning: Failed to generate dependency graph: {}", e),
+    }
+
+    // Generate JSON report (input for `cargo-copter diff`)
+    let json_path = PathBuf::from("copter-report.json");
+    let mut json_report = json_report::JsonReport::from_rows(&results.rows, &summary, &config.crate_name, &config.display_version());
+    json_report.published_during_run = results.published_during_run;
+    match json_report.write(&json_path) {
+        Ok(_) => println!("JSON report: {}", json_path.display()),
+        Err(e) => eprintln!("Warning: Failed to generate JSON report: {}", e),
+    }
+
+    // Append this run to the results database (read by `cargo-copter history`)
+    let run_key = history::RunKey {
+        crate_name: &config.crate_name,
+        crate_version: &config.version,
+        git_hash: config.git_hash.as_deref(),
+        dirty: config.is_dirty,
+    };
+    if let Err(e) = history::ResultsDb::open(&config.paths.results_db)
+        .and_then(|mut db| db.record_run(&run_key, &json_report)) {
+        eprintln!("Warning: Failed to record run in results database: {}", e);
+    }
+
+    // Mail the results of unattended runs
+    if let Some(ref email) = config.file_config.email {
+        if summary.regressed > 0 || email.always {
+            let message = email::summary_message(email, &results.rows, &config.crate_name, &config.display_version(), &results.notes, summary.regressed, &args.output);
+            match email::send(email, &message) {
+                Ok(_) => println!("Email: sent to {}", email.to.join(", ")),
+                Err(e) => eprintln!("Warning: Failed to send email: {}", e),
+            }
         }
     }
+
+    // Snapshot of this run's outcomes for a later --compare-baseline
+    if let Some(ref path) = args.save_baseline {
+        match json_report.write(path) {
+            Ok(_) => println!("Baseline snapshot: {}", path.display()),
+            Err(e) => eprintln!("Warning: Failed to save baseline snapshot: {}", e),
+        }
+    }
+
+    // Against a baseline snapshot, only failures that aren't already there count for --fail-on
+    let (summary, errors) = match args.compare_baseline {
+        Some(ref path) => match json_report::JsonReport::load(path) {
+            Ok(baseline) => {
+                print!("\n{}", diff::format_diff(&diff::diff_reports(&baseline, &json_report), &baseline, &json_report));
+                let new = diff::exclude_known_failures(&results.rows, results.errors, &baseline);
+                if new.pre_existing > 0 {
+                    println!("{} dependents were already failing the same way in {}; they don't fail this run", new.pre_existing, path.display());
+                }
+                (report::summarize_offered_rows(&new.rows), new.errors)
+            }
+            Err(e) => {
+                eprintln!("Warning: Failed to load baseline snapshot, so every failure counts: {}", e);
+                (summary, results.errors)
+            }
+        },
+        None => (summary, results.errors),
+    };
+
+    // With --strict, unreliable results fail the run even if everything passed
+    if args.strict && results.infra_warnings > 0 {
+        report_error(Error::StrictWarnings(results.infra_warnings));
+    }
+
+    // Exit with error code if the results include what --fail-on fails on
+    if args.fail_on.fails(&summary, errors) {
+        std::process::exit(-2);
+    }
 }
 
 fn report_error(e: Error) {
-    println!("");
+    println!();
     print_color("error", term::color::BRIGHT_RED);
     println!(": {}", e);
-    println!("");
+    println!();
 
     std::process::exit(-1);
 }
 
 // Report generation functions moved to src/report.rs
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 enum Error {
     ManifestName,
     SemverError(semver::Error),
     TomlError(toml::de::Error),
+    ManifestUnparsable(String),
     IoError(io::Error),
-    UreqError(Box<ureq::Error>),
     CratesIoApiError(String),
     RecvError(RecvError),
     NoCrateVersions,
@@ -2069,6 +3485,9 @@ enum Error {
     ProcessError(String),
     InvalidPath(PathBuf),
     InvalidVersion(String),
+    HistoryError(String),
+    InitError(String),
+    StrictWarnings(usize),
 }
 
 macro_rules! convert_error {
@@ -2087,27 +3506,24 @@ convert_error!(toml::de::Error, TomlError);
 convert_error!(RecvError, RecvError);
 convert_error!(FromUtf8Error, FromUtf8Error);
 
-impl From<ureq::Error> for Error {
-    fn from(e: ureq::Error) -> Error {
-        Error::UreqError(Box::new(e))
-    }
-}
-
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
             Error::ManifestName => write!(f, "error extracting crate name from manifest"),
             Error::SemverError(ref e) => write!(f, "semver error: {}", e),
             Error::TomlError(ref e) => write!(f, "TOML parse error: {}", e),
+            Error::ManifestUnparsable(ref e) => write!(f, "unparsable manifest: {}", e),
             Error::IoError(ref e) => write!(f, "IO error: {}", e),
-            Error::UreqError(ref e) => write!(f, "HTTP error: {}", e),
             Error::CratesIoApiError(ref e) => write!(f, "crates.io API error: {}", e),
             Error::RecvError(ref e) => write!(f, "receive error: {}", e),
             Error::NoCrateVersions => write!(f, "crate has no published versions"),
             Error::FromUtf8Error(ref e) => write!(f, "UTF-8 conversion error: {}", e),
             Error::ProcessError(ref s) => write!(f, "process error: {}", s),
+            Error::HistoryError(ref s) => write!(f, "results database error: {}", s),
+            Error::InitError(ref s) => write!(f, "init failed: {}", s),
             Error::InvalidPath(ref p) => write!(f, "invalid path: {}", p.display()),
             Error::InvalidVersion(ref s) => write!(f, "{}", s),
+            Error::StrictWarnings(n) => write!(f, "{} infrastructure warning(s) with --strict; results may not be meaningful", n),
         }
     }
 }
@@ -2118,7 +3534,6 @@ impl StdError for Error {
             Error::SemverError(ref e) => Some(e),
             Error::TomlError(ref e) => Some(e),
             Error::IoError(ref e) => Some(e),
-            Error::UreqError(ref e) => Some(e.as_ref()),
             Error::RecvError(ref e) => Some(e),
             Error::FromUtf8Error(ref e) => Some(e),
             _ => None
@@ -2131,6 +3546,66 @@ mod tests {
     use super::*;
     use semver::Version;
 
+    fn config_with_git(git_hash: Option<&str>, is_dirty: bool) -> Config {
+        Config {
+            crate_name: "rgb".to_string(),
+            version: "0.8.91".to_string(),
+            git_hash: git_hash.map(|h| h.to_string()),
+            is_dirty,
+            paths: paths::CopterPaths::default(),
+            next_override: CrateOverride::Default,
+            force_versions: vec![],
+            file_config: config_file::CopterConfig::default(),
+            dependent_features: HashMap::new(),
+            dependents_git: HashMap::new(),
+            dependent_paths: vec![],
+            companions: vec![],
+            miri: vec![],
+            dependent_prerelease: api::PrereleasePolicy::Include,
+            min_edition: None,
+            targets: vec![],
+            channels: vec![],
+            no_cache: false,
+            skip_check: false,
+            skip_test: false,
+            quick_scan: false,
+            settings: compile::RunSettings::default(),
+            slowdown_threshold: build_times::DEFAULT_THRESHOLD,
+        }
+    }
+
+    #[test]
+    fn test_this_label_includes_version_hash_and_dirty_flag() {
+        assert_eq!(config_with_git(None, false).this_label(), "this(0.8.91)");
+        assert_eq!(config_with_git(None, true).this_label(), "this(0.8.91+dirty)");
+        assert_eq!(config_with_git(Some("1a2b3c4"), false).this_label(), "this(0.8.91+g1a2b3c4)");
+        assert_eq!(config_with_git(Some("1a2b3c4"), true).this_label(), "this(0.8.91+g1a2b3c4.dirty)");
+    }
+
+    #[test]
+    fn test_newly_published() {
+        let versions = |list: &[&str]| -> Vec<(Version, bool)> {
+            list.iter().map(|v| (Version::parse(v).unwrap(), false)).collect()
+        };
+        let known = versions(&["0.8.50", "0.8.91"]);
+        assert_eq!(newly_published(&known, &versions(&["0.8.93", "0.8.50", "0.8.91", "0.8.92"])), vec!["0.8.92", "0.8.93"]);
+        assert!(newly_published(&known, &known).is_empty());
+    }
+
+    #[test]
+    fn test_resolved_in_nodes_uses_library_names() {
+        let metadata = serde_json::json!({
+            "resolve": { "nodes": [
+                { "id": "path+file:///work/viewer#0.3.0", "deps": [
+                    { "name": "load_image", "pkg": "registry+https://github.com/rust-lang/crates.io-index#load-image@3.2.1" },
+                ] },
+            ] },
+        });
+        assert_eq!(resolved_in_nodes(&metadata, "load-image").as_deref(), Some("3.2.1"));
+        assert_eq!(resolved_in_nodes(&metadata, "rgb"), None);
+        assert_eq!(resolved_in_nodes(&serde_json::json!({}), "load-image"), None);
+    }
+
     #[test]
     fn test_check_requirement_string_exact_version() {
         let req = toml::Value::String("0.2.0".to_string());
@@ -2217,4 +3692,32 @@ mod tests {
         assert!(check_requirement(&req, &version_compatible2).unwrap());
         assert!(!check_requirement(&req, &version_incompatible).unwrap());
     }
+
+    #[test]
+    fn test_quick_str_compares_against_baseline() {
+        let outcome = |version: &str, check_passed| VersionTestOutcome {
+            version_source: compile::VersionSource::Published(version.to_string()),
+            result: compile::ThreeStepResult::from_steps(
+                compile::CompileResult::for_test(compile::CompileStep::Fetch, true),
+                [compile::CompileResult::for_test(compile::CompileStep::Check, check_passed)],
+            ),
+        };
+        let result = |outcomes| TestResult {
+            rev_dep: RevDep {
+                name: "image".to_string(),
+                vers: Version::parse("0.25.0").unwrap(),
+                resolved_version: None,
+                manifest: None,
+                source_dir: None,
+                channel: None,
+            },
+            data: TestResultData::MultiVersion(outcomes),
+            warnings: vec![],
+            wall_time: Duration::ZERO,
+        };
+
+        assert_eq!(result(vec![outcome("0.8.50", true), outcome("0.8.91", false)]).quick_str(), "regressed");
+        assert_eq!(result(vec![outcome("0.8.50", false), outcome("0.8.91", false)]).quick_str(), "broken");
+        assert_eq!(result(vec![outcome("0.8.50", true), outcome("0.8.91", true)]).quick_str(), "passed");
+    }
 }
@@ -0,0 +1,102 @@
+//! Maintenance status of dependents (`--maintenance`, `--only-active`)
+//!
+//! A regression in a crate nobody has released in years matters less than
+//! one in a crate under active development: its users are pinned to what
+//! works anyway, and nobody will ship a fix. Each dependent is classified
+//! from its newest crates.io release, and from whether its GitHub repository
+//! is archived, so rows can be annotated and inactive dependents skipped.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Newest release within this many days: active
+const ACTIVE_DAYS: i64 = 365;
+
+/// No release in this many days: abandoned (five years)
+const ABANDONED_DAYS: i64 = 5 * 365;
+
+/// What crates.io and the repository say about a dependent
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateActivity {
+    /// When its newest non-yanked version was published
+    pub last_release: Option<DateTime<Utc>>,
+    /// Its repository is archived on GitHub
+    pub archived: bool,
+}
+
+/// How actively a dependent is maintained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Maintenance {
+    /// Released within the last year
+    Active,
+    /// Last released one to five years ago
+    Stale,
+    /// No release in five years, or its repository is archived
+    Abandoned,
+}
+
+impl Maintenance {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Maintenance::Active => "active",
+            Maintenance::Stale => "stale",
+            Maintenance::Abandoned => "abandoned",
+        }
+    }
+
+    /// None when there's nothing to go by
+    pub fn classify(activity: &CrateActivity, now: DateTime<Utc>) -> Option<Maintenance> {
+        if activity.archived {
+            return Some(Maintenance::Abandoned);
+        }
+        let age = now.signed_duration_since(activity.last_release?).num_days();
+        Some(match age {
+            age if age < ACTIVE_DAYS => Maintenance::Active,
+            age if age < ABANDONED_DAYS => Maintenance::Stale,
+            _ => Maintenance::Abandoned,
+        })
+    }
+}
+
+impl fmt::Display for Maintenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `owner/repo` of a GitHub repository URL, e.g. from a manifest's `repository`
+pub fn github_repo(url: &str) -> Option<String> {
+    let path = url.trim().strip_prefix("https://github.com/")
+        .or_else(|| url.trim().strip_prefix("http://github.com/"))?;
+    let mut parts = path.split('/').filter(|part| !part.is_empty());
+    let owner = parts.next()?;
+    let repo = parts.next()?.trim_end_matches(".git");
+    Some(format!("{}/{}", owner, repo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_classify() {
+        let now = Utc::now();
+        let released = |days| CrateActivity { last_release: Some(now - Duration::days(days)), archived: false };
+        assert_eq!(Maintenance::classify(&released(30), now), Some(Maintenance::Active));
+        assert_eq!(Maintenance::classify(&released(800), now), Some(Maintenance::Stale));
+        assert_eq!(Maintenance::classify(&released(6 * 365), now), Some(Maintenance::Abandoned));
+        assert_eq!(Maintenance::classify(&CrateActivity { archived: true, ..released(30) }, now), Some(Maintenance::Abandoned));
+        assert_eq!(Maintenance::classify(&CrateActivity::default(), now), None);
+    }
+
+    #[test]
+    fn test_github_repo() {
+        assert_eq!(github_repo("https://github.com/image-rs/image").as_deref(), Some("image-rs/image"));
+        assert_eq!(github_repo("https://github.com/kornelski/rust-rgb.git/").as_deref(), Some("kornelski/rust-rgb"));
+        assert_eq!(github_repo("https://github.com/image-rs/image/tree/main/png").as_deref(), Some("image-rs/image"));
+        assert_eq!(github_repo("https://gitlab.com/a/b"), None);
+    }
+}
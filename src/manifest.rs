@@ -0,0 +1,261 @@
+//! Edition, resolver and target kinds of a dependent's manifest
+//!
+//! Results are segmented by edition and resolver because they change how a
+//! dependent builds: resolver 1 unifies features across build/dev/target
+//! dependencies and behaves differently under `[patch]`, and each edition
+//! changes which code compiles at all. Target kinds decide what check/test
+//! exercise: a bin-only dependent has no library for others to link against,
+//! so only its binaries and their unit tests are built.
+//!
+//! Manifests of old crates aren't always valid by today's rules (Latin-1
+//! text, control characters in comments, `[project]` instead of
+//! `[package]`), so they are parsed leniently; see `parse_manifest`.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Editions `--min-edition` accepts, oldest first
+pub const EDITIONS: [&str; 4] = ["2015", "2018", "2021", "2024"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestInfo {
+    /// `package.edition` ("2015" when unset, as cargo assumes)
+    pub edition: String,
+    /// `package.resolver`, else `workspace.resolver`, else the edition's default
+    pub resolver: String,
+    /// Library and binary targets
+    pub targets: TargetKinds,
+    /// Target triples from `package.metadata.docs.rs` (`default-target` and `targets`)
+    pub platforms: Vec<String>,
+}
+
+/// Which kinds of targets a package has
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetKinds {
+    pub lib: bool,
+    pub bins: bool,
+}
+
+impl TargetKinds {
+    /// Targets declared in the manifest (`[lib]`, `[[bin]]`)
+    fn declared(value: &toml::Value) -> Self {
+        TargetKinds {
+            lib: value.get("lib").is_some(),
+            bins: value.get("bin")
+                .and_then(|b| b.as_array())
+                .is_some_and(|bins| !bins.is_empty()),
+        }
+    }
+
+    /// Add the targets cargo discovers from the standard file layout
+    fn discover(&mut self, crate_dir: &Path) {
+        let src = crate_dir.join("src");
+        self.lib |= src.join("lib.rs").is_file();
+        self.bins |= src.join("main.rs").is_file() || src.join("bin").is_dir();
+    }
+
+    pub fn is_bin_only(&self) -> bool {
+        self.bins && !self.lib
+    }
+
+    /// Extra cargo arguments for check/test: only the binaries of a bin-only package
+    pub fn cargo_args(&self) -> Vec<String> {
+        if self.is_bin_only() {
+            vec!["--bins".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// "lib", "bin" or "lib+bin"
+    pub fn label(&self) -> &'static str {
+        match (self.lib, self.bins) {
+            (true, true) => "lib+bin",
+            (false, true) => "bin",
+            _ => "lib",
+        }
+    }
+}
+
+/// Parse a Cargo.toml, tolerating what old published crates contain
+///
+/// Invalid UTF-8 is replaced and a BOM dropped before parsing. If that
+/// still fails, control characters (which TOML 1.0 forbids even in
+/// comments) are stripped and the document is parsed with toml_edit, as
+/// the manifest rewrite in compile.rs does. The error is the strict
+/// parser's, since that is where the real problem is reported.
+pub fn parse_manifest(bytes: &[u8]) -> Result<toml::Value, String> {
+    let content = String::from_utf8_lossy(bytes);
+    let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+    let strict_error = match toml::from_str(content) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    let cleaned: String = content.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    cleaned.parse::<toml_edit::DocumentMut>().ok()
+        .and_then(|doc| toml_edit::de::from_document(doc).ok())
+        .ok_or_else(|| strict_error.message().to_string())
+}
+
+impl ManifestInfo {
+    /// Read `Cargo.toml` in `crate_dir`
+    pub fn load(crate_dir: &Path) -> Result<Self, String> {
+        let path = crate_dir.join("Cargo.toml");
+        let content = fs::read(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut info = match crate::workspace::member_root(crate_dir) {
+            // Members may inherit their edition, and always use the root's resolver
+            Some(root) => {
+                let mut value = parse_manifest(&content)
+                    .map_err(|e| format!("Failed to parse Cargo.toml: {}", e))?;
+                crate::workspace::resolve(&mut value, &root);
+                let mut info = Self::from_value(&value);
+                info.resolver = Self::from_value(&root).resolver;
+                info
+            }
+            None => Self::parse(&content)?,
+        };
+        info.targets.discover(crate_dir);
+        Ok(info)
+    }
+
+    pub fn parse(content: &[u8]) -> Result<Self, String> {
+        let value = parse_manifest(content)
+            .map_err(|e| format!("Failed to parse Cargo.toml: {}", e))?;
+        Ok(Self::from_value(&value))
+    }
+
+    fn from_value(value: &toml::Value) -> Self {
+
+        // `[project]` is the pre-1.0 spelling of `[package]`
+        let field = |table: &str, key: &str| {
+            let section = match table {
+                "package" => value.get("package").or_else(|| value.get("project")),
+                _ => value.get(table),
+            };
+            section
+                .and_then(|t| t.get(key))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        };
+
+        let edition = field("package", "edition").unwrap_or_else(|| "2015".to_string());
+        let resolver = field("package", "resolver")
+            .or_else(|| field("workspace", "resolver"))
+            .unwrap_or_else(|| default_resolver(&edition).to_string());
+
+        let docs_rs = value.get("package")
+            .and_then(|p| p.get("metadata"))
+            .and_then(|m| m.get("docs"))
+            .and_then(|d| d.get("rs"));
+        let mut platforms: Vec<String> = docs_rs
+            .and_then(|d| d.get("default-target"))
+            .and_then(|t| t.as_str())
+            .map(|t| vec![t.to_string()])
+            .unwrap_or_default();
+        for target in docs_rs.and_then(|d| d.get("targets")).and_then(|t| t.as_array()).into_iter().flatten() {
+            if let Some(target) = target.as_str().filter(|t| !platforms.iter().any(|p| p == t)) {
+                platforms.push(target.to_string());
+            }
+        }
+
+        ManifestInfo { edition, resolver, targets: TargetKinds::declared(value), platforms }
+    }
+
+    /// Whether the edition is `min_edition` or newer
+    pub fn edition_at_least(&self, min_edition: &str) -> bool {
+        edition_rank(&self.edition) >= edition_rank(min_edition)
+    }
+}
+
+/// Resolver cargo uses when the manifest doesn't set one
+fn default_resolver(edition: &str) -> &'static str {
+    match edition {
+        "2015" | "2018" => "1",
+        "2021" => "2",
+        _ => "3",
+    }
+}
+
+/// Position in `EDITIONS`; unknown (future) editions sort last
+fn edition_rank(edition: &str) -> usize {
+    EDITIONS.iter().position(|e| *e == edition).unwrap_or(EDITIONS.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_edition_and_resolver() {
+        let info = ManifestInfo::parse(b"[package]\nname = \"a\"\nversion = \"1.0.0\"\nedition = \"2021\"\n").unwrap();
+        assert_eq!(info, ManifestInfo { edition: "2021".to_string(), resolver: "2".to_string(), targets: TargetKinds::default(), platforms: vec![] });
+
+        let info = ManifestInfo::parse(b"[package]\nname = \"a\"\nversion = \"1.0.0\"\n").unwrap();
+        assert_eq!(info, ManifestInfo { edition: "2015".to_string(), resolver: "1".to_string(), targets: TargetKinds::default(), platforms: vec![] });
+
+        let info = ManifestInfo::parse(
+            b"[package]\nname = \"a\"\nversion = \"1.0.0\"\nedition = \"2018\"\n[workspace]\nresolver = \"2\"\n",
+        ).unwrap();
+        assert_eq!(info.resolver, "2");
+
+        assert!(info.edition_at_least("2015"));
+        assert!(info.edition_at_least("2018"));
+        assert!(!info.edition_at_least("2021"));
+
+        let docs = ManifestInfo::parse(
+            b"[package]\nname = \"a\"\nversion = \"1.0.0\"\n[package.metadata.docs.rs]\n\
+             default-target = \"x86_64-pc-windows-msvc\"\ntargets = [\"x86_64-pc-windows-msvc\", \"i686-pc-windows-msvc\"]\n",
+        ).unwrap();
+        assert_eq!(docs.platforms, vec!["x86_64-pc-windows-msvc".to_string(), "i686-pc-windows-msvc".to_string()]);
+    }
+
+    #[test]
+    fn test_load_workspace_member() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\"]\nresolver = \"2\"\n[workspace.package]\nedition = \"2018\"\n").unwrap();
+        let member = dir.path().join("app");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(member.join("Cargo.toml"), "[package]\nname = \"app\"\nedition.workspace = true\n").unwrap();
+
+        let info = ManifestInfo::load(&member).unwrap();
+        assert_eq!(info.edition, "2018");
+        assert_eq!(info.resolver, "2");
+    }
+
+    #[test]
+    fn test_target_kinds() {
+        let info = ManifestInfo::parse(b"[package]\nname = \"a\"\nversion = \"1.0.0\"\n[[bin]]\nname = \"a\"\npath = \"src/main.rs\"\n").unwrap();
+        assert!(info.targets.is_bin_only());
+        assert_eq!(info.targets.cargo_args(), vec!["--bins".to_string()]);
+        assert_eq!(info.targets.label(), "bin");
+
+        // Older packages rely on the file layout instead of declaring targets
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"a\"\nversion = \"1.0.0\"\n").unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        let info = ManifestInfo::load(dir.path()).unwrap();
+        assert_eq!(info.targets, TargetKinds { lib: true, bins: true });
+        assert!(info.targets.cargo_args().is_empty());
+        assert_eq!(info.targets.label(), "lib+bin");
+    }
+
+    #[test]
+    fn test_parse_exotic_manifests() {
+        // Latin-1 author name, a form feed in a comment, the old [project] table
+        let manifest = b"\xef\xbb\xbf[project]\nname = \"a\"\nversion = \"1.0.0\"\nedition = \"2018\"\n\
+                         authors = [\"Ren\xe9\"]\n# page break \x0c\n";
+        let value = parse_manifest(manifest).unwrap();
+        assert_eq!(value["project"]["authors"][0].as_str(), Some("Ren\u{fffd}"));
+        assert_eq!(ManifestInfo::parse(manifest).unwrap().edition, "2018");
+
+        assert!(parse_manifest(b"[package\nname = ").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! rust-version (MSRV) conflicts between dependents and the offered version
+//!
+//! A dependent declaring `rust-version = "1.60"` promises to build with Rust
+//! 1.60. When the offered version of the base crate needs a newer Rust, the
+//! dependent still passes here on a current toolchain, yet its users on 1.60
+//! can't take the upgrade. Such rows are MSRV rather than PASSED; testing
+//! with that toolchain (`--channels 1.60`) shows what actually fails.
+//!
+//! Both rust-versions come from `cargo metadata` after fetch: the dependent's
+//! root package, and the version of the base crate it resolved.
+
+use std::fmt;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// Declared rust-versions in a dependent's resolved graph
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RustVersions {
+    /// The dependent's own, e.g. "1.60"
+    pub dependent: Option<String>,
+    /// The resolved version of the base crate's
+    pub base: Option<String>,
+}
+
+/// rust-versions of the root package and of `base_crate` at `base_version` (or any version)
+pub fn from_metadata(metadata: &serde_json::Value, base_crate: &str, base_version: Option<&str>) -> RustVersions {
+    let packages = metadata["packages"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let rust_version = |package: &serde_json::Value| package["rust_version"].as_str().map(str::to_string);
+    let root = metadata["resolve"]["root"].as_str();
+    let bases = || packages.iter().filter(|p| p["name"] == base_crate);
+    RustVersions {
+        dependent: packages.iter().find(|p| root.is_some() && p["id"].as_str() == root).and_then(rust_version),
+        base: bases().find(|p| base_version.is_some_and(|v| p["version"] == v))
+            .or_else(|| bases().next())
+            .and_then(rust_version),
+    }
+}
+
+/// "1.60" or "1.60.1" as a comparable version
+fn parse(rust_version: &str) -> Option<Version> {
+    let mut parts = rust_version.trim().split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some(Version::new(major, minor, patch))
+}
+
+/// The offered version needs a newer Rust than the dependent supports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsrvConflict {
+    pub dependent: String,
+    pub base: String,
+}
+
+impl fmt::Display for MsrvConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "needs Rust {}, dependent's rust-version is {}", self.base, self.dependent)
+    }
+}
+
+fn exceeds(versions: &RustVersions) -> bool {
+    match (versions.base.as_deref().and_then(parse), versions.dependent.as_deref().and_then(parse)) {
+        (Some(base), Some(dependent)) => base > dependent,
+        _ => false,
+    }
+}
+
+/// A conflict the offered version introduces; one the baseline already had isn't its doing
+pub fn conflict(baseline: &RustVersions, offered: &RustVersions) -> Option<MsrvConflict> {
+    if !exceeds(offered) || exceeds(baseline) {
+        return None;
+    }
+    Some(MsrvConflict { dependent: offered.dependent.clone()?, base: offered.base.clone()? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_versions_from_metadata() {
+        let metadata = serde_json::json!({
+            "packages": [
+                { "id": "path+file:///work/image#0.25.8", "name": "image", "version": "0.25.8", "rust_version": "1.60" },
+                { "id": "registry+https://github.com/rust-lang/crates.io-index#rgb@0.8.50", "name": "rgb", "version": "0.8.50", "rust_version": null },
+                { "id": "path+file:///work/rgb#0.9.0", "name": "rgb", "version": "0.9.0", "rust_version": "1.70" },
+            ],
+            "resolve": { "root": "path+file:///work/image#0.25.8", "nodes": [] },
+        });
+        let versions = from_metadata(&metadata, "rgb", Some("0.9.0"));
+        assert_eq!(versions, RustVersions { dependent: Some("1.60".to_string()), base: Some("1.70".to_string()) });
+        assert_eq!(from_metadata(&metadata, "rgb", Some("0.8.50")).base, None);
+    }
+
+    #[test]
+    fn test_conflict_introduced_by_offered_version() {
+        let versions = |dependent: Option<&str>, base: Option<&str>| RustVersions {
+            dependent: dependent.map(str::to_string),
+            base: base.map(str::to_string),
+        };
+        let baseline = versions(Some("1.60"), Some("1.56"));
+        assert_eq!(conflict(&baseline, &versions(Some("1.60"), Some("1.70"))),
+                   Some(MsrvConflict { dependent: "1.60".to_string(), base: "1.70".to_string() }));
+        assert_eq!(conflict(&baseline, &versions(Some("1.60"), Some("1.70"))).unwrap().to_string(),
+                   "needs Rust 1.70, dependent's rust-version is 1.60");
+        assert_eq!(conflict(&baseline, &versions(Some("1.60"), Some("1.60.0"))), None);
+        assert_eq!(conflict(&baseline, &versions(None, Some("1.70"))), None);
+        // Already the case with the baseline
+        assert_eq!(conflict(&versions(Some("1.60"), Some("1.65")), &versions(Some("1.60"), Some("1.70"))), None);
+    }
+}
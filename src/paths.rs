@@ -0,0 +1,151 @@
+//! Locations of everything cargo-copter keeps between runs
+//!
+//! All of them live under one home directory, chosen from (first wins):
+//! the `COPTER_HOME` environment variable, `home` in copter.toml, or
+//! `.copter` in the current directory. `--staging-dir` and `--artifacts-dir`
+//! still override their own location. `cargo-copter paths` prints the result.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding the home directory
+pub const HOME_ENV: &str = "COPTER_HOME";
+
+/// Home directory when neither the environment nor copter.toml set one
+pub const DEFAULT_HOME: &str = ".copter";
+
+/// Where the home directory setting came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HomeSource {
+    Env,
+    ConfigFile,
+    Default,
+}
+
+impl fmt::Display for HomeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HomeSource::Env => write!(f, "from {}", HOME_ENV),
+            HomeSource::ConfigFile => write!(f, "from copter.toml"),
+            HomeSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopterPaths {
+    pub home: PathBuf,
+    pub home_source: HomeSource,
+    /// Unpacked dependents and their target/ directories
+    pub staging: PathBuf,
+    /// Downloaded .crate files
+    pub crate_cache: PathBuf,
+    /// Cached crates.io responses
+    pub api_cache: PathBuf,
+    /// Results database read by `history`
+    pub results_db: PathBuf,
+    /// Per-dependent compressed logs (written with --artifacts-dir)
+    pub artifacts: PathBuf,
+    /// Complete output of each step of each dependent
+    pub logs: PathBuf,
+}
+
+impl CopterPaths {
+    /// Resolve every location from the home directory settings and path flags
+    pub fn resolve(
+        env_home: Option<PathBuf>,
+        config_home: Option<PathBuf>,
+        staging_dir: Option<&Path>,
+        artifacts_dir: Option<&Path>,
+    ) -> Self {
+        let (home, home_source) = match (env_home, config_home) {
+            (Some(home), _) => (home, HomeSource::Env),
+            (None, Some(home)) => (home, HomeSource::ConfigFile),
+            (None, None) => (PathBuf::from(DEFAULT_HOME), HomeSource::Default),
+        };
+
+        CopterPaths {
+            staging: staging_dir.map(Path::to_path_buf).unwrap_or_else(|| home.join("staging")),
+            crate_cache: home.join("crate-cache"),
+            api_cache: home.join("api-cache"),
+            results_db: home.join("results.db"),
+            artifacts: artifacts_dir.map(Path::to_path_buf).unwrap_or_else(|| home.join("artifacts")),
+            logs: home.join("logs"),
+            home,
+            home_source,
+        }
+    }
+
+    /// Table printed by `cargo-copter paths`
+    pub fn format(&self) -> String {
+        let rows = [
+            ("home", &self.home),
+            ("staging", &self.staging),
+            ("crate-cache", &self.crate_cache),
+            ("api-cache", &self.api_cache),
+            ("results-db", &self.results_db),
+            ("artifacts", &self.artifacts),
+            ("logs", &self.logs),
+        ];
+
+        let mut output = String::new();
+        for (name, path) in rows {
+            output.push_str(&format!("{:<12} {}", name, path.display()));
+            if name == "home" {
+                output.push_str(&format!("  ({})", self.home_source));
+            }
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// Cargo's home: `CARGO_HOME`, else `.cargo` in the user's home directory
+/// (`HOME`, or `USERPROFILE` on Windows)
+pub fn cargo_home() -> Option<PathBuf> {
+    if let Some(home) = std::env::var_os("CARGO_HOME") {
+        return Some(PathBuf::from(home));
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".cargo"))
+}
+
+impl Default for CopterPaths {
+    fn default() -> Self {
+        CopterPaths::resolve(None, None, None, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_precedence() {
+        let paths = CopterPaths::resolve(Some(PathBuf::from("/env")), Some(PathBuf::from("/config")), None, None);
+        assert_eq!(paths.home_source, HomeSource::Env);
+        assert_eq!(paths.crate_cache, PathBuf::from("/env/crate-cache"));
+
+        let paths = CopterPaths::resolve(None, Some(PathBuf::from("/config")), None, None);
+        assert_eq!(paths.home_source, HomeSource::ConfigFile);
+        assert_eq!(paths.results_db, PathBuf::from("/config/results.db"));
+
+        let paths = CopterPaths::default();
+        assert_eq!(paths.home_source, HomeSource::Default);
+        assert_eq!(paths.staging, Path::new(DEFAULT_HOME).join("staging"));
+    }
+
+    #[test]
+    fn test_flags_override_their_own_location() {
+        let paths = CopterPaths::resolve(Some(PathBuf::from("/env")), None, Some(Path::new("/tmp/stage")), Some(Path::new("out")));
+        assert_eq!(paths.staging, PathBuf::from("/tmp/stage"));
+        assert_eq!(paths.artifacts, PathBuf::from("out"));
+        assert_eq!(paths.api_cache, PathBuf::from("/env/api-cache"));
+        assert_eq!(paths.logs, PathBuf::from("/env/logs"));
+
+        let table = paths.format();
+        assert!(table.starts_with("home         /env  (from COPTER_HOME)\n"));
+        assert!(table.contains("staging      /tmp/stage\n"));
+    }
+}
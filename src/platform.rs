@@ -0,0 +1,171 @@
+//! Dependents that can't be built on this host's OS
+//!
+//! A crate that only builds on Windows or macOS fails on a Linux runner no
+//! matter which version of the base crate it gets, so counting it BROKEN is
+//! noise. A failed baseline is classified as platform-unsupported when its
+//! errors match a known OS-specific pattern, or when the dependent's docs.rs
+//! metadata only lists targets for other operating systems. With `--targets`,
+//! such dependents are checked with `cargo check --target` for a matching
+//! triple instead (tests can't run for a foreign target).
+//!
+//! The host itself has gaps too: per-process limits and CPU accounting need
+//! unix. Options that depend on them are skipped with a warning
+//! (`unsupported_options`) rather than failing the run.
+
+use crate::cli::CliArgs;
+use crate::compile::ThreeStepResult;
+
+/// Error fragments that mean the code needs another OS; None when the
+/// message doesn't say which
+const PATTERNS: &[(&str, Option<&str>)] = &[
+    ("could not find `windows` in `os`", Some("windows")),
+    ("std::os::windows", Some("windows")),
+    ("only supports windows", Some("windows")),
+    ("only works on windows", Some("windows")),
+    ("could not find `macos` in `os`", Some("macos")),
+    ("std::os::macos", Some("macos")),
+    ("only supports macos", Some("macos")),
+    ("could not find `unix` in `os`", Some("linux")),
+    ("std::os::unix", Some("linux")),
+    ("only supports linux", Some("linux")),
+    ("not supported on this platform", None),
+    ("unsupported platform", None),
+    ("unsupported target", None),
+    ("this crate only supports", None),
+];
+
+/// Why a dependent can't be built here
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// OS the dependent needs ("windows", "macos", ...), if known
+    pub os: Option<String>,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.os {
+            Some(os) => write!(f, "{}-only ({})", os, self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/// Operating system of a target triple, as in `std::env::consts::OS`
+pub fn target_os(triple: &str) -> Option<&'static str> {
+    [
+        ("windows", "windows"),
+        ("apple-darwin", "macos"),
+        ("apple-ios", "ios"),
+        ("android", "android"),
+        ("linux", "linux"),
+        ("freebsd", "freebsd"),
+        ("wasm32", "wasm"),
+    ].iter().find(|(fragment, _)| triple.contains(fragment)).map(|(_, os)| *os)
+}
+
+/// Decide whether a failed baseline failed because of the host OS
+///
+/// `declared_targets` are the dependent's docs.rs targets; `host_os` is
+/// `std::env::consts::OS` outside of tests.
+pub fn detect(result: &ThreeStepResult, declared_targets: &[String], host_os: &str) -> Option<Unsupported> {
+    let failure = result.first_failure()?;
+    let mut output = failure.stderr.to_lowercase();
+    for diagnostic in &failure.diagnostics {
+        output.push_str(&diagnostic.rendered.to_lowercase());
+    }
+
+    for (pattern, os) in PATTERNS {
+        if output.contains(pattern) && *os != Some(host_os) {
+            // The docs.rs targets may name the OS the message doesn't
+            let os = os.map(str::to_string).or_else(|| {
+                declared_targets.iter().filter_map(|t| target_os(t)).find(|os| *os != host_os).map(str::to_string)
+            });
+            return Some(Unsupported { os, reason: format!("build error mentions \"{}\"", pattern) });
+        }
+    }
+
+    let declared_os: Vec<&str> = declared_targets.iter().filter_map(|t| target_os(t)).collect();
+    if !declared_os.is_empty() && !declared_os.contains(&host_os) {
+        return Some(Unsupported {
+            os: Some(declared_os[0].to_string()),
+            reason: format!("docs.rs targets: {}", declared_targets.join(", ")),
+        });
+    }
+    None
+}
+
+/// First of the `--targets` triples for the OS the dependent needs
+pub fn matching_target<'a>(unsupported: &Unsupported, targets: &'a [String]) -> Option<&'a String> {
+    let os = unsupported.os.as_deref()?;
+    targets.iter().find(|t| target_os(t) == Some(os))
+}
+
+/// Options this host can't honor, with the reason each is skipped
+pub fn unsupported_options(args: &CliArgs) -> Vec<(&'static str, &'static str)> {
+    unsupported_options_on(args, cfg!(unix))
+}
+
+fn unsupported_options_on(args: &CliArgs, unix: bool) -> Vec<(&'static str, &'static str)> {
+    if unix {
+        return vec![];
+    }
+    let mut skipped = Vec::new();
+    if args.max_memory.is_some() {
+        skipped.push(("--max-memory", "per-process limits use setrlimit, which only exists on unix"));
+    }
+    if args.max_cpu_time.is_some() {
+        skipped.push(("--max-cpu-time", "per-process limits use setrlimit, which only exists on unix"));
+    }
+    if args.max_total_cpu_hours.is_some() {
+        skipped.push(("--max-total-cpu-hours", "child CPU time comes from getrusage, which only exists on unix"));
+    }
+    skipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::{CompileResult, CompileStep};
+
+    fn failed_check(stderr: &str) -> ThreeStepResult {
+        let check = CompileResult { stderr: stderr.to_string(), ..CompileResult::for_test(CompileStep::Check, false) };
+        ThreeStepResult::from_steps(CompileResult::for_test(CompileStep::Fetch, true), [check])
+    }
+
+    #[test]
+    fn test_detect_from_error_patterns() {
+        let result = failed_check("error[E0433]: failed to resolve: could not find `windows` in `os`");
+        let unsupported = detect(&result, &[], "linux").unwrap();
+        assert_eq!(unsupported.os.as_deref(), Some("windows"));
+        assert!(unsupported.to_string().starts_with("windows-only"));
+
+        // The same error on Windows is a real failure
+        assert_eq!(detect(&result, &[], "windows"), None);
+        // Unrelated errors aren't platform problems
+        assert_eq!(detect(&failed_check("error[E0308]: mismatched types"), &[], "linux"), None);
+    }
+
+    #[test]
+    fn test_detect_from_docs_rs_targets_and_route() {
+        let declared = vec!["x86_64-apple-darwin".to_string(), "aarch64-apple-darwin".to_string()];
+        let unsupported = detect(&failed_check("linker failed"), &declared, "linux").unwrap();
+        assert_eq!(unsupported.os.as_deref(), Some("macos"));
+
+        let targets = vec!["x86_64-pc-windows-msvc".to_string(), "aarch64-apple-darwin".to_string()];
+        assert_eq!(matching_target(&unsupported, &targets), Some(&targets[1]));
+        assert_eq!(matching_target(&unsupported, &targets[..1]), None);
+
+        // Declaring the host's own targets says nothing
+        assert_eq!(detect(&failed_check("linker failed"), &["x86_64-unknown-linux-gnu".to_string()], "linux"), None);
+    }
+
+    #[test]
+    fn test_unsupported_options_off_unix() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["cargo-copter", "--top-dependents", "1", "--max-memory", "4G", "--max-total-cpu-hours", "2"]);
+        assert!(unsupported_options_on(&args, true).is_empty());
+        let skipped: Vec<&str> = unsupported_options_on(&args, false).into_iter().map(|(flag, _)| flag).collect();
+        assert_eq!(skipped, vec!["--max-memory", "--max-total-cpu-hours"]);
+    }
+}
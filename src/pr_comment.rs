@@ -0,0 +1,243 @@
+//! Pull request comment (`--pr-comment`)
+//!
+//! One markdown file meant to be posted as-is on the PR under test: a
+//! headline, a compact table of the dependents that didn't pass, and a
+//! collapsed section per dependent with its version matrix, top errors and
+//! where its full log is. With `--update-marker ID` the comment starts with
+//! a hidden `<!-- cargo-copter:ID -->` line, so a bot can find the comment it
+//! posted last time and edit it instead of adding another.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::json_report::RowStatus;
+use crate::report::{dependent_status, dependents_by_status, failed_step, write_failures, write_version_matrix};
+use crate::OfferedRow;
+
+/// GitHub rejects comments longer than 65536 characters; leave room for the tail
+const MAX_COMMENT_LEN: usize = 60_000;
+
+/// Where a reader of the comment finds more than it shows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Links {
+    /// CI run that produced the report
+    pub run_url: Option<String>,
+    /// Per-dependent logs were written as artifacts (`--artifacts-dir`)
+    pub artifact_logs: bool,
+}
+
+/// The hidden line a bot searches its earlier comment for
+pub fn marker_line(marker: &str) -> String {
+    format!("<!-- cargo-copter:{} -->", marker)
+}
+
+/// Result column: REGRESSED(check), BROKEN, ..., or why it wasn't tested
+fn dependent_result(group: &[&OfferedRow]) -> String {
+    if group.iter().all(|row| row.offered.is_none()) {
+        // SKIPPED, UNSUPPORTED, NOT_RUN or ERROR; their single row says why
+        return group[0].primary.spec.clone();
+    }
+    let status = dependent_status(group);
+    let step = group.iter()
+        .filter(|row| row.offered.is_some() && crate::report::row_status(row) == RowStatus::Regressed)
+        .find_map(|row| failed_step(row));
+    match (status, step) {
+        (RowStatus::Regressed, Some(step)) => format!("REGRESSED({})", step.as_str()),
+        (status, _) => status.as_str().to_string(),
+    }
+}
+
+fn icon(result: &str) -> &'static str {
+    match result.split('(').next().unwrap_or(result) {
+        "REGRESSED" => "❌",
+        "BROKEN" | "FAILED" | "ERROR" => "⚠️",
+        "PASSED" => "✅",
+        "WARNED" => "🔶",
+        "DUPLICATED" => "🔀",
+        "MSRV" => "🕰️",
+        _ => "➖",
+    }
+}
+
+/// Why a dependent without offered-version rows wasn't tested
+fn untested_reason(row: &OfferedRow) -> String {
+    let error = row.test.commands.iter().flat_map(|cmd| &cmd.result.failures).next();
+    match error {
+        Some(failure) => failure.error_message.lines().next().unwrap_or_default().to_string(),
+        None => row.primary.resolved_version.clone(),
+    }
+}
+
+/// Table cells can't hold newlines or unescaped pipes
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Render the comment
+pub fn render(rows: &[OfferedRow], crate_name: &str, display_version: &str, notes: &[String], links: &Links, marker: Option<&str>) -> String {
+    let dependents = dependents_by_status(rows);
+    let results: Vec<String> = dependents.iter().map(|(_, group)| dependent_result(group)).collect();
+    let count = |prefix: &str| results.iter().filter(|r| r.starts_with(prefix)).count();
+    let (regressed, passed) = (count("REGRESSED"), count("PASSED"));
+
+    let mut out = String::new();
+    if let Some(marker) = marker {
+        out.push_str(&marker_line(marker));
+        out.push('\n');
+    }
+    let verdict = match regressed {
+        0 => "✅ no regressions".to_string(),
+        1 => "❌ 1 dependent regressed".to_string(),
+        n => format!("❌ {} dependents regressed", n),
+    };
+    out.push_str(&format!("### cargo-copter: {} {} - {}\n\n", crate_name, display_version, verdict));
+
+    let mut counts = vec![format!("{} passed", passed), format!("{} regressed", regressed)];
+    for (label, prefix) in [("broken", "BROKEN"), ("failed", "FAILED"), ("flaky", "FLAKY"), ("unreachable", "NETWORK")] {
+        if count(prefix) > 0 {
+            counts.push(format!("{} {}", count(prefix), label));
+        }
+    }
+    let untested = dependents.iter().filter(|(_, group)| group.iter().all(|row| row.offered.is_none())).count();
+    if untested > 0 {
+        counts.push(format!("{} not tested", untested));
+    }
+    out.push_str(&format!("{} of {} dependents", counts.join(", "), dependents.len()));
+    if let Some(ref url) = links.run_url {
+        out.push_str(&format!(" · [full report]({})", url));
+    }
+    out.push_str("\n\n");
+    for note in notes {
+        out.push_str(&format!("> **Note**: {}\n\n", note));
+    }
+
+    let attention: Vec<(&(String, Vec<&OfferedRow>), &String)> = dependents.iter().zip(&results)
+        .filter(|(_, result)| *result != "PASSED")
+        .collect();
+    if !attention.is_empty() {
+        out.push_str("| | Dependent | Requires | Result |\n|---|---|---|---|\n");
+        for ((name, group), result) in &attention {
+            let requires = match group.iter().all(|row| row.offered.is_none()) {
+                true => cell(&untested_reason(group[0])),
+                false => format!("`{}`", cell(&group[0].primary.spec)),
+            };
+            out.push_str(&format!("| {} | {} | {} | {} |\n", icon(result), cell(name), requires, result));
+        }
+        out.push('\n');
+    }
+
+    // Details go last and are cut off before the comment gets too long
+    let mut omitted = 0;
+    for ((name, group), result) in &attention {
+        if group.iter().all(|row| row.offered.is_none()) {
+            continue;
+        }
+        let section = details(name, group, result, links).unwrap_or_default();
+        if omitted > 0 || out.len() + section.len() > MAX_COMMENT_LEN {
+            omitted += 1;
+            continue;
+        }
+        out.push_str(&section);
+    }
+    if omitted > 0 {
+        out.push_str(&format!("Details of {} more dependents didn't fit in this comment; see the full report.\n\n", omitted));
+    }
+
+    let passed_names: Vec<&str> = dependents.iter().zip(&results)
+        .filter(|(_, result)| *result == "PASSED")
+        .map(|((name, _), _)| name.as_str())
+        .collect();
+    if !passed_names.is_empty() {
+        out.push_str(&format!("<details>\n<summary>{} passed</summary>\n\n{}\n\n</details>\n", passed_names.len(), passed_names.join(", ")));
+    }
+    out
+}
+
+/// Collapsed section for a dependent that didn't pass
+fn details(name: &str, group: &[&OfferedRow], result: &str, links: &Links) -> io::Result<String> {
+    let mut section = Vec::new();
+    writeln!(section, "<details>\n<summary>{} <b>{}</b>: {}</summary>\n", icon(result), name, result)?;
+    writeln!(section, "Requires `{}`.\n", group[0].primary.spec)?;
+    write_version_matrix(&mut section, group)?;
+    write_failures(&mut section, group)?;
+    if links.artifact_logs {
+        let row = &group[0].primary;
+        let log = crate::artifacts::log_file_name(&row.dependent_name, &row.dependent_version, row.channel.as_deref());
+        match links.run_url {
+            Some(ref url) => writeln!(section, "Full log: `{}` in the [run's artifacts]({}).\n", log, url)?,
+            None => writeln!(section, "Full log: `{}` in the artifacts directory.\n", log)?,
+        }
+    }
+    writeln!(section, "</details>\n")?;
+    Ok(String::from_utf8_lossy(&section).into_owned())
+}
+
+/// Write the comment to `path`
+pub fn write(path: &Path, rows: &[OfferedRow], crate_name: &str, display_version: &str, notes: &[String], links: &Links, marker: Option<&str>) -> io::Result<()> {
+    std::fs::write(path, render(rows, crate_name, display_version, notes, links, marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CrateFailure;
+
+    fn row(dependent: &str, spec: &str, offered: Option<&str>, baseline_passed: Option<bool>, passed: bool) -> OfferedRow {
+        let mut row = OfferedRow::for_test(dependent, offered, baseline_passed, passed);
+        row.primary.spec = spec.to_string();
+        if !passed {
+            row.test.commands[0].result.failures.push(CrateFailure {
+                crate_name: dependent.to_string(),
+                error_message: "error[E0425]: cannot find function `old_api`".to_string(),
+                error_codes: vec!["E0425".to_string()],
+                error_patterns: vec![],
+                failed_tests: vec![],
+                likely_causes: vec![],
+            });
+        }
+        row
+    }
+
+    #[test]
+    fn test_render_pr_comment() {
+        let mut skipped = row("lodepng", "NOT_RUN", None, None, true);
+        skipped.primary.resolved_version = "--max-duration 30m 00s reached".to_string();
+        let rows = vec![
+            row("image", "^0.8", None, None, true),
+            row("image", "^0.8", Some("0.8.91"), Some(true), true),
+            row("ravif", "^0.8.4", None, None, true),
+            row("ravif", "^0.8.4", Some("0.8.91"), Some(true), false),
+            skipped,
+        ];
+        let links = Links { run_url: Some("https://github.com/o/r/actions/runs/1".to_string()), artifact_logs: true };
+        let comment = render(&rows, "rgb", "0.8.91", &[], &links, Some("rgb"));
+
+        assert!(comment.starts_with("<!-- cargo-copter:rgb -->\n### cargo-copter: rgb 0.8.91 - ❌ 1 dependent regressed\n"));
+        assert!(comment.contains("1 passed, 1 regressed, 1 not tested of 3 dependents · [full report](https://github.com/o/r/actions/runs/1)"));
+        assert!(comment.contains("| ❌ | ravif 0.25.8 | `^0.8.4` | REGRESSED(check) |"));
+        assert!(comment.contains("| ➖ | lodepng 0.25.8 | --max-duration 30m 00s reached | NOT_RUN |"));
+        assert!(comment.contains("<summary>❌ <b>ravif 0.25.8</b>: REGRESSED(check)</summary>"));
+        assert!(comment.contains("cannot find function `old_api`"));
+        assert!(comment.contains("Full log: `ravif-0.25.8.log.zst` in the [run's artifacts]"));
+        assert!(comment.contains("<summary>1 passed</summary>\n\nimage 0.25.8"));
+
+        let without_marker = render(&rows, "rgb", "0.8.91", &[], &Links::default(), None);
+        assert!(without_marker.starts_with("### cargo-copter"));
+        assert!(!without_marker.contains("Full log"));
+    }
+
+    #[test]
+    fn test_long_comment_is_cut() {
+        let mut rows = Vec::new();
+        for i in 0..400 {
+            let name = format!("dependent-{}", i);
+            rows.push(row(&name, "^0.8", None, None, true));
+            let mut offered = row(&name, "^0.8", Some("0.8.91"), Some(true), false);
+            offered.test.commands[0].result.failures[0].error_message = "error: cannot find\n".repeat(20);
+            rows.push(offered);
+        }
+        let comment = render(&rows, "rgb", "0.8.91", &[], &Links::default(), None);
+        assert!(comment.len() < 65_536);
+        assert!(comment.contains("more dependents didn't fit in this comment"));
+    }
+}
@@ -0,0 +1,244 @@
+//! Live progress display for interactive runs
+//!
+//! While dependents build, the bottom of the terminal shows an overall bar
+//! (dependents done, elapsed time, and an ETA from the durations of those
+//! finished so far) and one line per dependent in flight with the version
+//! and step it is on. The display is only drawn when stdout and stderr are
+//! both terminals; results printed to stdout go through `suspend`, which
+//! clears the display, prints, and lets the next tick redraw it.
+
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+use crate::format::HumanDuration;
+
+/// How often the display is redrawn
+const TICK: Duration = Duration::from_millis(250);
+const BAR_WIDTH: usize = 24;
+
+/// A dependent being tested
+#[derive(Debug, Clone)]
+struct Task {
+    name: String,
+    started: Instant,
+    /// Versions finished and to test
+    done: usize,
+    total: usize,
+    /// Version being tested and its current step
+    version: String,
+    step: String,
+}
+
+#[derive(Debug)]
+struct State {
+    total: usize,
+    jobs: usize,
+    started: Instant,
+    /// Wall time of each finished dependent
+    finished: Vec<Duration>,
+    /// Keyed by the worker thread testing the dependent
+    tasks: HashMap<ThreadId, Task>,
+    /// Lines currently on screen
+    drawn: usize,
+}
+
+lazy_static! {
+    static ref STATE: Mutex<Option<State>> = Mutex::new(None);
+}
+
+/// Start drawing progress for a run of `total` dependents, if attached to a terminal
+pub fn start(total: usize, jobs: usize) {
+    if !std::io::stdout().is_terminal() || !std::io::stderr().is_terminal() {
+        return;
+    }
+    *STATE.lock().unwrap() = Some(State {
+        total,
+        jobs,
+        started: Instant::now(),
+        finished: Vec::new(),
+        tasks: HashMap::new(),
+        drawn: 0,
+    });
+    thread::spawn(|| loop {
+        thread::sleep(TICK);
+        let mut state = STATE.lock().unwrap();
+        match state.as_mut() {
+            Some(state) => state.redraw(),
+            None => break,
+        }
+    });
+}
+
+/// Clear the display and stop drawing
+pub fn stop() {
+    if let Some(mut state) = STATE.lock().unwrap().take() {
+        state.clear();
+    }
+}
+
+/// Print to stdout without garbling the display: it's cleared first and
+/// redrawn on the next tick
+pub fn suspend<F: FnOnce()>(f: F) {
+    let mut state = STATE.lock().unwrap();
+    if let Some(state) = state.as_mut() {
+        state.clear();
+    }
+    f();
+    let _ = std::io::stdout().flush();
+}
+
+/// The current thread starts testing dependent `name`, with `versions` versions to test
+pub fn begin(name: &str, versions: usize) {
+    with_state(|state| {
+        state.tasks.insert(thread::current().id(), Task {
+            name: name.to_string(),
+            started: Instant::now(),
+            done: 0,
+            total: versions,
+            version: String::new(),
+            step: String::new(),
+        });
+    });
+}
+
+/// The current thread's dependent moves on to the `index`th of `total` versions
+pub fn version(index: usize, total: usize, label: &str) {
+    with_task(|task| {
+        task.done = index;
+        task.total = total;
+        task.version = label.to_string();
+        task.step.clear();
+    });
+}
+
+/// The current thread's dependent starts a cargo step
+pub fn step(step: &str) {
+    with_task(|task| task.step = step.to_string());
+}
+
+/// The current thread finished its dependent
+pub fn end() {
+    with_state(|state| {
+        if let Some(task) = state.tasks.remove(&thread::current().id()) {
+            state.finished.push(task.started.elapsed());
+        }
+    });
+}
+
+fn with_state<F: FnOnce(&mut State)>(f: F) {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        f(state);
+    }
+}
+
+fn with_task<F: FnOnce(&mut Task)>(f: F) {
+    with_state(|state| {
+        if let Some(task) = state.tasks.get_mut(&thread::current().id()) {
+            f(task);
+        }
+    });
+}
+
+/// Time left for `remaining` dependents on `jobs` workers, from the mean
+/// duration of those finished; None until one has finished
+pub fn eta(finished: &[Duration], remaining: usize, jobs: usize) -> Option<Duration> {
+    if finished.is_empty() {
+        return None;
+    }
+    let mean = finished.iter().sum::<Duration>() / finished.len() as u32;
+    let rounds = remaining.div_ceil(jobs.max(1));
+    Some(mean * rounds as u32)
+}
+
+/// `[=========>          ]`
+fn bar(done: usize, total: usize, width: usize) -> String {
+    let filled = (done * width).checked_div(total).unwrap_or(0).min(width);
+    let head = if filled < width && done > 0 { ">" } else { "" };
+    format!("[{}{}{}]", "=".repeat(filled), head, " ".repeat(width - filled - head.len()))
+}
+
+impl State {
+    fn lines(&self) -> Vec<String> {
+        let done = self.finished.len();
+        let eta = match eta(&self.finished, self.total.saturating_sub(done), self.jobs) {
+            Some(eta) => format!("ETA {}", HumanDuration(eta)),
+            None => "ETA --".to_string(),
+        };
+        let mut lines = vec![format!("{} {}/{} dependents  {} elapsed  {}",
+            bar(done, self.total, BAR_WIDTH), done, self.total, HumanDuration(self.started.elapsed()), eta)];
+
+        let mut tasks: Vec<&Task> = self.tasks.values().collect();
+        tasks.sort_by_key(|task| task.started);
+        for task in tasks {
+            let mut line = format!("  {} {} {}/{}", bar(task.done, task.total, BAR_WIDTH / 2), task.name, task.done, task.total);
+            if !task.version.is_empty() {
+                line.push_str(&format!("  {}", task.version));
+            }
+            if !task.step.is_empty() {
+                line.push_str(&format!(": {}", task.step));
+            }
+            line.push_str(&format!("  {}", HumanDuration(task.started.elapsed())));
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn redraw(&mut self) {
+        let width = terminal_size::terminal_size().map(|(w, _)| w.0 as usize).unwrap_or(80);
+        let mut out = String::new();
+        self.erase_into(&mut out);
+        let lines = self.lines();
+        for line in &lines {
+            // Wrapped lines would throw off the count of lines to erase
+            out.extend(line.chars().take(width.saturating_sub(1)));
+            out.push('\n');
+        }
+        self.drawn = lines.len();
+        let mut stderr = std::io::stderr().lock();
+        let _ = stderr.write_all(out.as_bytes());
+        let _ = stderr.flush();
+    }
+
+    fn clear(&mut self) {
+        let mut out = String::new();
+        self.erase_into(&mut out);
+        self.drawn = 0;
+        let mut stderr = std::io::stderr().lock();
+        let _ = stderr.write_all(out.as_bytes());
+        let _ = stderr.flush();
+    }
+
+    /// Cursor up and erase each drawn line
+    fn erase_into(&self, out: &mut String) {
+        for _ in 0..self.drawn {
+            out.push_str("\x1b[1A\x1b[2K");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(&[], 10, 4), None);
+        let finished = [Duration::from_secs(30), Duration::from_secs(90)];
+        // Mean 60s; 10 left on 4 workers is 3 rounds
+        assert_eq!(eta(&finished, 10, 4), Some(Duration::from_secs(180)));
+        assert_eq!(eta(&finished, 0, 4), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(0, 4, 8), "[        ]");
+        assert_eq!(bar(2, 4, 8), "[====>   ]");
+        assert_eq!(bar(4, 4, 8), "[========]");
+        assert_eq!(bar(0, 0, 8), "[        ]");
+    }
+}
@@ -0,0 +1,224 @@
+//! Local registry snapshot for offline runs
+//!
+//! A snapshot is a cargo local registry (as written by
+//! `cargo local-registry`): an `index/` directory in the crates.io index
+//! layout plus `{name}-{version}.crate` files at the root. With
+//! `--registry-snapshot` every lookup and download the tool itself does is
+//! served from it, and every cargo invocation replaces crates.io with it, so
+//! no network access is needed once the snapshot is prepared.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::debug;
+use semver::Version;
+use serde::Deserialize;
+
+/// Source name used in the `--config` source replacement
+const SOURCE_NAME: &str = "copter-snapshot";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistrySnapshot {
+    root: PathBuf,
+}
+
+/// One line of an index file: a published version
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub vers: String,
+    #[serde(default)]
+    pub deps: Vec<IndexDependency>,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexDependency {
+    pub name: String,
+    /// Real crate name when the dependency is renamed
+    #[serde(default)]
+    pub package: Option<String>,
+    /// Index of another registry the dependency comes from; None for the same registry
+    #[serde(default)]
+    pub registry: Option<String>,
+}
+
+impl IndexDependency {
+    fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl IndexEntry {
+    /// Whether this version depends on `crate_name` from its own registry
+    pub fn depends_on(&self, crate_name: &str) -> bool {
+        self.name != crate_name && self.deps.iter().any(|d| d.registry.is_none() && d.crate_name() == crate_name)
+    }
+}
+
+impl RegistrySnapshot {
+    pub fn open(root: &Path) -> Result<Self, String> {
+        let root = fs::canonicalize(root)
+            .map_err(|e| format!("Registry snapshot {}: {}", root.display(), e))?;
+        if !root.join("index").is_dir() {
+            return Err(format!("Registry snapshot {} has no index/ directory", root.display()));
+        }
+        Ok(RegistrySnapshot { root })
+    }
+
+    /// Path of a crate's index file
+    fn index_file(&self, crate_name: &str) -> PathBuf {
+        self.root.join("index").join(index_path(crate_name))
+    }
+
+    /// Every version of `crate_name` in the snapshot
+    pub fn versions(&self, crate_name: &str) -> Result<Vec<IndexEntry>, String> {
+        let path = self.index_file(crate_name);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("{} is not in the registry snapshot ({}): {}", crate_name, path.display(), e))?;
+        parse_index(&content)
+    }
+
+    /// Bytes of `{name}-{version}.crate`
+    pub fn read_crate(&self, crate_name: &str, version: &str) -> Result<Vec<u8>, String> {
+        let path = self.root.join(format!("{}-{}.crate", crate_name, version));
+        fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    }
+
+    /// Crates whose newest non-yanked version depends on `crate_name`, by name
+    pub fn reverse_dependencies(&self, crate_name: &str) -> Result<Vec<String>, String> {
+        let mut dependents = Vec::new();
+        let mut dirs = vec![self.root.join("index")];
+
+        while let Some(dir) = dirs.pop() {
+            let entries = fs::read_dir(&dir)
+                .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let file_name = entry.file_name();
+                if file_name.to_string_lossy().starts_with('.') || file_name == "config.json" {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+
+                let content = match fs::read_to_string(&path) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        debug!("skipping unreadable index file {:?}: {}", path, e);
+                        continue;
+                    }
+                };
+                let latest = parse_index(&content).ok().and_then(latest_entry);
+                if let Some(latest) = latest {
+                    if latest.depends_on(crate_name) {
+                        dependents.push(latest.name);
+                    }
+                }
+            }
+        }
+
+        dependents.sort();
+        Ok(dependents)
+    }
+
+    /// `--config` arguments that make cargo use the snapshot instead of crates.io
+    pub fn cargo_config_args(&self) -> Vec<String> {
+        vec![
+            "--config".to_string(),
+            format!("source.crates-io.replace-with=\"{}\"", SOURCE_NAME),
+            "--config".to_string(),
+            format!("source.{}.local-registry=\"{}\"", SOURCE_NAME, self.root.display().to_string().replace('\\', "/")),
+        ]
+    }
+}
+
+/// Path of a crate's index file relative to the index root, following the
+/// crates.io index layout (shared by local registries and the sparse index)
+pub fn index_path(crate_name: &str) -> String {
+    let name = crate_name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Parse an index file: one JSON entry per line
+pub fn parse_index(content: &str) -> Result<Vec<IndexEntry>, String> {
+    content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|e| format!("Invalid index entry: {}", e)))
+        .collect()
+}
+
+/// Newest non-yanked version
+pub fn latest_entry(entries: Vec<IndexEntry>) -> Option<IndexEntry> {
+    entries.into_iter()
+        .filter(|e| !e.yanked)
+        .filter_map(|e| Version::parse(&e.vers).ok().map(|v| (v, e)))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, e)| e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_index(root: &Path, rel: &str, lines: &[&str]) {
+        let path = root.join("index").join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, lines.join("\n")).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_lookups() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write_index(root, "3/r/rgb", &[
+            r#"{"name":"rgb","vers":"0.8.50","deps":[],"yanked":false}"#,
+            r#"{"name":"rgb","vers":"0.8.52","deps":[],"yanked":false}"#,
+        ]);
+        write_index(root, "im/ag/image", &[
+            r#"{"name":"image","vers":"0.25.8","deps":[{"name":"rgb","req":"^0.8"}],"yanked":false}"#,
+        ]);
+        write_index(root, "ra/vi/ravif", &[
+            r#"{"name":"ravif","vers":"0.11.0","deps":[{"name":"rgb","req":"^0.8"}],"yanked":false}"#,
+            r#"{"name":"ravif","vers":"0.12.0","deps":[],"yanked":false}"#,
+        ]);
+        write_index(root, "lo/de/lodepng", &[
+            r#"{"name":"lodepng","vers":"3.10.5","deps":[{"name":"colors","req":"^0.8","package":"rgb"}],"yanked":false}"#,
+        ]);
+        fs::write(root.join("rgb-0.8.52.crate"), b"crate bytes").unwrap();
+
+        let snapshot = RegistrySnapshot::open(root).unwrap();
+        let versions: Vec<String> = snapshot.versions("rgb").unwrap().into_iter().map(|e| e.vers).collect();
+        assert_eq!(versions, vec!["0.8.50", "0.8.52"]);
+        assert_eq!(snapshot.read_crate("rgb", "0.8.52").unwrap(), b"crate bytes");
+        assert!(snapshot.versions("serde").is_err());
+
+        // ravif dropped rgb in its newest version; lodepng renamed it
+        assert_eq!(snapshot.reverse_dependencies("rgb").unwrap(), vec!["image", "lodepng"]);
+
+        let args = snapshot.cargo_config_args();
+        assert_eq!(args[1], "source.crates-io.replace-with=\"copter-snapshot\"");
+        assert!(args[3].starts_with("source.copter-snapshot.local-registry="));
+    }
+
+    #[test]
+    fn test_index_path() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("cc"), "2/cc");
+        assert_eq!(index_path("rgb"), "3/r/rgb");
+        assert_eq!(index_path("Serde_JSON"), "se/rd/serde_json");
+    }
+
+    #[test]
+    fn test_open_requires_index() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(RegistrySnapshot::open(dir.path()).is_err());
+    }
+}
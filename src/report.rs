@@ -1,16 +1,27 @@
-/// Report generation module - Clean rewrite for OfferedRow streaming
-///
-/// Provides console table output, HTML, and markdown reports
+//! Report generation module - Clean rewrite for OfferedRow streaming
+//!
+//! Provides console table output, HTML, and markdown reports
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
-use crate::{OfferedRow, DependencyRef, OfferedVersion, TestExecution, TestCommand, CommandType, CommandResult, CrateFailure, TransitiveTest, VersionSource};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::{OfferedRow, CommandType, TestCommand, VersionSource};
+use crate::error_extract::{error_code_url, ErrorPattern};
+use crate::format::{HumanDuration, HumanSize};
+use crate::graph::DependencyGraph;
+use crate::json_report::RowStatus;
 use term::color::Color;
 use unicode_width::{UnicodeWidthStr, UnicodeWidthChar};
 use terminal_size::{Width, terminal_size};
 use lazy_static::lazy_static;
 
+/// `println!` for table lines, honoring `--ascii`
+macro_rules! table_println {
+    ($($arg:tt)*) => { println!("{}", glyphs(&format!($($arg)*))) };
+}
+
 //
 // Rendering Model Types
 //
@@ -20,7 +31,6 @@ use lazy_static::lazy_static;
 pub enum StatusIcon {
     Passed,     // ✓
     Failed,     // ✗
-    Skipped,    // ⊘
 }
 
 impl StatusIcon {
@@ -28,7 +38,6 @@ impl StatusIcon {
         match self {
             StatusIcon::Passed => "✓",
             StatusIcon::Failed => "✗",
-            StatusIcon::Skipped => "⊘",
         }
     }
 }
@@ -144,14 +153,14 @@ impl TableWidths {
         let available = terminal_width.saturating_sub(borders);
 
         // Use fixed widths for columns with known/predictable values
-        // Offered: "✗ ≠0.8.91-preview [≠→!]" max ~28 chars
-        let offered = 25;
+        // Offered: "✓ =this(0.8.91+g1a2b3c4.dirty)" max ~32 chars
+        let offered = 34;
         // Spec: "^0.8.52" or "→ =this" max ~12 chars
         let spec = 12;
         // Resolved: "0.8.91-preview 📦" max ~18 chars
         let resolved = 18;
-        // Result: "REGRESSED ✓✗  1.3s" fixed ~20 chars
-        let result = 25;
+        // Result: "REGRESSED(check) ✓✗- 1m 30s" fixed ~28 chars
+        let result = 28;
 
         // Dependent gets remaining space (for long crate names)
         let fixed_total = offered + spec + resolved + result;
@@ -181,9 +190,23 @@ fn get_terminal_width() -> usize {
     }
 }
 
+/// `--table-width`, or 0 to size the table to the terminal
+static TABLE_WIDTH: AtomicUsize = AtomicUsize::new(0);
+/// `--wrap`: soft-wrap long cells instead of truncating them
+static WRAP: AtomicBool = AtomicBool::new(false);
+
+/// Table layout options; call before anything is printed
+pub fn init_table(width: Option<usize>, wrap: bool) {
+    TABLE_WIDTH.store(width.unwrap_or(0), Ordering::Relaxed);
+    WRAP.store(wrap, Ordering::Relaxed);
+}
+
 // Calculate table widths once at startup
 lazy_static! {
-    static ref WIDTHS: TableWidths = TableWidths::new(get_terminal_width());
+    static ref WIDTHS: TableWidths = TableWidths::new(match TABLE_WIDTH.load(Ordering::Relaxed) {
+        0 => get_terminal_width(),
+        width => width,
+    });
 }
 
 /// Print table header
@@ -212,7 +235,7 @@ pub fn format_table_header(crate_name: &str, display_version: &str, total_deps:
              width1 = w.offered, width2 = w.spec, width3 = w.resolved,
              width4 = w.dependent, width5 = w.result));
 
-    output
+    glyphs(&output)
 }
 
 pub fn print_table_header(crate_name: &str, display_version: &str, total_deps: usize) {
@@ -222,19 +245,194 @@ pub fn print_table_header(crate_name: &str, display_version: &str, total_deps: u
 /// Print separator line between dependents
 pub fn print_separator_line() {
     let w = &*WIDTHS;
-    println!("├{:─<width1$}┼{:─<width2$}┼{:─<width3$}┼{:─<width4$}┼{:─<width5$}┤",
+    table_println!("├{:─<width1$}┼{:─<width2$}┼{:─<width3$}┼{:─<width4$}┼{:─<width5$}┤",
              "", "", "", "", "",
              width1 = w.offered, width2 = w.spec, width3 = w.resolved,
              width4 = w.dependent, width5 = w.result);
 }
 
+//
+// CI log groups
+//
+
+/// CI system whose log viewer supports collapsible sections
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    GitHubActions,
+    GitLab,
+}
+
+impl CiProvider {
+    /// Detect the CI system from its standard environment variables
+    pub fn detect() -> Option<Self> {
+        if std::env::var("GITHUB_ACTIONS").map(|v| v == "true").unwrap_or(false) {
+            Some(CiProvider::GitHubActions)
+        } else if std::env::var("GITLAB_CI").is_ok() {
+            Some(CiProvider::GitLab)
+        } else {
+            None
+        }
+    }
+
+    /// Marker line that opens a collapsible section titled `title`
+    pub fn group_start(&self, title: &str) -> String {
+        match self {
+            CiProvider::GitHubActions => format!("::group::{}", title),
+            CiProvider::GitLab => format!(
+                "\x1b[0Ksection_start:{}:{}[collapsed=true]\r\x1b[0K{}",
+                unix_timestamp(), gitlab_section_name(title), title
+            ),
+        }
+    }
+
+    /// Marker line that closes the section opened for `title`
+    pub fn group_end(&self, title: &str) -> String {
+        match self {
+            CiProvider::GitHubActions => "::endgroup::".to_string(),
+            CiProvider::GitLab => format!(
+                "\x1b[0Ksection_end:{}:{}\r\x1b[0K",
+                unix_timestamp(), gitlab_section_name(title)
+            ),
+        }
+    }
+
+    /// Web page of the current CI job, from the standard environment variables
+    pub fn run_url(&self) -> Option<String> {
+        let var = |name| std::env::var(name).ok();
+        match self {
+            CiProvider::GitHubActions => Some(format!("{}/{}/actions/runs/{}",
+                var("GITHUB_SERVER_URL")?, var("GITHUB_REPOSITORY")?, var("GITHUB_RUN_ID")?)),
+            CiProvider::GitLab => var("CI_JOB_URL"),
+        }
+    }
+}
+
+/// GitLab section names may only contain letters, digits, '_', '.' and '-'
+fn gitlab_section_name(title: &str) -> String {
+    title.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') { c } else { '_' })
+        .collect()
+}
+
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+lazy_static! {
+    static ref CI_PROVIDER: Option<CiProvider> = CiProvider::detect();
+}
+
+/// When to color console output (`--color`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color a terminal unless NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color output; `no_color` is a non-empty NO_COLOR (https://no-color.org)
+    pub fn enabled(self, no_color: bool, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => !no_color && is_terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+static USE_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Decide once whether stdout gets colored, before anything is printed
+pub fn init_color(choice: ColorChoice) {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    USE_COLOR.store(choice.enabled(no_color, std::io::stdout().is_terminal()), Ordering::Relaxed);
+}
+
+/// Whether console output is colored
+pub fn use_color() -> bool {
+    USE_COLOR.load(Ordering::Relaxed)
+}
+
+/// `term` handle for stdout, or None when output isn't colored
+pub fn color_stdout() -> Option<Box<term::StdoutTerminal>> {
+    if use_color() { term::stdout() } else { None }
+}
+
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Draw tables and status icons with plain ASCII (`--ascii`)
+pub fn init_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+/// ASCII stand-in for a glyph of the table, the same display width
+fn ascii_glyph(c: char) -> Option<&'static str> {
+    Some(match c {
+        '│' => "|",
+        '─' | '—' => "-",
+        '━' => "=",
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' => "+",
+        '✓' => "+",
+        '✗' => "x",
+        '⊘' => "o",
+        '⚠' => "!",
+        '↑' => "^",
+        '≠' => "#",
+        '→' => ">",
+        '📦' => "io",
+        '📁' => "fs",
+        '🔀' => "gi",
+        _ => return None,
+    })
+}
+
+/// `s` with table glyphs swapped for ASCII when `--ascii` is on; widths are
+/// kept, so it can be applied to already padded lines
+fn glyphs(s: &str) -> String {
+    if !ASCII.load(Ordering::Relaxed) {
+        return s.to_string();
+    }
+    to_ascii(s)
+}
+
+fn to_ascii(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    for c in s.chars() {
+        match ascii_glyph(c) {
+            Some(ascii) => output.push_str(ascii),
+            None => output.push(c),
+        }
+    }
+    output
+}
+
+/// Open a collapsible CI log section for one dependent (no-op outside CI)
+pub fn print_group_start(title: &str) {
+    if let Some(provider) = *CI_PROVIDER {
+        println!("{}", provider.group_start(title));
+    }
+}
+
+/// Close the CI log section opened by `print_group_start` (no-op outside CI)
+pub fn print_group_end(title: &str) {
+    if let Some(provider) = *CI_PROVIDER {
+        println!("{}", provider.group_end(title));
+    }
+}
+
 /// Format table footer as a string
 pub fn format_table_footer() -> String {
     let w = &*WIDTHS;
-    format!("└{:─<width1$}┴{:─<width2$}┴{:─<width3$}┴{:─<width4$}┴{:─<width5$}┘\n",
+    glyphs(&format!("└{:─<width1$}┴{:─<width2$}┴{:─<width3$}┴{:─<width4$}┴{:─<width5$}┘\n",
              "", "", "", "", "",
              width1 = w.offered, width2 = w.spec, width3 = w.resolved,
-             width4 = w.dependent, width5 = w.result)
+             width4 = w.dependent, width5 = w.result))
 }
 
 /// Print table footer
@@ -250,27 +448,20 @@ pub fn print_offered_row(row: &OfferedRow, is_last_in_group: bool) {
     // Use dynamic widths
     let w = &*WIDTHS;
 
-    // Print main row
-    let offered_display = truncate_with_padding(&offered_str, w.offered - 2);
-    let spec_display = truncate_with_padding(&spec_str, w.spec - 2);
-    let resolved_display = truncate_with_padding(&resolved_str, w.resolved - 2);
-    let dependent_display = truncate_with_padding(&dependent_str, w.dependent - 2);
-    let result_display = format!("{:>12} {:>5}", result_str, time_str);
-    let result_display = truncate_with_padding(&result_display, w.result - 2);
-
     // Print main row with color
-    if let Some(ref mut t) = term::stdout() {
-        let _ = t.fg(color);
-        let _ = write!(t, "│ {} │", offered_display);
-        let _ = write!(t, " {} │", spec_display);
-        let _ = write!(t, " {} │", resolved_display);
-        let _ = write!(t, " {} │", dependent_display);
-        let _ = write!(t, " {} │", result_display);
-        let _ = t.reset();
-        println!();
-    } else {
-        println!("│ {} │ {} │ {} │ {} │ {} │",
-                 offered_display, spec_display, resolved_display, dependent_display, result_display);
+    let result_display = format!("{:>12} {:>5}", result_str, time_str);
+    for line in row_lines(&[
+        (&offered_str, w.offered - 2), (&spec_str, w.spec - 2), (&resolved_str, w.resolved - 2),
+        (&dependent_str, w.dependent - 2), (&result_display, w.result - 2),
+    ]) {
+        if let Some(ref mut t) = color_stdout() {
+            let _ = t.fg(color);
+            let _ = write!(t, "{}", glyphs(&line));
+            let _ = t.reset();
+            println!();
+        } else {
+            table_println!("{}", line);
+        }
     }
 
     // Print error details with dropped-panel border (if any)
@@ -286,31 +477,32 @@ pub fn print_offered_row(row: &OfferedRow, is_last_in_group: bool) {
         let error_text_width = w.total - 1 - shortened_offered - 1 - 1 - 1 - 1;
 
         if corner0_width > 0 {
-            println!("│{:shortened_offered$}┌{:─<corner0$}┴{:─<corner1$}┘{:padding$}└{:─<corner2$}┘{:w_result$}│",
-                     "", "", "", "", "",
+            table_println!("│{:shortened_offered$}┌{:─<corner0$}┴{:─<corner1$}┘{:padding$}└{:─<corner2$}┘{:w_result$}│",
+                     "", "", "", "", "", "",
                      shortened_offered = shortened_offered, corner0 = corner0_width, corner1 = corner1_width,
                      padding = padding_width, corner2 = corner2_width, w_result = w.result);
         } else {
-            println!("│{:w_offered$}├{:─<corner1$}┘{:padding$}└{:─<corner2$}┘{:w_result$}│",
+            table_println!("│{:w_offered$}├{:─<corner1$}┘{:padding$}└{:─<corner2$}┘{:w_result$}│",
                     "", "", "", "", "",
                     w_offered = w.offered, corner1 = corner1_width,
                     padding = padding_width, corner2 = corner2_width, w_result = w.result);
         }
         for error_line in &error_details {
-            let truncated = truncate_with_padding(error_line, error_text_width);
-            println!("│{:shortened_offered$}│ {} │",
-                     "", truncated,
-                     shortened_offered = shortened_offered);
+            for line in cell_lines(error_line, error_text_width) {
+                table_println!("│{:shortened_offered$}│ {} │",
+                         "", line,
+                         shortened_offered = shortened_offered);
+            }
         }
 
         if !is_last_in_group {
             if corner0_width > 0 {
-                println!("│{:shortened_offered$}└{:─<corner0$}┬{:─<corner1$}┬{:─<corner2$}┬{:─<corner3$}┬{:─<corner4$}┤",
-                         "", "", "", "", "",
+                table_println!("│{:shortened_offered$}└{:─<corner0$}┬{:─<corner1$}┬{:─<corner2$}┬{:─<corner3$}┬{:─<corner4$}┤",
+                         "", "", "", "", "", "",
                          shortened_offered = shortened_offered, corner0 = corner0_width, corner1 = w.spec, corner2 = w.resolved,
                          corner3 = w.dependent, corner4 = w.result);
             } else {
-                println!("│{:w_offered$}├{:─<w_spec$}┬{:─<w_resolved$}┬{:─<w_dependent$}┬{:─<w_result$}┤",
+                table_println!("│{:w_offered$}├{:─<w_spec$}┬{:─<w_resolved$}┬{:─<w_dependent$}┬{:─<w_result$}┤",
                         "", "", "", "", "",
                         w_offered = w.offered, w_spec = w.spec, w_resolved = w.resolved,
                         w_dependent = w.dependent, w_result = w.result);
@@ -319,19 +511,47 @@ pub fn print_offered_row(row: &OfferedRow, is_last_in_group: bool) {
     }
 
     // Print multi-version rows with ├─ prefixes (if any)
-    if !multi_version_rows.is_empty() {
-        for (_i, (spec, resolved, dependent)) in multi_version_rows.iter().enumerate() {
-            let spec_display = format!("├─ {}", spec);
-            let spec_display = truncate_with_padding(&spec_display, w.spec - 2);
-            let resolved_display = format!("├─ {}", resolved);
-            let resolved_display = truncate_with_padding(&resolved_display, w.resolved - 2);
-            let dependent_display = format!("├─ {}", dependent);
-            let dependent_display = truncate_with_padding(&dependent_display, w.dependent - 2);
+    for line in multi_version_rows.iter().flat_map(|row| multi_version_lines(row, w)) {
+        table_println!("{}", line);
+    }
+}
 
-            println!("│{:width$}│ {} │ {} │ {} │{:w_result$}│",
-                     "", spec_display, resolved_display, dependent_display, "",
-                     width = w.offered, w_result = w.result);
-        }
+/// Lines of a transitive dependency row: `├─ ` before spec, resolved and dependent
+fn multi_version_lines((spec, resolved, dependent): &(String, String, String), w: &TableWidths) -> Vec<String> {
+    row_lines(&[
+        ("", w.offered - 2),
+        (&format!("├─ {}", spec), w.spec - 2),
+        (&format!("├─ {}", resolved), w.resolved - 2),
+        (&format!("├─ {}", dependent), w.dependent - 2),
+        ("", w.result - 2),
+    ])
+}
+
+/// A table row of `(text, width)` cells: one line, or with `--wrap` as many
+/// as its tallest cell needs
+fn row_lines(cells: &[(&str, usize)]) -> Vec<String> {
+    let columns: Vec<Vec<String>> = cells.iter().map(|(text, width)| cell_lines(text, *width)).collect();
+    let height = columns.iter().map(Vec::len).max().unwrap_or(1);
+    (0..height)
+        .map(|i| {
+            let mut line = String::from("│");
+            for (column, (_, width)) in columns.iter().zip(cells) {
+                match column.get(i) {
+                    Some(text) => line.push_str(&format!(" {} │", text)),
+                    None => line.push_str(&format!(" {:width$} │", "", width = width)),
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+/// A cell's text padded to `width`: truncated, or soft-wrapped with `--wrap`
+fn cell_lines(s: &str, width: usize) -> Vec<String> {
+    if WRAP.load(Ordering::Relaxed) {
+        wrap_with_padding(s, width)
+    } else {
+        vec![truncate_with_padding(s, width)]
     }
 }
 
@@ -339,8 +559,21 @@ pub fn print_offered_row(row: &OfferedRow, is_last_in_group: bool) {
 // OfferedRow to renderable format conversion
 //
 
+/// Spec followed by the base crate features the dependent's graph enables ("^0.8 +serde,rayon")
+///
+/// "default" is left out since nearly every dependent has it on.
+fn spec_with_features(spec: &str, features: &[String]) -> String {
+    let features: Vec<&str> = features.iter().map(String::as_str).filter(|f| *f != "default").collect();
+    if features.is_empty() {
+        spec.to_string()
+    } else {
+        format!("{} +{}", spec, features.join(","))
+    }
+}
+
 /// Convert OfferedRow to renderable row data
 /// Returns: (offered_str, spec_str, resolved_str, dependent_str, result_str, time_str, color, error_details, multi_version_rows)
+#[allow(clippy::type_complexity)]
 fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, String, String, Color, Vec<String>, Vec<(String, String, String)>) {
     // Format Offered column using type-safe OfferedCell
     let offered_cell = OfferedCell::from_offered_row(row);
@@ -356,6 +589,7 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
     } else {
         row.primary.spec.clone()
     };
+    let spec_str = spec_with_features(&spec_str, &row.primary.base_features);
 
     // Format Resolved column
     let source_icon = match row.primary.resolved_source {
@@ -366,16 +600,29 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
     let resolved_str = format!("{} {}", row.primary.resolved_version, source_icon);
 
     // Format Dependent column
-    let dependent_str = format!("{} {}", row.primary.dependent_name, row.primary.dependent_version);
+    // Bin-only dependents are tagged, since only their binaries were checked/tested
+    let mut dependent_str = match row.primary.targets.as_deref() {
+        Some("bin") => format!("{} {} [bin]", row.primary.dependent_name, row.primary.dependent_version),
+        _ => format!("{} {}", row.primary.dependent_name, row.primary.dependent_version),
+    };
+    // Checked for another OS's target (--targets); tests didn't run
+    if let Some(ref triple) = row.primary.cross_target {
+        dependent_str.push_str(&format!(" [{}]", triple));
+    }
+    // Tested with another toolchain (--channels)
+    if let Some(ref channel) = row.primary.channel {
+        dependent_str.push_str(&format!(" [{}]", channel));
+    }
+    // Not actively maintained (--maintenance)
+    if let Some(status) = row.primary.maintenance.filter(|m| *m != crate::maintenance::Maintenance::Active) {
+        dependent_str.push_str(&format!(" [{}]", status));
+    }
 
     // Format Result column
-    let overall_passed = row.test.commands.iter().all(|cmd| cmd.result.passed);
-    let result_status = match (row.baseline_passed, overall_passed) {
-        (Some(true), true) => "PASSED",
-        (Some(true), false) => "REGRESSED",
-        (Some(false), _) => "BROKEN",
-        (None, true) => "PASSED",
-        (None, false) => "FAILED",
+    let status = row_status(row);
+    let result_status = match (status, failed_step(row)) {
+        (RowStatus::Regressed, Some(step)) => format!("REGRESSED({})", step.as_str()),
+        _ => status.as_str().to_string(),
     };
 
     // Format ICT marks
@@ -384,7 +631,12 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
         match cmd.command {
             CommandType::Fetch => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
             CommandType::Check => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
+            CommandType::Build | CommandType::Install => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
+            CommandType::Clippy => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
+            CommandType::Doc => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
             CommandType::Test => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
+            CommandType::Doctest => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
+            CommandType::Miri => ict_marks.push(if cmd.result.passed { '✓' } else { '✗' }),
         }
     }
     // Pad to 3 chars with '-' for skipped steps
@@ -398,15 +650,18 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
     let total_time: f64 = row.test.commands.iter()
         .map(|cmd| cmd.result.duration)
         .sum();
-    let time_str = format!("{:.1}s", total_time);
+    let time_str = HumanDuration::from_secs_f64(total_time).to_string();
 
     // Determine color
-    let color = match (row.baseline_passed, overall_passed) {
-        (Some(true), true) => term::color::BRIGHT_GREEN,
-        (Some(true), false) => term::color::BRIGHT_RED,
-        (Some(false), _) => term::color::BRIGHT_YELLOW,
-        (None, true) => term::color::BRIGHT_GREEN,
-        (None, false) => term::color::BRIGHT_RED,
+    let color = match status {
+        RowStatus::Passed => term::color::BRIGHT_GREEN,
+        RowStatus::Flaky => term::color::BRIGHT_MAGENTA,
+        RowStatus::Warned => term::color::YELLOW,
+        RowStatus::Duplicated => term::color::BRIGHT_YELLOW,
+        RowStatus::Msrv => term::color::BRIGHT_YELLOW,
+        RowStatus::Network => term::color::BRIGHT_BLUE,
+        RowStatus::Regressed | RowStatus::Failed => term::color::BRIGHT_RED,
+        RowStatus::Broken => term::color::BRIGHT_YELLOW,
     };
 
     // Extract error details
@@ -416,7 +671,13 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
             let cmd_name = match cmd.command {
                 CommandType::Fetch => "fetch",
                 CommandType::Check => "check",
+                CommandType::Build => "build",
+                CommandType::Install => "build --release --bins",
+                CommandType::Clippy => "clippy",
+                CommandType::Doc => "doc",
                 CommandType::Test => "test",
+                CommandType::Doctest => "test --doc",
+                CommandType::Miri => "miri test",
             };
             for failure in &cmd.result.failures {
                 error_details.push(format!("cargo {} failed on {}", cmd_name, failure.crate_name));
@@ -430,6 +691,9 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
                     }
                 }
             }
+            if let Some(ref log) = cmd.result.log {
+                error_details.push(format!("  full log: {}", log.display()));
+            }
         }
     }
 
@@ -444,7 +708,7 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
         multi_version_rows.push((
             transitive.dependency.spec.clone(),
             format!("{} {}", transitive.dependency.resolved_version, source_icon),
-            format!("{} {}", transitive.dependency.dependent_name, transitive.dependency.dependent_version),
+            format!("{} {} (depth {})", transitive.dependency.dependent_name, transitive.dependency.dependent_version, transitive.depth),
         ));
     }
 
@@ -455,22 +719,6 @@ fn format_offered_row(row: &OfferedRow) -> (String, String, String, String, Stri
 // Text formatting utilities
 //
 
-/// Truncate string to fit width, adding "..." if truncated
-fn truncate_str(s: &str, max_width: usize) -> String {
-    let char_count = s.chars().count();
-
-    if char_count <= max_width {
-        s.to_string()
-    } else if max_width >= 3 {
-        let truncate_at = max_width - 3;
-        let truncated: String = s.chars().take(truncate_at).collect();
-        format!("{}...", truncated)
-    } else {
-        let truncated: String = s.chars().take(max_width).collect();
-        truncated
-    }
-}
-
 /// Count the display width of a string, accounting for wide Unicode characters
 fn display_width(s: &str) -> usize {
     // Use unicode-width crate for accurate width calculation
@@ -485,7 +733,7 @@ fn truncate_with_padding(s: &str, width: usize) -> String {
         // Truncate
         let mut result = String::new();
         let mut current_width = 0;
-        let mut chars: Vec<char> = s.chars().collect();
+        let chars: Vec<char> = s.chars().collect();
 
         // Reserve space for "..."
         let target_width = if width >= 3 { width - 3 } else { width };
@@ -519,116 +767,459 @@ fn truncate_with_padding(s: &str, width: usize) -> String {
     }
 }
 
+/// Soft-wrap to lines of `width` display columns, each padded to it; breaks
+/// after spaces where possible, inside words only when one doesn't fit
+fn wrap_with_padding(s: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+    for word in s.split_inclusive(' ') {
+        let word_width = display_width(word.trim_end());
+        if line_width > 0 && line_width + word_width > width {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+        for c in word.chars() {
+            let c_width = UnicodeWidthChar::width(c).unwrap_or(1);
+            if line_width + c_width > width {
+                if c == ' ' {
+                    continue;  // Trailing space at a break
+                }
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            line.push(c);
+            line_width += c_width;
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines.iter().map(|line| truncate_with_padding(line.trim_end(), width)).collect()
+}
+
 //
 // Summary and statistics
 //
 
+#[derive(Default)]
 pub struct TestSummary {
     pub passed: usize,
     pub regressed: usize,
     pub broken: usize,
+    /// Passed only after retrying a failed `cargo test`
+    pub flaky: usize,
+    /// Passed with new warnings
+    pub warned: usize,
+    /// Passed with two incompatible versions of the base crate in the graph
+    pub duplicated: usize,
+    /// Passed, but need a newer Rust than the dependent's rust-version
+    pub msrv: usize,
+    /// Couldn't be fetched for network reasons; says nothing about the offered version
+    pub network: usize,
     pub total: usize,
+    /// rustc error codes across regressed and broken rows, most frequent first
+    pub error_codes: Vec<(String, usize)>,
+    /// Errors of regressed rows grouped by pattern, most dependents first
+    pub error_groups: Vec<ErrorGroup>,
+    /// Base-crate items behind regressions (`--api-diff`), most dependents first
+    pub api_breaks: Vec<ApiBreak>,
+    /// Lints warned about more often than with the baseline, most dependents first
+    pub warning_groups: Vec<WarningGroup>,
+    /// (dependent and version, note) about committed Cargo.locks (`--lockfile respect|both`)
+    pub lockfile_notes: Vec<(String, String)>,
+    /// (row label, step) for steps slower than `--slowdown-threshold`, slowest first
+    pub slowdowns: Vec<(String, crate::build_times::Slowdown)>,
+    /// (row label, artifact) for artifacts whose size changed (`--full-build`), biggest growth first
+    pub size_changes: Vec<(String, crate::sizes::SizeChange)>,
+}
+
+/// A removed or changed base-crate item and the dependents it regressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiBreak {
+    /// e.g. "removed `Rgb::as_slice`"
+    pub cause: String,
+    /// Names of the regressed dependents, sorted
+    pub dependents: Vec<String>,
+}
+
+/// An error that regressed several dependents, e.g. one removed method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorGroup {
+    pub pattern: ErrorPattern,
+    /// Names of the regressed dependents with this error, sorted
+    pub dependents: Vec<String>,
+}
+
+/// A lint the offered version made several dependents warn about, e.g. `deprecated`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarningGroup {
+    pub lint: String,
+    /// Message of one of the new warnings
+    pub example: String,
+    /// Names of the warned dependents, sorted
+    pub dependents: Vec<String>,
+}
+
+/// Error groups shown in the summary
+const SUMMARY_ERROR_GROUPS: usize = 10;
+/// Dependents named per error group in the console summary
+const SUMMARY_GROUP_DEPENDENTS: usize = 5;
+
+/// The first few names, and how many more there are
+fn dependent_names(dependents: &[String]) -> String {
+    let mut names = dependents.iter().take(SUMMARY_GROUP_DEPENDENTS).cloned().collect::<Vec<_>>().join(", ");
+    if dependents.len() > SUMMARY_GROUP_DEPENDENTS {
+        names.push_str(&format!(", +{} more", dependents.len() - SUMMARY_GROUP_DEPENDENTS));
+    }
+    names
+}
+
+fn dependents_label(count: usize) -> String {
+    match count {
+        1 => "1 dependent".to_string(),
+        n => format!("{} dependents", n),
+    }
+}
+
+/// Results that fail the run (`--fail-on`); each level includes the ones before it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FailOn {
+    /// Exit 0 whatever the results
+    None,
+    /// The offered version broke a dependent that builds with its baseline
+    #[default]
+    Regressed,
+    /// Also dependents that fail with the offered version but were already broken
+    Broken,
+    /// Also dependents that couldn't be tested (download, unpack or manifest errors)
+    Error,
+}
+
+impl FailOn {
+    /// Whether a run with `summary` and `errors` untestable dependents exits nonzero
+    pub fn fails(self, summary: &TestSummary, errors: usize) -> bool {
+        match self {
+            FailOn::None => false,
+            FailOn::Regressed => summary.regressed > 0,
+            FailOn::Broken => summary.regressed > 0 || summary.broken > 0,
+            FailOn::Error => summary.regressed > 0 || summary.broken > 0 || summary.network > 0 || errors > 0,
+        }
+    }
+}
+
+/// Classify a row the way the Result column shows it
+pub fn row_status(row: &OfferedRow) -> RowStatus {
+    if is_network_failure(row) {
+        return RowStatus::Network;
+    }
+    let overall_passed = row.test.commands.iter().filter(|cmd| counts_toward_status(row, cmd)).all(|cmd| cmd.result.passed);
+    let flaky = row.test.commands.iter().any(|cmd| cmd.result.flaky);
+    match (row.baseline_passed, overall_passed) {
+        (Some(true), true) | (None, true) if flaky => RowStatus::Flaky,
+        (Some(true), true) if row.msrv_conflict.is_some() => RowStatus::Msrv,
+        (Some(true), true) if !row.duplicate_versions.is_empty() => RowStatus::Duplicated,
+        (Some(true), true) if !row.warning_diff.is_empty() => RowStatus::Warned,
+        (Some(true), true) => RowStatus::Passed,
+        (Some(true), false) => RowStatus::Regressed,
+        (Some(false), _) => RowStatus::Broken,
+        (None, true) => RowStatus::Passed,     // PASSED (no baseline)
+        (None, false) => RowStatus::Failed,    // FAILED (no baseline)
+    }
+}
+
+/// A fetch that failed because crates.io or the network kept failing
+/// (see `api::NETWORK_MARKER`), not because of the dependent or the offered version
+fn is_network_failure(row: &OfferedRow) -> bool {
+    row.test.commands.iter()
+        .filter(|cmd| cmd.command == CommandType::Fetch && !cmd.result.passed)
+        .flat_map(|cmd| &cmd.result.failures)
+        .any(|failure| failure.error_message.contains(crate::api::NETWORK_MARKER))
+}
+
+/// First step that failed: a check regression is a compile break, a test
+/// regression a behavior change
+pub fn failed_step(row: &OfferedRow) -> Option<CommandType> {
+    row.test.commands.iter()
+        .find(|cmd| !cmd.result.passed && counts_toward_status(row, cmd))
+        .map(|cmd| cmd.command)
+}
+
+/// Optional steps (`--clippy`, `--doc`, `--doctest`, `--miri`) only matter compared to
+/// the baseline: a baseline row failing one still passes, and offered rows
+/// only carry the step when the baseline passed it
+fn counts_toward_status(row: &OfferedRow, cmd: &TestCommand) -> bool {
+    !cmd.command.is_optional() || row.offered.is_some()
 }
 
 /// Calculate summary statistics from OfferedRows
 pub fn summarize_offered_rows(rows: &[OfferedRow]) -> TestSummary {
-    let mut passed = 0;
-    let mut regressed = 0;
-    let mut broken = 0;
+    summarize_rows(rows)
+}
 
+/// Summaries per (edition, resolver) of the dependents; rows whose
+/// manifest couldn't be read are left out
+pub fn summarize_by_edition(rows: &[OfferedRow]) -> Vec<(String, String, TestSummary)> {
+    let mut groups: BTreeMap<(String, String), Vec<&OfferedRow>> = BTreeMap::new();
     for row in rows {
-        // Only count non-baseline rows
-        if row.offered.is_some() {
-            let overall_passed = row.test.commands.iter().all(|cmd| cmd.result.passed);
-
-            match (row.baseline_passed, overall_passed) {
-                (Some(true), true) => passed += 1,      // PASSED
-                (Some(true), false) => regressed += 1,  // REGRESSED
-                (Some(false), _) => broken += 1,        // BROKEN
-                (None, true) => passed += 1,            // PASSED (no baseline)
-                (None, false) => broken += 1,           // FAILED (no baseline)
-            }
+        if let (Some(edition), Some(resolver)) = (&row.primary.edition, &row.primary.resolver) {
+            groups.entry((edition.clone(), resolver.clone())).or_default().push(row);
         }
     }
+    groups.into_iter()
+        .map(|((edition, resolver), rows)| (edition, resolver, summarize_rows(rows)))
+        .collect()
+}
 
-    TestSummary {
-        passed,
-        regressed,
-        broken,
-        total: passed + regressed + broken,
+fn count_error_codes(row: &OfferedRow, counts: &mut HashMap<String, usize>) {
+    for failure in row.test.commands.iter().flat_map(|cmd| &cmd.result.failures) {
+        for code in &failure.error_codes {
+            *counts.entry(code.clone()).or_insert(0) += 1;
+        }
     }
 }
 
-/// Summarize TestResults into counts
-pub fn summarize_results(results: &[crate::TestResult]) -> TestSummary {
+fn summarize_rows<'a>(rows: impl IntoIterator<Item = &'a OfferedRow>) -> TestSummary {
     let mut passed = 0;
     let mut regressed = 0;
     let mut broken = 0;
+    let mut flaky = 0;
+    let mut warned = 0;
+    let mut duplicated = 0;
+    let mut msrv = 0;
+    let mut network = 0;
+    let mut lint_dependents: BTreeMap<String, (String, BTreeSet<String>)> = BTreeMap::new();
+    let mut code_counts: HashMap<String, usize> = HashMap::new();
+    let mut pattern_dependents: HashMap<ErrorPattern, BTreeSet<String>> = HashMap::new();
+    let mut cause_dependents: HashMap<String, BTreeSet<String>> = HashMap::new();
+    let mut lockfile_notes: BTreeSet<(String, String)> = BTreeSet::new();
+    let mut slowdowns: Vec<(String, crate::build_times::Slowdown)> = Vec::new();
+    let mut size_changes: Vec<(String, crate::sizes::SizeChange)> = Vec::new();
 
-    for result in results {
-        match &result.data {
-            crate::TestResultData::Skipped(_) => {
-                // Skip counting skipped tests
+    for row in rows {
+        for note in &row.lockfile_notes {
+            let version = row.offered.as_ref().map(|o| o.version.as_str()).unwrap_or("baseline");
+            lockfile_notes.insert((format!("{} ({})", row.primary.dependent_name, version), note.clone()));
+        }
+        if let Some(ref offered) = row.offered {
+            let label = format!("{} with {}", row.primary.dependent_label(), offered.version);
+            for slowdown in &row.slowdowns {
+                slowdowns.push((label.clone(), slowdown.clone()));
             }
-            crate::TestResultData::Error(_) => {
-                broken += 1;
+            for change in &row.size_diff {
+                size_changes.push((label.clone(), change.clone()));
             }
-            crate::TestResultData::MultiVersion(ref outcomes) => {
-                // Baseline is the first outcome
-                let baseline = outcomes.first();
-
-                let mut has_regressed = false;
-                let mut has_broken = false;
-
-                for (idx, outcome) in outcomes.iter().enumerate() {
-                    if idx == 0 {
-                        // Baseline - if it fails, mark as broken
-                        if !outcome.result.is_success() {
-                            has_broken = true;
+        }
+        // Only count non-baseline rows
+        if row.offered.is_some() {
+            match row_status(row) {
+                RowStatus::Passed => passed += 1,
+                RowStatus::Regressed => {
+                    regressed += 1;
+                    count_error_codes(row, &mut code_counts);
+                    // Only regressions say something about the offered version
+                    for failure in row.test.commands.iter().flat_map(|cmd| &cmd.result.failures) {
+                        for pattern in &failure.error_patterns {
+                            pattern_dependents.entry(pattern.clone()).or_default()
+                                .insert(row.primary.dependent_name.clone());
                         }
-                    } else {
-                        // Offered version - classify based on baseline
-                        if !outcome.result.is_success() {
-                            if let Some(base) = baseline {
-                                if base.result.is_success() {
-                                    has_regressed = true;
-                                } else {
-                                    has_broken = true;
-                                }
-                            } else {
-                                has_broken = true;
-                            }
-                        } else {
-                            // Offered version passed
-                            if let Some(base) = baseline {
-                                if base.result.is_success() {
-                                    // Don't count yet - only count if ALL offered versions pass
-                                }
-                            }
+                        for cause in &failure.likely_causes {
+                            cause_dependents.entry(cause.clone()).or_default()
+                                .insert(row.primary.dependent_name.clone());
                         }
                     }
                 }
-
-                // Count based on worst outcome
-                if has_regressed {
-                    regressed += 1;
-                } else if has_broken {
+                RowStatus::Broken | RowStatus::Failed => {  // FAILED = broken without baseline
                     broken += 1;
-                } else {
-                    passed += 1;
+                    count_error_codes(row, &mut code_counts);
                 }
+                RowStatus::Flaky => flaky += 1,
+                RowStatus::Duplicated => duplicated += 1,
+                RowStatus::Msrv => msrv += 1,
+                RowStatus::Warned => {
+                    warned += 1;
+                    for change in &row.warning_diff {
+                        let group = lint_dependents.entry(change.lint.clone()).or_insert_with(|| (change.example.clone(), BTreeSet::new()));
+                        group.1.insert(row.primary.dependent_name.clone());
+                    }
+                }
+                RowStatus::Network => network += 1,
             }
         }
     }
 
+    let mut error_codes: Vec<(String, usize)> = code_counts.into_iter().collect();
+    error_codes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut error_groups: Vec<ErrorGroup> = pattern_dependents.into_iter()
+        .map(|(pattern, dependents)| ErrorGroup { pattern, dependents: dependents.into_iter().collect() })
+        .collect();
+    error_groups.sort_by(|a, b| b.dependents.len().cmp(&a.dependents.len()).then_with(|| a.pattern.cmp(&b.pattern)));
+
+    let mut api_breaks: Vec<ApiBreak> = cause_dependents.into_iter()
+        .map(|(cause, dependents)| ApiBreak { cause, dependents: dependents.into_iter().collect() })
+        .collect();
+    api_breaks.sort_by(|a, b| b.dependents.len().cmp(&a.dependents.len()).then_with(|| a.cause.cmp(&b.cause)));
+
+    slowdowns.sort_by(|a, b| b.1.ratio().total_cmp(&a.1.ratio()));
+    size_changes.sort_by(|a, b| b.1.percent().total_cmp(&a.1.percent()));
+
+    let mut warning_groups: Vec<WarningGroup> = lint_dependents.into_iter()
+        .map(|(lint, (example, dependents))| WarningGroup { lint, example, dependents: dependents.into_iter().collect() })
+        .collect();
+    warning_groups.sort_by(|a, b| b.dependents.len().cmp(&a.dependents.len()).then_with(|| a.lint.cmp(&b.lint)));
+
     TestSummary {
         passed,
         regressed,
         broken,
-        total: passed + regressed + broken,
+        flaky,
+        warned,
+        duplicated,
+        msrv,
+        network,
+        total: passed + regressed + broken + flaky + warned + duplicated + msrv + network,
+        error_codes,
+        error_groups,
+        api_breaks,
+        warning_groups,
+        lockfile_notes: lockfile_notes.into_iter().collect(),
+        slowdowns,
+        size_changes,
     }
 }
 
+/// Smallest version bump the run's evidence allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Bump::Patch => "patch",
+            Bump::Minor => "minor",
+            Bump::Major => "major",
+        }
+    }
+
+    /// `version` bumped by this much, the way Cargo reads semver: below 1.0
+    /// the leftmost nonzero number is the breaking one, and additions go in
+    /// the patch number
+    pub fn apply(&self, version: &semver::Version) -> semver::Version {
+        let (major, minor, patch) = (version.major, version.minor, version.patch);
+        let (major, minor, patch) = match (self, major, minor) {
+            (_, 0, 0) => (0, 0, patch + 1),
+            (Bump::Major, 0, _) => (0, minor + 1, 0),
+            (_, 0, _) => (0, minor, patch + 1),
+            (Bump::Major, _, _) => (major + 1, 0, 0),
+            (Bump::Minor, _, _) => (major, minor + 1, 0),
+            (Bump::Patch, _, _) => (major, minor, patch + 1),
+        };
+        semver::Version::new(major, minor, patch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BumpRecommendation {
+    pub bump: Bump,
+    /// The evidence, strongest first
+    pub reasons: Vec<String>,
+}
+
+/// API items named in a bump reason
+const BUMP_REASON_ITEMS: usize = 5;
+
+/// Recommend the smallest bump for the offered version: regressions on versions
+/// dependents accept (in range), and with `--api-diff` the public API removed or
+/// changed (major) or only added (minor)
+///
+/// Rows forced onto a version outside the dependent's requirement, or where
+/// cargo resolved another version, say nothing about semver compatibility.
+pub fn recommend_bump(rows: &[OfferedRow], api_changes: Option<&[crate::api_diff::ApiChange]>) -> BumpRecommendation {
+    use crate::api_diff::ChangeKind;
+
+    let mut regressed = BTreeSet::new();
+    let mut tested = BTreeSet::new();
+    for row in rows {
+        let Some(ref offered) = row.offered else { continue };
+        if offered.forced || !row.primary.used_offered_version {
+            continue;
+        }
+        tested.insert(row.primary.dependent_name.clone());
+        if row_status(row) == RowStatus::Regressed {
+            regressed.insert(row.primary.dependent_name.clone());
+        }
+    }
+    let regressed: Vec<String> = regressed.into_iter().collect();
+
+    let mut bump = Bump::Patch;
+    let mut reasons = Vec::new();
+    if !regressed.is_empty() {
+        bump = Bump::Major;
+        reasons.push(format!("{} regressed on a version their requirement accepts: {}",
+            dependents_label(regressed.len()), dependent_names(&regressed)));
+    }
+    if let Some(changes) = api_changes {
+        let items = |kinds: &[ChangeKind]| -> Vec<String> {
+            changes.iter().filter(|c| kinds.contains(&c.kind)).map(|c| c.to_string()).collect()
+        };
+        let list = |items: &[String]| {
+            let mut list = items.iter().take(BUMP_REASON_ITEMS).cloned().collect::<Vec<_>>().join(", ");
+            if items.len() > BUMP_REASON_ITEMS {
+                list.push_str(&format!(", +{} more", items.len() - BUMP_REASON_ITEMS));
+            }
+            list
+        };
+        let broken = items(&[ChangeKind::Removed, ChangeKind::Changed]);
+        let added = items(&[ChangeKind::Added]);
+        if !broken.is_empty() {
+            bump = Bump::Major;
+            reasons.push(format!("public API removed or changed: {}", list(&broken)));
+        } else if !added.is_empty() {
+            bump = bump.max(Bump::Minor);
+            reasons.push(format!("public API added: {}", list(&added)));
+        }
+    }
+    if reasons.is_empty() && tested.is_empty() {
+        reasons.push("no dependent was tested on a version its requirement accepts".to_string());
+    } else if reasons.is_empty() {
+        let mut reason = format!("all {} tested passed", dependents_label(tested.len()));
+        if api_changes.is_some() {
+            reason.push_str(" and the public API is unchanged");
+        } else {
+            reason.push_str(" (run with --api-diff to tell whether API was added, which needs a minor bump)");
+        }
+        reasons.push(reason);
+    }
+    BumpRecommendation { bump, reasons }
+}
+
+/// Newest baseline version tested, what the bump applies to
+pub fn newest_baseline(rows: &[OfferedRow]) -> Option<semver::Version> {
+    rows.iter()
+        .filter(|row| row.offered.is_none())
+        .filter_map(|row| semver::Version::parse(&row.primary.resolved_version).ok())
+        .max()
+}
+
+pub fn format_bump(recommendation: &BumpRecommendation, from: Option<&semver::Version>) -> String {
+    let mut output = format!("Recommended version bump: {}", recommendation.bump.as_str());
+    if let Some(from) = from {
+        output.push_str(&format!(" ({} → {})", from, recommendation.bump.apply(from)));
+    }
+    output.push('\n');
+    for reason in &recommendation.reasons {
+        output.push_str(&format!("  - {}\n", reason));
+    }
+    output.push('\n');
+    glyphs(&output)
+}
+
 /// Format summary statistics as a string
 pub fn format_summary(summary: &TestSummary) -> String {
     let mut output = String::new();
@@ -636,10 +1227,79 @@ pub fn format_summary(summary: &TestSummary) -> String {
     output.push_str(&format!("  ✓ Passed:    {}\n", summary.passed));
     output.push_str(&format!("  ✗ Regressed: {}\n", summary.regressed));
     output.push_str(&format!("  ⚠ Broken:    {}\n", summary.broken));
+    if summary.flaky > 0 {
+        output.push_str(&format!("  ~ Flaky:     {}\n", summary.flaky));
+    }
+    if summary.warned > 0 {
+        output.push_str(&format!("  ! Warned:    {}\n", summary.warned));
+    }
+    if summary.duplicated > 0 {
+        output.push_str(&format!("  = Duplicate: {}\n", summary.duplicated));
+    }
+    if summary.msrv > 0 {
+        output.push_str(&format!("  > MSRV:      {}\n", summary.msrv));
+    }
+    if summary.network > 0 {
+        output.push_str(&format!("  ? Network:   {}\n", summary.network));
+    }
     output.push_str("  ━━━━━━━━━━━━━\n");
     output.push_str(&format!("  Total:       {}\n", summary.total));
+    if !summary.error_codes.is_empty() {
+        output.push_str("\nError codes in failures:\n");
+        for (code, count) in &summary.error_codes {
+            output.push_str(&format!("  {:<7} {:>4}  {}\n", code, count, error_code_url(code)));
+        }
+    }
+    if !summary.api_breaks.is_empty() {
+        output.push_str("\nLikely API breaks:\n");
+        for brk in summary.api_breaks.iter().take(SUMMARY_ERROR_GROUPS) {
+            output.push_str(&format!("  {} — {}\n", brk.cause, dependents_label(brk.dependents.len())));
+            output.push_str(&format!("      {}\n", dependent_names(&brk.dependents)));
+        }
+        if summary.api_breaks.len() > SUMMARY_ERROR_GROUPS {
+            output.push_str(&format!("  ({} more)\n", summary.api_breaks.len() - SUMMARY_ERROR_GROUPS));
+        }
+    }
+    if !summary.error_groups.is_empty() {
+        output.push_str("\nRegressions by error:\n");
+        for group in summary.error_groups.iter().take(SUMMARY_ERROR_GROUPS) {
+            output.push_str(&format!("  {} — {}\n", group.pattern, dependents_label(group.dependents.len())));
+            output.push_str(&format!("      {}\n", dependent_names(&group.dependents)));
+        }
+        if summary.error_groups.len() > SUMMARY_ERROR_GROUPS {
+            output.push_str(&format!("  ({} more)\n", summary.error_groups.len() - SUMMARY_ERROR_GROUPS));
+        }
+    }
+    if !summary.warning_groups.is_empty() {
+        output.push_str("\nNew warnings:\n");
+        for group in summary.warning_groups.iter().take(SUMMARY_ERROR_GROUPS) {
+            output.push_str(&format!("  {} — {} (e.g. {})\n", group.lint, dependents_label(group.dependents.len()), group.example));
+            output.push_str(&format!("      {}\n", dependent_names(&group.dependents)));
+        }
+        if summary.warning_groups.len() > SUMMARY_ERROR_GROUPS {
+            output.push_str(&format!("  ({} more)\n", summary.warning_groups.len() - SUMMARY_ERROR_GROUPS));
+        }
+    }
+    if !summary.slowdowns.is_empty() {
+        output.push_str("\nPerformance impact:\n");
+        for (row, slowdown) in &summary.slowdowns {
+            output.push_str(&format!("  {} — {}\n", row, slowdown));
+        }
+    }
+    if !summary.size_changes.is_empty() {
+        output.push_str("\nSize impact (debug profile):\n");
+        for (row, change) in &summary.size_changes {
+            output.push_str(&format!("  {} — {}\n", row, change));
+        }
+    }
+    if !summary.lockfile_notes.is_empty() {
+        output.push_str("\nCommitted Cargo.lock:\n");
+        for (row, note) in &summary.lockfile_notes {
+            output.push_str(&format!("  {} — {}\n", row, note));
+        }
+    }
     output.push('\n');
-    output
+    glyphs(&output)
 }
 
 /// Print summary statistics
@@ -648,11 +1308,11 @@ pub fn print_summary(summary: &TestSummary) {
 }
 
 //
-// HTML and Markdown report generation (simplified)
+// HTML and Markdown report generation
 //
 
 /// Generate HTML report from OfferedRows
-pub fn generate_html_report(rows: &[OfferedRow], crate_name: &str, display_version: &str, output_path: &PathBuf) -> std::io::Result<()> {
+pub fn generate_html_report(rows: &[OfferedRow], crate_name: &str, display_version: &str, total_deps: usize, notes: &[String], mermaid_cdn: bool, output_path: &PathBuf) -> std::io::Result<()> {
     let mut file = File::create(output_path)?;
 
     writeln!(file, "<!DOCTYPE html>")?;
@@ -664,69 +1324,345 @@ pub fn generate_html_report(rows: &[OfferedRow], crate_name: &str, display_versi
     writeln!(file, "th, td {{ border: 1px solid #ccc; padding: 8px; text-align: left; }}")?;
     writeln!(file, ".passed {{ color: green; }}")?;
     writeln!(file, ".regressed {{ color: red; }}")?;
+    writeln!(file, ".flaky {{ color: purple; }}")?;
+    writeln!(file, ".warned {{ color: #b08800; }}")?;
+    writeln!(file, ".duplicated {{ color: darkorange; }}")?;
+    writeln!(file, ".msrv {{ color: orangered; }}")?;
+    writeln!(file, ".network {{ color: gray; }}")?;
     writeln!(file, ".broken {{ color: orange; }}")?;
+    writeln!(file, ".note {{ background: #fff8c5; padding: 8px; }}")?;
+    writeln!(file, ".console {{ background: #1e1e1e; color: #d4d4d4; padding: 10px; overflow-x: auto; line-height: 1.2; }}")?;
+    file.write_all(HTML_REPORT_STYLE.as_bytes())?;
     writeln!(file, "</style></head><body>")?;
     writeln!(file, "<h1>Cargo Copter Report</h1>")?;
     writeln!(file, "<p>Crate: <strong>{}</strong> ({})</p>", crate_name, display_version)?;
-    writeln!(file, "<table><thead><tr>")?;
-    writeln!(file, "<th>Offered</th><th>Spec</th><th>Resolved</th><th>Dependent</th><th>Result</th>")?;
-    writeln!(file, "</tr></thead><tbody>")?;
+    for note in notes {
+        writeln!(file, "<p class='note'>Note: {}</p>", sanitize(note))?;
+    }
 
+    // One tbody per dependent, so sorting and filtering move its rows together
+    let mut groups: Vec<Vec<&OfferedRow>> = Vec::new();
     for row in rows {
-        let (offered, spec, resolved, dependent, result, time, _, _, _) = format_offered_row(row);
-        let class = if row.offered.is_some() {
-            let overall_passed = row.test.commands.iter().all(|cmd| cmd.result.passed);
-            match (row.baseline_passed, overall_passed) {
-                (Some(true), true) => "passed",
-                (Some(true), false) => "regressed",
-                _ => "broken",
-            }
-        } else {
-            ""
-        };
+        match groups.last_mut() {
+            Some(group) if dependent_anchor(group[0]) == dependent_anchor(row) => group.push(row),
+            _ => groups.push(vec![row]),
+        }
+    }
+    let group_statuses: Vec<(String, usize)> = groups.iter().map(|group| html_group_status(group)).collect();
 
-        writeln!(file, "<tr class='{}'><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{} {}</td></tr>",
-                 class, sanitize(&offered), sanitize(&spec), sanitize(&resolved),
-                 sanitize(&dependent), sanitize(&result), sanitize(&time))?;
+    let mut statuses: Vec<(&str, usize, usize)> = Vec::new();  // (status, rank, dependents)
+    for (status, rank) in &group_statuses {
+        match statuses.iter_mut().find(|(s, _, _)| s == status) {
+            Some((_, _, count)) => *count += 1,
+            None => statuses.push((status, *rank, 1)),
+        }
     }
+    statuses.sort_by_key(|(_, rank, _)| *rank);
+    let versions: BTreeSet<&str> = rows.iter().filter_map(|row| row.offered.as_ref()).map(|o| o.version.as_str()).collect();
 
-    writeln!(file, "</tbody></table>")?;
+    writeln!(file, "<div class='filters'>")?;
+    write!(file, "<label>Status <select id='status-filter'><option value=''>all</option>")?;
+    for (status, _, count) in &statuses {
+        write!(file, "<option value='{0}'>{0} ({1})</option>", status, count)?;
+    }
+    writeln!(file, "</select></label>")?;
+    write!(file, "<label>Version <select id='version-filter'><option value=''>all</option>")?;
+    for version in &versions {
+        write!(file, "<option>{}</option>", sanitize(version))?;
+    }
+    writeln!(file, "</select></label>")?;
+    writeln!(file, "<label>Dependent <input id='name-filter' type='search' placeholder='name'></label>")?;
+    writeln!(file, "<span id='shown'></span></div>")?;
+
+    writeln!(file, "<table id='results'><thead><tr>")?;
+    writeln!(file, "<th data-sort='text'>Offered</th><th data-sort='text'>Spec</th><th data-sort='text'>Resolved</th><th data-sort='dependent'>Dependent</th><th data-sort='rank'>Result</th><th data-sort='time'>Time</th>")?;
+    writeln!(file, "</tr></thead>")?;
+
+    for (group, (group_status, rank)) in groups.iter().zip(&group_statuses) {
+        let head = &group[0].primary;
+        let time: f64 = group.iter().flat_map(|row| &row.test.commands).map(|cmd| cmd.result.duration).sum();
+        writeln!(file, "<tbody id='{}' data-dependent='{} {}' data-status='{}' data-rank='{}' data-time='{:.1}'>",
+                 dependent_anchor(group[0]), sanitize(&head.dependent_name), sanitize(&head.dependent_version), group_status, rank, time)?;
+        for row in group {
+            write_html_row(&mut file, row)?;
+        }
+        writeln!(file, "</tbody>")?;
+    }
+
+    writeln!(file, "</table>")?;
+    file.write_all(HTML_REPORT_SCRIPT.as_bytes())?;
 
     let summary = summarize_offered_rows(rows);
     writeln!(file, "<h2>Summary</h2>")?;
-    writeln!(file, "<p>Passed: {}, Regressed: {}, Broken: {}</p>",
-             summary.passed, summary.regressed, summary.broken)?;
+    writeln!(file, "<p>Passed: {}, Regressed: {}, Broken: {}, Flaky: {}, Total: {}</p>",
+             summary.passed, summary.regressed, summary.broken, summary.flaky, summary.total)?;
+
+    // Same layout as the console, colors carried over from the ANSI escapes
+    writeln!(file, "<h2>Console Output</h2>")?;
+    write!(file, "<pre class='console'>")?;
+    write!(file, "{}", ansi_to_html(&format_table_header(crate_name, display_version, total_deps)))?;
+    for row in rows {
+        write!(file, "{}", ansi_to_html(&format_offered_row_string(row, true, true)))?;
+    }
+    write!(file, "{}", ansi_to_html(&format_table_footer()))?;
+    writeln!(file, "</pre>")?;
+
+    writeln!(file, "<h2>Dependency Graph</h2>")?;
+    writeln!(file, "<pre class='mermaid'>")?;
+    write!(file, "{}", sanitize(&DependencyGraph::from_rows(rows, crate_name).to_mermaid()))?;
+    writeln!(file, "</pre>")?;
+    if mermaid_cdn {
+        writeln!(file, "<script type='module'>import mermaid from 'https://cdn.jsdelivr.net/npm/mermaid@11/dist/mermaid.esm.min.mjs'; mermaid.initialize({{ startOnLoad: true }});</script>")?;
+    }
 
     writeln!(file, "</body></html>")?;
     Ok(())
 }
 
-/// Generate Markdown report from OfferedRows
-pub fn generate_markdown_report(rows: &[OfferedRow], crate_name: &str, display_version: &str, output_path: &PathBuf) -> std::io::Result<()> {
-    let mut file = File::create(output_path)?;
+/// Anchor of a dependent in the HTML report: `#image-0_25_8`
+fn dependent_anchor(row: &OfferedRow) -> String {
+    let mut anchor = format!("{}-{}", row.primary.dependent_name, row.primary.dependent_version);
+    if let Some(ref channel) = row.primary.channel {
+        anchor.push_str(&format!("-{}", channel));
+    }
+    crate::sanitize_link(&anchor)
+}
 
-    writeln!(file, "# Cargo Copter Report\n")?;
-    writeln!(file, "**Crate**: {} ({})\n", crate_name, display_version)?;
-    writeln!(file, "## Test Results\n")?;
-    writeln!(file, "| Offered | Spec | Resolved | Dependent | Result |")?;
-    writeln!(file, "|---------|------|----------|-----------|--------|")?;
+/// End of a step log embedded in the HTML report; the rest is in the file
+const MAX_EMBEDDED_LOG: usize = 64 * 1024;
 
-    for row in rows {
-        let (offered, spec, resolved, dependent, result, time, _, _, _) = format_offered_row(row);
-        writeln!(file, "| {} | {} | {} | {} | {} {} |",
-                 offered, spec, resolved, dependent, result, time)?;
+/// Status a dependent is filtered by in the HTML report, and its sort rank (worst first)
+fn html_group_status(group: &[&OfferedRow]) -> (String, usize) {
+    if group.iter().all(|row| row.offered.is_none()) {
+        // SKIPPED, UNSUPPORTED, NOT_RUN or ERROR
+        return (group[0].primary.spec.to_lowercase(), 4);
     }
+    let status = dependent_status(group);
+    let rank = match status {
+        RowStatus::Regressed => 0,
+        RowStatus::Broken | RowStatus::Failed => 1,
+        RowStatus::Flaky | RowStatus::Warned | RowStatus::Duplicated | RowStatus::Msrv | RowStatus::Network => 2,
+        RowStatus::Passed => 3,
+    };
+    (status.as_str().to_lowercase(), rank)
+}
 
-    let summary = summarize_offered_rows(rows);
-    writeln!(file, "\n## Summary\n")?;
-    writeln!(file, "- ✓ Passed: {}", summary.passed)?;
-    writeln!(file, "- ✗ Regressed: {}", summary.regressed)?;
-    writeln!(file, "- ⚠ Broken: {}", summary.broken)?;
-    writeln!(file, "- **Total**: {}", summary.total)?;
+/// One result row of the HTML report, followed by its collapsed details
+fn write_html_row(file: &mut impl Write, row: &OfferedRow) -> std::io::Result<()> {
+    let (offered, spec, resolved, dependent, _, time, _, _, _) = format_offered_row(row);
+    let status = row_status(row);
+    let class = if row.offered.is_some() {
+        match status {
+            RowStatus::Passed => "passed",
+            RowStatus::Regressed => "regressed",
+            RowStatus::Flaky => "flaky",
+            RowStatus::Warned => "warned",
+            RowStatus::Duplicated => "duplicated",
+            RowStatus::Msrv => "msrv",
+            RowStatus::Network => "network",
+            RowStatus::Broken | RowStatus::Failed => "broken",
+        }
+    } else {
+        ""
+    };
+    let offered_version = row.offered.as_ref().map(|o| sanitize(&o.version)).unwrap_or_default();
+    let result = match (status, failed_step(row)) {
+        (RowStatus::Regressed, Some(step)) => format!("REGRESSED({})", step.as_str()),
+        _ => status.as_str().to_string(),
+    };
+
+    let anchor = dependent_anchor(row);
+    writeln!(file, "<tr class='{}' data-offered='{}'><td>{}</td><td>{}</td><td>{}</td><td><a href='#{}'>{}</a></td><td>{} {}</td><td>{}</td></tr>",
+             class, offered_version, sanitize(&offered), sanitize(&spec), sanitize(&resolved),
+             anchor, sanitize(&dependent), sanitize(&result), ict_marks_html(row), sanitize(&time))?;
+
+    // Compiler output of each failed step, collapsed, with the end of its full log
+    for cmd in row.test.commands.iter().filter(|cmd| !cmd.result.passed) {
+        for failure in &cmd.result.failures {
+            writeln!(file, "<tr class='diagnostics' data-offered='{}'><td colspan='6'><details class='failure'><summary>cargo {} failed on {}</summary><pre class='console'>{}</pre>{}</details></td></tr>",
+                     offered_version, cmd.command.as_str(), sanitize(&failure.crate_name),
+                     diagnostics_html(&failure.error_message), embedded_log(cmd.result.log.as_deref()))?;
+        }
+    }
+
+    // What the offered version changed in the dependency graph
+    if !row.lock_diff.is_empty() {
+        let changes: Vec<String> = row.lock_diff.iter().map(|c| sanitize(&c.to_string())).collect();
+        writeln!(file, "<tr class='lock-diff' data-offered='{}'><td colspan='6'><details><summary>Cargo.lock: {} changed vs baseline</summary><pre>{}</pre></details></td></tr>",
+                 offered_version, changes.len(), changes.join("\n"))?;
+    }
+
+    // Steps the offered version made much slower
+    if !row.slowdowns.is_empty() {
+        let changes: Vec<String> = row.slowdowns.iter().map(|c| sanitize(&c.to_string())).collect();
+        writeln!(file, "<tr class='slowdowns' data-offered='{}'><td colspan='6'><details><summary>Slower than baseline: {}</summary><pre>{}</pre></details></td></tr>",
+                 offered_version, changes.len(), changes.join("\n"))?;
+    }
 
+    // Users on the dependent's rust-version can't take this version
+    if let Some(ref conflict) = row.msrv_conflict {
+        writeln!(file, "<tr class='msrv-conflict' data-offered='{}'><td colspan='6'>MSRV conflict: {}</td></tr>",
+                 offered_version, sanitize(&conflict.to_string()))?;
+    }
+
+    // Two incompatible copies of the base crate
+    if !row.duplicate_versions.is_empty() {
+        writeln!(file, "<tr class='duplicate-versions' data-offered='{}'><td colspan='6'>Incompatible versions in the graph: {}</td></tr>",
+                 offered_version, sanitize(&row.duplicate_versions.join(", ")))?;
+    }
+
+    // Libraries and binaries that grew or shrank
+    if !row.size_diff.is_empty() {
+        let changes: Vec<String> = row.size_diff.iter().map(|c| sanitize(&c.to_string())).collect();
+        writeln!(file, "<tr class='size-diff' data-offered='{}'><td colspan='6'><details><summary>Artifact sizes (debug profile): {} changed vs baseline</summary><pre>{}</pre></details></td></tr>",
+                 offered_version, changes.len(), changes.join("\n"))?;
+    }
+
+    // New warnings of the offered version
+    if !row.warning_diff.is_empty() {
+        let changes: Vec<String> = row.warning_diff.iter().map(|c| sanitize(&c.to_string())).collect();
+        writeln!(file, "<tr class='warning-diff' data-offered='{}'><td colspan='6'><details><summary>New warnings: {} vs baseline</summary><pre>{}</pre></details></td></tr>",
+                 offered_version, changes.len(), changes.join("\n"))?;
+    }
     Ok(())
 }
 
+/// Compiler output with errors, warnings, notes and source locations colored like rustc's
+fn diagnostics_html(text: &str) -> String {
+    let mut html = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let class = if trimmed.starts_with("error") || trimmed.starts_with("TIMEOUT") || trimmed.starts_with("RESOURCE LIMIT") {
+            Some("diag-error")
+        } else if trimmed.starts_with("warning") {
+            Some("diag-warning")
+        } else if trimmed.starts_with("-->") || trimmed.starts_with("::: ") {
+            Some("diag-location")
+        } else if ["note", "help", "= note", "= help"].iter().any(|p| trimmed.starts_with(p)) {
+            Some("diag-note")
+        } else {
+            None
+        };
+        match class {
+            Some(class) => html.push_str(&format!("<span class='{}'>{}</span>\n", class, sanitize(line))),
+            None => html.push_str(&format!("{}\n", sanitize(line))),
+        }
+    }
+    html
+}
+
+/// Link to a step's full log, with its end embedded if the file is there
+fn embedded_log(log: Option<&std::path::Path>) -> String {
+    let Some(log) = log else {
+        return String::new();
+    };
+    let path = sanitize(&log.display().to_string());
+    let Ok(contents) = std::fs::read_to_string(log) else {
+        return format!("<p>Full log: <a href='{0}'>{0}</a></p>", path);
+    };
+    let mut start = contents.len().saturating_sub(MAX_EMBEDDED_LOG);
+    while !contents.is_char_boundary(start) {
+        start += 1;
+    }
+    let omitted = match start {
+        0 => String::new(),
+        _ => format!("[first {} omitted]\n", crate::format::HumanSize(start as u64)),
+    };
+    format!("<details class='log'><summary>Full log: <a href='{0}'>{0}</a></summary><pre class='console'>{1}{2}</pre></details>",
+            path, omitted, sanitize(&contents[start..]))
+}
+
+/// Styles of the interactive parts of the HTML report
+const HTML_REPORT_STYLE: &str = r#".filters { margin: 10px 0; display: flex; gap: 16px; align-items: center; }
+th[data-sort] { cursor: pointer; user-select: none; }
+th[data-sort].asc::after { content: ' ▲'; }
+th[data-sort].desc::after { content: ' ▼'; }
+tbody { border-bottom: 2px solid #999; }
+tbody:target { outline: 3px solid #0969da; }
+.diagnostics pre { margin: 4px 0; }
+.diag-error { color: #f14c4c; font-weight: bold; }
+.diag-warning { color: #cca700; font-weight: bold; }
+.diag-location { color: #3b8eea; }
+.diag-note { color: #23d18b; }
+details.log { margin-left: 16px; }
+"#;
+
+/// Filtering, sorting and deep links in the HTML report
+const HTML_REPORT_SCRIPT: &str = r#"<script>
+(() => {
+  const table = document.getElementById('results');
+  const groups = () => [...table.tBodies];
+  const [status, version, name] = ['status-filter', 'version-filter', 'name-filter'].map(id => document.getElementById(id));
+
+  // A dependent shows if it matches every filter; with a version chosen, only its rows (and the baseline) show
+  function filter() {
+    let shown = 0;
+    for (const group of groups()) {
+      const offered = [...group.rows].map(row => row.dataset.offered).filter(Boolean);
+      group.hidden = !((!status.value || group.dataset.status === status.value)
+        && (!version.value || offered.includes(version.value))
+        && group.dataset.dependent.toLowerCase().includes(name.value.toLowerCase()));
+      for (const row of group.rows) {
+        row.hidden = !!version.value && !!row.dataset.offered && row.dataset.offered !== version.value;
+      }
+      shown += !group.hidden;
+    }
+    document.getElementById('shown').textContent = shown + ' of ' + groups().length + ' dependents';
+  }
+  [status, version, name].forEach(input => input.addEventListener('input', filter));
+
+  // Click a column to sort dependents by it, again to reverse
+  for (const th of table.tHead.rows[0].cells) {
+    th.addEventListener('click', () => {
+      const direction = th.classList.contains('asc') ? -1 : 1;
+      const key = group => {
+        switch (th.dataset.sort) {
+          case 'dependent': return group.dataset.dependent;
+          case 'rank': return Number(group.dataset.rank);
+          case 'time': return Number(group.dataset.time);
+          default: return group.rows[0].cells[th.cellIndex].textContent;
+        }
+      };
+      const sorted = groups().sort((a, b) => {
+        const [x, y] = [key(a), key(b)];
+        return direction * (typeof x === 'number' ? x - y : x.localeCompare(y, undefined, { numeric: true }));
+      });
+      table.append(...sorted);
+      for (const other of table.tHead.rows[0].cells) other.classList.remove('asc', 'desc');
+      th.classList.add(direction === 1 ? 'asc' : 'desc');
+    });
+  }
+
+  // #dependent-version links open that dependent's failures, even if filtered out
+  function openTarget() {
+    const group = location.hash && document.getElementById(decodeURIComponent(location.hash.slice(1)));
+    if (group && group.tagName === 'TBODY') {
+      group.hidden = false;
+      group.querySelectorAll('details.failure').forEach(details => details.open = true);
+    }
+  }
+  window.addEventListener('hashchange', openTarget);
+  filter();
+  openTarget();
+})();
+</script>
+"#;
+
+/// ICT marks with the step and its outcome as a tooltip on each
+fn ict_marks_html(row: &OfferedRow) -> String {
+    row.test.commands.iter()
+        .map(|cmd| {
+            let (mark, outcome) = match (cmd.result.passed, cmd.result.flaky) {
+                (true, true) => ('✓', "passed on retry"),
+                (true, false) => ('✓', "passed"),
+                (false, _) => ('✗', "failed"),
+            };
+            format!("<span title='{} {} ({})'>{}</span>", cmd.command.as_str(), outcome,
+                    HumanDuration::from_secs_f64(cmd.result.duration), mark)
+        })
+        .collect()
+}
+
 /// Sanitize HTML special characters
 fn sanitize(s: &str) -> String {
     s.chars()
@@ -739,25 +1675,99 @@ fn sanitize(s: &str) -> String {
         .collect()
 }
 
-//
-// Temporary compatibility stubs for old API (TO BE REMOVED)
-//
+/// ANSI escape selecting `color` as the foreground
+fn ansi_fg(color: Color) -> String {
+    if color < 8 {
+        format!("\x1b[{}m", 30 + color)
+    } else {
+        format!("\x1b[{}m", 90 + (color - 8))
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
 
-/// Stub for old API - needs migration to OfferedRow
-pub fn print_immediate_failure(_result: &crate::TestResult) {
-    // TODO: Migrate to OfferedRow-based error printing
-    eprintln!("Warning: print_immediate_failure not yet migrated to OfferedRow");
+/// CSS color for an ANSI foreground code (30-37, 90-97)
+fn ansi_css_color(code: u32) -> Option<&'static str> {
+    const NORMAL: [&str; 8] = ["#000000", "#cd3131", "#0dbc79", "#e5e510", "#2472c8", "#bc3fbc", "#11a8cd", "#e5e5e5"];
+    const BRIGHT: [&str; 8] = ["#666666", "#f14c4c", "#23d18b", "#f5f543", "#3b8eea", "#d670d6", "#29b8db", "#ffffff"];
+    match code {
+        30..=37 => Some(NORMAL[(code - 30) as usize]),
+        90..=97 => Some(BRIGHT[(code - 90) as usize]),
+        _ => None,
+    }
 }
 
-/// Stub for old API - needs migration to OfferedRow
-pub fn print_console_table_v2(_results: &[crate::TestResult], _crate_name: &str, _display_version: &str) {
-    // TODO: Migrate to OfferedRow streaming
-    println!("Warning: print_console_table_v2 not yet migrated to OfferedRow");
-    println!("Use: print_table_header(), print_offered_row(), print_table_footer()");
+/// Convert text with ANSI color escapes into sanitized HTML
+///
+/// Foreground colors and bold become `<span>`s; any other escape sequence
+/// is dropped so it can't show up as garbage inside the `<pre>`.
+fn ansi_to_html(s: &str) -> String {
+    let mut output = String::new();
+    let mut color: Option<&'static str> = None;
+    let mut bold = false;
+    let mut span_open = false;
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            output.push_str(&sanitize(c.encode_utf8(&mut [0; 4])));
+            continue;
+        }
+        if chars.peek() != Some(&'[') {
+            continue;
+        }
+        chars.next();
+
+        // Collect parameters up to the final byte of the sequence
+        let mut params = String::new();
+        let mut terminator = None;
+        for c in chars.by_ref() {
+            if c.is_ascii_digit() || c == ';' {
+                params.push(c);
+            } else {
+                terminator = Some(c);
+                break;
+            }
+        }
+        if terminator != Some('m') {
+            continue;
+        }
+
+        for param in params.split(';') {
+            match param.parse::<u32>().unwrap_or(0) {
+                0 => { color = None; bold = false; }
+                1 => bold = true,
+                22 => bold = false,
+                39 => color = None,
+                code => if let Some(css) = ansi_css_color(code) { color = Some(css) },
+            }
+        }
+
+        if span_open {
+            output.push_str("</span>");
+            span_open = false;
+        }
+        if color.is_some() || bold {
+            let mut style = String::new();
+            if let Some(css) = color {
+                style.push_str(&format!("color:{};", css));
+            }
+            if bold {
+                style.push_str("font-weight:bold;");
+            }
+            output.push_str(&format!("<span style='{}'>", style));
+            span_open = true;
+        }
+    }
+
+    if span_open {
+        output.push_str("</span>");
+    }
+    output
 }
 
 /// Generate markdown report with console table in code block
-pub fn export_markdown_table_report(rows: &[OfferedRow], output_path: &PathBuf, crate_name: &str, display_version: &str, total_deps: usize) -> std::io::Result<()> {
+pub fn export_markdown_table_report(rows: &[OfferedRow], output_path: &PathBuf, crate_name: &str, display_version: &str, total_deps: usize, notes: &[String]) -> std::io::Result<()> {
     let mut file = File::create(output_path)?;
     let summary = summarize_offered_rows(rows);
 
@@ -765,14 +1775,108 @@ pub fn export_markdown_table_report(rows: &[OfferedRow], output_path: &PathBuf,
     writeln!(file, "# Cargo Copter Test Report\n")?;
     writeln!(file, "**Crate**: {} ({})", crate_name, display_version)?;
     writeln!(file, "**Dependents Tested**: {}\n", total_deps)?;
+    for note in notes {
+        writeln!(file, "> **Note**: {}\n", note)?;
+    }
 
     // Write summary
     writeln!(file, "## Summary\n")?;
     writeln!(file, "- ✓ Passed: {}", summary.passed)?;
     writeln!(file, "- ✗ Regressed: {}", summary.regressed)?;
     writeln!(file, "- ⚠ Broken: {}", summary.broken)?;
+    if summary.flaky > 0 {
+        writeln!(file, "- ~ Flaky: {}", summary.flaky)?;
+    }
+    if summary.network > 0 {
+        writeln!(file, "- ? Network: {}", summary.network)?;
+    }
     writeln!(file, "- **Total**: {}\n", summary.total)?;
 
+    if !summary.error_codes.is_empty() {
+        writeln!(file, "## Error Codes in Failures\n")?;
+        writeln!(file, "| Code | Count |")?;
+        writeln!(file, "|------|-------|")?;
+        for (code, count) in &summary.error_codes {
+            writeln!(file, "| [{}]({}) | {} |", code, error_code_url(code), count)?;
+        }
+        writeln!(file)?;
+    }
+
+    if !summary.api_breaks.is_empty() {
+        writeln!(file, "## Likely API Breaks\n")?;
+        writeln!(file, "| Item | Dependents | |")?;
+        writeln!(file, "|------|------------|---|")?;
+        for brk in summary.api_breaks.iter().take(SUMMARY_ERROR_GROUPS) {
+            writeln!(file, "| {} | {} | {} |", brk.cause, brk.dependents.len(), brk.dependents.join(", "))?;
+        }
+        writeln!(file)?;
+    }
+
+    if !summary.error_groups.is_empty() {
+        writeln!(file, "## Regressions by Error\n")?;
+        writeln!(file, "| Error | Dependents | |")?;
+        writeln!(file, "|-------|------------|---|")?;
+        for group in summary.error_groups.iter().take(SUMMARY_ERROR_GROUPS) {
+            let error = match &group.pattern.code {
+                Some(code) => format!("[{}]({}) {}", code, error_code_url(code), group.pattern.message),
+                None => group.pattern.message.clone(),
+            };
+            writeln!(file, "| {} | {} | {} |", error.replace('|', "\\|"), group.dependents.len(), group.dependents.join(", "))?;
+        }
+        writeln!(file)?;
+    }
+
+    if !summary.slowdowns.is_empty() {
+        writeln!(file, "## Performance Impact\n")?;
+        writeln!(file, "| Dependent | Step | Baseline | Offered | Slowdown |")?;
+        writeln!(file, "|-----------|------|----------|---------|----------|")?;
+        for (row, slowdown) in &summary.slowdowns {
+            writeln!(file, "| {} | {} | {} | {} | {:.1}x |", row, slowdown.step.as_str(),
+                     HumanDuration(slowdown.baseline), HumanDuration(slowdown.offered), slowdown.ratio())?;
+        }
+        writeln!(file)?;
+    }
+
+    if !summary.size_changes.is_empty() {
+        writeln!(file, "## Size Impact (debug profile)\n")?;
+        writeln!(file, "| Dependent | Artifact | Baseline | Offered | Change |")?;
+        writeln!(file, "|-----------|----------|----------|---------|--------|")?;
+        for (row, change) in &summary.size_changes {
+            writeln!(file, "| {} | `{}` | {} | {} | {:+.1}% |", row, change.name,
+                     HumanSize(change.baseline), HumanSize(change.offered), change.percent())?;
+        }
+        writeln!(file)?;
+    }
+
+    if !summary.warning_groups.is_empty() {
+        writeln!(file, "## New Warnings\n")?;
+        writeln!(file, "| Lint | Example | Dependents |")?;
+        writeln!(file, "|------|---------|------------|")?;
+        for group in &summary.warning_groups {
+            writeln!(file, "| `{}` | {} | {} |", group.lint, group.example.replace('|', "\\|"), group.dependents.join(", "))?;
+        }
+        writeln!(file)?;
+    }
+
+    if !summary.lockfile_notes.is_empty() {
+        writeln!(file, "## Committed Cargo.lock\n")?;
+        for (row, note) in &summary.lockfile_notes {
+            writeln!(file, "- **{}**: {}", row, note)?;
+        }
+        writeln!(file)?;
+    }
+
+    let by_edition = summarize_by_edition(rows);
+    if by_edition.len() > 1 {
+        writeln!(file, "## Results by Edition\n")?;
+        writeln!(file, "| Edition | Resolver | Passed | Regressed | Broken |")?;
+        writeln!(file, "|---------|----------|--------|-----------|--------|")?;
+        for (edition, resolver, group) in &by_edition {
+            writeln!(file, "| {} | {} | {} | {} | {} |", edition, resolver, group.passed, group.regressed, group.broken)?;
+        }
+        writeln!(file)?;
+    }
+
     // Write console table in code block
     writeln!(file, "## Test Results\n")?;
     writeln!(file, "```")?;
@@ -781,13 +1885,13 @@ pub fn export_markdown_table_report(rows: &[OfferedRow], output_path: &PathBuf,
     write!(file, "{}", format_table_header(crate_name, display_version, total_deps))?;
 
     // Write all rows
-    for (i, row) in rows.iter().enumerate() {
+    for row in rows.iter() {
         // Determine if this is the last row in its group
         // For simplicity, assume each row is its own group (no separators in markdown)
         let is_last_in_group = true;
 
         // Format the row (we need a string-returning version of print_offered_row)
-        write!(file, "{}", format_offered_row_string(row, is_last_in_group))?;
+        write!(file, "{}", format_offered_row_string(row, is_last_in_group, false))?;
     }
 
     // Write table footer
@@ -795,26 +1899,35 @@ pub fn export_markdown_table_report(rows: &[OfferedRow], output_path: &PathBuf,
 
     writeln!(file, "```\n")?;
 
+    writeln!(file, "## Dependency Graph\n")?;
+    writeln!(file, "```mermaid")?;
+    write!(file, "{}", DependencyGraph::from_rows(rows, crate_name).to_mermaid())?;
+    writeln!(file, "```")?;
+
     Ok(())
 }
 
 /// Format an OfferedRow as a string (similar to print_offered_row but returns String)
-fn format_offered_row_string(row: &OfferedRow, is_last_in_group: bool) -> String {
-    let (offered_str, spec_str, resolved_str, dependent_str, result_str, time_str, _color, error_details, multi_version_rows) = format_offered_row(row);
+///
+/// With `ansi`, the main row is wrapped in the same color escapes the console uses.
+fn format_offered_row_string(row: &OfferedRow, is_last_in_group: bool, ansi: bool) -> String {
+    let (offered_str, spec_str, resolved_str, dependent_str, result_str, time_str, color, error_details, multi_version_rows) = format_offered_row(row);
     let w = &*WIDTHS;
 
     let mut output = String::new();
 
     // Main row
-    let offered_display = truncate_with_padding(&offered_str, w.offered - 2);
-    let spec_display = truncate_with_padding(&spec_str, w.spec - 2);
-    let resolved_display = truncate_with_padding(&resolved_str, w.resolved - 2);
-    let dependent_display = truncate_with_padding(&dependent_str, w.dependent - 2);
     let result_display = format!("{:>12} {:>5}", result_str, time_str);
-    let result_display = truncate_with_padding(&result_display, w.result - 2);
-
-    output.push_str(&format!("│ {} │ {} │ {} │ {} │ {} │\n",
-        offered_display, spec_display, resolved_display, dependent_display, result_display));
+    for main_row in row_lines(&[
+        (&offered_str, w.offered - 2), (&spec_str, w.spec - 2), (&resolved_str, w.resolved - 2),
+        (&dependent_str, w.dependent - 2), (&result_display, w.result - 2),
+    ]) {
+        if ansi {
+            output.push_str(&format!("{}{}{}\n", ansi_fg(color), main_row, ANSI_RESET));
+        } else {
+            output.push_str(&format!("{}\n", main_row));
+        }
+    }
 
     // Error details (if any)
     if !error_details.is_empty() {
@@ -829,8 +1942,9 @@ fn format_offered_row_string(row: &OfferedRow, is_last_in_group: bool) -> String
             padding = padding_width, corner2 = corner2_width, w_result = w.result));
 
         for error_line in &error_details {
-            let truncated = truncate_with_padding(error_line, error_text_width);
-            output.push_str(&format!("│{:w_offered$}│ {} │\n", "", truncated, w_offered = w.offered));
+            for line in cell_lines(error_line, error_text_width) {
+                output.push_str(&format!("│{:w_offered$}│ {} │\n", "", line, w_offered = w.offered));
+            }
         }
 
         if !is_last_in_group {
@@ -842,33 +1956,631 @@ fn format_offered_row_string(row: &OfferedRow, is_last_in_group: bool) -> String
     }
 
     // Multi-version rows (if any)
-    if !multi_version_rows.is_empty() {
-        for (_i, (spec, resolved, dependent)) in multi_version_rows.iter().enumerate() {
-            let spec_display = format!("├─ {}", spec);
-            let spec_display = truncate_with_padding(&spec_display, w.spec - 2);
-            let resolved_display = format!("├─ {}", resolved);
-            let resolved_display = truncate_with_padding(&resolved_display, w.resolved - 2);
-            let dependent_display = format!("├─ {}", dependent);
-            let dependent_display = truncate_with_padding(&dependent_display, w.dependent - 2);
+    for line in multi_version_rows.iter().flat_map(|row| multi_version_lines(row, w)) {
+        output.push_str(&format!("{}\n", line));
+    }
+
+    glyphs(&output)
+}
 
-            output.push_str(&format!("│{:width$}│ {} │ {} │ {} │{:w_result$}│\n",
-                "", spec_display, resolved_display, dependent_display, "",
-                width = w.offered, w_result = w.result));
+/// Error messages quoted per failed step in the analysis
+const ANALYSIS_TOP_ERRORS: usize = 3;
+/// Lines quoted per error message in the analysis
+const ANALYSIS_ERROR_LINES: usize = 20;
+
+/// Markdown analysis for a release PR: a section per dependent that didn't
+/// pass, with its offered-version matrix and the top error messages of each
+/// failed step; dependents that passed are listed at the end
+pub fn export_markdown_report(rows: &[OfferedRow], output_path: &PathBuf, crate_name: &str, display_version: &str, notes: &[String]) -> std::io::Result<()> {
+    let mut file = File::create(output_path)?;
+    let summary = summarize_offered_rows(rows);
+
+    writeln!(file, "# Downstream analysis: {} {}\n", crate_name, display_version)?;
+    write!(file, "{} passed, {} regressed, {} broken", summary.passed, summary.regressed, summary.broken)?;
+    if summary.flaky > 0 {
+        write!(file, ", {} flaky", summary.flaky)?;
+    }
+    if summary.network > 0 {
+        write!(file, ", {} unreachable", summary.network)?;
+    }
+    writeln!(file, " of {} offered-version results.\n", summary.total)?;
+    for note in notes {
+        writeln!(file, "> **Note**: {}\n", note)?;
+    }
+
+    let dependents = dependents_by_status(rows);
+    let mut passed = Vec::new();
+    for (name, group) in &dependents {
+        let status = dependent_status(group);
+        if status == RowStatus::Passed {
+            passed.push(name.as_str());
+            continue;
         }
+        writeln!(file, "## {} ({})\n", name, status.as_str())?;
+        writeln!(file, "Requires `{}`.\n", group[0].primary.spec)?;
+        write_version_matrix(&mut file, group)?;
+        write_failures(&mut file, group)?;
     }
 
-    output
+    if !passed.is_empty() {
+        writeln!(file, "## Passed\n")?;
+        writeln!(file, "{}", passed.join(", "))?;
+    }
+    Ok(())
 }
 
-/// Compatibility wrapper for old API
-pub fn export_markdown_report(_rows: &[crate::TestResult], _output_path: &PathBuf, _crate_name: &str, _display_version: &str) -> std::io::Result<()> {
-    // Deprecated - use export_markdown_table_report with OfferedRows instead
+/// Rows of each dependent (keyed `name version`), regressions first since
+/// they're what a PR has to answer for, otherwise in the order reported
+pub fn dependents_by_status(rows: &[OfferedRow]) -> Vec<(String, Vec<&OfferedRow>)> {
+    let mut dependents: Vec<(String, Vec<&OfferedRow>)> = Vec::new();
+    for row in rows {
+        let name = row.primary.dependent_label();
+        match dependents.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, group)) => group.push(row),
+            None => dependents.push((name, vec![row])),
+        }
+    }
+    dependents.sort_by_key(|(_, group)| match dependent_status(group) {
+        RowStatus::Regressed => 0,
+        RowStatus::Broken | RowStatus::Failed => 1,
+        RowStatus::Flaky | RowStatus::Warned | RowStatus::Duplicated | RowStatus::Msrv | RowStatus::Network => 2,
+        RowStatus::Passed => 3,
+    });
+    dependents
+}
+
+/// Worst status among a dependent's offered versions
+pub fn dependent_status(group: &[&OfferedRow]) -> RowStatus {
+    let statuses: Vec<RowStatus> = group.iter().filter(|r| r.offered.is_some()).map(|r| row_status(r)).collect();
+    if statuses.contains(&RowStatus::Regressed) {
+        RowStatus::Regressed
+    } else if statuses.iter().any(|s| s.is_failure() && *s != RowStatus::Network) {
+        RowStatus::Broken
+    } else if statuses.contains(&RowStatus::Network) {
+        RowStatus::Network
+    } else if statuses.contains(&RowStatus::Flaky) {
+        RowStatus::Flaky
+    } else if statuses.contains(&RowStatus::Msrv) {
+        RowStatus::Msrv
+    } else if statuses.contains(&RowStatus::Duplicated) {
+        RowStatus::Duplicated
+    } else if statuses.contains(&RowStatus::Warned) {
+        RowStatus::Warned
+    } else {
+        RowStatus::Passed
+    }
+}
+
+/// The top error messages of each step that failed for an offered version
+pub fn write_failures(file: &mut impl Write, group: &[&OfferedRow]) -> std::io::Result<()> {
+    for row in group.iter().filter(|r| r.offered.is_some() && row_status(r).is_failure()) {
+        for cmd in row.test.commands.iter().filter(|cmd| !cmd.result.passed) {
+            writeln!(file, "**{}**: `cargo {}` failed\n", offered_label(row), cmd.command.as_str())?;
+            for failure in cmd.result.failures.iter().take(ANALYSIS_TOP_ERRORS) {
+                let codes: Vec<String> = failure.error_codes.iter()
+                    .map(|code| format!("[{}]({})", code, error_code_url(code)))
+                    .collect();
+                if codes.is_empty() {
+                    writeln!(file, "In `{}`:\n", failure.crate_name)?;
+                } else {
+                    writeln!(file, "In `{}` ({}):\n", failure.crate_name, codes.join(", "))?;
+                }
+                if !failure.likely_causes.is_empty() {
+                    writeln!(file, "Likely cause: {}\n", failure.likely_causes.join(", "))?;
+                }
+                writeln!(file, "```text")?;
+                for line in failure.error_message.lines().take(ANALYSIS_ERROR_LINES) {
+                    writeln!(file, "{}", line)?;
+                }
+                writeln!(file, "```\n")?;
+            }
+        }
+    }
     Ok(())
 }
 
-/// Compatibility wrapper for old API
-pub fn export_html_report(rows: Vec<crate::TestResult>, output_path: &PathBuf, crate_name: &str, display_version: &str) -> std::io::Result<TestSummary> {
-    // TODO: Convert TestResult to OfferedRow, then call generate_html_report
-    eprintln!("Warning: export_html_report needs TestResult -> OfferedRow conversion");
-    Ok(TestSummary { passed: 0, regressed: 0, broken: 0, total: 0 })
+/// `this(0.8.91)`, `=0.8.90` when forced, or `baseline`
+fn offered_label(row: &OfferedRow) -> String {
+    match row.offered {
+        Some(ref offered) if offered.forced => format!("={}", offered.version),
+        Some(ref offered) => offered.version.clone(),
+        None => "baseline".to_string(),
+    }
+}
+
+/// Table of a dependent's rows with a column per step that ran
+pub fn write_version_matrix(file: &mut impl Write, group: &[&OfferedRow]) -> std::io::Result<()> {
+    let mut steps: Vec<CommandType> = Vec::new();
+    for cmd in group.iter().flat_map(|row| &row.test.commands) {
+        if !steps.contains(&cmd.command) {
+            steps.push(cmd.command);
+        }
+    }
+    let header: Vec<&str> = steps.iter().map(|step| step.as_str()).collect();
+    writeln!(file, "| Offered | Resolved | Result | {} |", header.join(" | "))?;
+    writeln!(file, "|---------|----------|--------|{}", "---|".repeat(steps.len()))?;
+    for row in group {
+        let result = match (row_status(row), failed_step(row)) {
+            (RowStatus::Regressed, Some(step)) => format!("REGRESSED({})", step.as_str()),
+            (status, _) => status.as_str().to_string(),
+        };
+        let marks: Vec<&str> = steps.iter()
+            .map(|step| match row.test.commands.iter().find(|cmd| cmd.command == *step) {
+                Some(cmd) if cmd.result.passed => "✓",
+                Some(_) => "✗",
+                None => "-",
+            })
+            .collect();
+        writeln!(file, "| {} | {} | {} | {} |", offered_label(row), row.primary.resolved_version, result, marks.join(" | "))?;
+    }
+    writeln!(file)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_group_markers() {
+        let provider = CiProvider::GitHubActions;
+        assert_eq!(provider.group_start("image 0.25.8"), "::group::image 0.25.8");
+        assert_eq!(provider.group_end("image 0.25.8"), "::endgroup::");
+    }
+
+    #[test]
+    fn test_gitlab_group_markers_use_sanitized_name() {
+        let provider = CiProvider::GitLab;
+        let start = provider.group_start("image 0.25.8");
+        assert!(start.starts_with("\x1b[0Ksection_start:"));
+        assert!(start.contains(":image_0.25.8[collapsed=true]\r\x1b[0Kimage 0.25.8"));
+        assert!(provider.group_end("image 0.25.8").contains(":image_0.25.8\r"));
+    }
+
+    fn row_with_failure(baseline_passed: Option<bool>, codes: &[&str]) -> OfferedRow {
+        use crate::{CrateFailure, DependencyRef};
+        let mut row = OfferedRow::for_test("image", Some("this(0.8.91)"), baseline_passed, false);
+        row.primary = DependencyRef {
+            resolved_source: VersionSource::Local,
+            used_offered_version: true,
+            edition: Some("2021".to_string()),
+            resolver: Some("2".to_string()),
+            targets: Some("lib".to_string()),
+            ..DependencyRef::for_test("image", "0.8.91")
+        };
+        let result = &mut row.test.commands[0].result;
+        result.exit = Some(crate::compile::ProcessExit::Code(101));
+        result.failures.push(CrateFailure {
+            crate_name: "image".to_string(),
+            error_message: String::new(),
+            error_codes: codes.iter().map(|c| c.to_string()).collect(),
+            error_patterns: vec![],
+            failed_tests: vec![],
+            likely_causes: vec![],
+        });
+        row
+    }
+
+    #[test]
+    fn test_summary_counts_error_codes_of_all_failures() {
+        let rows = vec![
+            row_with_failure(Some(true), &["E0425", "E0308"]),
+            row_with_failure(Some(true), &["E0308"]),
+            row_with_failure(Some(false), &["E0599", "E0308"]),  // BROKEN
+            row_with_failure(None, &["E0599"]),  // FAILED
+        ];
+        let summary = summarize_offered_rows(&rows);
+        assert_eq!(summary.regressed, 2);
+        assert_eq!(summary.broken, 2);
+        assert_eq!(summary.error_codes, vec![("E0308".to_string(), 3), ("E0599".to_string(), 2), ("E0425".to_string(), 1)]);
+        assert!(format_summary(&summary).contains("https://doc.rust-lang.org/error_codes/E0308.html"));
+    }
+
+    #[test]
+    fn test_summary_groups_regressions_by_error() {
+        let as_slice = ErrorPattern { code: Some("E0599".to_string()), message: "no method named `as_slice` found".to_string() };
+        let mismatch = ErrorPattern { code: Some("E0308".to_string()), message: "mismatched types".to_string() };
+        let mut rows = Vec::new();
+        for (name, baseline_passed, patterns) in [
+            ("image", Some(true), vec![as_slice.clone(), mismatch.clone()]),
+            ("ravif", Some(true), vec![as_slice.clone()]),
+            ("ravif", Some(true), vec![as_slice.clone()]),  // second offered version: same dependent
+            ("lodepng", Some(false), vec![as_slice.clone()]),  // BROKEN: not counted
+        ] {
+            let mut row = row_with_failure(baseline_passed, &[]);
+            row.primary.dependent_name = name.to_string();
+            row.test.commands[0].result.failures[0].error_patterns = patterns;
+            rows.push(row);
+        }
+        let summary = summarize_offered_rows(&rows);
+        assert_eq!(summary.error_groups, vec![
+            ErrorGroup { pattern: as_slice, dependents: vec!["image".to_string(), "ravif".to_string()] },
+            ErrorGroup { pattern: mismatch, dependents: vec!["image".to_string()] },
+        ]);
+        let text = format_summary(&summary);
+        assert!(text.contains("Regressions by error:\n  E0599 no method named `as_slice` found — 2 dependents\n      image, ravif\n"));
+        assert!(text.contains("  E0308 mismatched types — 1 dependent\n"));
+    }
+
+    #[test]
+    fn test_bump_apply() {
+        let v = |s: &str| semver::Version::parse(s).unwrap();
+        assert_eq!(Bump::Major.apply(&v("0.8.91")), v("0.9.0"));
+        assert_eq!(Bump::Minor.apply(&v("0.8.91")), v("0.8.92"));
+        assert_eq!(Bump::Patch.apply(&v("0.8.91")), v("0.8.92"));
+        assert_eq!(Bump::Major.apply(&v("1.4.2")), v("2.0.0"));
+        assert_eq!(Bump::Minor.apply(&v("1.4.2")), v("1.5.0"));
+        assert_eq!(Bump::Patch.apply(&v("1.4.2")), v("1.4.3"));
+        assert_eq!(Bump::Major.apply(&v("0.0.3")), v("0.0.4"));
+    }
+
+    #[test]
+    fn test_recommend_bump() {
+        use crate::api_diff::{ApiChange, ChangeKind};
+
+        let passing = || {
+            let mut row = row_with_failure(Some(true), &[]);
+            row.test.commands[0].result = crate::CommandResult { passed: true, duration: 1.0, failures: vec![], flaky: false, exit: None, log: None };
+            row
+        };
+        let recommendation = recommend_bump(&[passing()], None);
+        assert_eq!(recommendation.bump, Bump::Patch);
+        assert!(recommendation.reasons[0].starts_with("all 1 dependent tested passed (run with --api-diff"));
+
+        let added = [ApiChange { kind: ChangeKind::Added, path: "Rgb::as_slice_mut".to_string() }];
+        let recommendation = recommend_bump(&[passing()], Some(&added));
+        assert_eq!(recommendation, BumpRecommendation {
+            bump: Bump::Minor,
+            reasons: vec!["public API added: added `Rgb::as_slice_mut`".to_string()],
+        });
+
+        let mut forced = row_with_failure(Some(true), &["E0308"]);
+        forced.primary.dependent_name = "ravif".to_string();
+        forced.offered.as_mut().unwrap().forced = true;
+        let recommendation = recommend_bump(&[row_with_failure(Some(true), &["E0308"]), forced], Some(&added));
+        assert_eq!(recommendation.bump, Bump::Major);
+        assert_eq!(recommendation.reasons, vec![
+            "1 dependent regressed on a version their requirement accepts: image".to_string(),
+            "public API added: added `Rgb::as_slice_mut`".to_string(),
+        ]);
+        assert!(format_bump(&recommendation, Some(&semver::Version::new(0, 8, 91)))
+            .starts_with("Recommended version bump: major (0.8.91 → 0.9.0)\n  - 1 dependent regressed"));
+
+        // Regressions outside the requirement don't call for a bump
+        let mut upgraded = row_with_failure(Some(true), &["E0308"]);
+        upgraded.primary.used_offered_version = false;
+        let mut forced = row_with_failure(Some(true), &["E0308"]);
+        forced.offered.as_mut().unwrap().forced = true;
+        let recommendation = recommend_bump(&[passing(), forced.clone(), upgraded], None);
+        assert_eq!(recommendation.bump, Bump::Patch);
+        assert!(recommendation.reasons[0].starts_with("all 1 dependent tested passed"));
+        assert_eq!(recommend_bump(&[forced], None).reasons, vec!["no dependent was tested on a version its requirement accepts".to_string()]);
+    }
+
+    #[test]
+    fn test_summary_lists_likely_api_breaks() {
+        let mut rows = Vec::new();
+        for (name, causes) in [("image", vec!["removed `Rgb::as_slice`"]), ("ravif", vec!["removed `Rgb::as_slice`", "changed `Rgb::new`"])] {
+            let mut row = row_with_failure(Some(true), &[]);
+            row.primary.dependent_name = name.to_string();
+            row.test.commands[0].result.failures[0].likely_causes = causes.iter().map(|c| c.to_string()).collect();
+            rows.push(row);
+        }
+        let summary = summarize_offered_rows(&rows);
+        assert_eq!(summary.api_breaks, vec![
+            ApiBreak { cause: "removed `Rgb::as_slice`".to_string(), dependents: vec!["image".to_string(), "ravif".to_string()] },
+            ApiBreak { cause: "changed `Rgb::new`".to_string(), dependents: vec!["ravif".to_string()] },
+        ]);
+        assert!(format_summary(&summary).contains("Likely API breaks:\n  removed `Rgb::as_slice` — 2 dependents\n      image, ravif\n"));
+    }
+
+    #[test]
+    fn test_regressions_name_the_failed_step() {
+        let mut row = row_with_failure(Some(true), &["E0308"]);
+        assert_eq!(failed_step(&row), Some(CommandType::Check));
+        assert!(format_offered_row(&row).4.starts_with("REGRESSED(check) "));
+
+        row.test.commands[0].command = CommandType::Test;
+        assert!(format_offered_row(&row).4.starts_with("REGRESSED(test) "));
+
+        // Broken rows keep the plain status
+        row.baseline_passed = Some(false);
+        assert!(format_offered_row(&row).4.starts_with("BROKEN "));
+    }
+
+    #[test]
+    fn test_optional_step_failures_only_count_against_offered_versions() {
+        let mut row = row_with_failure(Some(true), &["E0000"]);
+        row.test.commands[0].command = CommandType::Doctest;
+        assert!(format_offered_row(&row).4.starts_with("REGRESSED(doctest) "));
+        row.test.commands[0].command = CommandType::Clippy;
+        assert_eq!(row_status(&row), RowStatus::Regressed);
+        assert!(format_offered_row(&row).4.starts_with("REGRESSED(clippy) "));
+
+        // The baseline's own lint failures don't fail it
+        row.baseline_passed = None;
+        row.offered = None;
+        assert_eq!(row_status(&row), RowStatus::Passed);
+        assert_eq!(failed_step(&row), None);
+    }
+
+    #[test]
+    fn test_spec_shows_enabled_base_features() {
+        let mut row = row_with_failure(Some(true), &[]);
+        assert_eq!(format_offered_row(&row).1, "^0.8");
+
+        row.primary.base_features = vec!["bytemuck".to_string(), "default".to_string(), "serde".to_string()];
+        assert_eq!(format_offered_row(&row).1, "^0.8 +bytemuck,serde");
+    }
+
+    #[test]
+    fn test_dependent_shows_maintenance_status() {
+        let mut row = row_with_failure(Some(true), &[]);
+        row.primary.maintenance = Some(crate::maintenance::Maintenance::Active);
+        assert_eq!(format_offered_row(&row).3, "image 0.25.8");
+
+        row.primary.maintenance = Some(crate::maintenance::Maintenance::Abandoned);
+        assert_eq!(format_offered_row(&row).3, "image 0.25.8 [abandoned]");
+    }
+
+    #[test]
+    fn test_flaky_rows_are_counted_separately() {
+        let mut flaky = row_with_failure(Some(true), &[]);
+        let result = &mut flaky.test.commands[0].result;
+        result.passed = true;
+        result.failures.clear();
+        result.flaky = true;
+        assert_eq!(row_status(&flaky), RowStatus::Flaky);
+
+        // A broken baseline still wins over a flaky offered version
+        let mut broken = flaky.clone();
+        broken.baseline_passed = Some(false);
+        assert_eq!(row_status(&broken), RowStatus::Broken);
+
+        let summary = summarize_offered_rows(&[flaky, broken]);
+        assert_eq!((summary.passed, summary.flaky, summary.broken, summary.total), (0, 1, 1, 2));
+        assert!(format_summary(&summary).contains("~ Flaky:     1\n"));
+    }
+
+    #[test]
+    fn test_rows_with_new_warnings_are_warned() {
+        let mut warned = row_with_failure(Some(true), &[]);
+        let result = &mut warned.test.commands[0].result;
+        result.passed = true;
+        result.failures.clear();
+        assert_eq!(row_status(&warned), RowStatus::Passed);
+
+        warned.warning_diff = vec![crate::warnings::WarningChange {
+            lint: "deprecated".to_string(),
+            baseline: 0,
+            offered: 2,
+            example: "use of deprecated method `rgb::Rgb::as_slice`".to_string(),
+        }];
+        assert_eq!(row_status(&warned), RowStatus::Warned);
+        assert!(!RowStatus::Warned.is_failure());
+
+        let summary = summarize_offered_rows(&[warned]);
+        assert_eq!((summary.passed, summary.warned, summary.total), (0, 1, 1));
+        assert_eq!(summary.warning_groups[0].lint, "deprecated");
+        let text = format_summary(&summary);
+        assert!(text.contains("! Warned:    1\n"));
+        assert!(text.contains("New warnings:\n  deprecated"));
+    }
+
+    #[test]
+    fn test_msrv_conflicts_are_flagged() {
+        let mut row = row_with_failure(Some(true), &[]);
+        let result = &mut row.test.commands[0].result;
+        result.passed = true;
+        result.failures.clear();
+        row.msrv_conflict = Some(crate::msrv::MsrvConflict { dependent: "1.60".to_string(), base: "1.70".to_string() });
+        assert_eq!(row_status(&row), RowStatus::Msrv);
+        assert!(!RowStatus::Msrv.is_failure());
+
+        // Failing on the current toolchain is still a regression
+        let mut regressed = row_with_failure(Some(true), &[]);
+        regressed.msrv_conflict = row.msrv_conflict.clone();
+        assert_eq!(row_status(&regressed), RowStatus::Regressed);
+
+        let summary = summarize_offered_rows(&[row]);
+        assert_eq!((summary.msrv, summary.passed, summary.total), (1, 0, 1));
+        assert!(format_summary(&summary).contains("> MSRV:      1\n"));
+    }
+
+    #[test]
+    fn test_duplicate_versions_are_flagged() {
+        let mut row = row_with_failure(Some(true), &[]);
+        let result = &mut row.test.commands[0].result;
+        result.passed = true;
+        result.failures.clear();
+        row.warning_diff = vec![crate::warnings::WarningChange {
+            lint: "deprecated".to_string(),
+            baseline: 0,
+            offered: 1,
+            example: "use of deprecated struct `rgb::RGB`".to_string(),
+        }];
+        row.duplicate_versions = vec!["0.8.50".to_string(), "0.9.0 (path)".to_string()];
+        // Two copies of the crate matter more than new warnings
+        assert_eq!(row_status(&row), RowStatus::Duplicated);
+        assert!(!RowStatus::Duplicated.is_failure());
+
+        let summary = summarize_offered_rows(&[row]);
+        assert_eq!((summary.duplicated, summary.warned, summary.total), (1, 0, 1));
+        assert!(format_summary(&summary).contains("= Duplicate: 1\n"));
+    }
+
+    #[test]
+    fn test_slowdowns_in_performance_impact() {
+        let mut row = row_with_failure(Some(true), &[]);
+        let result = &mut row.test.commands[0].result;
+        result.passed = true;
+        result.failures.clear();
+        row.slowdowns = vec![crate::build_times::Slowdown {
+            step: crate::compile::CompileStep::Check,
+            baseline: std::time::Duration::from_secs(10),
+            offered: std::time::Duration::from_secs(35),
+        }];
+        // Slower, but still passing
+        assert_eq!(row_status(&row), RowStatus::Passed);
+
+        let summary = summarize_offered_rows(&[row]);
+        assert_eq!(summary.slowdowns.len(), 1);
+        assert!(format_summary(&summary).contains("Performance impact:\n  image 0.25.8 with this(0.8.91) — check: 10.0s → 35.0s (3.5x)\n"));
+    }
+
+    #[test]
+    fn test_network_failures_are_counted_separately() {
+        let mut network = row_with_failure(Some(true), &[]);
+        let command = &mut network.test.commands[0];
+        command.command = CommandType::Fetch;
+        command.result.failures[0].error_message = "NETWORK: HTTP request failed: status code 503".to_string();
+        assert_eq!(row_status(&network), RowStatus::Network);
+
+        let mut summary = summarize_offered_rows(&[network, row_with_failure(Some(true), &["E0599"])]);
+        assert_eq!((summary.network, summary.regressed, summary.total), (1, 1, 2));
+        assert!(format_summary(&summary).contains("? Network:   1\n"));
+
+        // Unreachable dependents only fail the run at --fail-on error
+        summary.regressed = 0;
+        assert!(FailOn::Error.fails(&summary, 0));
+        assert!(!FailOn::Broken.fails(&summary, 0));
+    }
+
+    #[test]
+    fn test_html_report_details() {
+        let mut baseline = row_with_failure(None, &[]);
+        baseline.offered = None;
+        baseline.test.commands[0].result = crate::CommandResult { passed: true, duration: 1.0, failures: vec![], flaky: false, exit: None, log: None };
+        let mut regressed = row_with_failure(Some(true), &["E0308"]);
+        regressed.test.commands[0].result.failures[0].error_message = "error[E0308]: mismatched types <u8>".to_string();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.html");
+        generate_html_report(&[baseline, regressed], "rgb", "0.8.91", 1, &[], false, &path).unwrap();
+        let html = std::fs::read_to_string(&path).unwrap();
+
+        assert_eq!(html.matches("id='image-0_25_8'").count(), 1);
+        assert!(html.contains("<tbody id='image-0_25_8' data-dependent='image 0.25.8' data-status='regressed' data-rank='0' data-time='2.0'>"));
+        assert_eq!(html.matches("<a href='#image-0_25_8'>").count(), 2);
+        assert!(html.contains("<tr class='regressed' data-offered='this(0.8.91)'>"));
+        assert!(html.contains("REGRESSED(check) <span title='check failed (1.0s)'>✗</span></td><td>1.0s</td>"));
+        assert!(html.contains("<summary>cargo check failed on image</summary><pre class='console'><span class='diag-error'>error[E0308]: mismatched types &lt;u8&gt;</span>\n</pre>"));
+        assert!(html.contains("<option value='regressed'>regressed (1)</option>"));
+        assert!(html.contains("<option>this(0.8.91)</option>"));
+        assert!(html.contains("Passed: 0, Regressed: 1, Broken: 0, Flaky: 0, Total: 1"));
+        assert!(html.contains("<pre class='mermaid'>"));
+        assert!(!html.contains("cdn.jsdelivr.net"));
+    }
+
+    #[test]
+    fn test_html_embeds_step_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("check.log");
+        std::fs::write(&log, format!("{}error: the end\n", "x".repeat(MAX_EMBEDDED_LOG))).unwrap();
+        let html = embedded_log(Some(&log));
+        assert!(html.starts_with("<details class='log'><summary>Full log: <a href="));
+        assert!(html.contains("[first 15 B omitted]\n"));
+        assert!(html.ends_with("error: the end\n</pre></details>"));
+
+        assert!(embedded_log(Some(&dir.path().join("missing.log"))).starts_with("<p>Full log: "));
+        assert_eq!(embedded_log(None), "");
+    }
+
+    #[test]
+    fn test_diagnostics_html() {
+        let html = diagnostics_html("error[E0425]: cannot find `x`\n --> src/lib.rs:1:5\n  |\n  = help: a local variable\nwarning: unused");
+        assert_eq!(html, "<span class='diag-error'>error[E0425]: cannot find `x`</span>\n\
+                          <span class='diag-location'> --&gt; src/lib.rs:1:5</span>\n  |\n\
+                          <span class='diag-note'>  = help: a local variable</span>\n\
+                          <span class='diag-warning'>warning: unused</span>\n");
+    }
+
+    #[test]
+    fn test_markdown_analysis() {
+        let mut baseline = row_with_failure(None, &[]);
+        baseline.offered = None;
+        baseline.test.commands[0].result = crate::CommandResult { passed: true, duration: 1.0, failures: vec![], flaky: false, exit: None, log: None };
+        let mut regressed = row_with_failure(Some(true), &["E0308"]);
+        regressed.test.commands[0].result.failures[0].error_message = "error[E0308]: mismatched types".to_string();
+        let mut passing = baseline.clone();
+        passing.primary.dependent_name = "ravif".to_string();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("analysis.md");
+        export_markdown_report(&[passing, baseline, regressed], &path, "rgb", "0.8.91", &[]).unwrap();
+        let md = std::fs::read_to_string(&path).unwrap();
+
+        assert!(md.starts_with("# Downstream analysis: rgb 0.8.91\n\n0 passed, 1 regressed, 0 broken of 1"));
+        assert!(md.contains("## image 0.25.8 (REGRESSED)\n\nRequires `^0.8`.\n"));
+        assert!(md.contains("| Offered | Resolved | Result | check |\n|---------|----------|--------|---|\n\
+                             | baseline | 0.8.91 | PASSED | ✓ |\n\
+                             | this(0.8.91) | 0.8.91 | REGRESSED(check) | ✗ |\n"));
+        assert!(md.contains("In `image` ([E0308](https://doc.rust-lang.org/error_codes/E0308.html)):\n\n```text\nerror[E0308]: mismatched types\n```"));
+        assert!(md.ends_with("## Passed\n\nravif 0.25.8\n"));
+    }
+
+    #[test]
+    fn test_ascii_glyphs_keep_widths() {
+        let line = "│ ✓ =this(0.8.91) │ → =0.8.90 [≠→!] │ 0.8.91 📦 │ PASSED ✓✗- │";
+        let ascii = to_ascii(line);
+        assert_eq!(ascii, "| + =this(0.8.91) | > =0.8.90 [#>!] | 0.8.91 io | PASSED +x- |");
+        assert!(ascii.is_ascii());
+        assert_eq!(display_width(&ascii), display_width(line));
+    }
+
+    #[test]
+    fn test_wrap_with_padding() {
+        assert_eq!(wrap_with_padding("image-processing-toolkit 0.25.8", 12),
+                   vec!["image-proces", "sing-toolkit", "0.25.8      "]);
+        assert_eq!(wrap_with_padding("cargo check failed on image", 12),
+                   vec!["cargo check ", "failed on   ", "image       "]);
+        assert_eq!(wrap_with_padding("", 4), vec!["    "]);
+    }
+
+    #[test]
+    fn test_fail_on() {
+        let summary = |regressed, broken| TestSummary { passed: 3, regressed, broken, total: 3 + regressed + broken, ..Default::default() };
+        assert!(FailOn::Regressed.fails(&summary(1, 0), 0));
+        assert!(!FailOn::Regressed.fails(&summary(0, 2), 1));
+        assert!(FailOn::Broken.fails(&summary(0, 2), 0));
+        assert!(!FailOn::Broken.fails(&summary(0, 0), 1));
+        assert!(FailOn::Error.fails(&summary(0, 0), 1));
+        assert!(!FailOn::None.fails(&summary(5, 5), 5));
+    }
+
+    #[test]
+    fn test_color_choice() {
+        assert!(ColorChoice::Auto.enabled(false, true));
+        assert!(!ColorChoice::Auto.enabled(true, true));
+        assert!(!ColorChoice::Auto.enabled(false, false));
+        assert!(ColorChoice::Always.enabled(true, false));
+        assert!(!ColorChoice::Never.enabled(false, true));
+    }
+
+    #[test]
+    fn test_ansi_to_html() {
+        assert_eq!(ansi_to_html("a < b & c"), "a &lt; b &amp; c");
+        assert_eq!(ansi_to_html(&format!("{}│ ok │{}\n", ansi_fg(term::color::BRIGHT_GREEN), ANSI_RESET)),
+                   "<span style='color:#23d18b;'>│ ok │</span>\n");
+        assert_eq!(ansi_to_html("\x1b[1;31mbad\x1b[22m!\x1b[0m"),
+                   "<span style='color:#cd3131;font-weight:bold;'>bad</span><span style='color:#cd3131;'>!</span>");
+        // Non-color escapes are dropped
+        assert_eq!(ansi_to_html("\x1b[0Kplain\x1b[2J"), "plain");
+    }
+
+    #[test]
+    fn test_summarize_by_edition() {
+        let mut old = row_with_failure(Some(true), &[]);
+        old.primary.edition = Some("2018".to_string());
+        old.primary.resolver = Some("1".to_string());
+        let mut unknown = row_with_failure(Some(false), &[]);
+        unknown.primary.edition = None;
+        let rows = vec![row_with_failure(Some(true), &[]), row_with_failure(Some(false), &[]), old, unknown];
+
+        let groups = summarize_by_edition(&rows);
+        let keys: Vec<(&str, &str)> = groups.iter().map(|(e, r, _)| (e.as_str(), r.as_str())).collect();
+        assert_eq!(keys, vec![("2018", "1"), ("2021", "2")]);
+        assert_eq!((groups[1].2.regressed, groups[1].2.broken), (1, 1));
+        assert_eq!(groups[0].2.regressed, 1);
+    }
 }
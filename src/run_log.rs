@@ -0,0 +1,234 @@
+//! Verbosity (`-q`, `-v`, `-vv`) and the structured run log (`--log-file`)
+//!
+//! `-q` hides the `copter:` status lines, `-v` echoes cargo's stderr live
+//! and `-vv` also its stdout (rendered compiler messages and test output),
+//! each line prefixed with the dependent it belongs to.
+//!
+//! The run log is JSON Lines: every status line, every debug record of this
+//! crate and every line cargo writes to stderr, with a timestamp and the
+//! dependent being tested. It's written whatever `RUST_LOG` says; the console
+//! still follows `RUST_LOG` through env_logger.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    VeryVerbose,
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::VeryVerbose,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Verbosity::Quiet,
+            1 => Verbosity::Normal,
+            2 => Verbosity::Verbose,
+            _ => Verbosity::VeryVerbose,
+        }
+    }
+}
+
+/// Stream of a cargo process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Whether LOG_FILE is open, checked for every log record without locking
+static LOGGING_TO_FILE: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    /// Flushed after every entry, so the log is complete however the run ends
+    static ref LOG_FILE: Mutex<Option<LineWriter<File>>> = Mutex::new(None);
+}
+
+thread_local! {
+    /// Dependent the current thread is testing
+    static DEPENDENT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    time: String,
+    level: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependent: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a str>,
+    message: &'a str,
+}
+
+/// Forwards to env_logger and copies this crate's debug records to the run log
+struct RunLogger {
+    console: env_logger::Logger,
+}
+
+impl Log for RunLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata) || (logging_to_file() && is_ours(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.console.matches(record) {
+            self.console.log(record);
+        }
+        if is_ours(record.metadata()) {
+            let message = record.args().to_string();
+            let level = record.level().as_str().to_lowercase();
+            let dependent = current_dependent();
+            write_entry(&level, dependent.as_deref(), Some(record.target()), &message);
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+        if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+fn is_ours(metadata: &Metadata) -> bool {
+    metadata.level() <= Level::Debug && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+}
+
+fn logging_to_file() -> bool {
+    LOGGING_TO_FILE.load(Ordering::Relaxed)
+}
+
+/// Set the verbosity, open the run log and install the logger (in place of `env_logger::init`)
+pub fn init(verbosity: Verbosity, log_file: Option<&Path>) -> Result<(), String> {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+    if let Some(path) = log_file {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create log file {}: {}", path.display(), e))?;
+        *LOG_FILE.lock().unwrap() = Some(LineWriter::new(file));
+        LOGGING_TO_FILE.store(true, Ordering::Relaxed);
+    }
+
+    let console = env_logger::Builder::from_default_env().build();
+    let max_level = if log_file.is_some() { console.filter().max(LevelFilter::Debug) } else { console.filter() };
+    log::set_boxed_logger(Box::new(RunLogger { console }))
+        .map_err(|e| format!("Failed to install logger: {}", e))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+pub fn verbosity() -> Verbosity {
+    Verbosity::from_u8(VERBOSITY.load(Ordering::Relaxed))
+}
+
+/// The current thread tests `name` from now on
+pub fn set_dependent(name: Option<&str>) {
+    DEPENDENT.with(|d| *d.borrow_mut() = name.map(str::to_string));
+}
+
+pub fn current_dependent() -> Option<String> {
+    DEPENDENT.with(|d| d.borrow().clone())
+}
+
+/// Record a `copter:` status line
+pub fn status(message: &str) {
+    let dependent = current_dependent();
+    write_entry("status", dependent.as_deref(), None, message);
+}
+
+/// Whether cargo's output has to be read line by line, to echo or record it
+pub fn wants_cargo_output() -> bool {
+    verbosity() >= Verbosity::Verbose || logging_to_file()
+}
+
+/// A line cargo wrote while testing `dependent`
+pub fn cargo_line(dependent: Option<&str>, stream: Stream, line: &str) {
+    if stream == Stream::Stderr {
+        write_entry("cargo", dependent, None, line);
+    }
+    let echoed = match (verbosity(), stream) {
+        (Verbosity::Verbose, Stream::Stderr) | (Verbosity::VeryVerbose, Stream::Stderr) => Some(line.to_string()),
+        (Verbosity::VeryVerbose, Stream::Stdout) => displayed_stdout(line),
+        _ => None,
+    };
+    if let Some(echoed) = echoed {
+        let prefix = dependent.map(|d| format!("[{}] ", d)).unwrap_or_default();
+        crate::progress::suspend(|| {
+            for line in echoed.lines() {
+                println!("{}{}", prefix, line);
+            }
+        });
+    }
+}
+
+/// What to show of a stdout line: the rendered text of compiler messages,
+/// plain lines (test output) as is, and nothing for other JSON messages
+fn displayed_stdout(line: &str) -> Option<String> {
+    if !line.starts_with('{') {
+        return Some(line.to_string());
+    }
+    let message: serde_json::Value = serde_json::from_str(line).ok()?;
+    if message["reason"] != "compiler-message" {
+        return None;
+    }
+    message["message"]["rendered"].as_str().map(|r| r.trim_end().to_string())
+}
+
+fn write_entry(level: &str, dependent: Option<&str>, target: Option<&str>, message: &str) {
+    let mut file = LOG_FILE.lock().unwrap();
+    let Some(file) = file.as_mut() else {
+        return;
+    };
+    let entry = Entry {
+        time: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        level,
+        dependent,
+        target,
+        message,
+    };
+    if let Ok(json) = serde_json::to_string(&entry) {
+        let _ = writeln!(file, "{}", json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_from_flags() {
+        assert_eq!(Verbosity::from_flags(true, 2), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(false, 1), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(false, 3), Verbosity::VeryVerbose);
+        assert_eq!(Verbosity::from_u8(Verbosity::Verbose as u8), Verbosity::Verbose);
+    }
+
+    #[test]
+    fn test_displayed_stdout() {
+        assert_eq!(displayed_stdout("test tests::it_works ... ok").as_deref(), Some("test tests::it_works ... ok"));
+        assert_eq!(displayed_stdout(r#"{"reason":"compiler-artifact","target":{}}"#), None);
+        assert_eq!(displayed_stdout(r#"{"reason":"compiler-message","message":{"rendered":"error[E0308]: mismatched types\n"}}"#).as_deref(),
+                   Some("error[E0308]: mismatched types"));
+    }
+}
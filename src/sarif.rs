@@ -0,0 +1,192 @@
+//! SARIF export for regression diagnostics
+//!
+//! Converts the compiler errors of regressed dependents into a SARIF 2.1.0
+//! log so they can be uploaded to code-scanning UIs (e.g. GitHub's
+//! `upload-sarif` action). Each rustc error code becomes a rule; errors
+//! without a code share the `rustc-error` rule. Locations are relative to
+//! the staging directory, where each dependent is unpacked as
+//! `{name}-{version}/`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde_json::{json, Value};
+
+use crate::compile::VersionSource;
+use crate::error_extract::{error_code_url, Diagnostic};
+use crate::{TestResult, TestResultData};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Rule used for errors rustc didn't give an error code
+const UNCODED_RULE: &str = "rustc-error";
+
+/// Base id that result locations are relative to
+const STAGING_BASE_ID: &str = "STAGING";
+
+/// Collects regression diagnostics and writes them as a SARIF log
+#[derive(Default)]
+pub struct SarifBuilder {
+    rules: BTreeMap<String, String>,  // rule id -> first message seen
+    results: Vec<Value>,
+}
+
+impl SarifBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the errors of every offered version that regressed against the baseline
+    pub fn add_test_result(&mut self, result: &TestResult, this_label: &str) {
+        let outcomes = match &result.data {
+            TestResultData::MultiVersion(outcomes) => outcomes,
+            _ => return,
+        };
+        let baseline_passed = outcomes.first().map(|b| b.result.is_success()).unwrap_or(false);
+        if !baseline_passed {
+            return;  // BROKEN, not a regression
+        }
+
+        let dependent_dir = format!("{}-{}", result.rev_dep.name, result.rev_dep.vers);
+        for outcome in outcomes.iter().skip(1) {
+            let failure = match outcome.result.first_failure() {
+                Some(f) => f,
+                None => continue,
+            };
+            let offered = match &outcome.version_source {
+                VersionSource::Local(_) => this_label.to_string(),
+                VersionSource::Published(v) => v.clone(),
+            };
+            for diag in failure.diagnostics.iter().filter(|d| d.level.is_error()) {
+                self.add_diagnostic(diag, &result.rev_dep.name, &dependent_dir, &offered, failure.step.as_str());
+            }
+        }
+    }
+
+    fn add_diagnostic(&mut self, diag: &Diagnostic, dependent: &str, dependent_dir: &str, offered: &str, step: &str) {
+        let rule_id = diag.code.clone().unwrap_or_else(|| UNCODED_RULE.to_string());
+        self.rules.entry(rule_id.clone()).or_insert_with(|| diag.message.clone());
+
+        let mut sarif_result = json!({
+            "ruleId": rule_id,
+            "level": "error",
+            "message": {
+                "text": format!("{} (regression in {} with {} during cargo {})", diag.message, dependent, offered, step),
+            },
+            "properties": {
+                "dependent": dependent,
+                "offeredVersion": offered,
+                "step": step,
+            },
+        });
+
+        if let Some(span) = &diag.primary_span {
+            sarif_result["locations"] = json!([{
+                "physicalLocation": {
+                    "artifactLocation": {
+                        "uri": format!("{}/{}", dependent_dir, span.file_name.replace('\\', "/")),
+                        "uriBaseId": STAGING_BASE_ID,
+                    },
+                    "region": {
+                        "startLine": span.line,
+                        "startColumn": span.column,
+                    },
+                },
+            }]);
+        }
+
+        self.results.push(sarif_result);
+    }
+
+    /// Build the SARIF document
+    pub fn to_sarif(&self, staging_dir: &Path) -> Value {
+        let rules: Vec<Value> = self.rules.iter()
+            .map(|(id, message)| {
+                let mut rule = json!({
+                    "id": id,
+                    "shortDescription": { "text": message },
+                });
+                if id.starts_with('E') {
+                    rule["helpUri"] = json!(error_code_url(id));
+                }
+                rule
+            })
+            .collect();
+
+        let staging_path = staging_dir.display().to_string().replace('\\', "/");
+        // Windows paths (C:/...) need the extra slash of an empty authority
+        let staging_uri = format!("file://{}{}/",
+            if staging_path.starts_with('/') { "" } else { "/" }, staging_path.trim_end_matches('/'));
+
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/imazen/cargo-copter",
+                        "rules": rules,
+                    },
+                },
+                "originalUriBaseIds": {
+                    STAGING_BASE_ID: { "uri": staging_uri },
+                },
+                "results": self.results,
+            }],
+        })
+    }
+
+    /// Write the SARIF log to `output_path`
+    pub fn write(&self, output_path: &Path, staging_dir: &Path) -> io::Result<()> {
+        let staging_dir = fs::canonicalize(staging_dir).unwrap_or_else(|_| staging_dir.to_path_buf());
+        let json = serde_json::to_string_pretty(&self.to_sarif(&staging_dir)).map_err(io::Error::other)?;
+        fs::write(output_path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error_extract::{DiagnosticLevel, SpanInfo};
+
+    fn diagnostic(code: Option<&str>, message: &str) -> Diagnostic {
+        Diagnostic {
+            level: DiagnosticLevel::Error,
+            code: code.map(|c| c.to_string()),
+            message: message.to_string(),
+            rendered: String::new(),
+            primary_span: Some(SpanInfo {
+                file_name: "src/lib.rs".to_string(),
+                line: 6,
+                column: 5,
+                label: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_sarif_rules_and_locations() {
+        let mut builder = SarifBuilder::new();
+        builder.add_diagnostic(&diagnostic(Some("E0308"), "mismatched types"), "image", "image-0.25.8", "this(0.8.91)", "check");
+        builder.add_diagnostic(&diagnostic(None, "linking failed"), "image", "image-0.25.8", "this(0.8.91)", "test");
+        assert_eq!(builder.results.len(), 2);
+
+        let sarif = builder.to_sarif(Path::new("/tmp/staging"));
+        assert_eq!(sarif["version"], "2.1.0");
+
+        let run = &sarif["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules[0]["id"], "E0308");
+        assert_eq!(rules[0]["helpUri"], "https://doc.rust-lang.org/error_codes/E0308.html");
+        assert_eq!(rules[1]["id"], UNCODED_RULE);
+        assert!(rules[1].get("helpUri").is_none());
+
+        let location = &run["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "image-0.25.8/src/lib.rs");
+        assert_eq!(location["region"]["startLine"], 6);
+        assert_eq!(run["originalUriBaseIds"]["STAGING"]["uri"], "file:///tmp/staging/");
+    }
+}
@@ -0,0 +1,105 @@
+//! Work queue ordering for parallel runs
+//!
+//! Dependents are started longest-first (the LPT heuristic) using durations
+//! recorded in the results database, so huge dependents don't start last
+//! and leave every other job idle while they finish. Dependents without
+//! history are treated as the longest, since they could be anything.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::format::HumanDuration;
+
+/// How dependents are tested (`--strategy`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Strategy {
+    /// Check and test every dependent
+    #[default]
+    Full,
+    /// Check every dependent first (no tests or optional steps), then test
+    /// only those an offered version checks differently than the baseline
+    QuickThenDeep,
+}
+
+/// Indices of `names` in the order their jobs should be started
+pub fn lpt_order(names: &[String], estimates: &HashMap<String, f64>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..names.len()).collect();
+    // Stable sort keeps the requested order among equal (and unknown) estimates
+    order.sort_by(|&a, &b| {
+        let estimate = |i: usize| estimates.get(&names[i]).copied().unwrap_or(f64::INFINITY);
+        estimate(b).total_cmp(&estimate(a))
+    });
+    order
+}
+
+/// How well a run kept its jobs busy
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleStats {
+    /// Parallel jobs that could be used (never more than the dependents tested)
+    pub jobs: usize,
+    /// Wall time from the first job starting to the last one finishing
+    pub makespan: Duration,
+    /// Sum of every job's wall time
+    pub busy: Duration,
+    /// Longest single job
+    pub longest: Duration,
+}
+
+impl ScheduleStats {
+    pub fn new(jobs: usize, makespan: Duration, job_times: &[Duration]) -> Self {
+        ScheduleStats {
+            jobs: jobs.min(job_times.len()).max(1),
+            makespan,
+            busy: job_times.iter().sum(),
+            longest: job_times.iter().max().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Share of the available job time spent testing (1.0 = no idle jobs)
+    pub fn efficiency(&self) -> f64 {
+        let capacity = self.makespan.as_secs_f64() * self.jobs as f64;
+        if capacity == 0.0 {
+            return 1.0;
+        }
+        (self.busy.as_secs_f64() / capacity).min(1.0)
+    }
+
+    /// No schedule can finish faster than this
+    pub fn lower_bound(&self) -> Duration {
+        (self.busy / self.jobs as u32).max(self.longest)
+    }
+
+    pub fn format(&self) -> String {
+        format!("Schedule: {} jobs, makespan {}, efficiency {:.0}% (lower bound {})\n",
+            self.jobs, HumanDuration(self.makespan), self.efficiency() * 100.0, HumanDuration(self.lower_bound()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lpt_order() {
+        let names: Vec<String> = ["small", "new", "huge", "medium"].iter().map(|s| s.to_string()).collect();
+        let estimates: HashMap<String, f64> = [("small", 5.0), ("huge", 600.0), ("medium", 60.0)]
+            .iter().map(|(n, d)| (n.to_string(), *d)).collect();
+        // Unknown first, then longest to shortest
+        assert_eq!(lpt_order(&names, &estimates), vec![1, 2, 3, 0]);
+        assert_eq!(lpt_order(&names, &HashMap::new()), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_schedule_stats() {
+        let secs = Duration::from_secs;
+        let stats = ScheduleStats::new(2, secs(100), &[secs(100), secs(60), secs(20)]);
+        assert_eq!(stats.efficiency(), 0.9);
+        assert_eq!(stats.lower_bound(), secs(100));
+        assert_eq!(stats.format(), "Schedule: 2 jobs, makespan 1m 40s, efficiency 90% (lower bound 1m 40s)\n");
+
+        // More jobs than dependents: the extra jobs don't count as idle
+        let stats = ScheduleStats::new(8, secs(30), &[secs(30)]);
+        assert_eq!(stats.jobs, 1);
+        assert_eq!(stats.efficiency(), 1.0);
+    }
+}
@@ -0,0 +1,90 @@
+//! Custom report sinks
+//!
+//! Built-in reports (console, HTML, markdown, JSON) are written by the run
+//! loop directly. Integrators can register extra `ReportSink`s to receive
+//! every row as it streams and the summary once the run is done, e.g. to push
+//! results into their own database or chat system.
+
+use std::io;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+use crate::report::TestSummary;
+use crate::OfferedRow;
+
+/// Receives results while a run is in progress
+pub trait ReportSink: Send {
+    /// Called for each row as soon as its dependent finishes
+    fn write_row(&mut self, row: &OfferedRow) -> io::Result<()>;
+
+    /// Called once after the last row, with the totals of the run
+    fn finish(&mut self, summary: &TestSummary) -> io::Result<()>;
+}
+
+lazy_static! {
+    static ref SINKS: Mutex<Vec<Box<dyn ReportSink>>> = Mutex::new(Vec::new());
+}
+
+/// Register a sink to receive the rows and summary of subsequent runs
+pub fn register_report_sink(sink: Box<dyn ReportSink>) {
+    SINKS.lock().unwrap().push(sink);
+}
+
+/// Stream a row to every registered sink
+///
+/// A failing sink only produces a warning; it never aborts the run.
+pub fn write_row(row: &OfferedRow) {
+    for sink in SINKS.lock().unwrap().iter_mut() {
+        if let Err(e) = sink.write_row(row) {
+            eprintln!("Warning: Report sink failed to write row: {}", e);
+        }
+    }
+}
+
+/// Hand the summary to every registered sink and unregister them
+pub fn finish(summary: &TestSummary) {
+    for mut sink in SINKS.lock().unwrap().drain(..) {
+        if let Err(e) = sink.finish(summary) {
+            eprintln!("Warning: Report sink failed to finish: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::report::summarize_offered_rows;
+
+    /// Records what it receives into a shared log
+    struct RecordingSink(Arc<Mutex<Vec<String>>>);
+
+    impl ReportSink for RecordingSink {
+        fn write_row(&mut self, row: &OfferedRow) -> io::Result<()> {
+            self.0.lock().unwrap().push(format!("row {}", row.primary.dependent_name));
+            Ok(())
+        }
+
+        fn finish(&mut self, summary: &TestSummary) -> io::Result<()> {
+            self.0.lock().unwrap().push(format!("finish {}", summary.total));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registered_sink_receives_rows_and_summary() {
+        let row = OfferedRow::for_test("image", Some("0.8.91"), None, true);
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        register_report_sink(Box::new(RecordingSink(log.clone())));
+        write_row(&row);
+        finish(&summarize_offered_rows(std::slice::from_ref(&row)));
+
+        assert_eq!(*log.lock().unwrap(), vec!["row image".to_string(), "finish 1".to_string()]);
+
+        // Sinks are dropped after finishing
+        write_row(&row);
+        assert_eq!(log.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,149 @@
+//! Compiled size of dependents (`--full-build`)
+//!
+//! Embedded and wasm users notice when an upgrade bloats their binaries
+//! before anything fails to build. After the full build, the libraries and
+//! binaries cargo reports for the dependent's own package are measured, and
+//! the sizes with each offered version are compared with the baseline's.
+//!
+//! The full build uses the dev profile, so these are unoptimized artifacts
+//! with debug info: good for spotting an upgrade that pulls in much more
+//! code, not for the size of a release binary. Reports label them as such.
+//!
+//! Test harnesses and examples that `--all-targets` builds too are left out,
+//! as are dependencies, whose artifacts don't ship on their own.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::HumanSize;
+
+/// Smallest size change reported, in percent
+const MIN_CHANGE_PERCENT: f64 = 1.0;
+
+/// Target kinds whose artifacts are measured
+const MEASURED_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib", "bin"];
+
+/// A library or binary built for the dependent, e.g. `libimage.rlib`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactSize {
+    /// File name, without directories
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// Sizes of the artifacts cargo built for the package at `crate_path`,
+/// from the `compiler-artifact` messages of `--message-format=json`
+pub fn measure(stdout: &str, crate_path: &Path) -> Vec<ArtifactSize> {
+    let Ok(manifest) = crate_path.join("Cargo.toml").canonicalize() else { return vec![] };
+    let mut sizes: Vec<ArtifactSize> = Vec::new();
+    for line in stdout.lines().filter(|line| line.starts_with('{')) {
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else { continue };
+        if message["reason"] != "compiler-artifact" || message["profile"]["test"] == true {
+            continue;
+        }
+        let own = message["manifest_path"].as_str()
+            .and_then(|path| Path::new(path).canonicalize().ok())
+            .is_some_and(|path| path == manifest);
+        let measured = message["target"]["kind"].as_array()
+            .is_some_and(|kinds| kinds.iter().any(|kind| kind.as_str().is_some_and(|k| MEASURED_KINDS.contains(&k))));
+        if !own || !measured {
+            continue;
+        }
+        let files = message["filenames"].as_array().into_iter().flatten().filter_map(|file| file.as_str());
+        for file in files.map(Path::new) {
+            // Metadata for later crates, and debug info next to the binary
+            if matches!(file.extension().and_then(|e| e.to_str()), Some("rmeta" | "pdb" | "dwp")) {
+                continue;
+            }
+            let (Some(name), Ok(metadata)) = (file.file_name(), fs::metadata(file)) else { continue };
+            let name = name.to_string_lossy().into_owned();
+            if !sizes.iter().any(|size| size.name == name) {
+                sizes.push(ArtifactSize { name, bytes: metadata.len() });
+            }
+        }
+    }
+    sizes
+}
+
+/// An artifact that got bigger or smaller with the offered version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeChange {
+    pub name: String,
+    pub baseline: u64,
+    pub offered: u64,
+}
+
+impl SizeChange {
+    /// Change relative to the baseline, e.g. 25.0 for a quarter bigger
+    pub fn percent(&self) -> f64 {
+        (self.offered as f64 - self.baseline as f64) * 100.0 / (self.baseline as f64).max(1.0)
+    }
+}
+
+impl fmt::Display for SizeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} → {} ({:+.1}%)", self.name, HumanSize(self.baseline), HumanSize(self.offered), self.percent())
+    }
+}
+
+/// Artifacts of both runs whose size changed by at least MIN_CHANGE_PERCENT
+pub fn diff(baseline: &[ArtifactSize], offered: &[ArtifactSize]) -> Vec<SizeChange> {
+    offered.iter()
+        .filter_map(|after| {
+            let before = baseline.iter().find(|before| before.name == after.name)?;
+            let change = SizeChange { name: after.name.clone(), baseline: before.bytes, offered: after.bytes };
+            (change.percent().abs() >= MIN_CHANGE_PERCENT).then_some(change)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_own_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"image\"\n").unwrap();
+        let out = dir.path().join("target/debug");
+        fs::create_dir_all(&out).unwrap();
+        for (file, len) in [("libimage.rlib", 3000), ("libimage.rmeta", 100), ("image-cli", 5000), ("image-0123abcd", 9000)] {
+            fs::write(out.join(file), vec![0u8; len]).unwrap();
+        }
+        let manifest = dir.path().join("Cargo.toml");
+        let artifact = |kind: &str, test: bool, manifest: &Path, files: &[&str]| serde_json::json!({
+            "reason": "compiler-artifact",
+            "manifest_path": manifest,
+            "target": { "kind": [kind] },
+            "profile": { "test": test },
+            "filenames": files.iter().map(|f| out.join(f)).collect::<Vec<_>>(),
+        }).to_string();
+        let stdout = [
+            artifact("lib", false, &manifest, &["libimage.rlib", "libimage.rmeta"]),
+            artifact("bin", false, &manifest, &["image-cli"]),
+            artifact("lib", true, &manifest, &["image-0123abcd"]),
+            artifact("lib", false, Path::new("/registry/png/Cargo.toml"), &["libimage.rlib"]),
+            r#"{"reason":"build-finished","success":true}"#.to_string(),
+        ].join("\n");
+
+        assert_eq!(measure(&stdout, dir.path()), vec![
+            ArtifactSize { name: "libimage.rlib".to_string(), bytes: 3000 },
+            ArtifactSize { name: "image-cli".to_string(), bytes: 5000 },
+        ]);
+    }
+
+    #[test]
+    fn test_size_changes() {
+        let size = |name: &str, bytes| ArtifactSize { name: name.to_string(), bytes };
+        let baseline = [size("libimage.rlib", 1 << 20), size("image-cli", 4000), size("old-cli", 10)];
+        let offered = [size("libimage.rlib", 5 << 18), size("image-cli", 4010), size("new-cli", 10)];
+
+        let changes = diff(&baseline, &offered);
+        assert_eq!(changes, vec![SizeChange { name: "libimage.rlib".to_string(), baseline: 1 << 20, offered: 5 << 18 }]);
+        assert_eq!(changes[0].to_string(), "libimage.rlib: 1.0 MiB → 1.2 MiB (+25.0%)");
+        assert_eq!(diff(&offered, &baseline)[0].percent(), -20.0);
+    }
+}
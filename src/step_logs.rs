@@ -0,0 +1,124 @@
+//! Complete output of every step (`$COPTER_HOME/logs`)
+//!
+//! The failure log only has what failed, appended run after run. Here every
+//! step of every dependent gets its own plain text file,
+//! `logs/<dependent>-<version>/<offered>/<step>.log`, where `<offered>` is
+//! `baseline`, the offered version or `this` (with `-<target>` for
+//! `--target`), and the dependent's directory ends in `@<toolchain>` for
+//! `--channels`. A dependent's directory is replaced each time it's tested.
+//! The console, HTML and JSON reports point at the log of each step.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::compile::{CompileStep, VersionSource};
+use crate::{TestResult, TestResultData};
+
+/// Directory of one version tested against a dependent
+pub fn offered_dir(is_baseline: bool, source: &VersionSource, cross_target: Option<&str>) -> String {
+    let offered = match (is_baseline, source) {
+        (true, _) => "baseline".to_string(),
+        (false, VersionSource::Local(_)) => "this".to_string(),
+        (false, VersionSource::Published(version)) => version.clone(),
+    };
+    match cross_target {
+        Some(target) => format!("{}-{}", offered, target),
+        None => offered,
+    }
+}
+
+fn dependent_dir(logs: &Path, dependent: &str, version: &str, channel: Option<&str>) -> PathBuf {
+    match channel {
+        Some(channel) => logs.join(format!("{}-{}@{}", dependent, version, channel)),
+        None => logs.join(format!("{}-{}", dependent, version)),
+    }
+}
+
+/// Log of one step under `logs`
+pub fn path(logs: &Path, dependent: &str, version: &str, channel: Option<&str>, offered: &str, step: CompileStep) -> PathBuf {
+    dependent_dir(logs, dependent, version, channel).join(offered).join(format!("{}.log", step.as_str()))
+}
+
+/// Write the log of every step of `result` under `logs`, replacing the dependent's earlier ones
+pub fn write(logs: &Path, result: &TestResult) {
+    let TestResultData::MultiVersion(ref outcomes) = result.data else {
+        return;
+    };
+    let (dependent, version) = (&result.rev_dep.name, result.rev_dep.vers.to_string());
+    if let Err(e) = write_outcomes(logs, dependent, &version, result.rev_dep.channel.as_deref(), outcomes) {
+        eprintln!("Warning: Failed to write step logs of {} {}: {}", dependent, version, e);
+    }
+}
+
+fn write_outcomes(logs: &Path, dependent: &str, version: &str, channel: Option<&str>, outcomes: &[crate::VersionTestOutcome]) -> io::Result<()> {
+    let dir = dependent_dir(logs, dependent, version, channel);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    for (idx, outcome) in outcomes.iter().enumerate() {
+        let offered = offered_dir(idx == 0, &outcome.version_source, outcome.result.cross_target.as_deref());
+        fs::create_dir_all(dir.join(&offered))?;
+        for step in outcome.result.steps() {
+            let mut contents = String::new();
+            crate::artifacts::render_step(&mut contents, step);
+            fs::write(dir.join(&offered).join(format!("{}.log", step.step.as_str())), contents)?;
+        }
+    }
+    debug!("Wrote step logs of {} {} to {:?}", dependent, version, dir);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::{CompileResult, ThreeStepResult};
+    use crate::VersionTestOutcome;
+
+    fn outcome(source: VersionSource, check_passed: bool) -> VersionTestOutcome {
+        let step = |step, success, stderr: &str| CompileResult { stderr: stderr.to_string(), ..CompileResult::for_test(step, success) };
+        VersionTestOutcome {
+            version_source: source,
+            result: ThreeStepResult::from_steps(
+                step(CompileStep::Fetch, true, "Downloaded rgb"),
+                [step(CompileStep::Check, check_passed, "error[E0425]: cannot find function `old_api`")],
+            ),
+        }
+    }
+
+    #[test]
+    fn test_offered_dir() {
+        let published = VersionSource::Published("0.8.91".to_string());
+        assert_eq!(offered_dir(true, &published, None), "baseline");
+        assert_eq!(offered_dir(false, &published, None), "0.8.91");
+        assert_eq!(offered_dir(false, &VersionSource::Local(PathBuf::from("/work/rgb")), Some("wasm32-unknown-unknown")),
+            "this-wasm32-unknown-unknown");
+    }
+
+    #[test]
+    fn test_write_outcomes_replaces_earlier_logs() {
+        let logs = tempfile::tempdir().unwrap();
+        let stale = logs.path().join("ravif-0.11.0/0.8.90/check.log");
+        fs::create_dir_all(stale.parent().unwrap()).unwrap();
+        fs::write(&stale, "old run").unwrap();
+
+        let outcomes = [
+            outcome(VersionSource::Published("0.8.50".to_string()), true),
+            outcome(VersionSource::Published("0.8.91".to_string()), false),
+        ];
+        write_outcomes(logs.path(), "ravif", "0.11.0", None, &outcomes).unwrap();
+        write_outcomes(logs.path(), "ravif", "0.11.0", Some("beta"), &outcomes).unwrap();
+
+        let dir = logs.path().join("ravif-0.11.0");
+        assert!(!stale.exists());
+        assert!(fs::read_to_string(dir.join("baseline/fetch.log")).unwrap().contains("Downloaded rgb"));
+        let check = fs::read_to_string(dir.join("0.8.91/check.log")).unwrap();
+        assert!(check.starts_with("### cargo check (failed, "));
+        assert!(check.contains("cannot find function `old_api`"));
+        // Another channel's logs don't replace them
+        assert!(dir.join("0.8.91/check.log").exists());
+        assert!(logs.path().join("ravif-0.11.0@beta/0.8.91/check.log").exists());
+    }
+}
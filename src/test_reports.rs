@@ -0,0 +1,359 @@
+//! Results as CI test reports (`--junit`, `--teamcity`)
+//!
+//! Each version tested against a dependent becomes a test case in a suite
+//! per dependent, so results show up in the CI's own test UI: as a JUnit XML
+//! file (GitLab's `artifacts:reports:junit`, and most other CIs), or as
+//! TeamCity service messages printed while the run goes. Regressions, and
+//! failures without a baseline, fail their test case; dependents that
+//! couldn't be tested are errors; versions that already fail with the
+//! baseline, and dependents that were skipped, are ignored.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::json_report::RowStatus;
+use crate::report::{self, TestSummary};
+use crate::sink::ReportSink;
+use crate::OfferedRow;
+
+/// Rows that stand for a dependent that wasn't tested (see `TestResult::to_offered_rows`)
+const UNTESTED: [&str; 4] = ["SKIPPED", "UNSUPPORTED", "NOT_RUN", "ERROR"];
+
+/// How a test case ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// A regression, or a failure without a baseline: message and compiler output
+    Failed(String, String),
+    /// The dependent couldn't be tested
+    Error(String),
+    Ignored(String),
+}
+
+/// One version tested against one dependent
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    /// The dependent, `image 0.25.8` (`image 0.25.8 [beta]` with --channels)
+    pub suite: String,
+    /// The version, `baseline (0.8.50)`, `0.8.91` or `this(0.8.91+g1a2b3c4)`
+    pub name: String,
+    /// Seconds its steps took
+    pub duration: f64,
+    pub outcome: Outcome,
+}
+
+impl TestCase {
+    pub fn from_row(row: &OfferedRow) -> Self {
+        let suite = row.primary.dependent_label();
+        let duration = row.test.commands.iter().map(|cmd| cmd.result.duration).sum();
+        let error = || row.test.commands.iter().flat_map(|cmd| &cmd.result.failures)
+            .map(|failure| failure.error_message.clone())
+            .next()
+            .unwrap_or_default();
+
+        if row.offered.is_none() && UNTESTED.contains(&row.primary.spec.as_str()) {
+            let name = row.primary.spec.to_lowercase();
+            let outcome = match row.primary.spec.as_str() {
+                "ERROR" => Outcome::Error(error()),
+                _ => Outcome::Ignored(row.primary.resolved_version.clone()),
+            };
+            return TestCase { suite, name, duration, outcome };
+        }
+
+        let mut name = match row.offered {
+            Some(ref offered) if offered.forced => format!("={}", offered.version),
+            Some(ref offered) => offered.version.clone(),
+            None => format!("baseline ({})", row.primary.resolved_version),
+        };
+        if let Some(ref target) = row.primary.cross_target {
+            name.push_str(&format!(" [{}]", target));
+        }
+
+        let step = report::failed_step(row).map(|step| step.as_str()).unwrap_or("fetch");
+        let outcome = match (row.offered.is_some(), report::row_status(row)) {
+            (_, RowStatus::Passed | RowStatus::Flaky | RowStatus::Warned | RowStatus::Duplicated | RowStatus::Msrv) => Outcome::Passed,
+            (_, RowStatus::Network) => Outcome::Error(error()),
+            (false, _) => Outcome::Ignored(format!("cargo {} fails with the baseline", step)),
+            (true, RowStatus::Broken) => Outcome::Ignored(format!("already fails with the baseline (cargo {})", step)),
+            (true, status) => Outcome::Failed(format!("{}: cargo {} failed", status.as_str(), step), failure_details(row)),
+        };
+        TestCase { suite, name, duration, outcome }
+    }
+}
+
+/// The compiler output of every failed step, and where its full log is
+fn failure_details(row: &OfferedRow) -> String {
+    let mut details = String::new();
+    for cmd in row.test.commands.iter().filter(|cmd| !cmd.result.passed) {
+        for failure in &cmd.result.failures {
+            let _ = writeln!(details, "cargo {} failed on {}:\n{}\n", cmd.command.as_str(), failure.crate_name, failure.error_message.trim_end());
+        }
+        if let Some(ref log) = cmd.result.log {
+            let _ = writeln!(details, "Full log: {}\n", log.display());
+        }
+    }
+    details.trim_end().to_string()
+}
+
+//
+// JUnit XML
+//
+
+/// Writes a JUnit XML report to `path` when the run finishes
+pub struct JunitSink {
+    path: PathBuf,
+    title: String,
+    cases: Vec<TestCase>,
+}
+
+impl JunitSink {
+    pub fn new(path: &Path, crate_name: &str, crate_version: &str) -> Self {
+        JunitSink {
+            path: path.to_path_buf(),
+            title: format!("cargo-copter {} {}", crate_name, crate_version),
+            cases: Vec::new(),
+        }
+    }
+}
+
+impl ReportSink for JunitSink {
+    fn write_row(&mut self, row: &OfferedRow) -> io::Result<()> {
+        self.cases.push(TestCase::from_row(row));
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &TestSummary) -> io::Result<()> {
+        fs::write(&self.path, junit_xml(&self.title, &self.cases))?;
+        println!("JUnit report: {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// Counts of a suite's cases: tests, failures, errors, skipped and total seconds
+fn counts<'a>(cases: impl IntoIterator<Item = &'a TestCase>) -> (usize, usize, usize, usize, f64) {
+    cases.into_iter().fold((0, 0, 0, 0, 0.0), |(tests, failures, errors, skipped, time), case| (
+        tests + 1,
+        failures + matches!(case.outcome, Outcome::Failed(..)) as usize,
+        errors + matches!(case.outcome, Outcome::Error(_)) as usize,
+        skipped + matches!(case.outcome, Outcome::Ignored(_)) as usize,
+        time + case.duration,
+    ))
+}
+
+/// The report, with a `<testsuite>` per dependent in the order they finished
+pub fn junit_xml(title: &str, cases: &[TestCase]) -> String {
+    let mut suites: Vec<(&str, Vec<&TestCase>)> = Vec::new();
+    for case in cases {
+        match suites.iter_mut().find(|(suite, _)| *suite == case.suite) {
+            Some((_, group)) => group.push(case),
+            None => suites.push((&case.suite, vec![case])),
+        }
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let (tests, failures, errors, skipped, time) = counts(cases);
+    let _ = writeln!(xml, "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        xml_escape(title), tests, failures, errors, skipped, time);
+    for (suite, group) in suites {
+        let (tests, failures, errors, skipped, time) = counts(group.iter().copied());
+        let _ = writeln!(xml, "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            xml_escape(suite), tests, failures, errors, skipped, time);
+        for case in group {
+            let _ = write!(xml, "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                xml_escape(&case.suite), xml_escape(&case.name), case.duration);
+            let _ = match case.outcome {
+                Outcome::Passed => writeln!(xml, "/>"),
+                Outcome::Failed(ref message, ref details) => writeln!(xml, ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    xml_escape(message), xml_escape(details)),
+                Outcome::Error(ref message) => writeln!(xml, ">\n      <error message=\"{}\">{}</error>\n    </testcase>",
+                    xml_escape(message.lines().next().unwrap_or_default()), xml_escape(message)),
+                Outcome::Ignored(ref reason) => writeln!(xml, ">\n      <skipped message=\"{}\"/>\n    </testcase>", xml_escape(reason)),
+            };
+        }
+        let _ = writeln!(xml, "  </testsuite>");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Escape for XML text and attributes, dropping control characters XML can't hold (e.g. ANSI escapes)
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+//
+// TeamCity service messages
+//
+
+/// Prints TeamCity service messages for each row as it arrives
+pub struct TeamcitySink {
+    title: String,
+    started: bool,
+    suite: Option<String>,
+}
+
+impl TeamcitySink {
+    pub fn new(crate_name: &str, crate_version: &str) -> Self {
+        TeamcitySink {
+            title: format!("cargo-copter {} {}", crate_name, crate_version),
+            started: false,
+            suite: None,
+        }
+    }
+
+    /// Messages for `case`, opening its suite (and closing the previous one) first
+    fn messages(&mut self, case: &TestCase) -> Vec<String> {
+        let mut messages = Vec::new();
+        if !self.started {
+            self.started = true;
+            messages.push(service_message("testSuiteStarted", &[("name", &self.title)]));
+        }
+        if self.suite.as_deref() != Some(case.suite.as_str()) {
+            if let Some(suite) = self.suite.replace(case.suite.clone()) {
+                messages.push(service_message("testSuiteFinished", &[("name", &suite)]));
+            }
+            messages.push(service_message("testSuiteStarted", &[("name", &case.suite)]));
+        }
+
+        let name = case.name.as_str();
+        messages.push(service_message("testStarted", &[("name", name)]));
+        match case.outcome {
+            Outcome::Passed => {}
+            Outcome::Failed(ref message, ref details) => {
+                messages.push(service_message("testFailed", &[("name", name), ("message", message), ("details", details)]));
+            }
+            Outcome::Error(ref message) => {
+                let first = message.lines().next().unwrap_or_default();
+                messages.push(service_message("testFailed", &[("name", name), ("message", first), ("details", message)]));
+            }
+            Outcome::Ignored(ref reason) => {
+                messages.push(service_message("testIgnored", &[("name", name), ("message", reason)]));
+            }
+        }
+        let duration = format!("{}", (case.duration * 1000.0).round() as u64);
+        messages.push(service_message("testFinished", &[("name", name), ("duration", &duration)]));
+        messages
+    }
+
+    /// Messages closing the open suites
+    fn closing_messages(&mut self) -> Vec<String> {
+        let mut messages = Vec::new();
+        if let Some(suite) = self.suite.take() {
+            messages.push(service_message("testSuiteFinished", &[("name", &suite)]));
+        }
+        if std::mem::take(&mut self.started) {
+            messages.push(service_message("testSuiteFinished", &[("name", &self.title)]));
+        }
+        messages
+    }
+}
+
+impl ReportSink for TeamcitySink {
+    fn write_row(&mut self, row: &OfferedRow) -> io::Result<()> {
+        for message in self.messages(&TestCase::from_row(row)) {
+            println!("{}", message);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &TestSummary) -> io::Result<()> {
+        for message in self.closing_messages() {
+            println!("{}", message);
+        }
+        Ok(())
+    }
+}
+
+/// Running under TeamCity, which reads service messages from the build log
+pub fn in_teamcity() -> bool {
+    std::env::var("TEAMCITY_VERSION").is_ok()
+}
+
+/// `##teamcity[name key='value' ...]`
+fn service_message(name: &str, attributes: &[(&str, &str)]) -> String {
+    let mut message = format!("##teamcity[{}", name);
+    for (key, value) in attributes {
+        let _ = write!(message, " {}='{}'", key, teamcity_escape(value));
+    }
+    message.push(']');
+    message
+}
+
+fn teamcity_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            '\u{0085}' => escaped.push_str("|x"),
+            '\u{2028}' => escaped.push_str("|l"),
+            '\u{2029}' => escaped.push_str("|p"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(suite: &str, name: &str, outcome: Outcome) -> TestCase {
+        TestCase { suite: suite.to_string(), name: name.to_string(), duration: 1.5, outcome }
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let cases = [
+            case("image 0.25.8", "baseline (0.8.50)", Outcome::Passed),
+            case("image 0.25.8", "this(0.8.91)", Outcome::Failed("REGRESSED: cargo check failed".to_string(),
+                "error[E0308]: expected `u8`, found `&str`\n\x1b[1m<html>\x1b[0m".to_string())),
+            case("ravif 0.11.0", "skipped", Outcome::Ignored("yanked".to_string())),
+        ];
+        let xml = junit_xml("cargo-copter rgb 0.8.91", &cases);
+        assert!(xml.contains("<testsuites name=\"cargo-copter rgb 0.8.91\" tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\" time=\"4.500\">"));
+        assert!(xml.contains("<testsuite name=\"image 0.25.8\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"0\" time=\"3.000\">"));
+        assert!(xml.contains("<testcase classname=\"image 0.25.8\" name=\"baseline (0.8.50)\" time=\"1.500\"/>"));
+        assert!(xml.contains("<failure message=\"REGRESSED: cargo check failed\">error[E0308]: expected `u8`, found `&amp;str`\n[1m&lt;html&gt;[0m</failure>"));
+        assert!(xml.contains("<skipped message=\"yanked\"/>"));
+    }
+
+    #[test]
+    fn test_teamcity_messages() {
+        let mut sink = TeamcitySink::new("rgb", "0.8.91");
+        let first = sink.messages(&case("image 0.25.8", "this(0.8.91)", Outcome::Failed("REGRESSED: cargo check failed".to_string(), "x[0]\n'y'".to_string())));
+        assert_eq!(first, vec![
+            "##teamcity[testSuiteStarted name='cargo-copter rgb 0.8.91']",
+            "##teamcity[testSuiteStarted name='image 0.25.8']",
+            "##teamcity[testStarted name='this(0.8.91)']",
+            "##teamcity[testFailed name='this(0.8.91)' message='REGRESSED: cargo check failed' details='x|[0|]|n|'y|'']",
+            "##teamcity[testFinished name='this(0.8.91)' duration='1500']",
+        ]);
+        let second = sink.messages(&case("ravif 0.11.0", "skipped", Outcome::Ignored("yanked".to_string())));
+        assert_eq!(second[0], "##teamcity[testSuiteFinished name='image 0.25.8']");
+        assert_eq!(second[3], "##teamcity[testIgnored name='skipped' message='yanked']");
+        assert_eq!(sink.closing_messages(), vec![
+            "##teamcity[testSuiteFinished name='ravif 0.11.0']",
+            "##teamcity[testSuiteFinished name='cargo-copter rgb 0.8.91']",
+        ]);
+    }
+}
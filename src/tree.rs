@@ -0,0 +1,177 @@
+//! Intermediate crates that pull other versions of the base crate into a dependent
+//!
+//! After fetch, `cargo tree -i <crate>` is run in the dependent. Each
+//! inverted tree starts at one version of the base crate; its direct users
+//! other than the dependent itself are the intermediates. Those on a
+//! different version than the dependent explain why the offered version
+//! wasn't the only one built. When several versions are in the graph,
+//! cargo asks for `crate@version` specs, so each one gets its own run.
+
+use std::path::Path;
+use std::process::Command;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+/// A crate between the dependent and another version of the base crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Intermediate {
+    pub name: String,
+    pub version: String,
+    /// Version of the base crate it depends on
+    pub base_version: String,
+    /// The base crate comes from a path (the offered version), not a registry
+    pub base_is_path: bool,
+    /// Dependency depth below the dependent (1 = direct dependency)
+    pub depth: usize,
+}
+
+/// One line of `cargo tree --prefix depth --format {p}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TreeLine {
+    depth: usize,
+    name: String,
+    version: String,
+    is_path: bool,
+}
+
+fn parse_line(line: &str) -> Option<TreeLine> {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    let depth = line[..digits].parse().ok()?;
+    let mut parts = line[digits..].split_whitespace();
+    let name = parts.next()?.to_string();
+    let version = parts.next()?.strip_prefix('v')?.to_string();
+    // Registry crates have no source; paths print as "(/abs/path)"
+    let is_path = parts.next().is_some_and(|source| source.starts_with("(/") || source.contains(":\\"));
+    Some(TreeLine { depth, name, version, is_path })
+}
+
+/// Intermediates in the (possibly concatenated) output of inverted `cargo tree` runs
+pub fn parse_inverted_tree(output: &str, dependent: &str) -> Vec<Intermediate> {
+    let lines: Vec<TreeLine> = output.lines().filter_map(parse_line).collect();
+
+    // Split into one tree per base crate version
+    let mut trees: Vec<&[TreeLine]> = Vec::new();
+    let mut start = 0;
+    for i in 1..=lines.len() {
+        if i == lines.len() || lines[i].depth == 0 {
+            if lines.get(start).is_some_and(|l| l.depth == 0) {
+                trees.push(&lines[start..i]);
+            }
+            start = i;
+        }
+    }
+
+    // The version the dependent itself uses directly
+    let own_version = trees.iter()
+        .find(|tree| tree.iter().any(|l| l.depth == 1 && l.name == dependent))
+        .map(|tree| tree[0].version.clone());
+
+    let mut intermediates = Vec::new();
+    for tree in trees {
+        let base = &tree[0];
+        if Some(&base.version) == own_version.as_ref() {
+            continue;
+        }
+        for (i, line) in tree.iter().enumerate().skip(1) {
+            if line.depth != 1 || line.name == dependent {
+                continue;
+            }
+            // Nearest occurrence of the dependent in this crate's subtree
+            let subtree = tree[i + 1..].iter().take_while(|l| l.depth > 1);
+            let depth = subtree.filter(|l| l.name == dependent)
+                .map(|l| l.depth - 1)
+                .min()
+                .unwrap_or(1);
+            let intermediate = Intermediate {
+                name: line.name.clone(),
+                version: line.version.clone(),
+                base_version: base.version.clone(),
+                base_is_path: base.is_path,
+                depth,
+            };
+            if !intermediates.contains(&intermediate) {
+                intermediates.push(intermediate);
+            }
+        }
+    }
+    intermediates
+}
+
+/// Specs cargo suggests when `-i <crate>` matches several versions
+fn ambiguous_specs(stderr: &str, crate_name: &str) -> Vec<String> {
+    let prefix = format!("{}@", crate_name);
+    stderr.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with(&prefix))
+        .map(str::to_string)
+        .collect()
+}
+
+fn inverted_tree(crate_path: &Path, spec: &str, config_args: &[String]) -> Result<String, String> {
+    let output = Command::new("cargo")
+        .args(["tree", "--invert", spec, "--prefix", "depth", "--format", "{p}", "--no-dedupe"])
+        .args(config_args)
+        .args(crate::api::cargo_source_args())
+        .current_dir(crate_path)
+        .output()
+        .map_err(|e| format!("Failed to run cargo tree: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+}
+
+/// Intermediates of `dependent` (unpacked at `crate_path`) that use another
+/// version of `crate_name`; empty if cargo tree fails
+pub fn find_intermediates(crate_path: &Path, crate_name: &str, dependent: &str, config_args: &[String]) -> Vec<Intermediate> {
+    let output = match inverted_tree(crate_path, crate_name, config_args) {
+        Ok(output) => output,
+        Err(stderr) => {
+            let specs = ambiguous_specs(&stderr, crate_name);
+            if specs.is_empty() {
+                debug!("cargo tree -i {} failed in {:?}: {}", crate_name, crate_path, stderr.trim());
+                return Vec::new();
+            }
+            specs.iter()
+                .filter_map(|spec| inverted_tree(crate_path, spec, config_args).ok())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+    parse_inverted_tree(&output, dependent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inverted_tree() {
+        let output = "\
+0rgb v0.8.91 (/home/me/rgb)
+1image v0.25.8 (/tmp/staging/image-0.25.8)
+1ravif v0.11.0
+2image v0.25.8 (/tmp/staging/image-0.25.8)
+
+0rgb v0.7.3
+1png v0.16.0
+2gif-utils v0.2.0
+3image v0.25.8 (/tmp/staging/image-0.25.8)
+1lodepng v3.0.0
+2image v0.25.8 (/tmp/staging/image-0.25.8)
+";
+        // ravif is on the dependent's own version, so it explains nothing
+        assert_eq!(parse_inverted_tree(output, "image"), vec![
+            Intermediate { name: "png".to_string(), version: "0.16.0".to_string(), base_version: "0.7.3".to_string(), base_is_path: false, depth: 2 },
+            Intermediate { name: "lodepng".to_string(), version: "3.0.0".to_string(), base_version: "0.7.3".to_string(), base_is_path: false, depth: 1 },
+        ]);
+    }
+
+    #[test]
+    fn test_ambiguous_specs() {
+        let stderr = "error: There are multiple `rgb` packages in your project, and the specification `rgb` is ambiguous.\n\
+                      Please re-run this command with one of the following specifications:\n  rgb@0.7.3\n  rgb@0.8.91\n";
+        assert_eq!(ambiguous_specs(stderr, "rgb"), vec!["rgb@0.7.3".to_string(), "rgb@0.8.91".to_string()]);
+    }
+}
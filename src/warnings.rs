@@ -0,0 +1,149 @@
+//! New compiler warnings in dependents (WARNED)
+//!
+//! A dependent can still build and pass its tests with the offered version
+//! and yet get new warnings from it, most often `deprecated`: an early sign
+//! of what the next breaking release will break. The warnings of each
+//! offered version are counted per lint (`deprecated`, `unused_imports`, ...)
+//! and compared with the baseline's; a passing row with more warnings of
+//! some lint is WARNED.
+//!
+//! Only warnings in the dependent's own sources count. Registry dependencies
+//! have their lints capped, but a local base crate patched in doesn't, and
+//! its own warnings say nothing about the dependent. The same goes for
+//! `--deny-warnings`, which fails the check step on the dependent's warnings.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use crate::compile::{CompileStep, ThreeStepResult};
+use crate::error_extract::{Diagnostic, DiagnosticLevel};
+
+/// Lint of warnings rustc gives no code
+const UNNAMED_LINT: &str = "warning";
+
+/// A lint the offered version warns about more often than the baseline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarningChange {
+    /// `deprecated`, or `warning` for warnings without a lint name
+    pub lint: String,
+    pub baseline: usize,
+    pub offered: usize,
+    /// Message of one of the new warnings
+    pub example: String,
+}
+
+impl fmt::Display for WarningChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} → {} ({})", self.lint, self.baseline, self.offered, self.example)
+    }
+}
+
+/// A warning in one of the dependent's own files
+///
+/// Summaries ("2 warnings emitted") have no span; other crates' files are absolute.
+fn is_own_warning(diagnostic: &Diagnostic) -> bool {
+    diagnostic.level == DiagnosticLevel::Warning
+        && diagnostic.primary_span.as_ref().is_some_and(|span| !Path::new(&span.file_name).is_absolute())
+}
+
+/// Turn the dependent's own warnings into errors, as `-D warnings` would; returns how many
+pub fn deny_own_warnings(diagnostics: &mut [Diagnostic]) -> usize {
+    let mut denied = 0;
+    for diagnostic in diagnostics.iter_mut().filter(|d| is_own_warning(d)) {
+        diagnostic.level = DiagnosticLevel::Error;
+        denied += 1;
+    }
+    denied
+}
+
+/// Distinct warnings of the compile steps in the dependent's own files, by lint:
+/// (message, file, line) of each, as check and test report the same ones again
+fn warnings_by_lint(result: &ThreeStepResult) -> BTreeMap<String, BTreeSet<(String, String, usize)>> {
+    let mut lints: BTreeMap<String, BTreeSet<(String, String, usize)>> = BTreeMap::new();
+    let steps = result.steps().into_iter()
+        .filter(|step| matches!(step.step, CompileStep::Check | CompileStep::Build | CompileStep::Install | CompileStep::Test));
+    for diagnostic in steps.flat_map(|step| &step.diagnostics).filter(|d| is_own_warning(d)) {
+        let Some(ref span) = diagnostic.primary_span else { continue };
+        let lint = diagnostic.code.clone().unwrap_or_else(|| UNNAMED_LINT.to_string());
+        lints.entry(lint).or_default().insert((diagnostic.message.clone(), span.file_name.clone(), span.line));
+    }
+    lints
+}
+
+/// Lints `offered` warns about more often than `baseline`
+pub fn diff(baseline: &ThreeStepResult, offered: &ThreeStepResult) -> Vec<WarningChange> {
+    let before = warnings_by_lint(baseline);
+    let after = warnings_by_lint(offered);
+    let mut changes = Vec::new();
+    for (lint, warnings) in &after {
+        let previous = before.get(lint);
+        let baseline_count = previous.map_or(0, BTreeSet::len);
+        if warnings.len() <= baseline_count {
+            continue;
+        }
+        // One the baseline didn't have, by message, since new code shifts lines
+        let example = warnings.iter()
+            .find(|(message, _, _)| previous.is_none_or(|p| !p.iter().any(|(m, _, _)| m == message)))
+            .or_else(|| warnings.iter().next())
+            .map(|(message, _, _)| message.clone())
+            .unwrap_or_default();
+        changes.push(WarningChange { lint: lint.clone(), baseline: baseline_count, offered: warnings.len(), example });
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::CompileResult;
+    use crate::error_extract::{Diagnostic, DiagnosticLevel, SpanInfo};
+
+    fn warning(code: Option<&str>, message: &str, file: Option<&str>, line: usize) -> Diagnostic {
+        Diagnostic {
+            level: DiagnosticLevel::Warning,
+            code: code.map(str::to_string),
+            message: message.to_string(),
+            rendered: String::new(),
+            primary_span: file.map(|file| SpanInfo { file_name: file.to_string(), line, column: 1, label: None }),
+        }
+    }
+
+    fn result(check: Vec<Diagnostic>, test: Vec<Diagnostic>) -> ThreeStepResult {
+        let step = |step, diagnostics| CompileResult { diagnostics, ..CompileResult::for_test(step, true) };
+        ThreeStepResult::from_steps(step(CompileStep::Fetch, vec![]), [step(CompileStep::Check, check), step(CompileStep::Test, test)])
+    }
+
+    #[test]
+    fn test_new_warnings_by_lint() {
+        let unused = warning(Some("unused_imports"), "unused import: `rgb::RGB8`", Some("src/lib.rs"), 1);
+        let baseline = result(vec![unused.clone()], vec![unused.clone()]);
+
+        let deprecated = warning(Some("deprecated"), "use of deprecated method `rgb::Rgb::as_slice`", Some("src/pixels.rs"), 40);
+        let offered = result(
+            vec![unused.clone(), deprecated.clone(), warning(None, "2 warnings emitted", None, 0)],
+            // Reported again by the test build, and the base crate's own warnings
+            vec![unused, deprecated, warning(Some("dead_code"), "function `old` is never used", Some("/work/rgb/src/lib.rs"), 9)],
+        );
+
+        assert_eq!(diff(&baseline, &offered), vec![WarningChange {
+            lint: "deprecated".to_string(),
+            baseline: 0,
+            offered: 1,
+            example: "use of deprecated method `rgb::Rgb::as_slice`".to_string(),
+        }]);
+        assert!(diff(&offered, &baseline).is_empty());
+    }
+
+    #[test]
+    fn test_deny_own_warnings() {
+        let mut diagnostics = vec![
+            warning(Some("deprecated"), "use of deprecated method `rgb::Rgb::as_slice`", Some("src/pixels.rs"), 40),
+            warning(None, "1 warning emitted", None, 0),
+            warning(Some("dead_code"), "function `old` is never used", Some("/work/rgb/src/lib.rs"), 9),
+        ];
+        assert_eq!(deny_own_warnings(&mut diagnostics), 1);
+        let levels: Vec<_> = diagnostics.iter().map(|d| d.level.clone()).collect();
+        assert_eq!(levels, vec![DiagnosticLevel::Error, DiagnosticLevel::Warning, DiagnosticLevel::Warning]);
+    }
+}
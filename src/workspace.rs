@@ -0,0 +1,244 @@
+//! Dependents that are members of a workspace
+//!
+//! A member's manifest may inherit fields from its workspace root
+//! (`version.workspace = true`, `rgb = { workspace = true }`, ...). Git
+//! checkouts keep the root, so inherited fields are looked up there when we
+//! read a requirement, an edition or rewrite a dependency. An unpacked
+//! `.crate` has no root: `cargo publish` normally inlines everything, but if
+//! an inherited field survives it is synthesized (the published version,
+//! edition 2021, any version of a dependency) so the crate still builds.
+//! Unpacked crates also get an empty `[workspace]` when the staging
+//! directory sits inside another workspace, which cargo would otherwise
+//! claim them for.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use semver::Version;
+
+/// Edition assumed for an unpacked crate inheriting one from a workspace we don't have
+const SYNTHESIZED_EDITION: &str = "2021";
+
+fn read_manifest(dir: &Path) -> Option<toml::Value> {
+    let content = fs::read(dir.join("Cargo.toml")).ok()?;
+    crate::manifest::parse_manifest(&content).ok()
+}
+
+/// Nearest directory from `dir` upward whose manifest has a `[workspace]`
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| read_manifest(d).is_some_and(|m| m.get("workspace").is_some()))
+        .map(Path::to_path_buf)
+}
+
+/// Manifest of the workspace `crate_dir` is a member of, if it isn't its own root
+pub fn member_root(crate_dir: &Path) -> Option<toml::Value> {
+    let root = find_root(crate_dir)?;
+    if root == crate_dir {
+        return None;
+    }
+    debug!("{:?} is a member of the workspace at {:?}", crate_dir, root);
+    read_manifest(&root)
+}
+
+/// `{ workspace = true }`
+fn is_inherited(value: &toml::Value) -> bool {
+    value.get("workspace").and_then(|w| w.as_bool()) == Some(true)
+}
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Replace inherited fields of a member `manifest` with the values in its
+/// workspace `root`; fields the root doesn't define are left as they are
+pub fn resolve(manifest: &mut toml::Value, root: &toml::Value) {
+    let workspace = root.get("workspace");
+    if let Some(package) = manifest.get_mut("package").and_then(|p| p.as_table_mut()) {
+        for (key, value) in package.iter_mut() {
+            if is_inherited(value) {
+                if let Some(inherited) = workspace.and_then(|w| w.get("package")).and_then(|p| p.get(key.as_str())) {
+                    *value = inherited.clone();
+                }
+            }
+        }
+    }
+    for table in DEPENDENCY_TABLES {
+        let Some(deps) = manifest.get_mut(table).and_then(|d| d.as_table_mut()) else {
+            continue;
+        };
+        for (name, dep) in deps.iter_mut() {
+            if is_inherited(dep) {
+                if let Some(inherited) = workspace.and_then(|w| w.get("dependencies")).and_then(|d| d.get(name.as_str())) {
+                    *dep = merge_dependency(inherited, dep);
+                }
+            }
+        }
+    }
+}
+
+/// Workspace dependency `inherited` as used by a member: the member adds
+/// features and may make it optional
+fn merge_dependency(inherited: &toml::Value, member: &toml::Value) -> toml::Value {
+    let mut merged = match inherited {
+        toml::Value::String(version) => {
+            let mut table = toml::map::Map::new();
+            table.insert("version".to_string(), toml::Value::String(version.clone()));
+            table
+        }
+        toml::Value::Table(table) => table.clone(),
+        other => return other.clone(),
+    };
+    if let Some(features) = member.get("features").and_then(|f| f.as_array()) {
+        let all = merged.entry("features").or_insert_with(|| toml::Value::Array(Vec::new()));
+        if let Some(all) = all.as_array_mut() {
+            all.extend(features.iter().filter(|f| !all.contains(f)).cloned().collect::<Vec<_>>());
+        }
+    }
+    if let Some(optional) = member.get("optional") {
+        merged.insert("optional".to_string(), optional.clone());
+    }
+    toml::Value::Table(merged)
+}
+
+/// The workspace's definition of a member's inherited dependency `name`
+pub fn inherited_dependency(crate_dir: &Path, name: &str) -> Option<toml::Value> {
+    member_root(crate_dir)?.get("workspace")?.get("dependencies")?.get(name).cloned()
+}
+
+/// Make an unpacked `.crate` in `crate_dir` buildable on its own
+///
+/// Synthesizes fields still inherited from a workspace and adds an empty
+/// `[workspace]` when an enclosing workspace would claim the crate. Returns
+/// the synthesized fields.
+pub fn isolate_unpacked(crate_dir: &Path, version: &Version) -> Result<Vec<String>, String> {
+    let path = crate_dir.join("Cargo.toml");
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut doc: toml_edit::DocumentMut = content.parse()
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    let synthesized = synthesize_inherited(&mut doc, version);
+    let enclosed = crate_dir.parent().and_then(find_root).is_some();
+    let needs_workspace = doc.get("workspace").is_none() && (enclosed || !synthesized.is_empty());
+    if needs_workspace {
+        doc.insert("workspace", toml_edit::Item::Table(toml_edit::Table::new()));
+    }
+    if needs_workspace || !synthesized.is_empty() {
+        debug!("isolating {:?} from workspaces (synthesized: {:?})", crate_dir, synthesized);
+        fs::write(&path, doc.to_string())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(synthesized)
+}
+
+/// Replace `workspace = true` fields with stand-ins; returns their names
+fn synthesize_inherited(doc: &mut toml_edit::DocumentMut, version: &Version) -> Vec<String> {
+    let inherited = |item: &toml_edit::Item| {
+        item.as_table_like()
+            .and_then(|t| t.get("workspace"))
+            .and_then(|w| w.as_bool()) == Some(true)
+    };
+    let mut synthesized = Vec::new();
+
+    if let Some(package) = doc.get_mut("package").and_then(|p| p.as_table_like_mut()) {
+        let keys: Vec<String> = package.iter()
+            .filter(|(_, item)| inherited(item))
+            .map(|(key, _)| key.to_string())
+            .collect();
+        for key in keys {
+            match key.as_str() {
+                "version" => package.insert(&key, toml_edit::value(version.to_string())),
+                "edition" => package.insert(&key, toml_edit::value(SYNTHESIZED_EDITION)),
+                // Metadata (authors, license, ...) doesn't affect the build
+                _ => package.remove(&key),
+            };
+            synthesized.push(format!("package.{}", key));
+        }
+    }
+
+    for table in DEPENDENCY_TABLES {
+        let Some(deps) = doc.get_mut(table).and_then(|d| d.as_table_like_mut()) else {
+            continue;
+        };
+        let names: Vec<String> = deps.iter()
+            .filter(|(_, item)| inherited(item))
+            .map(|(name, _)| name.to_string())
+            .collect();
+        for name in names {
+            if let Some(dep) = deps.get_mut(&name).and_then(|d| d.as_table_like_mut()) {
+                dep.remove("workspace");
+                dep.insert("version", toml_edit::value("*"));
+            }
+            synthesized.push(format!("{}.{}", table, name));
+        }
+    }
+
+    if doc.get("lints").is_some_and(inherited) {
+        doc.remove("lints");
+        synthesized.push("lints".to_string());
+    }
+    synthesized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_member_manifest() {
+        let root: toml::Value = toml::from_str(r#"
+            [workspace]
+            members = ["crates/*"]
+            [workspace.package]
+            version = "0.26.0"
+            edition = "2024"
+            [workspace.dependencies]
+            rgb = { version = "0.8.50", default-features = false, features = ["bytemuck"] }
+            png = "0.17"
+        "#).unwrap();
+        let mut member: toml::Value = toml::from_str(r#"
+            [package]
+            name = "image"
+            version.workspace = true
+            edition.workspace = true
+            [dependencies]
+            rgb = { workspace = true, features = ["serde"], optional = true }
+            png.workspace = true
+        "#).unwrap();
+
+        resolve(&mut member, &root);
+        assert_eq!(member["package"]["edition"].as_str(), Some("2024"));
+        let rgb = &member["dependencies"]["rgb"];
+        assert_eq!(rgb["version"].as_str(), Some("0.8.50"));
+        assert_eq!(rgb["features"].as_array().unwrap().len(), 2);
+        assert_eq!(rgb["optional"].as_bool(), Some(true));
+        assert_eq!(member["dependencies"]["png"]["version"].as_str(), Some("0.17"));
+    }
+
+    #[test]
+    fn test_isolate_unpacked() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[workspace]\nmembers = [\"rgb\"]\n").unwrap();
+        let staged = dir.path().join("staging").join("image-0.25.8");
+        fs::create_dir_all(&staged).unwrap();
+
+        // Published manifests are complete; only the enclosing workspace is a problem
+        fs::write(staged.join("Cargo.toml"), "[package]\nname = \"image\"\nversion = \"0.25.8\"\n").unwrap();
+        assert!(isolate_unpacked(&staged, &Version::parse("0.25.8").unwrap()).unwrap().is_empty());
+        assert_eq!(find_root(&staged).as_deref(), Some(staged.as_path()));
+
+        fs::write(staged.join("Cargo.toml"),
+            "[package]\nname = \"image\"\nversion.workspace = true\nedition.workspace = true\nlicense.workspace = true\n\
+             [dependencies]\nrgb = { workspace = true, features = [\"serde\"] }\n[lints]\nworkspace = true\n").unwrap();
+        let synthesized = isolate_unpacked(&staged, &Version::parse("0.25.8").unwrap()).unwrap();
+        assert_eq!(synthesized, vec!["package.version", "package.edition", "package.license", "dependencies.rgb", "lints"]);
+
+        let manifest = read_manifest(&staged).unwrap();
+        assert_eq!(manifest["package"]["version"].as_str(), Some("0.25.8"));
+        assert_eq!(manifest["package"]["edition"].as_str(), Some(SYNTHESIZED_EDITION));
+        assert!(manifest["package"].get("license").is_none());
+        assert_eq!(manifest["dependencies"]["rgb"]["version"].as_str(), Some("*"));
+        assert_eq!(manifest["dependencies"]["rgb"]["features"].as_array().unwrap().len(), 1);
+        assert!(manifest.get("workspace").is_some());
+    }
+}